@@ -3,6 +3,7 @@ use glam_traits::{GBVec, GVec};
 /// Some volume defined by a minimum and maximum.
 #[repr(C)]
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct MinMax<T> {
 	pub min: T,
 	pub max: T,
@@ -0,0 +1,40 @@
+use std::io;
+
+/// Why a level file couldn't be read, carried inside the `io::Error`s returned by the readers.
+/// Recover it with `TrError::of`.
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum TrError {
+	/// Magic and extension don't match any known version.
+	#[error("Unknown file type\nVersion: 0x{magic:X}")]
+	UnknownVersion { magic: u32 },
+	/// Recognized, but not readable by this tool.
+	#[error("{0}")]
+	Unsupported(String),
+	/// File ended before the level did.
+	#[error("File is truncated")]
+	Truncated,
+	/// Value read contradicts the format.
+	#[error("Invalid level data: {0}")]
+	Invalid(&'static str),
+}
+
+impl From<TrError> for io::Error {
+	fn from(e: TrError) -> Self {
+		io::Error::new(io::ErrorKind::InvalidData, e)
+	}
+}
+
+impl TrError {
+	/// The `TrError` inside `e`, if any.
+	pub fn of(e: &io::Error) -> Option<&TrError> {
+		e.get_ref()?.downcast_ref()
+	}
+	
+	/// Replace an end of file error from reading with `Truncated`, leaving other errors as they are.
+	pub fn truncated_on_eof(e: io::Error) -> io::Error {
+		match e.kind() {
+			io::ErrorKind::UnexpectedEof => Self::Truncated.into(),
+			_ => e,
+		}
+	}
+}
@@ -0,0 +1,9 @@
+/// 64-bit FNV-1a, stable across runs and platforms unlike `DefaultHasher`.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+	bytes.iter().fold(0xCBF29CE484222325, |hash, &byte| (hash ^ byte as u64).wrapping_mul(0x100000001B3))
+}
+
+/// `fnv1a` as 16 uppercase hex digits, the form used in dumps and saved settings.
+pub fn fnv1a_hex(bytes: &[u8]) -> String {
+	format!("{:016X}", fnv1a(bytes))
+}
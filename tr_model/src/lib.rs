@@ -1,11 +1,17 @@
 #[cfg(target_endian = "big")]
 const _: () = panic!("big endian not supported");
 
+mod error;
+mod hash;
 mod u16_cursor;
 pub mod tr1;
 pub mod tr2;
 pub mod tr3;
 pub mod tr4;
 pub mod tr5;
+#[cfg(feature = "serde")]
+pub mod ser;
 
+pub use error::TrError;
+pub use hash::{fnv1a, fnv1a_hex};
 pub use tr_readable::Readable;
@@ -0,0 +1,73 @@
+use std::{cell::Cell, mem::size_of_val, ops::Deref, slice::from_raw_parts};
+use glam_traits::ext::U8Vec2;
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+use crate::{
+	fnv1a_hex,
+	tr1::{RoomFlags, StaticMeshFlags},
+	tr2::Color16BitArgb,
+	tr3::{Color16BitRgb, DsFaceTexture},
+	tr4::{AtlasIndexFaceType, FaceEffects},
+	tr5::EffectsFaceTexture,
+};
+
+thread_local! {
+	static FULL_BLOBS: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Serialize blobs in full as bytes while `f` runs on this thread. By default a blob serializes as its byte
+/// length and hash.
+pub fn with_full_blobs<R>(f: impl FnOnce() -> R) -> R {
+	let prev = FULL_BLOBS.replace(true);
+	let result = f();
+	FULL_BLOBS.set(prev);
+	result
+}
+
+/// Pixel data, samples and other large tables. Element types must be plain integers without padding.
+pub(crate) fn blob<S: Serializer, D: Deref<Target = T>, T: ?Sized>(
+	data: &D, serializer: S,
+) -> Result<S::Ok, S::Error> {
+	let data = &**data;
+	//safe: element types are plain integers, every byte initialized
+	let bytes = unsafe { from_raw_parts(data as *const T as *const u8, size_of_val(data)) };
+	if FULL_BLOBS.get() {
+		return serializer.serialize_bytes(bytes);
+	}
+	let mut state = serializer.serialize_struct("Blob", 2)?;
+	state.serialize_field("len", &bytes.len())?;
+	state.serialize_field("hash", &fnv1a_hex(bytes))?;
+	state.end()
+}
+
+/// Arrays longer than serde's derive supports.
+pub(crate) fn array<S: Serializer, D: Deref<Target = [T; N]>, T: Serialize, const N: usize>(
+	array: &D, serializer: S,
+) -> Result<S::Ok, S::Error> {
+	serializer.collect_seq(array.iter())
+}
+
+pub(crate) fn u8_vec2<S: Serializer>(v: &U8Vec2, serializer: S) -> Result<S::Ok, S::Error> {
+	[v.x, v.y].serialize(serializer)
+}
+
+/// Bitfields serialize as their named fields.
+macro_rules! serialize_bitfield {
+	($type:ident { $($field:ident),* }) => {
+		impl Serialize for $type {
+			fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+				let mut state = serializer.serialize_struct(stringify!($type), [$(stringify!($field)),*].len())?;
+				$(state.serialize_field(stringify!($field), &self.$field())?;)*
+				state.end()
+			}
+		}
+	};
+}
+
+serialize_bitfield!(RoomFlags { water });
+serialize_bitfield!(StaticMeshFlags { non_collidable, visible });
+serialize_bitfield!(Color16BitArgb { a, r, g, b });
+serialize_bitfield!(DsFaceTexture { double_sided, object_texture_index });
+serialize_bitfield!(Color16BitRgb { r, g, b });
+serialize_bitfield!(AtlasIndexFaceType { tri, atlas_index });
+serialize_bitfield!(FaceEffects { additive, shine_strength });
+serialize_bitfield!(EffectsFaceTexture { double_sided, object_texture_index });
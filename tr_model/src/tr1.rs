@@ -28,6 +28,7 @@ pub mod blend_mode {
 
 #[repr(C)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct RoomVertex {
 	/// Relative to room
 	pub pos: I16Vec3,
@@ -38,6 +39,7 @@ macro_rules! decl_face_type {
 	($name:ident, $num_indices:literal, $texture_field:ident) => {
 		#[repr(C)]
 		#[derive(Clone, Debug)]
+		#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 		pub struct $name {
 			pub vertex_indices: [u16; $num_indices],
 			pub $texture_field: u16,
@@ -50,6 +52,7 @@ decl_face_type!(TexturedTri, 3, object_texture_index);
 
 #[repr(C)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Sprite {
 	pub vertex_index: u16,
 	pub sprite_texture_index: u16,
@@ -57,6 +60,7 @@ pub struct Sprite {
 
 #[repr(C)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Portal {
 	pub adjoining_room_index: u16,
 	pub normal: I16Vec3,
@@ -65,6 +69,7 @@ pub struct Portal {
 
 #[repr(C)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct NumSectors {
 	pub z: u16,
 	pub x: u16,
@@ -78,6 +83,7 @@ impl ToLen for NumSectors {
 
 #[repr(C)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Sector {
 	pub floor_data_index: u16,
 	pub box_index: u16,
@@ -87,8 +93,16 @@ pub struct Sector {
 	pub ceiling: i8,
 }
 
+impl Sector {
+	/// TR3+ footstep sound material in the low 4 bits of `box_index`. Meaningless in TR1-2.
+	pub fn footstep_material(&self) -> u8 {
+		(self.box_index & 0xF) as u8
+	}
+}
+
 #[repr(C, packed(2))]
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Light {
 	pub pos: IVec3,
 	pub brightness: u16,
@@ -96,7 +110,8 @@ pub struct Light {
 }
 
 #[repr(C, packed(2))]
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct RoomStaticMesh {
 	/// World coords.
 	pub pos: IVec3,
@@ -115,6 +130,7 @@ bitfield! {
 }
 
 #[derive(Readable, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Room {
 	/// World coord.
 	pub x: i32,
@@ -140,10 +156,12 @@ pub struct Room {
 
 #[repr(C)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Animation {
 	pub frame_byte_offset: u32,
 	pub frame_duration: u8,
-	pub num_frames: u8,
+	/// Length of each frame in u16s.
+	pub frame_size: u8,
 	pub state_id: u16,
 	pub speed: u32,
 	pub accel: u32,
@@ -157,8 +175,27 @@ pub struct Animation {
 	pub anim_command_index: u16,
 }
 
+/// Decoded entry of `Level.anim_commands`.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum AnimCommand {
+	/// Applied at the end of the animation.
+	SetPosition(I16Vec3),
+	/// Applied at the end of the animation.
+	JumpVelocity { vertical: i16, horizontal: i16 },
+	EmptyHands,
+	Kill,
+	/// TR2+ use the top 2 bits of `sound_id` for land/water conditions.
+	PlaySound { frame: u16, sound_id: u16 },
+	/// TR2+ use the top 2 bits of `effect` for land/water conditions.
+	FlipEffect { frame: u16, effect: u16 },
+	/// Operand count unknown, so decoding stops here.
+	Unknown(u16),
+}
+
 #[repr(C)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct StateChange {
 	pub state_id: u16,
 	pub num_anim_dispatches: u16,
@@ -167,6 +204,7 @@ pub struct StateChange {
 
 #[repr(C)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct AnimDispatch {
 	pub low_frame: u16,
 	pub high_frame: u16,
@@ -175,7 +213,8 @@ pub struct AnimDispatch {
 }
 
 #[repr(C, packed(2))]
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Model {
 	pub id: u32,
 	pub num_meshes: u16,
@@ -191,25 +230,36 @@ pub struct Model {
 
 #[repr(C)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct BoundBox {
 	pub x: MinMax<i16>,
 	pub y: MinMax<i16>,
 	pub z: MinMax<i16>,
 }
 
+bitfield! {
+	#[repr(C)]
+	#[derive(Clone, Debug)]
+	pub struct StaticMeshFlags(u16);
+	pub non_collidable, _: 0;
+	pub visible, _: 1;
+}
+
 #[repr(C)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct StaticMesh {
 	pub id: u32,
 	/// Index into `Level.mesh_offsets`.
 	pub mesh_offset_index: u16,
 	pub visibility: BoundBox,
 	pub collision: BoundBox,
-	pub flags: u16,
+	pub flags: StaticMeshFlags,
 }
 
 #[repr(C)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ObjectTexture {
 	/// One of the blend modes in the `blend_mode` module.
 	pub blend_mode: u16,
@@ -221,9 +271,11 @@ pub struct ObjectTexture {
 
 #[repr(C)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct SpriteTexture {
 	/// Index into `Level.atlases`.
 	pub atlas_index: u16,
+	#[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::u8_vec2"))]
 	pub pos: U8Vec2,
 	pub size: U16Vec2,
 	pub world_bounds: [I16Vec2; 2],
@@ -231,6 +283,7 @@ pub struct SpriteTexture {
 
 #[repr(C)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct SpriteSequence {
 	pub id: u32,
 	pub neg_length: i16,
@@ -240,6 +293,7 @@ pub struct SpriteSequence {
 
 #[repr(C)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Camera {
 	pub pos: IVec3,
 	pub room_index: u16,
@@ -248,6 +302,7 @@ pub struct Camera {
 
 #[repr(C)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct SoundSource {
 	pub pos: IVec3,
 	pub sound_id: u16,
@@ -256,6 +311,7 @@ pub struct SoundSource {
 
 #[repr(C)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct TrBox {
 	pub z: MinMax<u32>,
 	pub x: MinMax<u32>,
@@ -264,7 +320,8 @@ pub struct TrBox {
 }
 
 #[repr(C, packed(2))]
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Entity {
 	/// Matched to `Model.id` in `Level.models` or `SpriteSequence.id` in `Level.sprite_sequences`.
 	pub model_id: u16,
@@ -282,6 +339,7 @@ pub struct Entity {
 /// 6 bits per channel
 #[repr(C)]
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Color24Bit {
 	pub r: u8,
 	pub g: u8,
@@ -290,6 +348,7 @@ pub struct Color24Bit {
 
 #[repr(C)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct CinematicFrame {
 	pub target: I16Vec3,
 	pub pos: I16Vec3,
@@ -299,6 +358,7 @@ pub struct CinematicFrame {
 
 #[repr(C)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct SoundDetails {
 	pub sample_index: u16,
 	pub volume: u16,
@@ -307,12 +367,15 @@ pub struct SoundDetails {
 }
 
 #[derive(Readable, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Level {
 	pub version: u32,
+	#[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::blob"))]
 	#[list(u32)] pub atlases: Box<[[u8; ATLAS_PIXELS]]>,
 	pub unused: u32,
 	#[list(u16)] #[delegate] pub rooms: Box<[Room]>,
 	#[list(u32)] pub floor_data: Box<[u16]>,
+	#[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::blob"))]
 	#[list(u32)] pub mesh_data: Box<[u16]>,
 	/// Byte offsets into `Level.mesh_data`.
 	#[list(u32)] pub mesh_offsets: Box<[u32]>,
@@ -334,12 +397,16 @@ pub struct Level {
 	#[list(boxes)] pub zone_data: Box<[[u16; 6]]>,
 	#[list(u32)] pub animated_textures: Box<[u16]>,
 	#[list(u32)] pub entities: Box<[Entity]>,
+	#[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::blob"))]
 	#[boxed] pub light_map: Box<[[u8; PALETTE_LEN]; LIGHT_MAP_LEN]>,
+	#[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::array"))]
 	#[boxed] pub palette: Box<[Color24Bit; PALETTE_LEN]>,
 	#[list(u16)] pub cinematic_frames: Box<[CinematicFrame]>,
 	#[list(u16)] pub demo_data: Box<[u8]>,
+	#[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::array"))]
 	#[boxed] pub sound_map: Box<[u16; SOUND_MAP_LEN]>,
 	#[list(u32)] pub sound_details: Box<[SoundDetails]>,
+	#[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::blob"))]
 	#[list(u32)] pub sample_data: Box<[u8]>,
 	#[list(u32)] pub sample_indices: Box<[u32]>,
 }
@@ -454,7 +521,43 @@ pub struct Frame {
 	pub rotations: [FrameRotation],
 }
 
+impl AnimCommand {
+	fn next<I: Iterator<Item = u16>>(words: &mut I) -> Option<Self> {
+		let mut next = || words.next();
+		let command = match next()? {
+			1 => AnimCommand::SetPosition(I16Vec3::new(next()? as i16, next()? as i16, next()? as i16)),
+			2 => AnimCommand::JumpVelocity { vertical: next()? as i16, horizontal: next()? as i16 },
+			3 => AnimCommand::EmptyHands,
+			4 => AnimCommand::Kill,
+			5 => AnimCommand::PlaySound { frame: next()?, sound_id: next()? },
+			6 => AnimCommand::FlipEffect { frame: next()?, effect: next()? },
+			opcode => AnimCommand::Unknown(opcode),
+		};
+		Some(command)
+	}
+	
+	/// Decode the commands of an animation. Stops early at unknown opcodes or the end of `anim_commands`.
+	pub fn get(anim_commands: &[u16], anim_command_index: u16, num_anim_commands: u16) -> Vec<Self> {
+		let mut words = anim_commands.get(anim_command_index as usize..).unwrap_or_default().iter().copied();
+		let mut commands = Vec::with_capacity(num_anim_commands as usize);
+		for _ in 0..num_anim_commands {
+			let Some(command) = Self::next(&mut words) else {
+				break;
+			};
+			commands.push(command);
+			if let AnimCommand::Unknown(_) = command {
+				break;
+			}
+		}
+		commands
+	}
+}
+
 impl Level {
+	pub fn get_anim_commands(&self, animation: &Animation) -> Vec<AnimCommand> {
+		AnimCommand::get(&self.anim_commands, animation.anim_command_index, animation.num_anim_commands)
+	}
+	
 	pub fn get_mesh(&self, mesh_offset: u32) -> Mesh {
 		Mesh::get(&self.mesh_data, mesh_offset)
 	}
@@ -463,11 +566,17 @@ impl Level {
 		MeshNode::get(&self.mesh_node_data, model.mesh_node_offset, model.num_meshes)
 	}
 	
+	/// Panics if the frame runs past `frame_data`, use `get_frame_at` for untrusted models.
 	pub fn get_frame(&self, model: &Model) -> &Frame {
+		self.get_frame_at(model.frame_byte_offset, model.num_meshes).expect("model frame out of bounds")
+	}
+	
+	/// Frame at any offset, such as a later frame of an animation. `None` if it runs past `frame_data`.
+	pub fn get_frame_at(&self, frame_byte_offset: u32, num_meshes: u16) -> Option<&Frame> {
 		let ptr = self.frame_data
-			[model.frame_byte_offset as usize / 2..]
-			[..10 + model.num_meshes as usize * (size_of::<FrameRotation>() / 2)]//bound check
+			.get(frame_byte_offset as usize / 2..)?
+			.get(..10 + num_meshes as usize * (size_of::<FrameRotation>() / 2))?
 			.as_ptr() as usize;
-		unsafe { transmute([ptr, model.num_meshes as usize]) }//no nice way to make unsized struct
+		Some(unsafe { transmute([ptr, num_meshes as usize]) })//no nice way to make unsized struct
 	}
 }
@@ -4,10 +4,10 @@ use glam::{I16Vec3, IVec3, U16Vec3};
 use shared::min_max::MinMax;
 use tr_readable::Readable;
 use crate::tr1::{
-	decl_mesh, get_packed_angles, AnimDispatch, Animation, Camera, CinematicFrame, Color24Bit, MeshLighting,
-	MeshNode, Model, NumSectors, ObjectTexture, Portal, RoomFlags, Sector, SoundDetails, SoundSource,
-	Sprite, SpriteSequence, SpriteTexture, StateChange, StaticMesh, TexturedQuad, TexturedTri, ATLAS_PIXELS,
-	LIGHT_MAP_LEN, PALETTE_LEN,
+	decl_mesh, get_packed_angles, AnimCommand, AnimDispatch, Animation, Camera, CinematicFrame, Color24Bit,
+	MeshLighting, MeshNode, Model, NumSectors, ObjectTexture, Portal, RoomFlags, Sector, SoundDetails,
+	SoundSource, Sprite, SpriteSequence, SpriteTexture, StateChange, StaticMesh, TexturedQuad, TexturedTri,
+	ATLAS_PIXELS, LIGHT_MAP_LEN, PALETTE_LEN,
 };
 
 pub const SOUND_MAP_LEN: usize = 370;
@@ -16,6 +16,7 @@ pub const SOUND_MAP_LEN: usize = 370;
 
 #[repr(C, align(4))]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Color32BitRgb {
 	pub r: u8,
 	pub g: u8,
@@ -35,6 +36,7 @@ bitfield! {
 
 #[repr(C)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct RoomVertex {
 	/// Relative to room
 	pub pos: I16Vec3,
@@ -45,6 +47,7 @@ pub struct RoomVertex {
 
 #[repr(C)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Light {
 	pub pos: IVec3,
 	pub brightness: u16,
@@ -55,6 +58,7 @@ pub struct Light {
 
 #[repr(C)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct RoomStaticMesh {
 	/// World coords.
 	pub pos: IVec3,
@@ -67,6 +71,7 @@ pub struct RoomStaticMesh {
 }
 
 #[derive(Readable, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Room {
 	/// World coord.
 	pub x: i32,
@@ -94,6 +99,7 @@ pub struct Room {
 
 #[repr(C)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct TrBox {
 	pub z: MinMax<u8>,
 	pub x: MinMax<u8>,
@@ -103,6 +109,7 @@ pub struct TrBox {
 
 #[repr(C)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Entity {
 	/// Matched to `Model.id` in `Level.models` or `SpriteSequence.id` in `Level.sprite_sequences`.
 	pub model_id: u16,
@@ -119,15 +126,21 @@ pub struct Entity {
 }
 
 #[derive(Readable, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Level {
 	pub version: u32,
+	#[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::array"))]
 	#[boxed] pub palette_24bit: Box<[Color24Bit; PALETTE_LEN]>,
+	#[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::array"))]
 	#[boxed] pub palette_32bit: Box<[Color32BitRgb; PALETTE_LEN]>,
+	#[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::blob"))]
 	#[list(u32)] pub atlases_palette: Box<[[u8; ATLAS_PIXELS]]>,
+	#[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::blob"))]
 	#[list(atlases_palette)] pub atlases_16bit: Box<[[Color16BitArgb; ATLAS_PIXELS]]>,
 	pub unused: u32,
 	#[list(u16)] #[delegate] pub rooms: Box<[Room]>,
 	#[list(u32)] pub floor_data: Box<[u16]>,
+	#[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::blob"))]
 	#[list(u32)] pub mesh_data: Box<[u16]>,
 	/// Byte offsets into `Level.mesh_data`.
 	#[list(u32)] pub mesh_offsets: Box<[u32]>,
@@ -149,9 +162,11 @@ pub struct Level {
 	#[list(boxes)] pub zone_data: Box<[[u16; 10]]>,
 	#[list(u32)] pub animated_textures: Box<[u16]>,
 	#[list(u32)] pub entities: Box<[Entity]>,
+	#[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::blob"))]
 	#[boxed] pub light_map: Box<[[u8; PALETTE_LEN]; LIGHT_MAP_LEN]>,
 	#[list(u16)] pub cinematic_frames: Box<[CinematicFrame]>,
 	#[list(u16)] pub demo_data: Box<[u8]>,
+	#[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::array"))]
 	#[boxed] pub sound_map: Box<[u16; SOUND_MAP_LEN]>,
 	#[list(u32)] pub sound_details: Box<[SoundDetails]>,
 	#[list(u32)] pub sample_indices: Box<[u32]>,
@@ -219,10 +234,11 @@ macro_rules! decl_frame {
 					return None;
 				}
 				self.remaining -= 1;
-				let word1 = *self.rotation_data.next().unwrap();
+				//frames near the end of frame_data may be cut short
+				let word1 = *self.rotation_data.next()?;
 				let rotation = match word1 >> 14 {
 					0 => {
-						let word2 = *self.rotation_data.next().unwrap();
+						let word2 = *self.rotation_data.next()?;
 						let angles = get_packed_angles(word1, word2);
 						Self::Item::AllAxes(angles)
 					},
@@ -248,6 +264,12 @@ macro_rules! decl_frame {
 				Self { num_meshes: num_meshes as usize, frame_data }
 			}
 			
+			/// `None` if the frame header runs past `frame_data`.
+			pub(crate) fn get_checked(frame_data: &'a [u16], frame_byte_offset: u32, num_meshes: u16) -> Option<Self> {
+				let header_end = frame_byte_offset as usize / 2 + 9;
+				(header_end <= frame_data.len()).then(|| Self::get(frame_data, frame_byte_offset, num_meshes))
+			}
+			
 			pub fn iter_rotations(&self) -> $rotation_iterator<'a> {
 				$rotation_iterator {
 					rotation_data: self.frame_data.rotation_data.iter(),
@@ -262,6 +284,10 @@ pub(crate) use decl_frame;
 decl_frame!(Frame, RotationIterator, FrameRotation, 0x3FF);
 
 impl Level {
+	pub fn get_anim_commands(&self, animation: &Animation) -> Vec<AnimCommand> {
+		AnimCommand::get(&self.anim_commands, animation.anim_command_index, animation.num_anim_commands)
+	}
+	
 	pub fn get_mesh(&self, mesh_offset: u32) -> Mesh {
 		Mesh::get(&self.mesh_data, mesh_offset)
 	}
@@ -273,4 +299,8 @@ impl Level {
 	pub fn get_frame(&self, model: &Model) -> Frame {
 		Frame::get(&self.frame_data, model.frame_byte_offset, model.num_meshes)
 	}
+	
+	pub fn get_frame_at(&self, frame_byte_offset: u32, num_meshes: u16) -> Option<Frame<'_>> {
+		Frame::get_checked(&self.frame_data, frame_byte_offset, num_meshes)
+	}
 }
@@ -3,7 +3,7 @@ use glam::{I16Vec3, IVec3};
 use tr_readable::Readable;
 use crate::{
 	tr1::{
-		AnimDispatch, Animation, Camera, CinematicFrame, Color24Bit, MeshNode, Model, NumSectors,
+		AnimCommand, AnimDispatch, Animation, Camera, CinematicFrame, Color24Bit, MeshNode, Model, NumSectors,
 		ObjectTexture, Portal, RoomFlags, Sector, SoundSource, Sprite, SpriteSequence, SpriteTexture,
 		StateChange, StaticMesh, ATLAS_PIXELS, LIGHT_MAP_LEN, PALETTE_LEN,
 	},
@@ -25,6 +25,7 @@ pub mod light_type {
 
 #[repr(C)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct RoomVertex {
 	/// Relative to room
 	pub pos: I16Vec3,
@@ -45,6 +46,7 @@ macro_rules! decl_face_type {
 	($name:ident, $num_indices:literal) => {
 		#[repr(C)]
 		#[derive(Clone, Debug)]
+		#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 		pub struct $name {
 			pub vertex_indices: [u16; $num_indices],
 			pub texture: DsFaceTexture,
@@ -57,6 +59,7 @@ decl_face_type!(DsTri, 3);
 
 #[repr(C)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Light {
 	pub pos: IVec3,
 	pub color: Color24Bit,
@@ -77,6 +80,7 @@ bitfield! {
 
 #[repr(C)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct RoomStaticMesh {
 	/// World coords.
 	pub pos: IVec3,
@@ -89,6 +93,7 @@ pub struct RoomStaticMesh {
 }
 
 #[derive(Readable, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Room {
 	/// World coord.
 	pub x: i32,
@@ -118,6 +123,7 @@ pub struct Room {
 
 #[repr(C)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct SoundDetails {
 	/// Index into `Level.sample_indices`.
 	pub sample_index: u16,
@@ -129,15 +135,21 @@ pub struct SoundDetails {
 }
 
 #[derive(Readable, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Level {
 	pub version: u32,
+	#[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::array"))]
 	#[boxed] pub palette_24bit: Box<[Color24Bit; PALETTE_LEN]>,
+	#[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::array"))]
 	#[boxed] pub palette_32bit: Box<[Color32BitRgb; PALETTE_LEN]>,
+	#[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::blob"))]
 	#[list(u32)] pub atlases_palette: Box<[[u8; ATLAS_PIXELS]]>,
+	#[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::blob"))]
 	#[list(atlases_palette)] pub atlases_16bit: Box<[[Color16BitArgb; ATLAS_PIXELS]]>,
 	pub unused: u32,
 	#[list(u16)] #[delegate] pub rooms: Box<[Room]>,
 	#[list(u32)] pub floor_data: Box<[u16]>,
+	#[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::blob"))]
 	#[list(u32)] pub mesh_data: Box<[u16]>,
 	/// Byte offsets into `Level.mesh_data`.
 	#[list(u32)] pub mesh_offsets: Box<[u32]>,
@@ -159,9 +171,11 @@ pub struct Level {
 	#[list(u32)] pub animated_textures: Box<[u16]>,
 	#[list(u32)] pub object_textures: Box<[ObjectTexture]>,
 	#[list(u32)] pub entities: Box<[Entity]>,
+	#[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::blob"))]
 	#[boxed] pub light_map: Box<[[u8; PALETTE_LEN]; LIGHT_MAP_LEN]>,
 	#[list(u16)] pub cinematic_frames: Box<[CinematicFrame]>,
 	#[list(u16)] pub demo_data: Box<[u8]>,
+	#[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::array"))]
 	#[boxed] pub sound_map: Box<[u16; SOUND_MAP_LEN]>,
 	#[list(u32)] pub sound_details: Box<[SoundDetails]>,
 	#[list(u32)] pub sample_indices: Box<[u32]>,
@@ -170,6 +184,10 @@ pub struct Level {
 //extraction
 
 impl Level {
+	pub fn get_anim_commands(&self, animation: &Animation) -> Vec<AnimCommand> {
+		AnimCommand::get(&self.anim_commands, animation.anim_command_index, animation.num_anim_commands)
+	}
+	
 	pub fn get_mesh(&self, mesh_offset: u32) -> Mesh {
 		Mesh::get(&self.mesh_data, mesh_offset)
 	}
@@ -181,4 +199,8 @@ impl Level {
 	pub fn get_frame(&self, model: &Model) -> Frame {
 		Frame::get(&self.frame_data, model.frame_byte_offset, model.num_meshes)
 	}
+	
+	pub fn get_frame_at(&self, frame_byte_offset: u32, num_meshes: u16) -> Option<Frame<'_>> {
+		Frame::get_checked(&self.frame_data, frame_byte_offset, num_meshes)
+	}
 }
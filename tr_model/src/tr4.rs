@@ -4,7 +4,7 @@ use glam::{I16Vec3, IVec3, U16Vec2, U16Vec3, UVec2, Vec3};
 use tr_readable::{read_into, Readable, ToLen};
 use crate::{
 	tr1::{
-		get_packed_angles, AnimDispatch, Camera, Color24Bit, MeshLighting, MeshNode, Model, NumSectors,
+		get_packed_angles, AnimCommand, AnimDispatch, Camera, Color24Bit, MeshLighting, MeshNode, Model, NumSectors,
 		Portal, RoomFlags, Sector, SoundSource, Sprite, SpriteSequence, SpriteTexture, StateChange,
 		StaticMesh, ATLAS_PIXELS,
 	},
@@ -15,9 +15,20 @@ use crate::{
 
 pub const EXTENDED_SOUND_MAP_LEN: usize = 1024;
 
+/// TR4 and TR5 extend TR3's modes. TRNG levels use further values, which are not listed.
+pub mod blend_mode {
+	pub use crate::tr3::blend_mode::*;
+	/// Not used by the original levels.
+	pub const UNUSED: u16 = 3;
+	pub const ADD_NO_DEPTH_TEST: u16 = 4;
+	pub const SUBTRACT: u16 = 5;
+	pub const WIREFRAME: u16 = 6;
+}
+
 //model
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct NumAtlases {
 	pub num_room_atlases: u16,
 	pub num_obj_atlases: u16,
@@ -32,6 +43,7 @@ impl ToLen for NumAtlases {
 
 #[repr(C, align(4))]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Color32BitBgra {
 	pub b: u8,
 	pub g: u8,
@@ -40,7 +52,8 @@ pub struct Color32BitBgra {
 }
 
 #[repr(C, packed(2))]
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Light {
 	pub pos: IVec3,
 	pub color: Color24Bit,
@@ -55,6 +68,7 @@ pub struct Light {
 }
 
 #[derive(Readable, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Room {
 	/// World coord.
 	pub x: i32,
@@ -82,12 +96,14 @@ pub struct Room {
 
 #[repr(C)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Animation {
 	/// Byte offset into `Level.frame_data`.
 	pub frame_byte_offset: u32,
 	/// 30ths of a second.
 	pub frame_duration: u8,
-	pub num_frames: u8,
+	/// Length of each frame in u16s.
+	pub frame_size: u8,
 	pub state: u16,
 	/// Fixed-point.
 	pub speed: u32,
@@ -109,6 +125,7 @@ pub struct Animation {
 
 #[repr(C)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct FlybyCamera {
 	pub pos: IVec3,
 	pub direction: IVec3,
@@ -132,7 +149,8 @@ bitfield! {
 }
 
 #[repr(C, packed(2))]
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ObjectTexture {
 	/// One of the blend modes in the `blend_mode` module.
 	pub blend_mode: u16,
@@ -147,6 +165,7 @@ pub struct ObjectTexture {
 
 #[repr(C)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Entity {
 	/// Matched to `Model.id` in `Level.models` or `SpriteSequence.id` in `Level.sprite_sequences`.
 	pub model_id: u16,
@@ -163,6 +182,7 @@ pub struct Entity {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Ai {
 	pub model_id: u16,
 	/// Index into `LevelData.rooms`.
@@ -175,8 +195,11 @@ pub struct Ai {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum SoundMap {
+	#[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::array"))]
 	Original(Box<[u16; SOUND_MAP_LEN]>),
+	#[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::array"))]
 	Extended(Box<[u16; EXTENDED_SOUND_MAP_LEN]>),
 }
 
@@ -194,10 +217,12 @@ unsafe fn read_sound_map<R: Read>(reader: &mut R, this: *mut SoundMap, demo_data
 }
 
 #[derive(Readable, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct LevelData {
 	pub unused: u32,
 	#[list(u16)] #[delegate] pub rooms: Box<[Room]>,
 	#[list(u32)] pub floor_data: Box<[u16]>,
+	#[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::blob"))]
 	#[list(u32)] pub mesh_data: Box<[u16]>,
 	/// Byte offsets into `Level.mesh_data`.
 	#[list(u32)] pub mesh_offsets: Box<[u32]>,
@@ -232,17 +257,23 @@ pub struct LevelData {
 }
 
 #[derive(Readable, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Sample {
 	pub uncompressed_size: u32,
+	#[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::blob"))]
 	#[list(u32)] pub data: Box<[u8]>,
 }
 
 #[derive(Readable, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Level {
 	pub version: u32,
 	pub num_atlases: NumAtlases,
+	#[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::blob"))]
 	#[zlib] #[list(num_atlases)] pub atlases_32bit: Box<[[Color32BitBgra; ATLAS_PIXELS]]>,
+	#[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::blob"))]
 	#[zlib] #[list(num_atlases)] pub atlases_16bit: Box<[[Color16BitArgb; ATLAS_PIXELS]]>,
+	#[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::blob"))]
 	#[zlib] #[boxed] pub misc_images: Box<[[Color32BitBgra; ATLAS_PIXELS]; 2]>,
 	#[zlib] #[delegate] pub level_data: LevelData,
 	#[list(u32)] #[delegate] pub samples: Box<[Sample]>,
@@ -255,6 +286,7 @@ bitfield! {
 	#[derive(Clone, Debug)]
 	pub struct FaceEffects(u16);
 	pub additive, _: 0;
+	pub u8, shine_strength, _: 7, 1;
 }
 
 macro_rules! decl_face_type {
@@ -305,6 +337,10 @@ impl<'a> Mesh<'a> {
 decl_frame!(Frame, RotationIterator, FrameRotation, 0xFFF);
 
 impl Level {
+	pub fn get_anim_commands(&self, animation: &Animation) -> Vec<AnimCommand> {
+		AnimCommand::get(&self.level_data.anim_commands, animation.anim_command_index, animation.num_anim_commands)
+	}
+	
 	pub fn get_mesh(&self, mesh_offset: u32) -> Mesh {
 		Mesh::get(&self.level_data.mesh_data, mesh_offset)
 	}
@@ -316,4 +352,8 @@ impl Level {
 	pub fn get_frame(&self, model: &Model) -> Frame {
 		Frame::get(&self.level_data.frame_data, model.frame_byte_offset, model.num_meshes)
 	}
+	
+	pub fn get_frame_at(&self, frame_byte_offset: u32, num_meshes: u16) -> Option<Frame<'_>> {
+		Frame::get_checked(&self.level_data.frame_data, frame_byte_offset, num_meshes)
+	}
 }
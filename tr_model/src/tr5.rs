@@ -1,12 +1,13 @@
-use std::io::{Error, Read, Result, Seek, SeekFrom};
+use std::io::{Read, Result, Seek, SeekFrom};
 use bitfield::bitfield;
 use glam::{IVec3, U16Vec2, UVec2, Vec3};
 use shared::min_max::MinMax;
 use tr_readable::{read_slice_get, Readable, ToLen};
 use crate::{
+	error::TrError,
 	tr1::{
-		AnimDispatch, Camera, MeshNode, NumSectors, Portal, RoomFlags, Sector, SoundSource, SpriteSequence,
-		SpriteTexture, StateChange, StaticMesh, ATLAS_PIXELS,
+		AnimCommand, AnimDispatch, Camera, MeshNode, NumSectors, Portal, RoomFlags, Sector, SoundSource,
+		SpriteSequence, SpriteTexture, StateChange, StaticMesh, ATLAS_PIXELS,
 	},
 	tr2::{Color16BitArgb, TrBox},
 	tr3::{RoomStaticMesh, SoundDetails},
@@ -17,11 +18,14 @@ use crate::{
 };
 
 pub const SOUND_MAP_LEN: usize = 450;
+/// Uninitialized memory written in place of some room fields, mostly in unused placeholder rooms.
+pub const FILLER: u32 = 0xCDCDCDCD;
 
 //model
 
 #[repr(C)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct RoomVertex {
 	pub pos: Vec3,
 	pub normal: Vec3,
@@ -30,20 +34,36 @@ pub struct RoomVertex {
 
 #[repr(C)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct NumVertexBytes(pub u32);
 
 impl ToLen for NumVertexBytes {
 	fn get_len(&self) -> Result<usize> {
-		if self.0 as usize % size_of::<RoomVertex>() == 0 {
+		if self.0 == FILLER {
+			Ok(0)
+		} else if self.0 as usize % size_of::<RoomVertex>() == 0 {
 			Ok(self.0 as usize / size_of::<RoomVertex>())
 		} else {
-			Err(Error::other("tr5 room num vertex bytes not multiple of room vertex size"))
+			Err(TrError::Invalid("tr5 room num vertex bytes not multiple of room vertex size").into())
 		}
 	}
 }
 
+/// Room list length, `FILLER` read as 0.
 #[repr(C)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RoomCount(pub u32);
+
+impl ToLen for RoomCount {
+	fn get_len(&self) -> Result<usize> {
+		Ok(if self.0 == FILLER { 0 } else { self.0 as usize })
+	}
+}
+
+#[repr(C)]
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Light {
 	pub pos: Vec3,
 	pub color: Vec3,
@@ -62,6 +82,7 @@ pub struct Light {
 
 #[repr(C)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct FogBulb {
 	pub pos: Vec3,
 	pub color: Vec3,
@@ -72,6 +93,7 @@ pub struct FogBulb {
 
 #[repr(C)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Layer {
 	pub num_vertices: u16,
 	pub unused1: [u16; 2],
@@ -94,6 +116,7 @@ macro_rules! decl_face_type {
 	($name:ident, $num_indices:literal) => {
 		#[repr(C)]
 		#[derive(Clone, Debug)]
+		#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 		pub struct $name {
 			pub vertex_indices: [u16; $num_indices],
 			pub texture: EffectsFaceTexture,
@@ -106,6 +129,7 @@ decl_face_type!(EffectsQuad, 4);
 decl_face_type!(EffectsTri, 3);
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct LayerFaces {
 	pub quads: Box<[EffectsQuad]>,
 	pub tris: Box<[EffectsTri]>,
@@ -126,6 +150,7 @@ unsafe fn read_faces<R: Read + Seek>(
 }
 
 #[derive(Readable, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Room {
 	pub xela: [u8; 4],
 	pub size: u32,
@@ -152,10 +177,10 @@ pub struct Room {
 	pub num_tris: u32,
 	pub num_quads: u32,
 	pub unused6: [u32; 3],
-	pub num_fog_bulbs: u32,
+	pub num_fog_bulbs: RoomCount,
 	pub y_top2: f32,
 	pub y_bottom2: f32,
-	pub num_layers: u32,
+	pub num_layers: RoomCount,
 	pub layers_offset: u32,
 	pub vertices_offset: u32,
 	pub faces_offset: u32,
@@ -172,8 +197,16 @@ pub struct Room {
 	#[seek(data_start2, faces_offset)] #[delegate(read_faces, layers, size, data_start)] pub layer_faces: Box<[LayerFaces]>,
 }
 
+impl Room {
+	/// Whether any list length was `FILLER` and read as empty.
+	pub fn has_filler(&self) -> bool {
+		[self.num_fog_bulbs.0, self.num_layers.0, self.num_vertex_bytes.0].contains(&FILLER)
+	}
+}
+
 #[repr(C)]
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Model {
 	pub id: u32,
 	pub num_meshes: u16,
@@ -189,7 +222,8 @@ pub struct Model {
 }
 
 #[repr(C, packed(2))]
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ObjectTexture {
 	/// One of the blend modes in the `blend_mode` module.
 	pub blend_mode: u16,
@@ -204,11 +238,15 @@ pub struct ObjectTexture {
 }
 
 #[derive(Readable, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Level {
 	pub version: u32,
 	pub num_atlases: NumAtlases,
+	#[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::blob"))]
 	#[zlib] #[list(num_atlases)] pub atlases_32bit: Box<[[Color32BitBgra; ATLAS_PIXELS]]>,
+	#[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::blob"))]
 	#[zlib] #[list(num_atlases)] pub atlases_16bit: Box<[[Color16BitArgb; ATLAS_PIXELS]]>,
+	#[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::blob"))]
 	#[zlib] #[boxed] pub misc_images: Box<[[Color32BitBgra; ATLAS_PIXELS]; 3]>,
 	pub lara_type: u16,
 	pub weather_type: u16,
@@ -218,6 +256,7 @@ pub struct Level {
 	pub unused: u32,
 	#[list(u32)] #[delegate] pub rooms: Box<[Room]>,
 	#[list(u32)] pub floor_data: Box<[u16]>,
+	#[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::blob"))]
 	#[list(u32)] pub mesh_data: Box<[u16]>,
 	#[list(u32)] pub mesh_offsets: Box<[u32]>,
 	#[list(u32)] pub animations: Box<[Animation]>,
@@ -244,6 +283,7 @@ pub struct Level {
 	#[list(u32)] pub entities: Box<[Entity]>,
 	#[list(u32)] pub ais: Box<[Ai]>,
 	#[list(u16)] pub demo_data: Box<[u8]>,
+	#[cfg_attr(feature = "serde", serde(serialize_with = "crate::ser::array"))]
 	#[boxed] pub sound_map: Box<[u16; SOUND_MAP_LEN]>,
 	#[list(u32)] pub sound_details: Box<[SoundDetails]>,
 	#[list(u32)] pub sample_indices: Box<[u32]>,
@@ -252,6 +292,10 @@ pub struct Level {
 }
 
 impl Level {
+	pub fn get_anim_commands(&self, animation: &Animation) -> Vec<AnimCommand> {
+		AnimCommand::get(&self.anim_commands, animation.anim_command_index, animation.num_anim_commands)
+	}
+	
 	pub fn get_mesh(&self, mesh_offset: u32) -> Mesh {
 		Mesh::get(&self.mesh_data, mesh_offset)
 	}
@@ -263,4 +307,79 @@ impl Level {
 	pub fn get_frame(&self, model: &Model) -> Frame {
 		Frame::get(&self.frame_data, model.frame_byte_offset, model.num_meshes)
 	}
+	
+	pub fn get_frame_at(&self, frame_byte_offset: u32, num_meshes: u16) -> Option<Frame<'_>> {
+		Frame::get_checked(&self.frame_data, frame_byte_offset, num_meshes)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{io::Cursor, mem::MaybeUninit};
+	use super::*;
+	
+	const HEADER_SIZE: usize = 216;
+	
+	fn put_u32(bytes: &mut [u8], offset: usize, val: u32) {
+		bytes[offset..offset + 4].copy_from_slice(&val.to_le_bytes());
+	}
+	
+	/// Placeholder room as found in retail levels: header filled with `FILLER`, no lights, statics or sectors,
+	/// an empty portal list, and all data offsets pointing at it.
+	fn placeholder_room() -> Vec<u8> {
+		let mut bytes = vec![0xCD; HEADER_SIZE];
+		bytes[..4].copy_from_slice(b"XELA");
+		put_u32(&mut bytes, 4, (HEADER_SIZE - 8 + 2) as u32);
+		for offset in [16, 24, 180, 184, 188] {
+			put_u32(&mut bytes, offset, 0);//data offsets
+		}
+		put_u32(&mut bytes, 48, 0);//num sectors
+		put_u32(&mut bytes, 56, 0);//num lights, num room static meshes
+		bytes.extend(0u16.to_le_bytes());//num portals
+		bytes
+	}
+	
+	fn read_room(bytes: &[u8]) -> Result<(Room, u64)> {
+		let mut reader = Cursor::new(bytes);
+		let mut room = MaybeUninit::<Room>::uninit();
+		unsafe {
+			Room::read(&mut reader, room.as_mut_ptr())?;
+			Ok((room.assume_init(), reader.position()))
+		}
+	}
+	
+	#[test]
+	fn filler_counts_read_as_empty() {
+		assert_eq!(RoomCount(FILLER).get_len().unwrap(), 0);
+		assert_eq!(RoomCount(3).get_len().unwrap(), 3);
+		assert_eq!(NumVertexBytes(FILLER).get_len().unwrap(), 0);
+		assert_eq!(NumVertexBytes(size_of::<RoomVertex>() as u32 * 2).get_len().unwrap(), 2);
+		assert!(NumVertexBytes(size_of::<RoomVertex>() as u32 + 1).get_len().is_err());
+	}
+	
+	#[test]
+	fn placeholder_room_reads_empty() {
+		let mut bytes = placeholder_room();
+		let room_end = bytes.len() as u64;
+		bytes.extend(*b"next");
+		let (room, pos) = read_room(&bytes).unwrap();
+		assert!(room.has_filler());
+		assert!(room.fog_bulbs.is_empty());
+		assert!(room.layers.is_empty());
+		assert!(room.vertices.is_empty());
+		assert!(room.layer_faces.is_empty());
+		assert!(room.portals.is_empty());
+		assert_eq!(pos, room_end);
+	}
+	
+	#[test]
+	fn room_ends_at_declared_size() {
+		let mut bytes = placeholder_room();
+		put_u32(&mut bytes, 4, (HEADER_SIZE - 8 + 6) as u32);
+		bytes.extend([0; 4]);//trailing data not covered by any offset
+		let room_end = bytes.len() as u64;
+		bytes.extend(*b"next");
+		let (_, pos) = read_room(&bytes).unwrap();
+		assert_eq!(pos, room_end);
+	}
 }
@@ -0,0 +1,201 @@
+use std::collections::HashSet;
+use glam::U16Vec2;
+use crate::tr_traits::{Level, Mesh, ObjectTexture, Room, RoomGeom, TexturedFace};
+
+#[derive(Clone, Copy, Default)]
+struct Usage {
+	quad: bool,
+	tri: bool,
+}
+
+/// Object textures with UVs that render incorrectly, by object texture index.
+#[derive(Default)]
+pub struct ObjectTextureIssues {
+	/// UVs collapse to a point or line.
+	pub zero_area: Vec<u16>,
+	/// UVs wind opposite to the majority of object textures in the level.
+	pub flipped: Vec<u16>,
+	/// TR4-5 tri flag set but used by quads, or unset but used only by tris.
+	pub tri_flag_mismatch: Vec<u16>,
+}
+
+impl ObjectTextureIssues {
+	pub fn is_empty(&self) -> bool {
+		self.zero_area.is_empty() && self.flipped.is_empty() && self.tri_flag_mismatch.is_empty()
+	}
+	
+	pub fn has_issue(&self, object_texture_index: u16) -> bool {
+		[&self.zero_area, &self.flipped, &self.tri_flag_mismatch]
+			.iter()
+			.any(|list| list.contains(&object_texture_index))
+	}
+	
+	/// Whether each object texture has any issue.
+	pub fn degenerate(&self, num_object_textures: usize) -> Vec<bool> {
+		let mut degenerate = vec![false; num_object_textures];
+		for &index in self.zero_area.iter().chain(&self.flipped).chain(&self.tri_flag_mismatch) {
+			degenerate[index as usize] = true;
+		}
+		degenerate
+	}
+}
+
+/// Twice the signed area of the UV polygon. Tri textures use the first three UVs.
+fn uv_area(uvs: [U16Vec2; 4], tri: bool) -> i64 {
+	let uvs = if tri { &uvs[..3] } else { &uvs[..] };
+	(0..uvs.len())
+		.map(|i| {
+			let [a, b] = [uvs[i], uvs[(i + 1) % uvs.len()]].map(|uv| uv.as_i64vec2());
+			a.x * b.y - b.x * a.y
+		})
+		.sum()
+}
+
+fn mark<F: TexturedFace>(usage: &mut [Usage], faces: &[F], tri: bool) {
+	for face in faces {
+		//out of bounds indices are left to rendering
+		if let Some(usage) = usage.get_mut(face.object_texture_index() as usize) {
+			if tri {
+				usage.tri = true;
+			} else {
+				usage.quad = true;
+			}
+		}
+	}
+}
+
+fn object_texture_usage<L: Level>(level: &L) -> Vec<Usage> {
+	let mut usage = vec![Usage::default(); level.object_textures().len()];
+	for room in level.rooms() {
+		for RoomGeom { quads, tris, .. } in room.geom() {
+			mark(&mut usage, quads, false);
+			mark(&mut usage, tris, true);
+		}
+	}
+	let mesh_offsets = level.mesh_offsets().iter().copied().collect::<HashSet<_>>();
+	for mesh_offset in mesh_offsets {
+		let mesh = level.get_mesh(mesh_offset);
+		mark(&mut usage, mesh.textured_quads(), false);
+		mark(&mut usage, mesh.textured_tris(), true);
+	}
+	usage
+}
+
+/// Classify object texture UVs, comparing winding against the level's majority and the tri flag against
+/// face usage.
+pub fn object_texture_issues<L: Level>(level: &L) -> ObjectTextureIssues {
+	classify(level.object_textures(), &object_texture_usage(level))
+}
+
+fn classify<O: ObjectTexture>(object_textures: &[O], usage: &[Usage]) -> ObjectTextureIssues {
+	let areas = object_textures
+		.iter()
+		.zip(usage)
+		.map(|(object_texture, usage)| {
+			let tri = object_texture.tri().unwrap_or(usage.tri && !usage.quad);
+			uv_area(object_texture.uvs(), tri)
+		})
+		.collect::<Vec<_>>();
+	let positive = areas.iter().filter(|&&area| area > 0).count();
+	let negative = areas.iter().filter(|&&area| area < 0).count();
+	let majority_sign = if positive >= negative { 1 } else { -1 };
+	let mut issues = ObjectTextureIssues::default();
+	for (index, ((object_texture, usage), &area)) in object_textures.iter().zip(usage).zip(&areas).enumerate() {
+		let index = index as u16;
+		if area == 0 {
+			issues.zero_area.push(index);
+		} else if area.signum() != majority_sign {
+			issues.flipped.push(index);
+		}
+		let mismatch = match object_texture.tri() {
+			Some(true) => usage.quad,
+			Some(false) => usage.tri && !usage.quad,
+			None => false,
+		};
+		if mismatch {
+			issues.tri_flag_mismatch.push(index);
+		}
+	}
+	issues
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::as_bytes::ReinterpretAsBytes;
+	use super::*;
+	
+	struct TestObjectTexture {
+		uvs: [U16Vec2; 4],
+		tri: Option<bool>,
+	}
+	
+	impl ReinterpretAsBytes for TestObjectTexture {}
+	
+	impl ObjectTexture for TestObjectTexture {
+		const UVS_OFFSET: u32 = 0;
+		fn blend_mode(&self) -> u16 { 0 }
+		fn atlas_index(&self) -> u16 { 0 }
+		fn uvs(&self) -> [U16Vec2; 4] { self.uvs }
+		fn tri(&self) -> Option<bool> { self.tri }
+	}
+	
+	/// Positive area.
+	const SQUARE: [[u16; 2]; 4] = [[0, 0], [0x100, 0], [0x100, 0x100], [0, 0x100]];
+	const QUAD: Usage = Usage { quad: true, tri: false };
+	const TRI: Usage = Usage { quad: false, tri: true };
+	const BOTH: Usage = Usage { quad: true, tri: true };
+	
+	fn object_texture(uvs: [[u16; 2]; 4], tri: Option<bool>) -> TestObjectTexture {
+		TestObjectTexture { uvs: uvs.map(U16Vec2::from_array), tri }
+	}
+	
+	#[test]
+	fn zero_area() {
+		let line = [[0, 0], [0x100, 0], [0x200, 0], [0x300, 0]];
+		//the first three UVs are on a line, the fourth only counts for quads
+		let tri_line = [[0, 0], [0x100, 0x100], [0x200, 0x200], [0, 0x200]];
+		let object_textures = [
+			object_texture(SQUARE, None),
+			object_texture(line, None),
+			object_texture(tri_line, None),
+			object_texture(tri_line, None),
+			object_texture(tri_line, Some(true)),
+		];
+		let issues = classify(&object_textures, &[QUAD, QUAD, TRI, BOTH, QUAD]);
+		assert_eq!(issues.zero_area, [1, 2, 4]);
+		assert!(issues.flipped.is_empty());
+	}
+	
+	#[test]
+	fn flipped_winding() {
+		let reversed = SQUARE.map(|[u, v]| [v, u]);
+		let object_textures = [
+			object_texture(SQUARE, None),
+			object_texture(reversed, None),
+			object_texture(SQUARE, None),
+		];
+		let issues = classify(&object_textures, &[QUAD; 3]);
+		assert_eq!(issues.flipped, [1]);
+		assert!(issues.zero_area.is_empty() && issues.tri_flag_mismatch.is_empty());
+		//the majority decides which winding is flipped, a tie keeps the positive one
+		let object_textures = [
+			object_texture(reversed, None),
+			object_texture(SQUARE, None),
+			object_texture(reversed, None),
+		];
+		assert_eq!(classify(&object_textures, &[QUAD; 3]).flipped, [1]);
+		assert_eq!(classify(&object_textures[..2], &[QUAD; 2]).flipped, [0]);
+	}
+	
+	#[test]
+	fn tri_flag_mismatch() {
+		let usages = [QUAD, TRI, BOTH];
+		let flagged = |tri| usages.map(|_| object_texture(SQUARE, tri));
+		//set but used by quads
+		assert_eq!(classify(&flagged(Some(true)), &usages).tri_flag_mismatch, [0, 2]);
+		//unset but used only by tris
+		assert_eq!(classify(&flagged(Some(false)), &usages).tri_flag_mismatch, [1]);
+		//TR1-3 have no flag
+		assert!(classify(&flagged(None), &usages).tri_flag_mismatch.is_empty());
+	}
+}
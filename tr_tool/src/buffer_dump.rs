@@ -0,0 +1,117 @@
+use std::{fmt::Write, fs, io::Result, path::Path};
+use wgpu::{Buffer, BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Device, Maintain, MapMode, Queue};
+use crate::{face_instance::{self, DecodedFaceInstance}, LoadedLevel};
+
+/// Written to `layout.txt` ahead of the statics read back from the GPU.
+const LAYOUT: &str = "\
+geom_page_N.bin: storage buffer bound for geometry page N, all pages the same size.
+  [G..][P..][T..][L..][F..][O..][S..]
+  G: geometry of the page, 4-aligned records of
+     vertex array: SSSS[V..], S vertex size in 2-byte units, V vertices
+     face array: VVVVSSTT[F..], V vertex array offset in 4-byte units, S face size in 2-byte units,
+       T texture offset in 2-byte units, F faces
+  P: padding to align-16 past the largest page's geometry, so the tables are at the same offsets in all pages
+  T: transform matrices, 64 bytes each, column-major f32
+  L: transform lights, u32 each, RGB8 in the low bytes
+  F: face array offsets, u32 in 4-byte units into the page
+  O: object textures, as in the level file
+  S: sprite textures, as in the level file
+face_instances.bin: vertex buffer, 12 bytes per instance
+  u16 face_array_index, u16 face_index, u16 transform_index, u16 flags, u32 object_data_index
+  flags: bit 0 degenerate texture, bits 1-7 shine strength, bits 8-15 geometry page
+sprite_instances.bin: vertex buffer, 20 bytes per instance
+  i32 x, i32 y, i32 z, u32 sprite_texture_index, u32 object_data_index
+";
+
+const STATICS_NAMES: [&str; 9] = [
+	"transforms_offset (16-byte units)",
+	"transform_lights_offset (4-byte units)",
+	"face_array_offsets_offset (4-byte units)",
+	"object_textures_offset (2-byte units)",
+	"object_texture_size (2-byte units)",
+	"sprite_textures_offset (2-byte units)",
+	"num_atlases",
+	"render_flags",
+	"highlight_object_texture",
+];
+
+/// Contents of a buffer created with `COPY_SRC`, waiting for the GPU.
+fn read_buffer(device: &Device, queue: &Queue, buffer: &Buffer) -> Vec<u8> {
+	let staging = device.create_buffer(&BufferDescriptor {
+		label: None,
+		size: buffer.size(),
+		usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+		mapped_at_creation: false,
+	});
+	let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor::default());
+	encoder.copy_buffer_to_buffer(buffer, 0, &staging, 0, buffer.size());
+	let submission_index = queue.submit([encoder.finish()]);
+	staging.slice(..).map_async(MapMode::Read, |r| r.expect("map dumped buffer"));
+	device.poll(Maintain::WaitForSubmissionIndex(submission_index));
+	let bytes = staging.slice(..).get_mapped_range().to_vec();
+	bytes
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+	u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+}
+
+/// One line per instance, fields in buffer order with the flags decoded.
+fn face_instances_text(bytes: &[u8]) -> String {
+	let mut text =
+		"face_array_index face_index transform_index degenerate shine page object_data_index\n".to_string();
+	for instance in bytes.chunks_exact(12) {
+		let DecodedFaceInstance {
+			face_array_index,
+			face_index,
+			transform_index,
+			degenerate_texture,
+			shine_strength,
+			page,
+			object_data_index,
+		} = face_instance::decode([0, 4, 8].map(|offset| u32_at(instance, offset)));
+		writeln!(
+			text,
+			"{} {} {} {} {} {} {}",
+			face_array_index,
+			face_index,
+			transform_index,
+			degenerate_texture as u8,
+			shine_strength,
+			page,
+			object_data_index,
+		).unwrap();
+	}
+	text
+}
+
+fn sprite_instances_text(bytes: &[u8]) -> String {
+	let mut text = "x y z sprite_texture_index object_data_index\n".to_string();
+	for instance in bytes.chunks_exact(20) {
+		let [x, y, z] = [0, 4, 8].map(|offset| u32_at(instance, offset) as i32);
+		writeln!(text, "{} {} {} {} {}", x, y, z, u32_at(instance, 12), u32_at(instance, 16)).unwrap();
+	}
+	text
+}
+
+/// Write the geometry pages, face and sprite instances and statics as the GPU holds them to `out_dir`, raw and
+/// as text, with the layout the shader expects.
+pub fn dump_buffers(device: &Device, queue: &Queue, loaded_level: &LoadedLevel, out_dir: &Path) -> Result<()> {
+	fs::create_dir_all(out_dir)?;
+	for (page, data_buffer) in loaded_level.geom_data_buffers.iter().enumerate() {
+		fs::write(out_dir.join(format!("geom_page_{}.bin", page)), read_buffer(device, queue, data_buffer))?;
+	}
+	let face_instances = read_buffer(device, queue, &loaded_level.face_instance_buffer);
+	fs::write(out_dir.join("face_instances.bin"), &face_instances)?;
+	fs::write(out_dir.join("face_instances.txt"), face_instances_text(&face_instances))?;
+	let sprite_instances = read_buffer(device, queue, &loaded_level.sprite_instance_buffer);
+	fs::write(out_dir.join("sprite_instances.bin"), &sprite_instances)?;
+	fs::write(out_dir.join("sprite_instances.txt"), sprite_instances_text(&sprite_instances))?;
+	let statics = read_buffer(device, queue, &loaded_level.statics_buffer);
+	let mut layout = LAYOUT.to_string();
+	writeln!(layout, "statics ({} geometry pages)", loaded_level.geom_data_buffers.len()).unwrap();
+	for (index, name) in STATICS_NAMES.iter().enumerate() {
+		writeln!(layout, "  {}: {}", name, u32_at(&statics, index * 4)).unwrap();
+	}
+	fs::write(out_dir.join("layout.txt"), layout)
+}
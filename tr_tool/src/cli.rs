@@ -0,0 +1,45 @@
+//! Command line modes, which the binary runs in place of the window.
+use std::{ffi::OsString, io::Result, path::Path};
+use crate::{dump, settings::Settings, snapshot, texture_export};
+#[cfg(feature = "headless")]
+use std::{ffi::OsStr, io::Error};
+#[cfg(feature = "headless")]
+use crate::CameraSpec;
+
+#[cfg(feature = "headless")]
+const THUMBNAIL_SIZE: (u32, u32) = (640, 480);
+
+/// `--export-textures`, see `texture_export::export_atlases_cli`. False if any export failed.
+pub fn export_textures(args: &[OsString]) -> bool {
+	texture_export::export_atlases_cli(args)
+}
+
+/// `--thumbnail <level> <out_path> [camera]`, with `camera` in the form copied from the viewer.
+#[cfg(feature = "headless")]
+pub fn thumbnail(level_path: &Path, out_path: &Path, camera: Option<&OsStr>) -> Result<()> {
+	let (width, height) = THUMBNAIL_SIZE;
+	let camera = camera.map(|camera| camera.to_string_lossy().parse::<CameraSpec>()).transpose()?;
+	let learned_versions = Settings::load().learned_versions;
+	let image = snapshot::render_snapshot_with(level_path, width, height, camera, &learned_versions)?;
+	image.save(out_path).map_err(Error::other)
+}
+
+/// `--dump-chunks <level> <out_dir>`.
+pub fn dump_chunks(level_path: &Path, out_dir: &Path) -> Result<()> {
+	dump::dump_chunks(level_path, out_dir)
+}
+
+/// `--dump-buffers <level> <out_dir>`.
+pub fn dump_buffers(level_path: &Path, out_dir: &Path) -> Result<()> {
+	snapshot::dump_level_buffers(level_path, out_dir, &Settings::load().learned_versions)
+}
+
+/// `--dump-json <level> <out_path>`.
+pub fn dump_json(level_path: &Path, out_path: &Path) -> Result<()> {
+	dump::write_json(level_path, out_path, dump::dump_json)
+}
+
+/// `--metadata-json <level> <out_path>`.
+pub fn metadata_json(level_path: &Path, out_path: &Path) -> Result<()> {
+	dump::write_json(level_path, out_path, dump::metadata_json)
+}
@@ -0,0 +1,29 @@
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+	Info,
+	Warning,
+}
+
+/// Print to stdout or stderr and keep for the Log window.
+pub fn push(severity: Severity, text: String) {
+	match severity {
+		Severity::Info => println!("{}", text),
+		Severity::Warning => eprintln!("{}", text),
+	}
+	#[cfg(feature = "gui")]
+	crate::gui::log::keep(severity, text);
+}
+
+macro_rules! info {
+	($($arg:tt)*) => {
+		$crate::console::push($crate::console::Severity::Info, format!($($arg)*))
+	};
+}
+
+macro_rules! log_warn {
+	($($arg:tt)*) => {
+		$crate::console::push($crate::console::Severity::Warning, format!($($arg)*))
+	};
+}
+
+pub(crate) use {info, log_warn};
@@ -1,33 +1,90 @@
-use std::ops::Range;
-use glam::IVec3;
-use tr_model::{tr1, tr3};
+use std::{io::Result, mem::size_of, ops::Range};
+use glam::{IVec3, Vec3};
+use tr_model::{tr1, tr4};
 use crate::{
-	as_bytes::ReinterpretAsBytes, geom_buffer::{self, GeomBuffer}, object_data::{MeshFaceType, ObjectData}, tr_traits::{Level, MeshTexturedFace, ObjectTexture, RoomFace, RoomVertex}, WrittenFaceArray, WrittenMesh
+	as_bytes::ReinterpretAsBytes, face_instance::{DecodedFaceInstance, FaceInstance}, geom_buffer::{self, GeomBuffer, VertexArray}, level_buffers::{WrittenFaceArray, WrittenMesh}, object_data::{MeshFaceType, ObjectData}, tr_traits::{vertex_indices, Level, MeshTexturedFace, ObjectTexture, RoomFace, RoomVertex}
 };
 
 #[repr(C)]
-#[derive(Clone, Copy, Debug)]
-pub struct FaceInstance {
-	face_array_index: u16,
-	face_index: u16,
-	transform_index: u16,
+pub struct SpriteInstance {
+	pos: IVec3,
+	sprite_texture_index: u32,
+	/// Full width, levels can have more than 65535 object data.
 	object_data_index: u32,
 }
 
-impl ReinterpretAsBytes for FaceInstance {}
+impl ReinterpretAsBytes for SpriteInstance {}
+
+impl SpriteInstance {
+	/// Byte offset of `sprite_texture_index`.
+	pub const SPRITE_TEXTURE_INDEX_OFFSET: u64 = size_of::<IVec3>() as u64;
+}
 
 #[repr(C)]
-pub struct SpriteInstance {
-	pos: IVec3,
-	sprite_texture_index: u16,
-	object_data_index: u16,
+pub struct PortalInstance {
+	/// World space.
+	vertices: [IVec3; 4],
+	/// RGBA.
+	color: u32,
+	object_data_index: u32,
 }
 
-impl ReinterpretAsBytes for SpriteInstance {}
+impl ReinterpretAsBytes for PortalInstance {}
+
+const PORTAL_ALPHA: u32 = 96;
+
+/// Translucent RGBA color distinct per adjoining room.
+pub fn portal_color(adjoining_room_index: u16) -> u32 {
+	let hash = (adjoining_room_index as u32 + 1).wrapping_mul(0x9E3779B9);
+	(hash & 0xFFFFFF) | (PORTAL_ALPHA << 24)
+}
+
+/// Pipeline a textured face is drawn with. Raw blend modes map as follows; modes without a matching
+/// pipeline use the nearest one:
+///
+/// | Mode | Meaning                     | Pipeline                             |
+/// |------|-----------------------------|--------------------------------------|
+/// | 0    | opaque                      | Opaque                               |
+/// | 1    | alpha test                  | Opaque, transparent texels discarded |
+/// | 2    | additive                    | Additive                             |
+/// | 3    | unused                      | Alpha                                |
+/// | 4    | additive without depth test | Additive, depth tested               |
+/// | 5    | subtractive                 | Alpha                                |
+/// | 6    | wireframe                   | Alpha, filled                        |
+/// | 7+   | unknown, TRNG               | Alpha                                |
+///
+/// TR4-5 mesh faces flagged additive are Additive regardless of mode.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FaceBlend {
+	Opaque,
+	Additive,
+	/// Standard alpha blending by texel alpha.
+	Alpha,
+}
+
+impl FaceBlend {
+	pub fn of(blend_mode: u16, additive_flag: bool) -> Self {
+		match blend_mode {
+			_ if additive_flag => FaceBlend::Additive,
+			tr4::blend_mode::OPAQUE | tr4::blend_mode::TEST => FaceBlend::Opaque,
+			tr4::blend_mode::ADD | tr4::blend_mode::ADD_NO_DEPTH_TEST => FaceBlend::Additive,
+			_ => FaceBlend::Alpha,
+		}
+	}
+	
+	pub fn label(&self) -> &'static str {
+		match self {
+			FaceBlend::Opaque => "Opaque",
+			FaceBlend::Additive => "Additive",
+			FaceBlend::Alpha => "Alpha blend",
+		}
+	}
+}
 
 pub struct MeshTexturedFaceOffsets {
 	pub opaque: u32,
 	pub additive: u32,
+	pub alpha: u32,
 	pub end: u32,
 }
 
@@ -37,7 +94,19 @@ impl MeshTexturedFaceOffsets {
 	}
 	
 	pub fn additive(&self) -> Range<u32> {
-		self.additive..self.end
+		self.additive..self.alpha
+	}
+	
+	pub fn alpha(&self) -> Range<u32> {
+		self.alpha..self.end
+	}
+	
+	pub fn faces(&self, face_blend: FaceBlend) -> Range<u32> {
+		match face_blend {
+			FaceBlend::Opaque => self.opaque(),
+			FaceBlend::Additive => self.additive(),
+			FaceBlend::Alpha => self.alpha(),
+		}
 	}
 }
 
@@ -48,52 +117,139 @@ pub struct MeshFaceOffsets {
 	pub solid_tris: Range<u32>,
 }
 
+#[derive(Default)]
+struct TexturedFaceLists {
+	opaque: Vec<FaceInstance>,
+	additive: Vec<FaceInstance>,
+	alpha: Vec<FaceInstance>,
+}
+
+/// Face instances of one or more mesh placements, grouped by kind.
+#[derive(Default)]
+pub struct MeshBatch {
+	textured_quads: TexturedFaceLists,
+	textured_tris: TexturedFaceLists,
+	solid_quads: Vec<FaceInstance>,
+	solid_tris: Vec<FaceInstance>,
+}
+
+/// Share of a range's face area kept at low detail.
+const LOD_AREA_FRACTION: f32 = 0.8;
+
+/// Faces of each range are sorted by area, largest first, so a prefix of a range is its low detail set.
 pub struct RoomFaceOffsets {
 	pub opaque_obverse: u32,
 	pub opaque_reverse: u32,
 	pub additive_obverse: u32,
 	pub additive_reverse: u32,
+	pub alpha_obverse: u32,
+	pub alpha_reverse: u32,
 	pub end: u32,
+	/// Low detail face counts of the six ranges.
+	pub lod_lens: [u32; 6],
+}
+
+fn lod_range(range: Range<u32>, lod_len: u32, lod: bool) -> Range<u32> {
+	if lod {
+		range.start..range.start + lod_len
+	} else {
+		range
+	}
 }
 
 impl RoomFaceOffsets {
-	pub fn opaque_obverse(&self) -> Range<u32> {
-		self.opaque_obverse..self.opaque_reverse
+	pub fn opaque_obverse(&self, lod: bool) -> Range<u32> {
+		lod_range(self.opaque_obverse..self.opaque_reverse, self.lod_lens[0], lod)
+	}
+	
+	pub fn opaque_reverse(&self, lod: bool) -> Range<u32> {
+		lod_range(self.opaque_reverse..self.additive_obverse, self.lod_lens[1], lod)
+	}
+	
+	pub fn additive_obverse(&self, lod: bool) -> Range<u32> {
+		lod_range(self.additive_obverse..self.additive_reverse, self.lod_lens[2], lod)
+	}
+	
+	pub fn additive_reverse(&self, lod: bool) -> Range<u32> {
+		lod_range(self.additive_reverse..self.alpha_obverse, self.lod_lens[3], lod)
+	}
+	
+	pub fn alpha_obverse(&self, lod: bool) -> Range<u32> {
+		lod_range(self.alpha_obverse..self.alpha_reverse, self.lod_lens[4], lod)
 	}
 	
-	pub fn opaque_reverse(&self) -> Range<u32> {
-		self.opaque_reverse..self.additive_obverse
+	pub fn alpha_reverse(&self, lod: bool) -> Range<u32> {
+		lod_range(self.alpha_reverse..self.end, self.lod_lens[5], lod)
 	}
 	
-	pub fn additive_obverse(&self) -> Range<u32> {
-		self.additive_obverse..self.additive_reverse
+	pub fn obverse(&self, face_blend: FaceBlend, lod: bool) -> Range<u32> {
+		match face_blend {
+			FaceBlend::Opaque => self.opaque_obverse(lod),
+			FaceBlend::Additive => self.additive_obverse(lod),
+			FaceBlend::Alpha => self.alpha_obverse(lod),
+		}
 	}
 	
-	pub fn additive_reverse(&self) -> Range<u32> {
-		self.additive_reverse..self.end
+	pub fn reverse(&self, face_blend: FaceBlend, lod: bool) -> Range<u32> {
+		match face_blend {
+			FaceBlend::Opaque => self.opaque_reverse(lod),
+			FaceBlend::Additive => self.additive_reverse(lod),
+			FaceBlend::Alpha => self.alpha_reverse(lod),
+		}
 	}
 }
 
+fn face_area<F: RoomFace, V: RoomVertex>(face: &F, vertices: &[V]) -> f32 {
+	let positions = vertex_indices(face)
+		.map(|index| vertices.get(index as usize).map(|v| v.pos()).unwrap_or_default())
+		.collect::<Vec<_>>();
+	let first = positions[0];
+	let area = positions[1..]
+		.windows(2)
+		.map(|pair| (pair[0] - first).cross(pair[1] - first))
+		.sum::<Vec3>();
+	area.length() / 2.0
+}
+
+/// Sort largest first and count the faces covering `LOD_AREA_FRACTION` of the total area.
+fn sort_by_area(faces: &mut [(f32, FaceInstance)]) -> u32 {
+	faces.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+	let total = faces.iter().map(|&(area, _)| area).sum::<f32>();
+	let mut covered = 0.0;
+	let lod_len = faces.iter().take_while(|&&(area, _)| {
+		let keep = covered < total * LOD_AREA_FRACTION;
+		covered += area;
+		keep
+	}).count();
+	lod_len as u32
+}
+
 pub struct Output {
 	pub geom_output: geom_buffer::Output,
 	pub face_buffer: Vec<FaceInstance>,
 	pub sprite_buffer: Vec<SpriteInstance>,
+	pub portal_buffer: Vec<PortalInstance>,
 	pub object_data: Vec<ObjectData>,
 }
 
 pub struct DataWriter {
 	pub geom_buffer: GeomBuffer,
+	/// By object texture index.
+	degenerate_object_textures: Vec<bool>,
 	face_buffer: Vec<FaceInstance>,
 	sprite_buffer: Vec<SpriteInstance>,
+	portal_buffer: Vec<PortalInstance>,
 	object_data: Vec<ObjectData>,
 }
 
 impl DataWriter {
-	pub fn new(geom_buffer: GeomBuffer) -> Self {
+	pub fn new(geom_buffer: GeomBuffer, degenerate_object_textures: Vec<bool>) -> Self {
 		Self {
 			geom_buffer,
+			degenerate_object_textures,
 			face_buffer: vec![],
 			sprite_buffer: vec![],
+			portal_buffer: vec![],
 			object_data: vec![],
 		}
 	}
@@ -104,129 +260,187 @@ impl DataWriter {
 		index
 	}
 	
-	pub fn write_room_face_array<L: Level, F: RoomFace, O: Fn(u16) -> ObjectData>(
-		&mut self, level: &L, vertex_array_offset: u32, faces: &[F], transform_index: u16,
+	fn degenerate_texture(&self, object_texture_index: u16) -> bool {
+		self.degenerate_object_textures.get(object_texture_index as usize).copied().unwrap_or(false)
+	}
+	
+	pub fn write_room_face_array<L: Level, V: RoomVertex, F: RoomFace, O: Fn(u16) -> ObjectData>(
+		&mut self, level: &L, vertices: &[V], vertex_array: VertexArray, faces: &[F], transform_index: u16,
 		object_data_maker: O,
-	) -> RoomFaceOffsets {
-		let face_array_index = self.geom_buffer.write_face_array(faces, vertex_array_offset);
+	) -> Result<RoomFaceOffsets> {
+		let face_array_index = self.geom_buffer.write_face_array(faces, vertex_array, false)?;
 		let mut opaque_obverse_faces = Vec::with_capacity(faces.len());
 		let mut opaque_reverse_faces = Vec::with_capacity(faces.len());
 		let mut additive_obverse_faces = Vec::with_capacity(faces.len());
 		let mut additive_reverse_faces = Vec::with_capacity(faces.len());
+		let mut alpha_obverse_faces = vec![];
+		let mut alpha_reverse_faces = vec![];
 		for (face_index, face) in faces.iter().enumerate() {
 			let face_index = face_index as u16;
 			let blend_mode = level.object_textures()[face.object_texture_index() as usize].blend_mode();
-			let (obverse, reverse) = if blend_mode == tr3::blend_mode::ADD {
-				(&mut additive_obverse_faces, &mut additive_reverse_faces)
-			} else {
-				(&mut opaque_obverse_faces, &mut opaque_reverse_faces)
+			let (obverse, reverse) = match FaceBlend::of(blend_mode, false) {
+				FaceBlend::Opaque => (&mut opaque_obverse_faces, &mut opaque_reverse_faces),
+				FaceBlend::Additive => (&mut additive_obverse_faces, &mut additive_reverse_faces),
+				FaceBlend::Alpha => (&mut alpha_obverse_faces, &mut alpha_reverse_faces),
 			};
+			let area = face_area(face, vertices);
 			let object_data_index = self.add_object_data(object_data_maker(face_index));
-			obverse.push(FaceInstance {
+			let fields = DecodedFaceInstance {
 				face_array_index,
 				face_index,
 				transform_index,
+				degenerate_texture: self.degenerate_texture(face.object_texture_index()),
+				shine_strength: 0,
+				page: self.geom_buffer.face_array_page(face_array_index),
 				object_data_index,
-			});
+			};
+			obverse.push((area, FaceInstance::pack(fields)));
 			if face.double_sided() {
 				let object_data_index = self.add_object_data(ObjectData::Reverse { object_data_index });
-				reverse.push(FaceInstance {
-					face_array_index,
-					face_index,
-					transform_index,
-					object_data_index,
-				});
+				reverse.push((area, FaceInstance::pack(DecodedFaceInstance { object_data_index, ..fields })));
 			}
 		}
+		let lod_lens = [
+			&mut opaque_obverse_faces,
+			&mut opaque_reverse_faces,
+			&mut additive_obverse_faces,
+			&mut additive_reverse_faces,
+			&mut alpha_obverse_faces,
+			&mut alpha_reverse_faces,
+		].map(|faces| sort_by_area(faces));
 		let additional =
 			opaque_obverse_faces.len() +
 			opaque_reverse_faces.len() +
 			additive_obverse_faces.len() +
-			additive_reverse_faces.len();
+			additive_reverse_faces.len() +
+			alpha_obverse_faces.len() +
+			alpha_reverse_faces.len();
 		self.face_buffer.reserve(additional);
 		let opaque_obverse = self.face_buffer.len() as u32;
-		self.face_buffer.extend(opaque_obverse_faces);
+		self.face_buffer.extend(opaque_obverse_faces.into_iter().map(|(_, face)| face));
 		let opaque_reverse = self.face_buffer.len() as u32;
-		self.face_buffer.extend(opaque_reverse_faces);
+		self.face_buffer.extend(opaque_reverse_faces.into_iter().map(|(_, face)| face));
 		let additive_obverse = self.face_buffer.len() as u32;
-		self.face_buffer.extend(additive_obverse_faces);
+		self.face_buffer.extend(additive_obverse_faces.into_iter().map(|(_, face)| face));
 		let additive_reverse = self.face_buffer.len() as u32;
-		self.face_buffer.extend(additive_reverse_faces);
+		self.face_buffer.extend(additive_reverse_faces.into_iter().map(|(_, face)| face));
+		let alpha_obverse = self.face_buffer.len() as u32;
+		self.face_buffer.extend(alpha_obverse_faces.into_iter().map(|(_, face)| face));
+		let alpha_reverse = self.face_buffer.len() as u32;
+		self.face_buffer.extend(alpha_reverse_faces.into_iter().map(|(_, face)| face));
 		let end = self.face_buffer.len() as u32;
-		RoomFaceOffsets { opaque_obverse, opaque_reverse, additive_obverse, additive_reverse, end }
+		Ok(RoomFaceOffsets {
+			opaque_obverse,
+			opaque_reverse,
+			additive_obverse,
+			additive_reverse,
+			alpha_obverse,
+			alpha_reverse,
+			end,
+			lod_lens,
+		})
 	}
 	
-	fn mesh_textured_face_array<L, F, O>(
-		&mut self, level: &L, face_array: &WrittenFaceArray<F>, transform_index: u16,
-		object_data_maker: O,
-	) -> MeshTexturedFaceOffsets
+	fn add_textured_faces<L, F, O>(
+		&mut self, level: &L, lists: &mut TexturedFaceLists, face_array: &WrittenFaceArray<F>,
+		transform_index: u16, object_data_maker: O,
+	)
 	where L: Level, F: MeshTexturedFace, O: Fn(u16) -> ObjectData {
-		let mut opaque_faces = Vec::with_capacity(face_array.faces.len());
-		let mut additive_faces = Vec::with_capacity(face_array.faces.len());
 		for (face_index, face) in face_array.faces.iter().enumerate() {
 			let face_index = face_index as u16;
 			let blend_mode = level.object_textures()[face.object_texture_index() as usize].blend_mode();
-			let faces_list = if blend_mode == tr3::blend_mode::ADD || face.additive() {
-				&mut additive_faces
-			} else {
-				&mut opaque_faces
+			let faces_list = match FaceBlend::of(blend_mode, face.additive()) {
+				FaceBlend::Opaque => &mut lists.opaque,
+				FaceBlend::Additive => &mut lists.additive,
+				FaceBlend::Alpha => &mut lists.alpha,
 			};
 			let object_data_index = self.add_object_data(object_data_maker(face_index));
-			faces_list.push(FaceInstance {
+			faces_list.push(FaceInstance::pack(DecodedFaceInstance {
 				face_array_index: face_array.index,
 				face_index,
 				transform_index,
+				degenerate_texture: self.degenerate_texture(face.object_texture_index()),
+				shine_strength: face.shine_strength(),
+				page: self.geom_buffer.face_array_page(face_array.index),
 				object_data_index,
-			});
+			}));
 		}
-		self.face_buffer.reserve(face_array.faces.len());
-		let opaque = self.face_buffer.len() as u32;
-		self.face_buffer.extend(opaque_faces);
-		let additive = self.face_buffer.len() as u32;
-		self.face_buffer.extend(additive_faces);
-		let end = self.face_buffer.len() as u32;
-		MeshTexturedFaceOffsets { opaque, additive, end }
 	}
 	
-	fn mesh_solid_face_array<F, O: Fn(u16) -> ObjectData>(
-		&mut self, face_array: &WrittenFaceArray<F>, transform_index: u16, object_data_maker: O,
-	) -> Range<u32> {
-		self.face_buffer.reserve(face_array.faces.len());
-		let start = self.face_buffer.len() as u32;
+	fn add_solid_faces<F, O: Fn(u16) -> ObjectData>(
+		&mut self, list: &mut Vec<FaceInstance>, face_array: &WrittenFaceArray<F>, transform_index: u16,
+		object_data_maker: O,
+	) {
+		let page = self.geom_buffer.face_array_page(face_array.index);
 		for face_index in 0..face_array.faces.len() as u16 {
 			let object_data_index = self.add_object_data(object_data_maker(face_index));
-			self.face_buffer.push(FaceInstance {
+			list.push(FaceInstance::pack(DecodedFaceInstance {
 				face_array_index: face_array.index,
 				face_index,
 				transform_index,
+				degenerate_texture: false,
+				shine_strength: 0,
+				page,
 				object_data_index,
-			});
+			}));
 		}
+	}
+	
+	/// Add a mesh placement to a batch. Nothing is written until `write_batch`.
+	pub fn add_mesh<L: Level, O: Fn(MeshFaceType, u16) -> ObjectData>(
+		&mut self, batch: &mut MeshBatch, level: &L, mesh: &WrittenMesh<L>, transform_index: u16,
+		object_data_maker: O,
+	) {
+		self.add_textured_faces(
+			level, &mut batch.textured_quads, &mesh.textured_quads, transform_index,
+			|face_index| object_data_maker(MeshFaceType::TexturedQuad, face_index),
+		);
+		self.add_textured_faces(
+			level, &mut batch.textured_tris, &mesh.textured_tris, transform_index,
+			|face_index| object_data_maker(MeshFaceType::TexturedTri, face_index),
+		);
+		self.add_solid_faces(
+			&mut batch.solid_quads, &mesh.solid_quads, transform_index,
+			|face_index| object_data_maker(MeshFaceType::SolidQuad, face_index),
+		);
+		self.add_solid_faces(
+			&mut batch.solid_tris, &mesh.solid_tris, transform_index,
+			|face_index| object_data_maker(MeshFaceType::SolidTri, face_index),
+		);
+	}
+	
+	/// Sorted by page, so each page draws a part of the range.
+	fn write_faces(&mut self, mut faces: Vec<FaceInstance>) -> Range<u32> {
+		faces.sort_by_key(FaceInstance::page);
+		let start = self.face_buffer.len() as u32;
+		self.face_buffer.extend(faces);
 		let end = self.face_buffer.len() as u32;
 		start..end
 	}
 	
+	fn write_textured_faces(&mut self, lists: TexturedFaceLists) -> MeshTexturedFaceOffsets {
+		let opaque = self.write_faces(lists.opaque);
+		let additive = self.write_faces(lists.additive);
+		let alpha = self.write_faces(lists.alpha);
+		MeshTexturedFaceOffsets { opaque: opaque.start, additive: additive.start, alpha: alpha.start, end: alpha.end }
+	}
+	
+	/// Write all faces of the batch contiguously by kind, so each kind is drawn with one call.
+	pub fn write_batch(&mut self, batch: MeshBatch) -> MeshFaceOffsets {
+		MeshFaceOffsets {
+			textured_quads: self.write_textured_faces(batch.textured_quads),
+			textured_tris: self.write_textured_faces(batch.textured_tris),
+			solid_quads: self.write_faces(batch.solid_quads),
+			solid_tris: self.write_faces(batch.solid_tris),
+		}
+	}
+	
 	pub fn place_mesh<L: Level, O: Fn(MeshFaceType, u16) -> ObjectData>(
 		&mut self, level: &L, mesh: &WrittenMesh<L>, transform_index: u16, object_data_maker: O,
 	) -> MeshFaceOffsets {
-		MeshFaceOffsets {
-			textured_quads: self.mesh_textured_face_array(
-				level, &mesh.textured_quads, transform_index,
-				|face_index| object_data_maker(MeshFaceType::TexturedQuad, face_index),
-			),
-			textured_tris: self.mesh_textured_face_array(
-				level, &mesh.textured_tris, transform_index,
-				|face_index| object_data_maker(MeshFaceType::TexturedTri, face_index),
-			),
-			solid_quads: self.mesh_solid_face_array(
-				&mesh.solid_quads, transform_index,
-				|face_index| object_data_maker(MeshFaceType::SolidQuad, face_index),
-			),
-			solid_tris: self.mesh_solid_face_array(
-				&mesh.solid_tris, transform_index,
-				|face_index| object_data_maker(MeshFaceType::SolidTri, face_index),
-			),
-		}
+		let mut batch = MeshBatch::default();
+		self.add_mesh(&mut batch, level, mesh, transform_index, object_data_maker);
+		self.write_batch(batch)
 	}
 	
 	pub fn sprite_offset(&self) -> u32 {
@@ -238,10 +452,10 @@ impl DataWriter {
 	) -> Range<u32> {
 		let start = self.sprite_buffer.len() as u32;
 		for &tr1::Sprite { vertex_index, sprite_texture_index } in sprites {
-			let object_data_index = self.add_object_data(object_data_maker(sprite_texture_index)) as u16;
+			let object_data_index = self.add_object_data(object_data_maker(sprite_texture_index));
 			self.sprite_buffer.push(SpriteInstance {
 				pos: room_pos + vertices[vertex_index as usize].pos().as_ivec3(),
-				sprite_texture_index,
+				sprite_texture_index: sprite_texture_index as u32,
 				object_data_index,
 			});
 		}
@@ -249,19 +463,65 @@ impl DataWriter {
 		start..end
 	}
 	
-	pub fn write_entity_sprite(&mut self, entity_index: u16, pos: IVec3, sprite_texture_index: u16) {
-		let object_data_index = self.add_object_data(ObjectData::EntitySprite { entity_index }) as u16;
+	/// Returns instance index.
+	pub fn write_entity_sprite(&mut self, entity_index: u16, pos: IVec3, sprite_texture_index: u16) -> u32 {
+		let instance_index = self.sprite_buffer.len() as u32;
+		let object_data_index = self.add_object_data(ObjectData::EntitySprite { entity_index });
+		let sprite_texture_index = sprite_texture_index as u32;
 		self.sprite_buffer.push(SpriteInstance { pos, sprite_texture_index, object_data_index });
+		instance_index
+	}
+	
+	pub fn write_room_portals<O: Fn(u16) -> ObjectData>(
+		&mut self, room_pos: IVec3, portals: &[tr1::Portal], object_data_maker: O,
+	) -> Range<u32> {
+		let start = self.portal_buffer.len() as u32;
+		for (portal_index, portal) in portals.iter().enumerate() {
+			let object_data_index = self.add_object_data(object_data_maker(portal_index as u16));
+			self.portal_buffer.push(PortalInstance {
+				vertices: portal.vertices.map(|v| room_pos + v.as_ivec3()),
+				color: portal_color(portal.adjoining_room_index),
+				object_data_index,
+			});
+		}
+		let end = self.portal_buffer.len() as u32;
+		start..end
 	}
 	
 	pub fn done<O: ReinterpretAsBytes>(
 		self, object_textures: &[O], sprite_textures: &[tr1::SpriteTexture],
-	) -> Output {
-		Output {
-			geom_output: self.geom_buffer.into_buffer(object_textures, sprite_textures),
+	) -> Result<Output> {
+		Ok(Output {
+			geom_output: self.geom_buffer.into_buffer(object_textures, sprite_textures)?,
 			face_buffer: self.face_buffer,
 			sprite_buffer: self.sprite_buffer,
+			portal_buffer: self.portal_buffer,
 			object_data: self.object_data,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn face_blend_of_each_mode() {
+		let expected = [
+			FaceBlend::Opaque,
+			FaceBlend::Opaque,
+			FaceBlend::Additive,
+			FaceBlend::Alpha,
+			FaceBlend::Additive,
+			FaceBlend::Alpha,
+			FaceBlend::Alpha,
+			//unknown modes fall back to alpha blending
+			FaceBlend::Alpha,
+		];
+		for (blend_mode, expected) in (0..).zip(expected) {
+			assert_eq!(FaceBlend::of(blend_mode, false), expected, "mode {}", blend_mode);
+			assert_eq!(FaceBlend::of(blend_mode, true), FaceBlend::Additive, "mode {} flagged additive", blend_mode);
 		}
+		assert_eq!(FaceBlend::of(u16::MAX, false), FaceBlend::Alpha);
 	}
 }
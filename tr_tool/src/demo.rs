@@ -0,0 +1,31 @@
+use glam::IVec3;
+
+/// Words before the inputs: Lara's x, y, z, rotation x, y, z and room.
+pub const HEADER_LEN: usize = 7;
+/// Input word ending the demo.
+pub const END: u32 = u32::MAX;
+
+/// TR1 demo: where Lara starts and the inputs held each game frame.
+pub struct Demo {
+	/// World coords.
+	pub pos: IVec3,
+	/// Units are 1/65536 of a rotation.
+	pub angle: u16,
+	pub room_index: u16,
+	pub inputs: Vec<u32>,
+}
+
+/// Words of `demo_data` up to the end marker. `None` if too short for the header.
+pub fn decode(demo_data: &[u8]) -> Option<Demo> {
+	let mut words = demo_data.chunks_exact(4).map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]));
+	let header = words.by_ref().take(HEADER_LEN).collect::<Vec<_>>();
+	let [x, y, z, _, angle, _, room_index] = header[..] else {
+		return None;
+	};
+	Some(Demo {
+		pos: IVec3::new(x as i32, y as i32, z as i32),
+		angle: angle as u16,
+		room_index: room_index as u16,
+		inputs: words.take_while(|&word| word != END).collect(),
+	})
+}
@@ -0,0 +1,489 @@
+use std::{fs, io::{Error, Result, Seek, SeekFrom}, path::Path};
+use serde::Serialize;
+use shared::min_max::{MinMax, VecMinMaxFromIterator};
+use tr_model::{fnv1a_hex, tr1, tr2, tr3, tr4, tr5, TrError};
+use crate::{
+	as_bytes::{AsBytes, ReinterpretAsBytes}, demo, read_level,
+	settings::Settings,
+	tr_traits::{
+		with_level, Entity, Level, LevelDyn, LevelStore, Model, ObjectTexture, Room, RoomStaticMesh, RoomVertex,
+		StaticMesh, TrBox,
+	},
+	version::{self, ReadMode, Version},
+};
+
+/// Stand-in for bulk data such as pixels, in the same form as blobs in tr_model's serde output.
+#[derive(Serialize)]
+struct Bulk {
+	/// In bytes.
+	len: usize,
+	/// `fnv1a_hex` of the bytes.
+	hash: String,
+}
+
+#[derive(Serialize)]
+struct Bounds {
+	min: [f32; 3],
+	max: [f32; 3],
+}
+
+#[derive(Serialize)]
+struct RoomDump {
+	pos: [i32; 3],
+	/// World coords, `None` if the room has no vertices.
+	bounds: Option<Bounds>,
+	num_vertices: usize,
+	num_quads: usize,
+	num_tris: usize,
+	num_sprites: usize,
+	num_static_meshes: usize,
+	flip_room_index: Option<u16>,
+	flip_group: u8,
+}
+
+#[derive(Serialize)]
+struct EntityDump {
+	model_id: u16,
+	room_index: u16,
+	pos: [i32; 3],
+	angle: u16,
+	flags: u16,
+	ocb: Option<u16>,
+}
+
+#[derive(Serialize)]
+struct ModelDump {
+	id: u32,
+	mesh_offset_index: u16,
+	num_meshes: u16,
+}
+
+#[derive(Serialize)]
+struct StaticMeshDump {
+	id: u32,
+	mesh_offset_index: u16,
+	non_collidable: bool,
+	visible: bool,
+}
+
+#[derive(Serialize)]
+struct ObjectTextureDump {
+	blend_mode: u16,
+	atlas_index: u16,
+	uvs: [[u16; 2]; 4],
+}
+
+#[derive(Serialize)]
+struct SpriteTextureDump {
+	atlas_index: u16,
+	pos: [u8; 2],
+	size: [u16; 2],
+	world_bounds: [[i16; 2]; 2],
+}
+
+#[derive(Serialize)]
+struct BoxDump {
+	z: [u32; 2],
+	x: [u32; 2],
+	y: i16,
+	overlap: u16,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type")]
+enum AnimCommandDump {
+	SetPosition { offset: [i16; 3] },
+	JumpVelocity { vertical: i16, horizontal: i16 },
+	EmptyHands,
+	Kill,
+	PlaySound { frame: u16, sound_id: u16 },
+	FlipEffect { frame: u16, effect: u16 },
+	Unknown { opcode: u16 },
+}
+
+impl From<tr1::AnimCommand> for AnimCommandDump {
+	fn from(anim_command: tr1::AnimCommand) -> Self {
+		match anim_command {
+			tr1::AnimCommand::SetPosition(offset) => AnimCommandDump::SetPosition { offset: offset.to_array() },
+			tr1::AnimCommand::JumpVelocity { vertical, horizontal } => {
+				AnimCommandDump::JumpVelocity { vertical, horizontal }
+			},
+			tr1::AnimCommand::EmptyHands => AnimCommandDump::EmptyHands,
+			tr1::AnimCommand::Kill => AnimCommandDump::Kill,
+			tr1::AnimCommand::PlaySound { frame, sound_id } => AnimCommandDump::PlaySound { frame, sound_id },
+			tr1::AnimCommand::FlipEffect { frame, effect } => AnimCommandDump::FlipEffect { frame, effect },
+			tr1::AnimCommand::Unknown(opcode) => AnimCommandDump::Unknown { opcode },
+		}
+	}
+}
+
+#[derive(Serialize)]
+struct LevelDump<'a> {
+	version: &'static str,
+	rooms: Vec<RoomDump>,
+	entities: Vec<EntityDump>,
+	models: Vec<ModelDump>,
+	/// By animation index.
+	anim_commands: Vec<Vec<AnimCommandDump>>,
+	static_meshes: Vec<StaticMeshDump>,
+	object_textures: Vec<ObjectTextureDump>,
+	sprite_textures: Vec<SpriteTextureDump>,
+	sound_map: &'a [u16],
+	boxes: Vec<BoxDump>,
+	atlases_palette: Option<Bulk>,
+	atlases_16bit: Option<Bulk>,
+	atlases_32bit: Option<Bulk>,
+	misc_images: Option<Bulk>,
+}
+
+#[derive(Serialize)]
+struct Counts {
+	rooms: usize,
+	entities: usize,
+	models: usize,
+	static_meshes: usize,
+	object_textures: usize,
+	sprite_textures: usize,
+	atlases: usize,
+	cameras: usize,
+	sound_sources: usize,
+	demo_data_bytes: usize,
+}
+
+#[derive(Serialize)]
+struct RoomMetadata {
+	pos: [i32; 3],
+	bounds: Option<Bounds>,
+	flip_room_index: Option<u16>,
+	flip_group: u8,
+	water: bool,
+	/// Adjoining room indices.
+	portals: Vec<u16>,
+}
+
+#[derive(Serialize)]
+struct EntityMetadata {
+	model_id: u16,
+	room_index: u16,
+	pos: [i32; 3],
+	angle: u16,
+}
+
+#[derive(Serialize)]
+struct RoomStaticMeshMetadata {
+	room_index: usize,
+	static_mesh_id: u16,
+	pos: [i32; 3],
+	angle: u16,
+}
+
+#[derive(Serialize)]
+struct CameraMetadata {
+	pos: [i32; 3],
+	room_index: u16,
+}
+
+/// Start of a decoded TR1 demo.
+#[derive(Serialize)]
+struct DemoMetadata {
+	pos: [i32; 3],
+	angle: u16,
+	room_index: u16,
+	frames: usize,
+}
+
+#[derive(Serialize)]
+struct SoundSourceMetadata {
+	pos: [i32; 3],
+	sound_id: u16,
+}
+
+/// High-level level info for indexing, no geometry.
+#[derive(Serialize)]
+struct LevelMetadata {
+	version: &'static str,
+	counts: Counts,
+	rooms: Vec<RoomMetadata>,
+	entities: Vec<EntityMetadata>,
+	room_static_meshes: Vec<RoomStaticMeshMetadata>,
+	cameras: Vec<CameraMetadata>,
+	sound_sources: Vec<SoundSourceMetadata>,
+	demo: Option<DemoMetadata>,
+}
+
+fn bulk<T: ReinterpretAsBytes>(items: &[T]) -> Bulk {
+	Bulk {
+		len: items.as_bytes().len(),
+		hash: fnv1a_hex(items.as_bytes()),
+	}
+}
+
+fn min_max_pair<T>(MinMax { min, max }: MinMax<T>) -> [T; 2] {
+	[min, max]
+}
+
+fn room_bounds<R: Room>(room: &R) -> Option<Bounds> {
+	let pos = room.pos().as_vec3();
+	room.vertices().iter().map(|v| v.pos()).min_max().map(|MinMax { min, max }| {
+		Bounds {
+			min: (min + pos).to_array(),
+			max: (max + pos).to_array(),
+		}
+	})
+}
+
+fn flip_room_index<R: Room>(room: &R) -> Option<u16> {
+	Some(room.flip_room_index()).filter(|&index| index != u16::MAX)
+}
+
+fn dump_room<R: Room>(room: &R) -> RoomDump {
+	let pos = room.pos();
+	let (num_quads, num_tris) = room
+		.geom()
+		.into_iter()
+		.fold((0, 0), |(quads, tris), geom| (quads + geom.quads.len(), tris + geom.tris.len()));
+	RoomDump {
+		pos: pos.to_array(),
+		bounds: room_bounds(room),
+		num_vertices: room.vertices().len(),
+		num_quads,
+		num_tris,
+		num_sprites: room.sprites().len(),
+		num_static_meshes: room.room_static_meshes().len(),
+		flip_room_index: flip_room_index(room),
+		flip_group: room.flip_group(),
+	}
+}
+
+fn dump_level<L: Level>(level: &L) -> LevelDump<'_> {
+	LevelDump {
+		version: level.version().label(),
+		rooms: level.rooms().iter().map(dump_room).collect(),
+		entities: level.entities().iter().map(|entity| {
+			EntityDump {
+				model_id: entity.model_id(),
+				room_index: entity.room_index(),
+				pos: entity.pos().to_array(),
+				angle: entity.angle(),
+				flags: entity.flags(),
+				ocb: entity.ocb(),
+			}
+		}).collect(),
+		models: level.models().iter().map(|model| {
+			ModelDump {
+				id: model.id(),
+				mesh_offset_index: model.mesh_offset_index(),
+				num_meshes: model.num_meshes(),
+			}
+		}).collect(),
+		anim_commands: level.animations().iter().map(|animation| {
+			level.get_anim_commands(animation).into_iter().map(AnimCommandDump::from).collect()
+		}).collect(),
+		static_meshes: level.static_meshes().iter().map(|static_mesh| {
+			StaticMeshDump {
+				id: static_mesh.id(),
+				mesh_offset_index: static_mesh.mesh_offset_index(),
+				non_collidable: static_mesh.non_collidable(),
+				visible: static_mesh.visible(),
+			}
+		}).collect(),
+		object_textures: level.object_textures().iter().map(|object_texture| {
+			ObjectTextureDump {
+				blend_mode: object_texture.blend_mode(),
+				atlas_index: object_texture.atlas_index(),
+				uvs: object_texture.uvs().map(|uv| uv.to_array()),
+			}
+		}).collect(),
+		sprite_textures: level.sprite_textures().iter().map(|sprite_texture| {
+			let tr1::SpriteTexture { atlas_index, pos, size, world_bounds } = *sprite_texture;
+			SpriteTextureDump {
+				atlas_index,
+				pos: pos.to_array(),
+				size: size.to_array(),
+				world_bounds: world_bounds.map(|corner| corner.to_array()),
+			}
+		}).collect(),
+		sound_map: level.sound_map(),
+		boxes: level.boxes().iter().map(|tr_box| {
+			BoxDump {
+				z: min_max_pair(tr_box.z()),
+				x: min_max_pair(tr_box.x()),
+				y: tr_box.y(),
+				overlap: tr_box.overlap(),
+			}
+		}).collect(),
+		atlases_palette: level.atlases_palette().map(bulk),
+		atlases_16bit: level.atlases_16bit().map(bulk),
+		atlases_32bit: level.atlases_32bit().map(bulk),
+		misc_images: level.misc_images().map(bulk),
+	}
+}
+
+fn level_metadata<L: Level>(level: &L) -> LevelMetadata {
+	LevelMetadata {
+		version: level.version().label(),
+		counts: Counts {
+			rooms: level.rooms().len(),
+			entities: level.entities().len(),
+			models: level.models().len(),
+			static_meshes: level.static_meshes().len(),
+			object_textures: level.object_textures().len(),
+			sprite_textures: level.sprite_textures().len(),
+			atlases: level.num_atlases(),
+			cameras: level.cameras().len(),
+			sound_sources: level.sound_sources().len(),
+			demo_data_bytes: level.demo_data().len(),
+		},
+		rooms: level.rooms().iter().map(|room| {
+			RoomMetadata {
+				pos: room.pos().to_array(),
+				bounds: room_bounds(room),
+				flip_room_index: flip_room_index(room),
+				flip_group: room.flip_group(),
+				water: room.water(),
+				portals: room.portals().iter().map(|portal| portal.adjoining_room_index).collect(),
+			}
+		}).collect(),
+		entities: level.entities().iter().map(|entity| {
+			EntityMetadata {
+				model_id: entity.model_id(),
+				room_index: entity.room_index(),
+				pos: entity.pos().to_array(),
+				angle: entity.angle(),
+			}
+		}).collect(),
+		room_static_meshes: level.rooms().iter().enumerate().flat_map(|(room_index, room)| {
+			room.room_static_meshes().iter().map(move |room_static_mesh| {
+				RoomStaticMeshMetadata {
+					room_index,
+					static_mesh_id: room_static_mesh.static_mesh_id(),
+					pos: room_static_mesh.pos().to_array(),
+					angle: room_static_mesh.angle(),
+				}
+			})
+		}).collect(),
+		cameras: level.cameras().iter().map(|camera| {
+			CameraMetadata { pos: camera.pos.to_array(), room_index: camera.room_index }
+		}).collect(),
+		sound_sources: level.sound_sources().iter().map(|sound_source| {
+			SoundSourceMetadata { pos: sound_source.pos.to_array(), sound_id: sound_source.sound_id }
+		}).collect(),
+		demo: match level.version() {
+			Version::Tr1 => demo::decode(level.demo_data()).map(|demo| {
+				DemoMetadata {
+					pos: demo.pos.to_array(),
+					angle: demo.angle,
+					room_index: demo.room_index,
+					frames: demo.inputs.len(),
+				}
+			}),
+			_ => None,
+		},
+	}
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String> {
+	serde_json::to_string_pretty(value).map_err(Error::other)
+}
+
+/// Diffable representation of a level, excluding bulk data.
+pub fn dump_json(level: &LevelStore) -> Result<String> {
+	with_level!(level, level => to_json(&dump_level(level)))
+}
+
+pub fn metadata_json(level: &LevelStore) -> Result<String> {
+	with_level!(level, level => to_json(&level_metadata(level)))
+}
+
+/// Read a level without rendering it.
+pub fn read_level_store(level_path: &Path) -> Result<LevelStore> {
+	let learned_versions = Settings::load().learned_versions;
+	let mut reader = version::open_level(level_path, ReadMode::Buffered)?;
+	let extension = version::level_extension(level_path);
+	let level = match version::get_version(&mut reader, extension, &learned_versions)? {
+		(_, Some(Version::Tr1)) => read_level::<tr1::Level>(&mut reader)?.store(),
+		(_, Some(Version::Tr2)) => read_level::<tr2::Level>(&mut reader)?.store(),
+		(_, Some(Version::Tr3)) => read_level::<tr3::Level>(&mut reader)?.store(),
+		(_, Some(Version::Tr4)) => read_level::<tr4::Level>(&mut reader)?.store(),
+		(_, Some(Version::Tr5)) => read_level::<tr5::Level>(&mut reader)?.store(),
+		(magic, None) => return Err(TrError::UnknownVersion { magic }.into()),
+	};
+	Ok(level)
+}
+
+/// Convert a level file to JSON with `to_json`, for command line use.
+pub fn write_json(level_path: &Path, out_path: &Path, to_json: fn(&LevelStore) -> Result<String>) -> Result<()> {
+	let level = read_level_store(level_path)?;
+	fs::write(out_path, to_json(&level)?)
+}
+
+/// Write the decompressed bytes of each zlib chunk of a TR4 or TR5 level to separate files in `out_dir`.
+pub fn dump_chunks(level_path: &Path, out_dir: &Path) -> Result<()> {
+	let learned_versions = Settings::load().learned_versions;
+	let mut reader = version::open_level(level_path, ReadMode::Buffered)?;
+	let extension = version::level_extension(level_path);
+	let chunk_names = match version::get_version(&mut reader, extension, &learned_versions)? {
+		(_, Some(Version::Tr4)) => &["atlases_32bit", "atlases_16bit", "misc_images", "level_data"][..],
+		(_, Some(Version::Tr5)) => &["atlases_32bit", "atlases_16bit", "misc_images"],//TR5 level data is not compressed
+		(_, Some(version)) => {
+			let msg = format!("{} files have no zlib chunks", version.label());
+			return Err(TrError::Unsupported(msg).into());
+		},
+		(magic, None) => return Err(TrError::UnknownVersion { magic }.into()),
+	};
+	fs::create_dir_all(out_dir)?;
+	reader.seek(SeekFrom::Start(version::TR4_CHUNKS_OFFSET))?;
+	for chunk_name in chunk_names {
+		let chunk = tr_readable::zlib(&mut reader)?;
+		fs::write(out_dir.join(format!("{}.bin", chunk_name)), chunk.into_inner())?;
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{test_level::{self, TempDir}, tr_traits::LevelDyn};
+	use super::*;
+	
+	const TINY_DUMP: &str = include_str!("../testdata/tiny_tr1.dump.json");
+	const TINY_SERDE: &str = include_str!("../testdata/tiny_tr1.serde.json");
+	
+	#[test]
+	fn dump_json_golden() {
+		let level = test_level::tiny().level().store();
+		assert_eq!(dump_json(&level).unwrap(), TINY_DUMP);
+	}
+	
+	/// tr_model's own serde feature, which the dumps don't go through.
+	#[test]
+	fn tr_model_serde_golden() {
+		let level = test_level::tiny().level();
+		assert_eq!(to_json(&level).unwrap(), TINY_SERDE);
+		let full = tr_model::ser::with_full_blobs(|| serde_json::to_value(&level).unwrap());
+		assert_eq!(full["atlases"].as_array().unwrap().len(), tr1::ATLAS_PIXELS);
+		assert_eq!(full["atlases"][0], 1);
+	}
+	
+	#[test]
+	fn dump_json_from_file_is_byte_identical() {
+		let dir = TempDir::new("dump");
+		let level_path = dir.join("tiny.phd");
+		fs::write(&level_path, test_level::tiny().bytes()).unwrap();
+		for name in ["a.json", "b.json"] {
+			write_json(&level_path, &dir.join(name), dump_json).unwrap();
+		}
+		let a = fs::read_to_string(dir.join("a.json")).unwrap();
+		let b = fs::read_to_string(dir.join("b.json")).unwrap();
+		assert_eq!(a, TINY_DUMP);
+		assert_eq!(a, b);
+	}
+	
+	#[test]
+	fn bulk_data_is_hashed() {
+		let level = test_level::tiny().level();
+		let dump = dump_level(level.as_ref());
+		let atlases = dump.atlases_palette.unwrap();
+		assert_eq!(atlases.len, tr1::ATLAS_PIXELS);
+		assert_eq!(atlases.hash, fnv1a_hex(&vec![1; tr1::ATLAS_PIXELS]));
+	}
+}
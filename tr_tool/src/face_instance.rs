@@ -0,0 +1,187 @@
+use std::ops::Range;
+use crate::as_bytes::ReinterpretAsBytes;
+
+/// Face uses an object texture with UV issues.
+const FLAG_DEGENERATE_TEXTURE: u16 = 1;
+/// Flags bits from this up to the page hold the mesh face shine strength.
+const SHINE_SHIFT: u16 = 1;
+pub const MAX_SHINE_STRENGTH: u8 = 0x7F;
+/// Flags bits above this hold the geometry page of the face array.
+const PAGE_SHIFT: u16 = 8;
+
+/// Instance of one face, read by `get_position_texture` in mesh.wgsl as three u32s:
+/// `face_array_index | face_index << 16`, `transform_index | flags << 16`, `object_data_index`.
+/// Only built by `pack`, so the flags always match the shader's constants from `wgsl_constants`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct FaceInstance {
+	face_array_index: u16,
+	face_index: u16,
+	transform_index: u16,
+	flags: u16,
+	object_data_index: u32,
+}
+
+impl ReinterpretAsBytes for FaceInstance {}
+
+/// Fields of a face instance with the flags split out.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct DecodedFaceInstance {
+	pub face_array_index: u16,
+	pub face_index: u16,
+	pub transform_index: u16,
+	pub degenerate_texture: bool,
+	/// Up to `MAX_SHINE_STRENGTH`.
+	pub shine_strength: u8,
+	/// Geometry page holding the face array.
+	pub page: u8,
+	pub object_data_index: u32,
+}
+
+impl FaceInstance {
+	/// Shine strength is masked to `MAX_SHINE_STRENGTH`.
+	pub fn pack(fields: DecodedFaceInstance) -> Self {
+		let shine = (fields.shine_strength & MAX_SHINE_STRENGTH) as u16;
+		let flags = (fields.degenerate_texture as u16 * FLAG_DEGENERATE_TEXTURE)
+			| shine << SHINE_SHIFT
+			| (fields.page as u16) << PAGE_SHIFT;
+		Self {
+			face_array_index: fields.face_array_index,
+			face_index: fields.face_index,
+			transform_index: fields.transform_index,
+			flags,
+			object_data_index: fields.object_data_index,
+		}
+	}
+	
+	/// The u32s the shader reads.
+	pub fn words(&self) -> [u32; 3] {
+		[
+			self.face_array_index as u32 | (self.face_index as u32) << 16,
+			self.transform_index as u32 | (self.flags as u32) << 16,
+			self.object_data_index,
+		]
+	}
+	
+	pub fn object_data_index(&self) -> u32 {
+		self.object_data_index
+	}
+	
+	pub fn page(&self) -> u8 {
+		(self.flags >> PAGE_SHIFT) as u8
+	}
+}
+
+/// Faces of `faces` whose face arrays are in geometry page `page`. The faces of a range must be sorted by page.
+pub fn page_faces(face_instances: &[FaceInstance], faces: Range<u32>, page: u8) -> Range<u32> {
+	let range = &face_instances[faces.start as usize..faces.end as usize];
+	let start = range.partition_point(|face| face.page() < page) as u32;
+	let end = range.partition_point(|face| face.page() <= page) as u32;
+	faces.start + start..faces.start + end
+}
+
+/// Unpack as the shader does.
+pub fn decode(words: [u32; 3]) -> DecodedFaceInstance {
+	let flags = (words[1] >> 16) as u16;
+	DecodedFaceInstance {
+		face_array_index: words[0] as u16,
+		face_index: (words[0] >> 16) as u16,
+		transform_index: words[1] as u16,
+		degenerate_texture: flags & FLAG_DEGENERATE_TEXTURE != 0,
+		shine_strength: (flags >> SHINE_SHIFT) as u8 & MAX_SHINE_STRENGTH,
+		page: (flags >> PAGE_SHIFT) as u8,
+		object_data_index: words[2],
+	}
+}
+
+/// Flag constants prepended to mesh.wgsl.
+pub fn wgsl_constants() -> String {
+	format!(
+		"const FACE_FLAG_DEGENERATE_TEXTURE: u32 = {};\n\
+		const FACE_SHINE_SHIFT: u32 = {};\n\
+		const FACE_SHINE_MASK: u32 = {};\n",
+		FLAG_DEGENERATE_TEXTURE, SHINE_SHIFT, MAX_SHINE_STRENGTH,
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::as_bytes::AsBytes;
+	use super::*;
+	
+	/// All fields at their maximum, so a field spilling into a neighbor shows.
+	const MAX: DecodedFaceInstance = DecodedFaceInstance {
+		face_array_index: u16::MAX,
+		face_index: u16::MAX,
+		transform_index: u16::MAX,
+		degenerate_texture: true,
+		shine_strength: MAX_SHINE_STRENGTH,
+		page: u8::MAX,
+		object_data_index: u32::MAX,
+	};
+	
+	const ZERO: DecodedFaceInstance = DecodedFaceInstance {
+		face_array_index: 0,
+		face_index: 0,
+		transform_index: 0,
+		degenerate_texture: false,
+		shine_strength: 0,
+		page: 0,
+		object_data_index: 0,
+	};
+	
+	/// Pack and decode from the words and from the bytes uploaded, as the shader reads them.
+	fn round_trip(fields: DecodedFaceInstance) {
+		let face_instance = FaceInstance::pack(fields);
+		let words = face_instance.words();
+		assert_eq!(decode(words), fields);
+		let bytes = face_instance.as_bytes();
+		let uploaded = [0, 1, 2].map(|i| u32::from_le_bytes(bytes[i * 4..][..4].try_into().unwrap()));
+		assert_eq!(uploaded, words);
+	}
+	
+	#[test]
+	fn pack_decode_round_trip() {
+		for base in [ZERO, MAX] {
+			for value in 0..=u16::MAX {
+				round_trip(DecodedFaceInstance { face_array_index: value, ..base });
+				round_trip(DecodedFaceInstance { face_index: value, ..base });
+				round_trip(DecodedFaceInstance { transform_index: value, ..base });
+			}
+			for degenerate_texture in [false, true] {
+				round_trip(DecodedFaceInstance { degenerate_texture, ..base });
+			}
+			for shine_strength in 0..=MAX_SHINE_STRENGTH {
+				round_trip(DecodedFaceInstance { shine_strength, ..base });
+			}
+			for page in 0..=u8::MAX {
+				round_trip(DecodedFaceInstance { page, ..base });
+				assert_eq!(FaceInstance::pack(DecodedFaceInstance { page, ..base }).page(), page);
+			}
+			for bit in 0..32 {
+				round_trip(DecodedFaceInstance { object_data_index: 1 << bit, ..base });
+				round_trip(DecodedFaceInstance { object_data_index: u32::MAX >> bit, ..base });
+			}
+		}
+	}
+	
+	#[test]
+	fn shine_strength_masked() {
+		let packed = FaceInstance::pack(DecodedFaceInstance { shine_strength: u8::MAX, ..ZERO });
+		assert_eq!(decode(packed.words()), DecodedFaceInstance { shine_strength: MAX_SHINE_STRENGTH, ..ZERO });
+	}
+	
+	fn on_page(page: u8) -> FaceInstance {
+		FaceInstance::pack(DecodedFaceInstance { page, ..ZERO })
+	}
+	
+	#[test]
+	fn page_faces_splits_range_by_page() {
+		let face_instances = [0, 0, 1, 1, 1, 3].map(on_page);
+		assert_eq!(page_faces(&face_instances, 0..6, 0), 0..2);
+		assert_eq!(page_faces(&face_instances, 0..6, 1), 2..5);
+		assert_eq!(page_faces(&face_instances, 0..6, 3), 5..6);
+		assert!(page_faces(&face_instances, 0..6, 2).is_empty());
+		assert_eq!(page_faces(&face_instances, 3..6, 1), 3..5);
+	}
+}
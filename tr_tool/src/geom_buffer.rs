@@ -1,10 +1,18 @@
-use std::{iter, mem::size_of};
-use glam::Mat4;
-use tr_model::tr1;
-use crate::{as_bytes::{AsBytes, ReinterpretAsBytes}, object_data::PolyType, tr_traits::Face};
+use std::{io::{Error, Result}, iter, mem::size_of};
+use glam::{Mat4, Vec3};
+use tr_model::{tr1, TrError};
+use crate::{as_bytes::{AsBytes, ReinterpretAsBytes}, console, object_data::PolyType, tr_traits::Face};
 
 /// 4 MB
 pub const GEOM_BUFFER_SIZE: usize = 4194304;
+/// Geometry bytes per page. The rest of each page holds the tables shared by all pages.
+const PAGE_GEOM_SIZE: usize = GEOM_BUFFER_SIZE / 4 * 3;
+/// Face instances hold the page in 8 bits.
+const MAX_PAGES: usize = 256;
+/// Set in a face array's face size when its faces are drawn with reversed winding.
+const REVERSED_FACE_ARRAY: u16 = 0x8000;
+/// Light of transforms written without one.
+const UNLIT: u32 = 0xFFFFFF;
 
 fn texture_offset(poly_type: PolyType) -> u16 {
 	match poly_type {
@@ -13,10 +21,23 @@ fn texture_offset(poly_type: PolyType) -> u16 {
 	}
 }
 
+/// Vertex array written to the geometry buffer.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct VertexArray {
+	page: usize,
+	/// Byte offset in the page.
+	offset: usize,
+	/// Bytes including the size header.
+	len: usize,
+}
+
 pub struct Output {
-	pub data_buffer: Box<[u8; GEOM_BUFFER_SIZE]>,
+	/// One buffer per page. Each holds its page's geometry followed by the shared tables at the same offsets.
+	pub data_buffers: Vec<Box<[u8; GEOM_BUFFER_SIZE]>>,
 	/// Offset of transforms in 16-byte units.
 	pub transforms_offset: u32,
+	/// Offset of transform lights in 4-byte units.
+	pub transform_lights_offset: u32,
 	/// Offset of face array offsets in 4-byte units.
 	pub face_array_offsets_offset: u32,
 	/// Offset of object textures in 2-byte units.
@@ -26,114 +47,287 @@ pub struct Output {
 }
 
 pub struct GeomBuffer {
-	geom: Vec<u8>,
+	/// Geometry split into pages that are bound one at a time. Never empty.
+	pages: Vec<Vec<u8>>,
+	/// Offsets into the page of each face array.
 	face_array_offsets: Vec<u32>,
+	/// Page of each face array.
+	face_array_pages: Vec<u8>,
 	transforms: Vec<Mat4>,
+	/// RGB8 light of each transform, multiplied into the color of its faces.
+	transform_lights: Vec<u32>,
+	/// Vertex array copied into the current page and its offset there in 4-byte units.
+	copied_vertex_array: Option<(VertexArray, u32)>,
+	/// Why the geometry doesn't fit, returned by `into_buffer`. Writing goes on so indices stay valid.
+	overflow: Option<&'static str>,
+}
+
+fn pad_to_4(geom: &mut Vec<u8>) {
+	let padding = geom.len() % 4;//always multiple of 2
+	geom.extend(iter::repeat_n(0, padding));
 }
 
 impl GeomBuffer {
 	pub fn new() -> Self {
 		Self {
-			geom: vec![],
+			pages: vec![vec![]],
 			face_array_offsets: vec![],
+			face_array_pages: vec![],
 			transforms: vec![],
+			transform_lights: vec![],
+			copied_vertex_array: None,
+			overflow: None,
+		}
+	}
+	
+	fn page(&mut self) -> &mut Vec<u8> {
+		//unwrap: pages never empty
+		self.pages.last_mut().unwrap()
+	}
+	
+	/// Start a new page if `size` more bytes don't fit in the current one.
+	fn reserve(&mut self, size: usize) {
+		if self.page().len() + 3 + size > PAGE_GEOM_SIZE {
+			if size > PAGE_GEOM_SIZE {
+				self.overflow.get_or_insert("geometry record larger than a page");
+			}
+			if self.pages.len() == MAX_PAGES {
+				self.overflow.get_or_insert("geometry exceeds page limit");
+				return;
+			}
+			self.pages.push(vec![]);
+			self.copied_vertex_array = None;
 		}
 	}
 	
+	pub fn face_array_page(&self, face_array_index: u16) -> u8 {
+		self.face_array_pages[face_array_index as usize]
+	}
+	
 	/**
 	Writes the following record to the geometry buffer 4-aligned:  
 	`SSSS[V..]`  
 	`S`: Vertex size in 2-byte units.  
 	`V`: Verices. Always a multiple of 2 bytes.  
-	Returns offset in 4-byte units.
 	*/
-	pub fn write_vertex_array<V: ReinterpretAsBytes>(&mut self, vertices: &[V]) -> u32 {
-		let offset = self.geom.len();//always multiple of 2
-		let padding = offset % 4;//pad to 4-align
-		self.geom.reserve(padding + 4 + size_of_val(vertices));
-		self.geom.extend(iter::repeat_n(0, padding));
-		self.geom.extend_from_slice((size_of::<V>() as u32 / 2).as_bytes());
-		self.geom.extend_from_slice(vertices.as_bytes());
-		(offset + padding) as u32 / 4
+	pub fn write_vertex_array<V: ReinterpretAsBytes>(&mut self, vertices: &[V]) -> VertexArray {
+		let len = 4 + size_of_val(vertices);
+		self.reserve(len);
+		let page = self.pages.len() - 1;
+		let geom = self.page();
+		pad_to_4(geom);
+		let offset = geom.len();
+		geom.extend_from_slice((size_of::<V>() as u32 / 2).as_bytes());
+		geom.extend_from_slice(vertices.as_bytes());
+		VertexArray { page, offset, len }
+	}
+	
+	/// Offset of the vertex array in the current page in 4-byte units, copying it there if it was written
+	/// to an earlier page.
+	fn vertex_array_offset(&mut self, vertex_array: VertexArray) -> u32 {
+		let current_page = self.pages.len() - 1;
+		if vertex_array.page == current_page {
+			return vertex_array.offset as u32 / 4;
+		}
+		if let Some((copied, offset)) = self.copied_vertex_array {
+			if copied == vertex_array {
+				return offset;
+			}
+		}
+		let (previous, current) = self.pages.split_at_mut(current_page);
+		let geom = &mut current[0];
+		pad_to_4(geom);
+		let offset = geom.len() as u32 / 4;
+		let bytes = &previous[vertex_array.page][vertex_array.offset..][..vertex_array.len];
+		geom.extend_from_slice(bytes);
+		self.copied_vertex_array = Some((vertex_array, offset));
+		offset
 	}
 	
 	/**
 	Writes the following record to the geometry buffer 4-aligned:  
 	`VVVVSSTT[F..]`  
 	`V`: Vertex array offset in 4-byte units.  
-	`S`: Face size in 2-byte units.  
+	`S`: Face size in 2-byte units, with `REVERSED_FACE_ARRAY` set if `reversed`.  
 	`T`: Texture offset in 2-byte units.  
 	`F`: Faces. Always a multiple of 2 bytes.  
-	Returns index of face array.
+	If the page is full, the face array and a copy of its vertex array go in a new page.  
+	Returns index of face array. Fails if there are already 65536 face arrays.
 	*/
-	pub fn write_face_array<F: Face>(&mut self, faces: &[F], vertex_array_offset: u32) -> u16 {
-		let index = self.face_array_offsets.len().try_into().unwrap();
-		let offset = self.geom.len();//always multiple of 2
-		let padding = offset % 4;//pad to 4-align
-		self.geom.reserve(padding + 8 + size_of_val(faces));
-		self.geom.extend(iter::repeat_n(0, padding));
-		self.geom.extend_from_slice(vertex_array_offset.as_bytes());
-		self.geom.extend_from_slice((size_of::<F>() as u16 / 2).as_bytes());
-		self.geom.extend_from_slice(texture_offset(F::POLY_TYPE).as_bytes());
-		self.geom.extend_from_slice(faces.as_bytes());
-		self.face_array_offsets.push((offset + padding) as u32 / 4);
-		index
+	pub fn write_face_array<F: Face>(
+		&mut self, faces: &[F], vertex_array: VertexArray, reversed: bool,
+	) -> Result<u16> {
+		let index = self
+			.face_array_offsets
+			.len()
+			.try_into()
+			.map_err(|_| TrError::Invalid("more than 65536 face arrays"))?;
+		let len = 8 + size_of_val(faces);
+		let current_page = self.pages.len() - 1;
+		let copied = matches!(self.copied_vertex_array, Some((copied, _)) if copied == vertex_array);
+		let copy_len = if vertex_array.page == current_page || copied { 0 } else { vertex_array.len + 3 };
+		self.reserve(copy_len + len);
+		if self.pages.len() - 1 != current_page && vertex_array.len + 3 + len > PAGE_GEOM_SIZE {
+			//the vertex array is copied to the new page as well
+			self.overflow.get_or_insert("geometry record larger than a page");
+		}
+		let vertex_array_offset = self.vertex_array_offset(vertex_array);
+		let page = (self.pages.len() - 1) as u8;
+		let geom = self.page();
+		pad_to_4(geom);
+		let offset = geom.len() as u32 / 4;
+		geom.extend_from_slice(vertex_array_offset.as_bytes());
+		let face_size = (size_of::<F>() as u16 / 2) | (reversed as u16 * REVERSED_FACE_ARRAY);
+		geom.extend_from_slice(face_size.as_bytes());
+		geom.extend_from_slice(texture_offset(F::POLY_TYPE).as_bytes());
+		geom.extend_from_slice(faces.as_bytes());
+		self.face_array_offsets.push(offset);
+		self.face_array_pages.push(page);
+		Ok(index)
+	}
+	
+	/// Fails if there are already 65536 transforms.
+	pub fn write_transform(&mut self, transform: &Mat4) -> Result<u16> {
+		self.push_transform(transform, UNLIT)
+	}
+	
+	/// Transform whose faces are tinted by `light`, RGB from 0 to 1.
+	pub fn write_tinted_transform(&mut self, transform: &Mat4, light: Vec3) -> Result<u16> {
+		let [r, g, b] = (light.clamp(Vec3::ZERO, Vec3::ONE) * 255.0).round().to_array().map(|c| c as u32);
+		self.push_transform(transform, r | g << 8 | b << 16)
 	}
 	
-	pub fn write_transform(&mut self, transform: &Mat4) -> u16 {
-		let index = self.transforms.len().try_into().unwrap();
+	fn push_transform(&mut self, transform: &Mat4, light: u32) -> Result<u16> {
+		let index = self.transforms.len().try_into().map_err(|_| TrError::Invalid("more than 65536 transforms"))?;
 		self.transforms.push(*transform);
-		index
+		self.transform_lights.push(light);
+		Ok(index)
 	}
 	
 	/**
-	Creates the following record:  
-	`[G..][P..][T..][F..][O..][S..]`  
-	`G`: Geometry data. Always a multiple of 2 bytes.  
-	`P`: Padding to align-16.  
+	Creates the following record for each page:  
+	`[G..][P..][T..][L..][F..][O..][S..]`  
+	`G`: Geometry data of the page. Always a multiple of 2 bytes.  
+	`P`: Padding to align-16 past the largest page's geometry, so tables are at the same offsets in all pages.  
 	`T`: Transform matrices. Always a multiple of 64 bytes.  
+	`L`: Transform lights, RGB8 in the low bytes of a u32. Always a multiple of 4 bytes.  
 	`F`: Face array offsets. Always a multiple of 4 bytes.  
 	`O`: Object textures. Always a multiple of 2 bytes.  
-	`S`: Sprite textures. Always a multiple of 2 bytes.
+	`S`: Sprite textures. Always a multiple of 2 bytes.  
+	Fails if a record didn't fit in a page, there were too many pages, or the tables don't fit after the geometry.
 	*/
 	pub fn into_buffer<O: ReinterpretAsBytes>(
 		self, object_textures: &[O], sprite_textures: &[tr1::SpriteTexture],
-	) -> Output {
-		let geom_bytes = self.geom.len();
+	) -> Result<Output> {
+		if let Some(overflow) = self.overflow {
+			return Err(Error::other(overflow));
+		}
+		//unwrap: pages never empty
+		let geom_bytes = self.pages.iter().map(|geom| geom.len()).max().unwrap();
 		let transforms_bytes = size_of_val(&*self.transforms);
+		let transform_lights_bytes = size_of_val(&*self.transform_lights);
 		let face_array_offsets_bytes = size_of_val(&*self.face_array_offsets);
 		let object_textures_bytes = size_of_val(object_textures);
 		let sprite_textures_bytes = size_of_val(sprite_textures);
 		
-		println!("geom_bytes: {}", geom_bytes);
-		println!("transforms_bytes: {}", transforms_bytes);
-		println!("face_array_offsets_bytes: {}", face_array_offsets_bytes);
-		println!("object_textures_bytes: {}", object_textures_bytes);
-		println!("sprite_textures_bytes: {}", sprite_textures_bytes);
+		console::info!("geom_bytes: {} ({} pages)", geom_bytes, self.pages.len());
+		console::info!("transforms_bytes: {}", transforms_bytes);
+		console::info!("transform_lights_bytes: {}", transform_lights_bytes);
+		console::info!("face_array_offsets_bytes: {}", face_array_offsets_bytes);
+		console::info!("object_textures_bytes: {}", object_textures_bytes);
+		console::info!("sprite_textures_bytes: {}", sprite_textures_bytes);
 		
 		let padding = (16 - (geom_bytes % 16)) % 16;
 		let transforms_offset = geom_bytes + padding;
-		let face_array_offsets_offset = transforms_offset + transforms_bytes;
+		let transform_lights_offset = transforms_offset + transforms_bytes;
+		let face_array_offsets_offset = transform_lights_offset + transform_lights_bytes;
 		let object_textures_offset = face_array_offsets_offset + face_array_offsets_bytes;
 		let sprite_textures_offset = object_textures_offset + object_textures_bytes;
 		let size = sprite_textures_offset + sprite_textures_bytes;
 		
-		println!("total: {}", size);
-		assert!(size < GEOM_BUFFER_SIZE);
+		console::info!("total: {}", size);
+		if size > GEOM_BUFFER_SIZE {
+			return Err(Error::other("geometry tables exceed the buffer size"));
+		}
 		
-		let mut data_buffer = unsafe { Box::<[u8; GEOM_BUFFER_SIZE]>::new_uninit().assume_init() };
-		data_buffer[..geom_bytes].copy_from_slice(&self.geom);
-		data_buffer[transforms_offset..][..transforms_bytes].copy_from_slice(self.transforms.as_bytes());
-		data_buffer[face_array_offsets_offset..][..face_array_offsets_bytes].copy_from_slice(self.face_array_offsets.as_bytes());
-		data_buffer[object_textures_offset..][..object_textures_bytes].copy_from_slice(object_textures.as_bytes());
-		data_buffer[sprite_textures_offset..][..sprite_textures_bytes].copy_from_slice(sprite_textures.as_bytes());
+		let data_buffers = self.pages.iter().map(|geom| {
+			let mut data_buffer = unsafe { Box::<[u8; GEOM_BUFFER_SIZE]>::new_uninit().assume_init() };
+			data_buffer[..geom.len()].copy_from_slice(geom);
+			data_buffer[transforms_offset..][..transforms_bytes].copy_from_slice(self.transforms.as_bytes());
+			data_buffer[transform_lights_offset..][..transform_lights_bytes]
+				.copy_from_slice(self.transform_lights.as_bytes());
+			data_buffer[face_array_offsets_offset..][..face_array_offsets_bytes].copy_from_slice(self.face_array_offsets.as_bytes());
+			data_buffer[object_textures_offset..][..object_textures_bytes].copy_from_slice(object_textures.as_bytes());
+			data_buffer[sprite_textures_offset..][..sprite_textures_bytes].copy_from_slice(sprite_textures.as_bytes());
+			data_buffer
+		}).collect();
 		
-		Output {
-			data_buffer,
+		Ok(Output {
+			data_buffers,
 			transforms_offset: transforms_offset as u32 / 16,
+			transform_lights_offset: transform_lights_offset as u32 / 4,
 			face_array_offsets_offset: face_array_offsets_offset as u32 / 4,
 			object_textures_offset: object_textures_offset as u32 / 2,
 			sprite_textures_offset: sprite_textures_offset as u32 / 2,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use glam::I16Vec3;
+	use super::*;
+	
+	/// Quads filling `bytes` of a page.
+	fn quads(bytes: usize) -> Vec<tr1::TexturedQuad> {
+		let quad = tr1::TexturedQuad { vertex_indices: [0, 1, 2, 3], object_texture_index: 0 };
+		vec![quad; bytes / size_of::<tr1::TexturedQuad>()]
+	}
+	
+	fn u32_at(data_buffer: &[u8], offset: usize) -> u32 {
+		u32::from_le_bytes(data_buffer[offset..][..4].try_into().unwrap())
+	}
+	
+	#[test]
+	fn face_array_overflowing_page_starts_next_page_with_its_vertices() {
+		let vertices = (0..4).map(|i| tr1::RoomVertex { pos: I16Vec3::splat(i), light: 0 }).collect::<Vec<_>>();
+		let mut geom_buffer = GeomBuffer::new();
+		let vertex_array = geom_buffer.write_vertex_array(&vertices);
+		let first = geom_buffer.write_face_array(&quads(PAGE_GEOM_SIZE / 3 * 2), vertex_array, false).unwrap();
+		let second = geom_buffer.write_face_array(&quads(PAGE_GEOM_SIZE / 3 * 2), vertex_array, false).unwrap();
+		assert_eq!((geom_buffer.face_array_page(first), geom_buffer.face_array_page(second)), (0, 1));
+		let output = geom_buffer.into_buffer::<tr1::ObjectTexture>(&[], &[]).unwrap();
+		assert_eq!(output.data_buffers.len(), 2);
+		let face_array_offsets = output.face_array_offsets_offset as usize * 4;
+		let [page_0, page_1] = [0, 1].map(|page| &output.data_buffers[page][face_array_offsets..][..8]);
+		assert_eq!(page_0, page_1);
+		//the second face array's vertex array offset leads to a copy of the vertices in the second page
+		let data_buffer = &*output.data_buffers[1];
+		let face_array_offset = u32_at(data_buffer, face_array_offsets + 4) as usize * 4;
+		let vertex_array_offset = u32_at(data_buffer, face_array_offset) as usize * 4;
+		assert_eq!(u32_at(data_buffer, vertex_array_offset), size_of::<tr1::RoomVertex>() as u32 / 2);
+		assert_eq!(&data_buffer[vertex_array_offset + 4..][..size_of_val(&*vertices)], vertices.as_bytes());
+	}
+	
+	#[test]
+	fn face_array_larger_than_page_is_an_error() {
+		let mut geom_buffer = GeomBuffer::new();
+		let vertex_array = geom_buffer.write_vertex_array(&[tr1::RoomVertex { pos: I16Vec3::ZERO, light: 0 }]);
+		geom_buffer.write_face_array(&quads(PAGE_GEOM_SIZE + 1024), vertex_array, false).unwrap();
+		assert!(geom_buffer.into_buffer::<tr1::ObjectTexture>(&[], &[]).is_err());
+	}
+	
+	#[test]
+	fn face_array_and_transform_past_u16_range_are_errors() {
+		let mut geom_buffer = GeomBuffer::new();
+		let vertex_array = geom_buffer.write_vertex_array(&[tr1::RoomVertex { pos: I16Vec3::ZERO, light: 0 }]);
+		for _ in 0..65536 {
+			geom_buffer.write_face_array::<tr1::TexturedQuad>(&[], vertex_array, false).unwrap();
+			geom_buffer.write_transform(&Mat4::IDENTITY).unwrap();
 		}
+		let error = geom_buffer.write_face_array::<tr1::TexturedQuad>(&[], vertex_array, false).unwrap_err();
+		assert_eq!(TrError::of(&error), Some(&TrError::Invalid("more than 65536 face arrays")));
+		let error = geom_buffer.write_transform(&Mat4::IDENTITY).unwrap_err();
+		assert_eq!(TrError::of(&error), Some(&TrError::Invalid("more than 65536 transforms")));
 	}
 }
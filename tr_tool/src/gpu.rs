@@ -0,0 +1,41 @@
+//! Device setup shared by the window and offscreen rendering, which must not need winit.
+use std::future::Future;
+use pollster::block_on;
+use wgpu::{Adapter, Device, DeviceDescriptor, Features, Limits, Queue, RequestDeviceError};
+use crate::geom_buffer::GEOM_BUFFER_SIZE;
+
+/// Same shape as winit's, which the window converts from.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct PhysicalSize<T> {
+	pub width: T,
+	pub height: T,
+}
+
+impl<T> PhysicalSize<T> {
+	pub fn new(width: T, height: T) -> Self {
+		Self { width, height }
+	}
+}
+
+pub trait Wait: Future {
+	fn wait(self) -> Self::Output;
+}
+
+impl<T: Future> Wait for T {
+	fn wait(self) -> Self::Output {
+		block_on(self)
+	}
+}
+
+pub fn request_device(adapter: &Adapter) -> Result<(Device, Queue), RequestDeviceError> {
+	let mut required_limits = Limits::downlevel_webgl2_defaults().using_resolution(adapter.limits());
+	required_limits.max_storage_buffers_per_shader_stage = 1;
+	required_limits.max_storage_buffer_binding_size = GEOM_BUFFER_SIZE as u32;
+	required_limits.max_texture_array_layers = 512;
+	adapter
+		.request_device(
+			&DeviceDescriptor { label: None, required_features: Features::empty(), required_limits },
+			None,
+		)
+		.wait()
+}
@@ -1,13 +1,46 @@
+//! The viewer window. Everything that needs egui or winit lives under this module, so builds without the `gui`
+//! feature gate only its declaration.
+
+mod viewer;
+mod keys;
+mod file_dialog;
+pub(crate) mod log;
+mod atlas_usage;
+mod room_graph;
+mod room_path;
+mod obj;
+mod compare;
+mod export_space;
+mod script;
+mod flyby;
+mod cinematic;
+mod demo_playback;
+mod camera_path;
+mod turntable;
+mod footstep;
+mod units;
+mod walk;
+mod playlist;
+mod mesh_viewer;
+mod object_info;
+mod sfx;
+mod sounds;
+mod pick;
+mod overrides;
+mod settings;
+mod stats_window;
+mod texture_export;
+
+pub use viewer::run_viewer;
+
 use glam::DVec2;
-use pollster::block_on;
 use std::{
-	future::Future, num::NonZeroU32, sync::{mpsc::{channel, TryRecvError}, Arc}, thread::{sleep, spawn},
-	time::{Duration, Instant},
+	num::NonZeroU32, sync::{mpsc::{channel, TryRecvError}, Arc}, thread::{sleep, spawn}, time::{Duration, Instant},
 };
 use wgpu::{
-	CommandEncoder, CommandEncoderDescriptor, Device, DeviceDescriptor, Features, Instance, Limits,
-	LoadOp, Operations, PowerPreference, Queue, RenderPassColorAttachment, RenderPassDescriptor,
-	RequestAdapterOptions, StoreOp, TextureFormat, TextureView, TextureViewDescriptor,
+	CommandEncoder, CommandEncoderDescriptor, Device, Instance, LoadOp, Operations, PowerPreference, Queue,
+	RenderPassColorAttachment, RenderPassDescriptor, RequestAdapterOptions, StoreOp, SurfaceError, TextureFormat,
+	TextureView, TextureViewDescriptor,
 };
 use winit::{
 	dpi::{PhysicalPosition, PhysicalSize},
@@ -17,29 +50,25 @@ use winit::{
 	platform::windows::WindowBuilderExtWindows,
 	window::{Icon, Window, WindowBuilder},
 };
-use crate::geom_buffer::GEOM_BUFFER_SIZE;
+use crate::gpu::{request_device, Wait};
 
 const TEXTURE_FORMAT: TextureFormat = TextureFormat::Bgra8Unorm;
 
-trait Wait: Future {
-	fn wait(self) -> Self::Output;
-}
-
-impl<T: Future> Wait for T {
-	fn wait(self) -> Self::Output {
-		block_on(self)
+impl From<PhysicalSize<u32>> for crate::gpu::PhysicalSize<u32> {
+	fn from(PhysicalSize { width, height }: PhysicalSize<u32>) -> Self {
+		Self { width, height }
 	}
 }
 
-fn sb_surface(window: &Window, size: PhysicalSize<u32>) -> softbuffer::Surface<&Window, &Window> {
+/// Returns `None` if the window has zero size (e.g. minimized).
+fn sb_surface(window: &Window, size: PhysicalSize<u32>) -> Option<softbuffer::Surface<&Window, &Window>> {
+	let width = NonZeroU32::new(size.width)?;
+	let height = NonZeroU32::new(size.height)?;
 	let mut surface = softbuffer::Surface::new(
 		&softbuffer::Context::new(window).expect("sb context"), window,
 	).expect("sb surface");
-	surface.resize(
-		NonZeroU32::new(size.width).expect("nonzero window width"),
-		NonZeroU32::new(size.width).expect("nonzero window height"),
-	).expect("sb resize");
-	surface
+	surface.resize(width, height).expect("sb resize");
+	Some(surface)
 }
 
 pub trait Gui {
@@ -57,6 +86,8 @@ pub trait Gui {
 		&mut self, encoder: &mut CommandEncoder, view: &TextureView, delta_time: Duration,
 		last_render_time: Duration,
 	);
+	/// The event loop is about to stop.
+	fn exiting(&mut self);
 }
 
 pub fn run<G, F>(title: &str, window_icon: Icon, taskbar_icon: Icon, make_gui: F)
@@ -72,12 +103,16 @@ where G: Gui, F: FnOnce(Arc<Window>, Arc<Device>, Arc<Queue>, PhysicalSize<u32>)
 		.build(&event_loop)
 		.expect("build window");
 	let window = Arc::new(window);
-	let mut window_size = window.inner_size();
+	let inner_size = window.inner_size();
+	//surface and render targets must be nonzero, even if the window starts minimized
+	let mut window_size = PhysicalSize::new(inner_size.width.max(1), inner_size.height.max(1));
 	let painter_window = window.clone();
 	let (tx, rx) = channel();
 	let painter = spawn(move || {
-		let mut surface = sb_surface(&painter_window, window_size);
-		let w = window_size.width;
+		let Some(mut surface) = sb_surface(&painter_window, inner_size) else {
+			return;
+		};
+		let w = inner_size.width;
 		let mut t = 0;
 		while let Err(TryRecvError::Empty) = rx.try_recv() {
 			let mut buffer = surface.buffer_mut().expect("sb buffer_mut");
@@ -102,17 +137,7 @@ where G: Gui, F: FnOnce(Arc<Window>, Arc<Device>, Arc<Queue>, PhysicalSize<u32>)
 		)
 		.wait()
 		.expect("request adapter");//430ms
-	let mut required_limits = Limits::downlevel_webgl2_defaults().using_resolution(adapter.limits());
-	required_limits.max_storage_buffers_per_shader_stage = 1;
-	required_limits.max_storage_buffer_binding_size = GEOM_BUFFER_SIZE as u32;
-	required_limits.max_texture_array_layers = 512;
-	let (device, queue) = adapter
-		.request_device(
-			&DeviceDescriptor { label: None, required_features: Features::empty(), required_limits },
-			None,
-		)
-		.wait()
-		.expect("request device");//250ms
+	let (device, queue) = request_device(&adapter).expect("request device");//250ms
 	let device = Arc::new(device);
 	let queue = Arc::new(queue);
 	let mut config = surface
@@ -126,11 +151,11 @@ where G: Gui, F: FnOnce(Arc<Window>, Arc<Device>, Arc<Queue>, PhysicalSize<u32>)
 	);
 	let mut egui_renderer = egui_wgpu::Renderer::new(&device, TEXTURE_FORMAT, None, 1);
 	let mut gui = make_gui(window.clone(), device.clone(), queue.clone(), window_size);
-	tx.send(()).expect("signal painter");
+	_ = tx.send(());//painter may have already returned if window is zero-size
 	painter.join().expect("join painter");
 	let mut last_frame = Instant::now();
 	let mut last_render_time = Duration::ZERO;
-	let mut draw = true;
+	let mut draw = inner_size.width * inner_size.height != 0;
 	event_loop.run(|event, target| match event {
 		Event::DeviceEvent { event: DeviceEvent::MouseMotion { delta: (x, y) }, .. } => {
 			gui.mouse_motion(DVec2 { x, y });
@@ -155,6 +180,7 @@ where G: Gui, F: FnOnce(Arc<Window>, Arc<Device>, Arc<Queue>, PhysicalSize<u32>)
 							surface.configure(&device, &config);
 							gui.resize(window_size);
 							draw = true;
+							window.request_redraw();//redraw loop stops while minimized
 						} else {
 							draw = false
 						}
@@ -162,9 +188,21 @@ where G: Gui, F: FnOnce(Arc<Window>, Arc<Device>, Arc<Queue>, PhysicalSize<u32>)
 					WindowEvent::RedrawRequested => if draw {
 						let start = Instant::now();
 						let delta_time = start - last_frame;
+						let frame = match surface.get_current_texture() {
+							Ok(frame) => frame,
+							Err(SurfaceError::Lost | SurfaceError::Outdated) => {
+								surface.configure(&device, &config);
+								window.request_redraw();
+								return;
+							},
+							Err(SurfaceError::Timeout) => {
+								window.request_redraw();
+								return;
+							},
+							Err(SurfaceError::OutOfMemory) => panic!("get current texture: out of memory"),
+						};
 						let mut encoder = device
 							.create_command_encoder(&CommandEncoderDescriptor::default());
-						let frame = surface.get_current_texture().expect("get current texture");
 						let view = &frame.texture.create_view(&TextureViewDescriptor::default());
 						
 						gui.render(&mut encoder, view, delta_time, last_render_time);
@@ -217,6 +255,7 @@ where G: Gui, F: FnOnce(Arc<Window>, Arc<Device>, Arc<Queue>, PhysicalSize<u32>)
 				}
 			}
 		},
+		Event::LoopExiting => gui.exiting(),
 		_ => {},
 	}).expect("run event loop");
 }
@@ -0,0 +1,202 @@
+use std::cmp::Reverse;
+use glam::{U16Vec2, UVec2};
+use tr_model::tr1;
+use crate::tr_traits::{Level, ObjectTexture};
+
+const SIDE: u32 = tr1::ATLAS_SIDE_LEN as u32;
+const WORDS_PER_ATLAS: usize = (SIDE * SIDE / 64) as usize;
+
+/// Pixel rectangle in an atlas, `max` exclusive.
+#[derive(Clone, Copy)]
+pub struct Rect {
+	pub atlas_index: usize,
+	pub min: UVec2,
+	pub max: UVec2,
+}
+
+impl Rect {
+	fn contains(&self, atlas_index: usize, pixel: UVec2) -> bool {
+		self.atlas_index == atlas_index && self.min.cmple(pixel).all() && pixel.cmplt(self.max).all()
+	}
+	
+	fn overlaps(&self, other: &Rect) -> bool {
+		self.atlas_index == other.atlas_index && self.min.cmplt(other.max).all() && other.min.cmplt(self.max).all()
+	}
+	
+	fn union(&self, other: &Rect) -> Rect {
+		Rect { atlas_index: self.atlas_index, min: self.min.min(other.min), max: self.max.max(other.max) }
+	}
+}
+
+/// Atlas space used by object and sprite textures, and an estimate of the atlases they would need if repacked.
+pub struct AtlasUsage {
+	/// Pixels covered by at least one texture, by atlas.
+	pub occupied: Vec<u32>,
+	pub packed_atlases: usize,
+}
+
+impl AtlasUsage {
+	pub fn occupancy(&self, atlas_index: usize) -> f32 {
+		self.occupied[atlas_index] as f32 / (SIDE * SIDE) as f32
+	}
+}
+
+/// Bounds of the UVs rounded to whole pixels as rendered. Tri textures use the first three UVs, TR1-3 tri
+/// textures being told apart by a zero fourth UV.
+pub fn object_texture_rect<O: ObjectTexture>(object_texture: &O) -> Rect {
+	let uvs = object_texture.uvs();
+	let tri = object_texture.tri().unwrap_or(uvs[3] == U16Vec2::ZERO);
+	let uvs = if tri { &uvs[..3] } else { &uvs[..] };
+	let pixels = uvs.iter().map(|&uv| ((uv.as_uvec2() + 128) / 256).min(UVec2::splat(SIDE)));
+	let (min, max) = pixels.fold((UVec2::splat(SIDE), UVec2::ZERO), |(min, max), p| (min.min(p), max.max(p)));
+	//single pixel wide textures still occupy their pixel
+	let max = max.max(min + 1).min(UVec2::splat(SIDE));
+	Rect { atlas_index: object_texture.atlas_index() as usize, min, max }
+}
+
+/// Index of the object texture whose bounds contain `pixel` of the atlas, the smallest if several overlap.
+pub fn object_texture_at<L: Level>(level: &L, atlas_index: usize, pixel: UVec2) -> Option<usize> {
+	level
+		.object_textures()
+		.iter()
+		.map(object_texture_rect)
+		.enumerate()
+		.filter(|(_, rect)| rect.contains(atlas_index, pixel))
+		.min_by_key(|(_, rect)| (rect.max - rect.min).element_product())
+		.map(|(index, _)| index)
+}
+
+pub fn sprite_texture_rect(sprite_texture: &tr1::SpriteTexture) -> Rect {
+	let min = sprite_texture.pos.as_uvec2();
+	let max = (min + sprite_texture.size.as_uvec2() / 256).min(UVec2::splat(SIDE));
+	Rect { atlas_index: sprite_texture.atlas_index as usize, min, max }
+}
+
+/// Merge overlapping rectangles into their bounds until none overlap, so shared regions are packed once.
+fn merge_overlapping(mut rects: Vec<Rect>) -> Vec<Rect> {
+	let mut merged = Vec::<Rect>::with_capacity(rects.len());
+	while let Some(mut rect) = rects.pop() {
+		while let Some(index) = merged.iter().position(|other| other.overlaps(&rect)) {
+			rect = rect.union(&merged.swap_remove(index));
+		}
+		merged.push(rect);
+	}
+	merged
+}
+
+/// Number of atlases a shelf packer fits `sizes` into, tallest first, each into the first shelf with room.
+fn pack(mut sizes: Vec<UVec2>) -> usize {
+	struct Shelf {
+		height: u32,
+		used_width: u32,
+	}
+	sizes.sort_unstable_by_key(|size| Reverse((size.y, size.x)));
+	let mut shelves = Vec::<Shelf>::new();
+	//used height of each atlas
+	let mut atlases = Vec::<u32>::new();
+	for size in sizes {
+		let shelf = shelves.iter_mut().find(|shelf| size.y <= shelf.height && shelf.used_width + size.x <= SIDE);
+		if let Some(shelf) = shelf {
+			shelf.used_width += size.x;
+			continue;
+		}
+		match atlases.iter_mut().find(|used_height| **used_height + size.y <= SIDE) {
+			Some(used_height) => *used_height += size.y,
+			None => atlases.push(size.y),
+		}
+		shelves.push(Shelf { height: size.y, used_width: size.x });
+	}
+	atlases.len()
+}
+
+pub fn atlas_usage<L: Level>(level: &L) -> AtlasUsage {
+	let num_atlases = level.num_atlases();
+	let rects = level
+		.object_textures()
+		.iter()
+		.map(object_texture_rect)
+		.chain(level.sprite_textures().iter().map(sprite_texture_rect))
+		.filter(|rect| rect.atlas_index < num_atlases && rect.min.cmplt(rect.max).all())
+		.collect::<Vec<_>>();
+	let mut bits = vec![0u64; num_atlases * WORDS_PER_ATLAS];
+	for rect in &rects {
+		let atlas_bits = &mut bits[rect.atlas_index * WORDS_PER_ATLAS..][..WORDS_PER_ATLAS];
+		for y in rect.min.y..rect.max.y {
+			for x in rect.min.x..rect.max.x {
+				let bit = (y * SIDE + x) as usize;
+				atlas_bits[bit / 64] |= 1 << (bit % 64);
+			}
+		}
+	}
+	let occupied = bits
+		.chunks_exact(WORDS_PER_ATLAS)
+		.map(|atlas_bits| atlas_bits.iter().map(|word| word.count_ones()).sum())
+		.collect();
+	let sizes = merge_overlapping(rects).into_iter().map(|rect| rect.max - rect.min).collect();
+	AtlasUsage { occupied, packed_atlases: pack(sizes) }
+}
+
+#[cfg(test)]
+mod tests {
+	use glam::U16Vec2;
+	use crate::test_level;
+	use super::*;
+	
+	fn rect(min: [u32; 2], max: [u32; 2]) -> Rect {
+		Rect { atlas_index: 0, min: UVec2::from_array(min), max: UVec2::from_array(max) }
+	}
+	
+	fn sizes(size: [u32; 2], count: usize) -> Vec<UVec2> {
+		vec![UVec2::from_array(size); count]
+	}
+	
+	#[test]
+	fn pack_exact_fits() {
+		assert_eq!(pack(vec![]), 0);
+		assert_eq!(pack(sizes([256, 256], 3)), 3);
+		assert_eq!(pack(sizes([128, 128], 4)), 1);
+		assert_eq!(pack(sizes([128, 128], 5)), 2);
+		assert_eq!(pack(sizes([16, 16], 256)), 1);
+		assert_eq!(pack(sizes([16, 16], 257)), 2);
+	}
+	
+	#[test]
+	fn pack_fills_shelves_tallest_first() {
+		//a 128 high shelf takes the 256 wide row, the 64 high ones stack under it
+		let mut rects = sizes([64, 64], 8);
+		rects.extend(sizes([128, 128], 2));
+		assert_eq!(pack(rects), 1);
+		//short rects fit beside taller ones on an open shelf
+		let mut rects = sizes([200, 100], 2);
+		rects.extend(sizes([56, 10], 20));
+		assert_eq!(pack(rects), 1);
+		assert_eq!(pack(sizes([200, 100], 3)), 2);
+	}
+	
+	#[test]
+	fn merge_overlapping_rects() {
+		let merged = merge_overlapping(vec![rect([0, 0], [10, 10]), rect([20, 0], [30, 10])]);
+		assert_eq!(merged.len(), 2);
+		//the third only overlaps the bounds of the first two
+		let rects = vec![rect([0, 0], [10, 10]), rect([5, 5], [20, 20]), rect([15, 0], [25, 4])];
+		let merged = merge_overlapping(rects);
+		assert_eq!(merged.len(), 1);
+		assert_eq!((merged[0].min, merged[0].max), (UVec2::new(0, 0), UVec2::new(25, 20)));
+		let other_atlas = Rect { atlas_index: 1, ..rect([0, 0], [10, 10]) };
+		assert_eq!(merge_overlapping(vec![rect([0, 0], [10, 10]), other_atlas]).len(), 2);
+	}
+	
+	#[test]
+	fn occupancy_counts_overlap_once() {
+		let mut tiny = test_level::tiny();
+		let mut shifted = tiny.object_textures[0].clone();
+		shifted.uvs = shifted.uvs.map(|uv| uv + U16Vec2::splat(32 * 256));
+		tiny.object_textures.push(shifted);
+		let usage = atlas_usage(tiny.level().as_ref());
+		//two 63 pixel squares overlapping by 31, and the 31 pixel sprite beside them
+		let squares = 63 * 63 * 2 - 31 * 31;
+		let sprite = 31 * 31;
+		assert_eq!(usage.occupied, [squares + sprite]);
+		assert_eq!(usage.packed_atlases, 1);
+	}
+}
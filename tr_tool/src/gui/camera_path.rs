@@ -0,0 +1,110 @@
+use std::{
+	fmt::{self, Display, Formatter},
+	io::{Error, Result},
+	str::FromStr,
+};
+use glam::{EulerRot, Quat};
+use crate::snapshot::CameraSpec;
+use super::flyby::catmull_rom;
+
+/// Camera at `time` seconds from the start of the path.
+#[derive(Clone, Copy)]
+pub struct Keyframe {
+	pub time: f32,
+	pub camera: CameraSpec,
+}
+
+/// Recorded fly-through, keyframes in time order.
+#[derive(Default)]
+pub struct CameraPath {
+	pub keyframes: Vec<Keyframe>,
+}
+
+fn orientation(camera: &CameraSpec) -> Quat {
+	Quat::from_euler(EulerRot::YXZ, camera.yaw, camera.pitch, 0.0)
+}
+
+impl CameraPath {
+	pub fn duration(&self) -> f32 {
+		self.keyframes.last().map_or(0.0, |keyframe| keyframe.time)
+	}
+	
+	/// Append `camera` `interval` seconds after the last keyframe, or at 0 for the first.
+	pub fn record(&mut self, camera: CameraSpec, interval: f32) {
+		let time = if self.keyframes.is_empty() { 0.0 } else { self.duration() + interval };
+		self.keyframes.push(Keyframe { time, camera });
+	}
+	
+	/// Camera at `time`, clamped to the path. Position follows a Catmull-Rom spline through the keyframes with
+	/// end keyframes repeated as control points, orientation is slerped. `None` if empty.
+	pub fn sample(&self, time: f32) -> Option<CameraSpec> {
+		let last = self.keyframes.len().checked_sub(1)?;
+		let index = self.keyframes.partition_point(|keyframe| keyframe.time <= time).clamp(1, last.max(1)) - 1;
+		let keyframe = |offset: isize| &self.keyframes[(index as isize + offset).clamp(0, last as isize) as usize];
+		let [k0, k1, k2, k3] = [keyframe(-1), keyframe(0), keyframe(1), keyframe(2)];
+		let span = k2.time - k1.time;
+		let t = if span > 0.0 { ((time - k1.time) / span).clamp(0.0, 1.0) } else { 0.0 };
+		let pos = catmull_rom(k0.camera.pos, k1.camera.pos, k2.camera.pos, k3.camera.pos, t);
+		let (yaw, pitch, _) = orientation(&k1.camera).slerp(orientation(&k2.camera), t).to_euler(EulerRot::YXZ);
+		Some(CameraSpec { pos, yaw, pitch })
+	}
+}
+
+/// One keyframe per line, `time camera x y z yaw pitch`.
+impl Display for CameraPath {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		for Keyframe { time, camera } in &self.keyframes {
+			writeln!(f, "{:.3} {}", time, camera)?;
+		}
+		Ok(())
+	}
+}
+
+/// Blank lines are skipped. Keyframes must be in time order.
+impl FromStr for CameraPath {
+	type Err = Error;
+	
+	fn from_str(s: &str) -> Result<Self> {
+		let mut keyframes = Vec::<Keyframe>::new();
+		for (line_index, line) in s.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+			let line_error = |e: Error| Error::other(format!("line {}: {}", line_index + 1, e));
+			let (time, camera) = line.trim().split_once(char::is_whitespace).unwrap_or((line.trim(), ""));
+			let time = time.parse::<f32>().map_err(|e| line_error(Error::other(e)))?;
+			let camera = camera.parse::<CameraSpec>().map_err(line_error)?;
+			if keyframes.last().is_some_and(|last| time < last.time) {
+				return Err(line_error(Error::other("keyframe before the previous one")));
+			}
+			keyframes.push(Keyframe { time, camera });
+		}
+		Ok(CameraPath { keyframes })
+	}
+}
+
+/// Timeline position over a `CameraPath`.
+pub struct CameraPathPlayback {
+	pub time: f32,
+	pub playing: bool,
+	/// Multiplier on real time.
+	pub speed: f32,
+}
+
+impl Default for CameraPathPlayback {
+	fn default() -> Self {
+		Self { time: 0.0, playing: false, speed: 1.0 }
+	}
+}
+
+impl CameraPathPlayback {
+	/// Advance by `delta_secs` if playing, stopping at the end. Camera to show, `None` if paused.
+	pub fn advance(&mut self, path: &CameraPath, delta_secs: f32) -> Option<CameraSpec> {
+		if !self.playing {
+			return None;
+		}
+		self.time += delta_secs * self.speed;
+		if self.time >= path.duration() {
+			self.time = path.duration();
+			self.playing = false;
+		}
+		path.sample(self.time)
+	}
+}
@@ -0,0 +1,59 @@
+use std::f32::consts::TAU;
+use glam::{Mat4, Vec3};
+use tr_model::tr1;
+use crate::gpu::PhysicalSize;
+
+/// Cinematic frames play one per game frame.
+const FPS: f32 = 30.0;
+
+/// Cinematic frame in world space.
+pub struct CinematicFrame {
+	pub pos: Vec3,
+	pub target: Vec3,
+	/// Horizontal field of view in radians.
+	pub fov: f32,
+	/// Radians.
+	pub roll: f32,
+}
+
+fn angle_radians(angle: i16) -> f32 {
+	angle as u16 as f32 / 65536.0 * TAU
+}
+
+/// Frames are relative to the cutscene origin, placed by `origin`.
+pub fn frames(cinematic_frames: &[tr1::CinematicFrame], origin: Mat4) -> Vec<CinematicFrame> {
+	cinematic_frames
+		.iter()
+		.map(|frame| CinematicFrame {
+			pos: origin.transform_point3(frame.pos.as_vec3()),
+			target: origin.transform_point3(frame.target.as_vec3()),
+			fov: angle_radians(frame.fov),
+			roll: angle_radians(frame.roll),
+		})
+		.collect()
+}
+
+/// Vertical field of view showing `fov` horizontally in a window of `window_size`.
+pub fn vertical_fov(fov: f32, window_size: PhysicalSize<u32>) -> f32 {
+	let aspect_ratio = window_size.width.max(1) as f32 / window_size.height.max(1) as f32;
+	2.0 * ((fov / 2.0).tan() / aspect_ratio).atan()
+}
+
+/// Camera stepping through the frames at the game's frame rate.
+#[derive(Default)]
+pub struct CinematicPlayback {
+	time: f32,
+}
+
+impl CinematicPlayback {
+	pub fn frame_index(&self) -> usize {
+		(self.time * FPS) as usize
+	}
+	
+	/// Frame to show, then advance by `delta_secs`. `None` when done.
+	pub fn advance<'a>(&mut self, frames: &'a [CinematicFrame], delta_secs: f32) -> Option<&'a CinematicFrame> {
+		let frame = frames.get(self.frame_index())?;
+		self.time += delta_secs;
+		Some(frame)
+	}
+}
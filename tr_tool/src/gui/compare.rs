@@ -0,0 +1,283 @@
+use std::io::{Error, Result};
+use glam::IVec3;
+use tr_model::fnv1a;
+use crate::{as_bytes::AsBytes, tr_traits::{Entity, Level, LevelStore, Room, RoomGeom}};
+
+/// Unmatched entities of the same model within this distance on every axis are considered moved.
+const MOVE_DISTANCE: i32 = 1024;
+
+/// Room present in both levels with different position or geometry. Counts are `[old, new]`.
+pub struct RoomChange {
+	pub room_index: usize,
+	pub vertices: [usize; 2],
+	pub faces: [usize; 2],
+	pub pos: [IVec3; 2],
+}
+
+pub struct EntityMove {
+	pub old_index: usize,
+	pub new_index: usize,
+	pub model_id: u16,
+	pub pos: [IVec3; 2],
+}
+
+/// Structural differences between two levels of the same version. Lists of removed items hold old
+/// indices, lists of added items hold new indices.
+pub struct LevelDiff {
+	pub num_rooms: [usize; 2],
+	pub rooms_added: Vec<usize>,
+	pub rooms_removed: Vec<usize>,
+	pub rooms_changed: Vec<RoomChange>,
+	pub num_entities: [usize; 2],
+	pub entities_added: Vec<usize>,
+	pub entities_removed: Vec<usize>,
+	pub entities_moved: Vec<EntityMove>,
+	pub num_object_textures: [usize; 2],
+	pub object_textures_changed: Vec<usize>,
+	pub palette_24bit_changed: Vec<usize>,
+	pub palette_32bit_changed: Vec<usize>,
+}
+
+/// Combined hash of room geometry bytes.
+fn geom_hash<R: Room>(room: &R) -> u64 {
+	let hashes = room
+		.geom()
+		.into_iter()
+		.flat_map(|RoomGeom { vertices, quads, tris }| {
+			[fnv1a(vertices.as_bytes()), fnv1a(quads.as_bytes()), fnv1a(tris.as_bytes())]
+		})
+		.flat_map(u64::to_le_bytes)
+		.collect::<Vec<_>>();
+	fnv1a(&hashes)
+}
+
+fn num_faces<R: Room>(room: &R) -> usize {
+	room.geom().into_iter().map(|RoomGeom { quads, tris, .. }| quads.len() + tris.len()).sum()
+}
+
+/// Indices in the common range whose bytes differ.
+fn changed_indices<T: AsBytes>(old: &[T], new: &[T]) -> Vec<usize> {
+	old
+		.iter()
+		.zip(new)
+		.enumerate()
+		.filter(|(_, (old, new))| old.as_bytes() != new.as_bytes())
+		.map(|(index, _)| index)
+		.collect()
+}
+
+fn compare_rooms<L: Level>(old: &L, new: &L, diff: &mut LevelDiff) {
+	let [old_rooms, new_rooms] = [old.rooms(), new.rooms()];
+	diff.rooms_removed = (new_rooms.len()..old_rooms.len()).collect();
+	diff.rooms_added = (old_rooms.len()..new_rooms.len()).collect();
+	for (room_index, (old_room, new_room)) in old_rooms.iter().zip(new_rooms).enumerate() {
+		if old_room.pos() != new_room.pos() || geom_hash(old_room) != geom_hash(new_room) {
+			diff.rooms_changed.push(RoomChange {
+				room_index,
+				vertices: [old_room.vertices().len(), new_room.vertices().len()],
+				faces: [num_faces(old_room), num_faces(new_room)],
+				pos: [old_room.pos(), new_room.pos()],
+			});
+		}
+	}
+}
+
+/// Match by index first, then unmatched entities by model and proximity.
+fn compare_entities<L: Level>(old: &L, new: &L, diff: &mut LevelDiff) {
+	let [old_entities, new_entities] = [old.entities(), new.entities()];
+	let mut unmatched_old = vec![];
+	let mut unmatched_new = vec![];
+	for index in 0..old_entities.len().max(new_entities.len()) {
+		match (old_entities.get(index), new_entities.get(index)) {
+			(Some(old_entity), Some(new_entity)) if old_entity.model_id() == new_entity.model_id() => {
+				if old_entity.pos() != new_entity.pos() {
+					diff.entities_moved.push(EntityMove {
+						old_index: index,
+						new_index: index,
+						model_id: old_entity.model_id(),
+						pos: [old_entity.pos(), new_entity.pos()],
+					});
+				}
+			},
+			(old_entity, new_entity) => {
+				if old_entity.is_some() {
+					unmatched_old.push(index);
+				}
+				if new_entity.is_some() {
+					unmatched_new.push(index);
+				}
+			},
+		}
+	}
+	for old_index in unmatched_old {
+		let old_entity = &old_entities[old_index];
+		let nearest = unmatched_new
+			.iter()
+			.enumerate()
+			.filter(|&(_, &new_index)| new_entities[new_index].model_id() == old_entity.model_id())
+			.map(|(unmatched_index, &new_index)| {
+				(unmatched_index, (new_entities[new_index].pos() - old_entity.pos()).abs().max_element())
+			})
+			.filter(|&(_, distance)| distance <= MOVE_DISTANCE)
+			.min_by_key(|&(_, distance)| distance);
+		match nearest {
+			Some((unmatched_index, _)) => {
+				let new_index = unmatched_new.swap_remove(unmatched_index);
+				let new_entity = &new_entities[new_index];
+				if old_entity.pos() != new_entity.pos() {
+					diff.entities_moved.push(EntityMove {
+						old_index,
+						new_index,
+						model_id: old_entity.model_id(),
+						pos: [old_entity.pos(), new_entity.pos()],
+					});
+				}
+			},
+			None => diff.entities_removed.push(old_index),
+		}
+	}
+	unmatched_new.sort();
+	diff.entities_added = unmatched_new;
+}
+
+fn compare_levels<L: Level>(old: &L, new: &L) -> LevelDiff {
+	let mut diff = LevelDiff {
+		num_rooms: [old.rooms().len(), new.rooms().len()],
+		rooms_added: vec![],
+		rooms_removed: vec![],
+		rooms_changed: vec![],
+		num_entities: [old.entities().len(), new.entities().len()],
+		entities_added: vec![],
+		entities_removed: vec![],
+		entities_moved: vec![],
+		num_object_textures: [old.object_textures().len(), new.object_textures().len()],
+		object_textures_changed: changed_indices(old.object_textures(), new.object_textures()),
+		palette_24bit_changed: vec![],
+		palette_32bit_changed: vec![],
+	};
+	compare_rooms(old, new, &mut diff);
+	compare_entities(old, new, &mut diff);
+	if let (Some(old_palette), Some(new_palette)) = (old.palette_24bit(), new.palette_24bit()) {
+		diff.palette_24bit_changed = changed_indices(old_palette, new_palette);
+	}
+	if let (Some(old_palette), Some(new_palette)) = (old.palette_32bit(), new.palette_32bit()) {
+		diff.palette_32bit_changed = changed_indices(old_palette, new_palette);
+	}
+	diff
+}
+
+/// Diff two levels. Errors if versions differ.
+pub fn compare(old: &LevelStore, new: &LevelStore) -> Result<LevelDiff> {
+	let diff = match (old, new) {
+		(LevelStore::Tr1(old), LevelStore::Tr1(new)) => compare_levels(old.as_ref(), new.as_ref()),
+		(LevelStore::Tr2(old), LevelStore::Tr2(new)) => compare_levels(old.as_ref(), new.as_ref()),
+		(LevelStore::Tr3(old), LevelStore::Tr3(new)) => compare_levels(old.as_ref(), new.as_ref()),
+		(LevelStore::Tr4(old), LevelStore::Tr4(new)) => compare_levels(old.as_ref(), new.as_ref()),
+		(LevelStore::Tr5(old), LevelStore::Tr5(new)) => compare_levels(old.as_ref(), new.as_ref()),
+		_ => {
+			let msg = format!("Cannot compare {} level with {} level", old.version().label(), new.version().label());
+			return Err(Error::other(msg));
+		},
+	};
+	Ok(diff)
+}
+
+fn section<T>(ui: &mut egui::Ui, title: &str, items: &[T], item_label: impl Fn(&T) -> String) {
+	ui.add_enabled_ui(!items.is_empty(), |ui| {
+		ui.collapsing(format!("{} ({})", title, items.len()), |ui| {
+			for item in items {
+				ui.label(item_label(item));
+			}
+		});
+	});
+}
+
+fn ivec3_label(v: IVec3) -> String {
+	format!("{}, {}, {}", v.x, v.y, v.z)
+}
+
+impl LevelDiff {
+	pub fn is_empty(&self) -> bool {
+		self.rooms_added.is_empty()
+			&& self.rooms_removed.is_empty()
+			&& self.rooms_changed.is_empty()
+			&& self.entities_added.is_empty()
+			&& self.entities_removed.is_empty()
+			&& self.entities_moved.is_empty()
+			&& self.num_object_textures[0] == self.num_object_textures[1]
+			&& self.object_textures_changed.is_empty()
+			&& self.palette_24bit_changed.is_empty()
+			&& self.palette_32bit_changed.is_empty()
+	}
+	
+	/// Nonzero counts in one line, such as "3 rooms changed, 2 entities moved".
+	pub fn summary(&self) -> String {
+		[
+			(self.rooms_added.len(), "rooms added"),
+			(self.rooms_removed.len(), "rooms removed"),
+			(self.rooms_changed.len(), "rooms changed"),
+			(self.entities_added.len(), "entities added"),
+			(self.entities_removed.len(), "entities removed"),
+			(self.entities_moved.len(), "entities moved"),
+			(self.object_textures_changed.len(), "object textures changed"),
+			(self.palette_24bit_changed.len() + self.palette_32bit_changed.len(), "palette colors changed"),
+		]
+			.into_iter()
+			.filter(|&(count, _)| count > 0)
+			.map(|(count, what)| format!("{} {}", count, what))
+			.collect::<Vec<_>>()
+			.join(", ")
+	}
+	
+	/// `show_moves` toggles the viewport lines from each moved entity's old position to its new one.
+	pub fn show(&self, ui: &mut egui::Ui, show_moves: &mut bool) {
+		if self.is_empty() {
+			ui.label("No differences");
+			return;
+		}
+		ui.label(self.summary());
+		ui.add_enabled(!self.entities_moved.is_empty(), egui::Checkbox::new(show_moves, "Show entity moves"));
+		ui.separator();
+		let [old, new] = self.num_rooms;
+		ui.label(format!("Rooms: {} → {}", old, new));
+		section(ui, "Rooms added", &self.rooms_added, |index| format!("Room {}", index));
+		section(ui, "Rooms removed", &self.rooms_removed, |index| format!("Room {}", index));
+		section(ui, "Rooms changed", &self.rooms_changed, |change| {
+			let RoomChange { room_index, vertices, faces, pos } = *change;
+			let mut label = format!(
+				"Room {}: vertices {} → {}, faces {} → {}", room_index, vertices[0], vertices[1], faces[0], faces[1],
+			);
+			if pos[0] != pos[1] {
+				label += &format!(", moved {} → {}", ivec3_label(pos[0]), ivec3_label(pos[1]));
+			}
+			label
+		});
+		ui.separator();
+		let [old, new] = self.num_entities;
+		ui.label(format!("Entities: {} → {}", old, new));
+		section(ui, "Entities added", &self.entities_added, |index| format!("Entity {}", index));
+		section(ui, "Entities removed", &self.entities_removed, |index| format!("Entity {}", index));
+		section(ui, "Entities moved", &self.entities_moved, |entity_move| {
+			let EntityMove { old_index, new_index, model_id, pos: [old_p, new_p] } = *entity_move;
+			let index = if old_index == new_index {
+				old_index.to_string()
+			} else {
+				format!("{} → {}", old_index, new_index)
+			};
+			format!("Entity {} (model {}): {} → {}", index, model_id, ivec3_label(old_p), ivec3_label(new_p))
+		});
+		ui.separator();
+		let [old, new] = self.num_object_textures;
+		ui.label(format!("Object textures: {} → {}", old, new));
+		section(ui, "Object textures changed", &self.object_textures_changed, |index| {
+			format!("Object texture {}", index)
+		});
+		ui.separator();
+		section(ui, "24-bit palette colors changed", &self.palette_24bit_changed, |index| {
+			format!("Color {}", index)
+		});
+		section(ui, "32-bit palette colors changed", &self.palette_32bit_changed, |index| {
+			format!("Color {}", index)
+		});
+	}
+}
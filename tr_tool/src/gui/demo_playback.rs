@@ -0,0 +1,203 @@
+use std::f32::consts::TAU;
+use glam::Vec3;
+use crate::demo::Demo;
+use super::walk::{WalkRoom, WalkState, EYE_HEIGHT};
+
+/// Demo inputs play one per game frame.
+const FPS: f32 = 30.0;
+/// Units per frame, roughly Lara's speeds.
+const RUN_SPEED: f32 = 32.0;
+const WALK_SPEED: f32 = 12.0;
+const BACK_SPEED: f32 = 12.0;
+const STEP_SPEED: f32 = 12.0;
+/// Radians turned per frame while left or right is held.
+const TURN_RATE: f32 = 4.0 / 360.0 * TAU;
+
+/// Input bit of a TR1 demo word, as the original game reads them.
+#[derive(Clone, Copy)]
+pub enum Input {
+	Forward,
+	Back,
+	Left,
+	Right,
+	Jump,
+	Draw,
+	Action,
+	Walk,
+	Option,
+	Look,
+	StepLeft,
+	StepRight,
+	Roll,
+}
+
+impl Input {
+	pub const ALL: [Input; 13] = [
+		Input::Forward,
+		Input::Back,
+		Input::Left,
+		Input::Right,
+		Input::Jump,
+		Input::Draw,
+		Input::Action,
+		Input::Walk,
+		Input::Option,
+		Input::Look,
+		Input::StepLeft,
+		Input::StepRight,
+		Input::Roll,
+	];
+	
+	pub fn label(&self) -> &'static str {
+		match self {
+			Input::Forward => "Forward",
+			Input::Back => "Back",
+			Input::Left => "Left",
+			Input::Right => "Right",
+			Input::Jump => "Jump",
+			Input::Draw => "Draw",
+			Input::Action => "Action",
+			Input::Walk => "Walk",
+			Input::Option => "Option",
+			Input::Look => "Look",
+			Input::StepLeft => "Step left",
+			Input::StepRight => "Step right",
+			Input::Roll => "Roll",
+		}
+	}
+	
+	fn bit(&self) -> u32 {
+		1 << *self as u32
+	}
+	
+	fn held(&self, word: u32) -> bool {
+		word & self.bit() != 0
+	}
+}
+
+/// Frames each input is held for, in the order of `Input::ALL`.
+pub fn histogram(inputs: &[u32]) -> [usize; Input::ALL.len()] {
+	Input::ALL.map(|input| inputs.iter().filter(|&&word| input.held(word)).count())
+}
+
+/// Lara in a demo frame.
+#[derive(Clone, Copy)]
+pub struct DemoFrame {
+	/// Feet, world coords.
+	pub pos: Vec3,
+	/// Radians, 0 facing +z.
+	pub angle: f32,
+}
+
+impl DemoFrame {
+	pub fn forward(&self) -> Vec3 {
+		Vec3::new(self.angle.sin(), 0.0, self.angle.cos())
+	}
+}
+
+/// Lara's path reconstructed from the inputs by turning and moving at fixed speeds, kept on the floor by
+/// `walk_rooms`. Jumps, rolls, climbing, swimming and other game logic aren't simulated, so the path drifts from
+/// what the game plays.
+pub fn path(demo: &Demo, walk_rooms: &[WalkRoom]) -> Vec<DemoFrame> {
+	let mut frame = DemoFrame { pos: demo.pos.as_vec3(), angle: demo.angle as f32 / 65536.0 * TAU };
+	let mut walk = WalkState::start(walk_rooms, frame.pos - Vec3::Y * EYE_HEIGHT);
+	let mut frames = Vec::with_capacity(demo.inputs.len() + 1);
+	frames.push(frame);
+	for &word in &demo.inputs {
+		let turn = Input::Right.held(word) as i32 - Input::Left.held(word) as i32;
+		frame.angle += turn as f32 * TURN_RATE;
+		let forward = frame.forward();
+		let right = Vec3::new(forward.z, 0.0, -forward.x);
+		let speed = if Input::Walk.held(word) { WALK_SPEED } else { RUN_SPEED };
+		let mut movement = Vec3::ZERO;
+		if Input::Forward.held(word) {
+			movement += forward * speed;
+		} else if Input::Back.held(word) {
+			movement -= forward * BACK_SPEED;
+		}
+		if Input::StepLeft.held(word) {
+			movement -= right * STEP_SPEED;
+		}
+		if Input::StepRight.held(word) {
+			movement += right * STEP_SPEED;
+		}
+		let eye = frame.pos - Vec3::Y * EYE_HEIGHT;
+		let stepped = walk.as_mut().and_then(|walk| walk.step(walk_rooms, eye, movement, 1.0 / FPS));
+		frame.pos = stepped.map_or(frame.pos + movement, |eye| eye + Vec3::Y * EYE_HEIGHT);
+		frames.push(frame);
+	}
+	frames
+}
+
+/// Ghost stepping through the frames at the game's frame rate.
+#[derive(Default)]
+pub struct DemoPlayback {
+	time: f32,
+}
+
+impl DemoPlayback {
+	pub fn frame_index(&self) -> usize {
+		(self.time * FPS) as usize
+	}
+	
+	/// Frame to show, then advance by `delta_secs`. `None` when done.
+	pub fn advance<'a>(&mut self, frames: &'a [DemoFrame], delta_secs: f32) -> Option<&'a DemoFrame> {
+		let frame = frames.get(self.frame_index())?;
+		self.time += delta_secs;
+		Some(frame)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use glam::IVec3;
+	use crate::{demo::{decode, END, HEADER_LEN}, test_level, tr_traits::LevelDyn};
+	use super::*;
+	
+	const FORWARD: u32 = 1 << Input::Forward as u32;
+	const LEFT: u32 = 1 << Input::Left as u32;
+	const WALK: u32 = 1 << Input::Walk as u32;
+	
+	/// Lara at (1536, 0, 2560) in room 0 facing +x, running 3 frames, walking 1, turning 1, then words past the end.
+	fn demo_data() -> Vec<u8> {
+		[1536, 0, 2560, 0, 0x4000, 0, 0, FORWARD, FORWARD, FORWARD, FORWARD | WALK, LEFT, END, FORWARD]
+			.into_iter()
+			.flat_map(u32::to_le_bytes)
+			.collect()
+	}
+	
+	#[test]
+	fn decodes_level_demo_data() {
+		let mut test_level = test_level::tiny();
+		test_level.demo_data = demo_data();
+		let level = test_level.level();
+		let demo = decode(level.demo_data()).unwrap();
+		assert_eq!(demo.pos, IVec3::new(1536, 0, 2560));
+		assert_eq!(demo.angle, 0x4000);
+		assert_eq!(demo.room_index, 0);
+		assert_eq!(demo.inputs, [FORWARD, FORWARD, FORWARD, FORWARD | WALK, LEFT]);
+		let histogram = histogram(&demo.inputs);
+		assert_eq!(histogram[Input::Forward as usize], 4);
+		assert_eq!(histogram[Input::Walk as usize], 1);
+		assert_eq!(histogram[Input::Left as usize], 1);
+		assert_eq!(histogram.iter().sum::<usize>(), 6);
+	}
+	
+	#[test]
+	fn short_demo_data_has_no_demo() {
+		assert!(decode(&[]).is_none());
+		assert!(decode(&demo_data()[..HEADER_LEN * 4 - 1]).is_none());
+	}
+	
+	#[test]
+	fn path_follows_inputs_without_floor() {
+		let demo = decode(&demo_data()).unwrap();
+		let frames = path(&demo, &[]);
+		assert_eq!(frames.len(), 6);
+		let expected_x = [1536.0, 1568.0, 1600.0, 1632.0, 1644.0, 1644.0];
+		for (frame, x) in frames.iter().zip(expected_x) {
+			assert!(frame.pos.abs_diff_eq(Vec3::new(x, 0.0, 2560.0), 0.001), "{}", frame.pos);
+		}
+		assert!((frames[5].angle - (TAU / 4.0 - TURN_RATE)).abs() < 0.001);
+	}
+}
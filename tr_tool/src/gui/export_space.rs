@@ -0,0 +1,128 @@
+use std::f32::consts::FRAC_PI_2;
+use glam::{Mat4, Vec3};
+
+/// TR units per sector.
+const SECTOR_UNITS: f32 = 1024.0;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum UpAxis {
+	/// Negate y. Mirrors the level, faces keep their vertex order.
+	Y,
+	/// Rotate TR up (-y) to +z.
+	Z,
+}
+
+impl UpAxis {
+	pub const ALL: [UpAxis; 2] = [UpAxis::Y, UpAxis::Z];
+	
+	pub fn label(&self) -> &'static str {
+		match self {
+			UpAxis::Y => "Y up (flip)",
+			UpAxis::Z => "Z up (rotate)",
+		}
+	}
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ExportScale {
+	TrUnits,
+	MeterPerSector,
+}
+
+impl ExportScale {
+	pub const ALL: [ExportScale; 2] = [ExportScale::TrUnits, ExportScale::MeterPerSector];
+	
+	pub fn label(&self) -> &'static str {
+		match self {
+			ExportScale::TrUnits => "1 unit = 1 TR unit",
+			ExportScale::MeterPerSector => "1 m per sector (1024 units)",
+		}
+	}
+	
+	fn factor(&self) -> f32 {
+		match self {
+			ExportScale::TrUnits => 1.0,
+			ExportScale::MeterPerSector => 1.0 / SECTOR_UNITS,
+		}
+	}
+}
+
+#[derive(Clone, Copy)]
+pub struct ExportSettings {
+	pub up_axis: UpAxis,
+	pub scale: ExportScale,
+	/// Add room offsets to vertex positions, otherwise rooms and their contents are room-relative.
+	pub bake_room_offsets: bool,
+}
+
+impl Default for ExportSettings {
+	fn default() -> Self {
+		Self {
+			up_axis: UpAxis::Y,
+			scale: ExportScale::TrUnits,
+			bake_room_offsets: true,
+		}
+	}
+}
+
+impl ExportSettings {
+	pub fn show(&mut self, ui: &mut egui::Ui) {
+		for up_axis in UpAxis::ALL {
+			ui.radio_value(&mut self.up_axis, up_axis, up_axis.label());
+		}
+		ui.separator();
+		for scale in ExportScale::ALL {
+			ui.radio_value(&mut self.scale, scale, scale.label());
+		}
+		ui.separator();
+		ui.checkbox(&mut self.bake_room_offsets, "Bake room offsets");
+	}
+}
+
+/// Conversion from TR world space to export space, applied to every exported position.
+#[derive(Clone, Copy)]
+pub struct ExportSpace {
+	pub transform: Mat4,
+}
+
+impl ExportSpace {
+	pub fn new(settings: &ExportSettings) -> Self {
+		let axis = match settings.up_axis {
+			UpAxis::Y => Mat4::from_scale(Vec3::new(1.0, -1.0, 1.0)),
+			UpAxis::Z => Mat4::from_rotation_x(-FRAC_PI_2),
+		};
+		let scale = Mat4::from_scale(Vec3::splat(settings.scale.factor()));
+		Self { transform: scale * axis }
+	}
+	
+	pub fn point(&self, point: Vec3) -> Vec3 {
+		self.transform.transform_point3(point)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	/// 1 sector east, 2 sectors up, 3 sectors north in TR space.
+	const POINT: Vec3 = Vec3::new(1024.0, -2048.0, 3072.0);
+	
+	fn export(up_axis: UpAxis, scale: ExportScale) -> Vec3 {
+		let settings = ExportSettings { up_axis, scale, ..Default::default() };
+		ExportSpace::new(&settings).point(POINT)
+	}
+	
+	#[test]
+	fn presets_map_known_point() {
+		let cases = [
+			(UpAxis::Y, ExportScale::TrUnits, Vec3::new(1024.0, 2048.0, 3072.0)),
+			(UpAxis::Y, ExportScale::MeterPerSector, Vec3::new(1.0, 2.0, 3.0)),
+			(UpAxis::Z, ExportScale::TrUnits, Vec3::new(1024.0, 3072.0, 2048.0)),
+			(UpAxis::Z, ExportScale::MeterPerSector, Vec3::new(1.0, 3.0, 2.0)),
+		];
+		for (up_axis, scale, expected) in cases {
+			let point = export(up_axis, scale);
+			assert!(point.abs_diff_eq(expected, 1e-3), "{} {}: {}", up_axis.label(), scale.label(), point);
+		}
+	}
+}
@@ -0,0 +1,338 @@
+use std::{fs, path::PathBuf};
+#[cfg(unix)]
+use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+use egui_file_dialog::{DialogState, FileDialog};
+use crate::console;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State<T> {
+	SelectingLevel,
+	SelectingComparison,
+	SelectingScript,
+	SavingTexture(T),//index into texture_bind_group
+	SelectingExportDir,
+	SelectingFramesDir,
+	SavingMetadata,
+	SavingVisibleObj,
+	SelectingCameraPath,
+	SavingCameraPath,
+	SelectingSfx,
+	SavingSample(u32),//index into MAIN.SFX
+}
+
+pub struct FileDialogWrapper<T> {
+	file_dialog: FileDialog,
+	state: Option<State<T>>,
+	level_dir: Option<PathBuf>,
+	texture_dir: Option<PathBuf>,
+}
+
+/// Inverse of `as_encoded_bytes` in `encode_dirs`. Any bytes are a path on Unix. Elsewhere only UTF-8 is
+/// accepted, so a path that can't be restored exactly is dropped rather than mangled.
+fn path_from_bytes(bytes: &[u8]) -> Option<PathBuf> {
+	#[cfg(unix)]
+	return Some(OsStr::from_bytes(bytes).into());
+	#[cfg(not(unix))]
+	return std::str::from_utf8(bytes).ok().map(PathBuf::from);
+}
+
+/// Level and texture dirs, one per line.
+fn encode_dirs(level_dir: &Option<PathBuf>, texture_dir: &Option<PathBuf>) -> Vec<u8> {
+	let [level_dir, texture_dir] = [level_dir, texture_dir].map(|dir| {
+		dir.as_ref().map(|dir| dir.as_os_str().as_encoded_bytes()).unwrap_or_default()
+	});
+	[level_dir, b"\n", texture_dir].concat()
+}
+
+fn decode_dirs(dirs: &[u8], level_dir: &mut Option<PathBuf>, texture_dir: &mut Option<PathBuf>) -> Option<()> {
+	let mut dirs = dirs.split(|&b| b == b'\n').map(|dir| dir.strip_suffix(b"\r").unwrap_or(dir));
+	*level_dir = path_from_bytes(dirs.next()?);
+	*texture_dir = path_from_bytes(dirs.next()?);
+	Some(())
+}
+
+fn read_dirs(level_dir: &mut Option<PathBuf>, texture_dir: &mut Option<PathBuf>) -> Option<()> {
+	decode_dirs(&fs::read("dir").ok()?, level_dir, texture_dir)
+}
+
+impl<T> FileDialogWrapper<T> {
+	pub fn new() -> Self {
+		let mut level_dir = None;
+		let mut texture_dir = None;
+		read_dirs(&mut level_dir, &mut texture_dir);
+		Self {
+			file_dialog: FileDialog::new(),
+			state: None,
+			level_dir,
+			texture_dir,
+		}
+	}
+	
+	pub fn update(&mut self, ctx: &egui::Context) {
+		self.file_dialog.update(ctx);
+		if matches!(self.file_dialog.state(), DialogState::Closed | DialogState::Cancelled) {
+			self.state = None;
+		}
+	}
+	
+	fn save_dirs(&self) {
+		if let Err(e) = fs::write("dir", encode_dirs(&self.level_dir, &self.texture_dir)) {
+			console::log_warn!("failed to save dir: {}", e);
+		}
+	}
+	
+	fn try_initiate(&mut self, state: State<T>) {
+		if self.state.is_none() {
+			let (dir, fd_fn): (_, fn(&mut FileDialog)) = match state {
+				State::SelectingLevel => (&self.level_dir, FileDialog::select_file),
+				State::SelectingComparison => (&self.level_dir, FileDialog::select_file),
+				State::SelectingScript => (&self.level_dir, FileDialog::select_file),
+				State::SavingTexture(_) => (&self.texture_dir, FileDialog::save_file),
+				State::SelectingExportDir => (&self.texture_dir, FileDialog::select_directory),
+				State::SelectingFramesDir => (&self.texture_dir, FileDialog::select_directory),
+				State::SavingMetadata => (&self.level_dir, FileDialog::save_file),
+				State::SavingVisibleObj => (&self.level_dir, FileDialog::save_file),
+				State::SelectingCameraPath => (&self.level_dir, FileDialog::select_file),
+				State::SavingCameraPath => (&self.level_dir, FileDialog::save_file),
+				State::SelectingSfx => (&self.level_dir, FileDialog::select_file),
+				State::SavingSample(_) => (&self.texture_dir, FileDialog::save_file),
+			};
+			if let Some(dir) = dir {
+				self.file_dialog.config_mut().initial_directory = dir.clone();
+			}
+			self.state = Some(state);
+			fd_fn(&mut self.file_dialog);
+		}
+	}
+	
+	pub fn is_closed(&self) -> bool {
+		self.state.is_none()
+	}
+	
+	pub fn select_level(&mut self) {
+		self.try_initiate(State::SelectingLevel);
+	}
+	
+	pub fn select_comparison(&mut self) {
+		self.try_initiate(State::SelectingComparison);
+	}
+	
+	pub fn select_script(&mut self) {
+		self.try_initiate(State::SelectingScript);
+	}
+	
+	pub fn save_texture(&mut self, arg: T) {
+		self.try_initiate(State::SavingTexture(arg));
+	}
+	
+	pub fn select_export_dir(&mut self) {
+		self.try_initiate(State::SelectingExportDir);
+	}
+	
+	pub fn select_frames_dir(&mut self) {
+		self.try_initiate(State::SelectingFramesDir);
+	}
+	
+	pub fn save_metadata(&mut self) {
+		self.try_initiate(State::SavingMetadata);
+	}
+	
+	pub fn save_visible_obj(&mut self) {
+		self.try_initiate(State::SavingVisibleObj);
+	}
+	
+	pub fn select_camera_path(&mut self) {
+		self.try_initiate(State::SelectingCameraPath);
+	}
+	
+	pub fn save_camera_path(&mut self) {
+		self.try_initiate(State::SavingCameraPath);
+	}
+	
+	pub fn select_sfx(&mut self) {
+		self.try_initiate(State::SelectingSfx);
+	}
+	
+	pub fn save_sample(&mut self, sample: u32) {
+		self.try_initiate(State::SavingSample(sample));
+	}
+	
+	pub fn get_level_path(&mut self) -> Option<PathBuf> {
+		if let Some(State::SelectingLevel) = self.state {
+			let path = self.file_dialog.take_selected()?;
+			let save_path = path.parent().unwrap_or(&path);
+			self.level_dir = Some(save_path.to_owned());
+			self.save_dirs();
+			self.state = None;
+			Some(path)
+		} else {
+			None
+		}
+	}
+	
+	pub fn get_comparison_path(&mut self) -> Option<PathBuf> {
+		if let Some(State::SelectingComparison) = self.state {
+			let path = self.file_dialog.take_selected()?;
+			self.state = None;
+			Some(path)
+		} else {
+			None
+		}
+	}
+	
+	pub fn get_script_path(&mut self) -> Option<PathBuf> {
+		if let Some(State::SelectingScript) = self.state {
+			let path = self.file_dialog.take_selected()?;
+			self.state = None;
+			Some(path)
+		} else {
+			None
+		}
+	}
+	
+	pub fn get_texture_path(&mut self) -> Option<(PathBuf, T)> {
+		match self.state.take() {
+			Some(State::SavingTexture(arg)) => {
+				let Some(path) = self.file_dialog.take_selected() else {
+					self.state = Some(State::SavingTexture(arg));
+					return None;
+				};
+				let save_path = path.parent().unwrap_or(&path);
+				self.texture_dir = Some(save_path.to_owned());
+				self.save_dirs();
+				self.state = None;
+				Some((path, arg))
+			},
+			other => {
+				self.state = other;
+				None
+			},
+		}
+	}
+	
+	/// Remembered as the texture folder.
+	pub fn get_export_dir(&mut self) -> Option<PathBuf> {
+		if let Some(State::SelectingExportDir) = self.state {
+			let path = self.file_dialog.take_selected()?;
+			self.texture_dir = Some(path.clone());
+			self.save_dirs();
+			self.state = None;
+			Some(path)
+		} else {
+			None
+		}
+	}
+	
+	/// Folder for turntable frames, remembered as the texture folder.
+	pub fn get_frames_dir(&mut self) -> Option<PathBuf> {
+		if let Some(State::SelectingFramesDir) = self.state {
+			let path = self.file_dialog.take_selected()?;
+			self.texture_dir = Some(path.clone());
+			self.save_dirs();
+			self.state = None;
+			Some(path)
+		} else {
+			None
+		}
+	}
+	
+	pub fn get_metadata_path(&mut self) -> Option<PathBuf> {
+		if let Some(State::SavingMetadata) = self.state {
+			let path = self.file_dialog.take_selected()?;
+			self.state = None;
+			Some(path)
+		} else {
+			None
+		}
+	}
+	
+	pub fn get_visible_obj_path(&mut self) -> Option<PathBuf> {
+		if let Some(State::SavingVisibleObj) = self.state {
+			let path = self.file_dialog.take_selected()?;
+			self.state = None;
+			Some(path)
+		} else {
+			None
+		}
+	}
+	
+	pub fn get_camera_path_to_load(&mut self) -> Option<PathBuf> {
+		if let Some(State::SelectingCameraPath) = self.state {
+			let path = self.file_dialog.take_selected()?;
+			self.state = None;
+			Some(path)
+		} else {
+			None
+		}
+	}
+	
+	pub fn get_camera_path_to_save(&mut self) -> Option<PathBuf> {
+		if let Some(State::SavingCameraPath) = self.state {
+			let path = self.file_dialog.take_selected()?;
+			self.state = None;
+			Some(path)
+		} else {
+			None
+		}
+	}
+	
+	pub fn get_sfx_path(&mut self) -> Option<PathBuf> {
+		if let Some(State::SelectingSfx) = self.state {
+			let path = self.file_dialog.take_selected()?;
+			self.state = None;
+			Some(path)
+		} else {
+			None
+		}
+	}
+	
+	/// Path and MAIN.SFX sample index. Remembered as the texture folder.
+	pub fn get_sample_path(&mut self) -> Option<(PathBuf, u32)> {
+		if let Some(State::SavingSample(sample)) = self.state {
+			let path = self.file_dialog.take_selected()?;
+			let save_path = path.parent().unwrap_or(&path);
+			self.texture_dir = Some(save_path.to_owned());
+			self.save_dirs();
+			self.state = None;
+			Some((path, sample))
+		} else {
+			None
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	fn round_trip(level_dir: PathBuf, texture_dir: PathBuf) -> (Option<PathBuf>, Option<PathBuf>) {
+		let (mut decoded_level_dir, mut decoded_texture_dir) = (None, None);
+		let encoded = encode_dirs(&Some(level_dir), &Some(texture_dir));
+		decode_dirs(&encoded, &mut decoded_level_dir, &mut decoded_texture_dir).unwrap();
+		(decoded_level_dir, decoded_texture_dir)
+	}
+	
+	#[test]
+	fn utf8_dirs_round_trip() {
+		let [level_dir, texture_dir] = ["levels/Ägypten", "textures/日本語"].map(PathBuf::from);
+		assert_eq!(round_trip(level_dir.clone(), texture_dir.clone()), (Some(level_dir), Some(texture_dir)));
+	}
+	
+	#[cfg(unix)]
+	#[test]
+	fn non_utf8_dir_round_trips_on_unix() {
+		let level_dir = PathBuf::from(OsStr::from_bytes(b"levels/\xFFlatin1 \xE9"));
+		let texture_dir = PathBuf::from("textures");
+		assert_eq!(round_trip(level_dir.clone(), texture_dir.clone()), (Some(level_dir), Some(texture_dir)));
+	}
+	
+	#[cfg(windows)]
+	#[test]
+	fn invalid_utf16_dir_is_dropped_on_windows() {
+		use std::{ffi::OsString, os::windows::ffi::OsStringExt};
+		//unpaired surrogate
+		let level_dir = PathBuf::from(OsString::from_wide(&[b'a' as u16, 0xD800]));
+		let texture_dir = PathBuf::from("textures");
+		assert_eq!(round_trip(level_dir, texture_dir.clone()), (None, Some(texture_dir)));
+	}
+}
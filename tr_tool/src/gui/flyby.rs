@@ -0,0 +1,100 @@
+use std::collections::BTreeMap;
+use glam::Vec3;
+use tr_model::tr4;
+
+/// Spline segments per second for a node speed of 1. The game advances by `speed` per frame at 30 fps
+/// over 0x10000 per segment.
+const SPEED_SCALE: f32 = 30.0 / 65536.0;
+
+pub struct FlybyNode {
+	pub pos: Vec3,
+	pub target: Vec3,
+	/// Spline segments per second.
+	pub speed: f32,
+}
+
+pub struct FlybySequence {
+	pub id: u8,
+	/// In node index order.
+	pub nodes: Vec<FlybyNode>,
+}
+
+impl FlybySequence {
+	/// Position and target at `time`, node index plus fraction of the way to the next node. End nodes are
+	/// repeated as spline control points.
+	pub fn sample(&self, time: f32) -> (Vec3, Vec3) {
+		let last = self.nodes.len() - 1;
+		let index = (time as usize).min(last);
+		let t = time - index as f32;
+		let node = |offset: isize| &self.nodes[(index as isize + offset).clamp(0, last as isize) as usize];
+		let [n0, n1, n2, n3] = [node(-1), node(0), node(1), node(2)];
+		let pos = catmull_rom(n0.pos, n1.pos, n2.pos, n3.pos, t);
+		let target = catmull_rom(n0.target, n1.target, n2.target, n3.target, t);
+		(pos, target)
+	}
+	
+	/// Points along the camera position spline, `steps` per segment.
+	pub fn spline(&self, steps: usize) -> Vec<Vec3> {
+		let Some(last) = self.nodes.len().checked_sub(1) else {
+			return vec![];
+		};
+		(0..=last * steps).map(|step| self.sample(step as f32 / steps as f32).0).collect()
+	}
+}
+
+/// Group cameras by sequence, each ordered by node index.
+pub fn sequences(flyby_cameras: &[tr4::FlybyCamera]) -> Vec<FlybySequence> {
+	let mut sequences = BTreeMap::<u8, Vec<&tr4::FlybyCamera>>::new();
+	for camera in flyby_cameras {
+		sequences.entry(camera.sequence).or_default().push(camera);
+	}
+	sequences
+		.into_iter()
+		.map(|(id, mut cameras)| {
+			cameras.sort_by_key(|camera| camera.index);
+			let nodes = cameras
+				.into_iter()
+				.map(|camera| FlybyNode {
+					pos: camera.pos.as_vec3(),
+					target: camera.direction.as_vec3(),
+					speed: camera.speed as f32 * SPEED_SCALE,
+				})
+				.collect();
+			FlybySequence { id, nodes }
+		})
+		.collect()
+}
+
+pub fn catmull_rom(p0: Vec3, p1: Vec3, p2: Vec3, p3: Vec3, t: f32) -> Vec3 {
+	let t2 = t * t;
+	let t3 = t2 * t;
+	0.5 * (2.0 * p1
+		+ (p2 - p0) * t
+		+ (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+		+ (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+}
+
+/// Camera travelling a sequence.
+pub struct FlybyPlayback {
+	pub sequence_index: usize,
+	/// Node index plus fraction of the way to the next node.
+	pub time: f32,
+}
+
+impl FlybyPlayback {
+	pub fn new(sequence_index: usize) -> Self {
+		Self { sequence_index, time: 0.0 }
+	}
+	
+	/// Advance by `delta_secs` at the current node's speed. Position and target, or `None` when done.
+	pub fn advance(&mut self, sequence: &FlybySequence, delta_secs: f32) -> Option<(Vec3, Vec3)> {
+		let nodes = &sequence.nodes;
+		let last = nodes.len().checked_sub(1)?;
+		let index = self.time as usize;
+		if index >= last {
+			return None;
+		}
+		self.time += nodes[index].speed.max(SPEED_SCALE) * delta_secs;
+		Some(sequence.sample(self.time))
+	}
+}
@@ -0,0 +1,47 @@
+/// Footstep sound materials by `Sector::footstep_material`, as assigned in TR3 and kept by TR4-5.
+/// 14 and 15 are unassigned in the original games.
+const MATERIAL_LABELS: [&str; 16] = [
+	"Mud",
+	"Snow",
+	"Sand",
+	"Gravel",
+	"Ice",
+	"Water",
+	"Stone",
+	"Wood",
+	"Metal",
+	"Marble",
+	"Grass",
+	"Concrete",
+	"Old wood",
+	"Old metal",
+	"Unassigned 14",
+	"Unassigned 15",
+];
+
+const MATERIAL_COLORS: [egui::Color32; 16] = [
+	egui::Color32::from_rgb(110, 80, 50),
+	egui::Color32::from_rgb(240, 240, 255),
+	egui::Color32::from_rgb(230, 200, 120),
+	egui::Color32::from_rgb(150, 150, 140),
+	egui::Color32::from_rgb(150, 220, 255),
+	egui::Color32::from_rgb(40, 90, 230),
+	egui::Color32::from_rgb(120, 120, 120),
+	egui::Color32::from_rgb(170, 110, 60),
+	egui::Color32::from_rgb(90, 110, 140),
+	egui::Color32::from_rgb(230, 210, 220),
+	egui::Color32::from_rgb(60, 180, 60),
+	egui::Color32::from_rgb(190, 190, 180),
+	egui::Color32::from_rgb(120, 70, 40),
+	egui::Color32::from_rgb(140, 60, 50),
+	egui::Color32::from_rgb(255, 0, 255),
+	egui::Color32::from_rgb(0, 255, 255),
+];
+
+pub fn label(material: u8) -> &'static str {
+	MATERIAL_LABELS[material as usize & 0xF]
+}
+
+pub fn color(material: u8) -> egui::Color32 {
+	MATERIAL_COLORS[material as usize & 0xF]
+}
@@ -0,0 +1,110 @@
+use std::{
+	cell::Cell, collections::VecDeque, fmt::Write, sync::{atomic::{AtomicU32, Ordering}, Mutex, OnceLock},
+	time::Instant,
+};
+use crate::console::Severity;
+
+/// Messages kept for the Log window, oldest dropped first.
+const CAPACITY: usize = 1000;
+
+struct Message {
+	severity: Severity,
+	text: String,
+	time: Instant,
+	/// Level load the message was pushed during, cleared by the next load.
+	load: Option<u32>,
+}
+
+static MESSAGES: Mutex<VecDeque<Message>> = Mutex::new(VecDeque::new());
+/// Times are shown relative to the first message.
+static START: OnceLock<Instant> = OnceLock::new();
+static LAST_LOAD: AtomicU32 = AtomicU32::new(0);
+
+thread_local! {
+	static CURRENT_LOAD: Cell<Option<u32>> = const { Cell::new(None) };
+}
+
+/// Keep a message pushed to the console for the Log window.
+pub fn keep(severity: Severity, text: String) {
+	let time = Instant::now();
+	START.get_or_init(|| time);
+	let load = CURRENT_LOAD.get();
+	let mut messages = MESSAGES.lock().unwrap();
+	if messages.len() == CAPACITY {
+		messages.pop_front();
+	}
+	messages.push_back(Message { severity, text, time, load });
+}
+
+/// Drop the messages of earlier level loads and return the new load's id for `with_load`.
+pub fn begin_load() -> u32 {
+	MESSAGES.lock().unwrap().retain(|message| message.load.is_none());
+	LAST_LOAD.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+/// Run `f` with messages it pushes on this thread attributed to `load`.
+pub fn with_load<T>(load: u32, f: impl FnOnce() -> T) -> T {
+	CURRENT_LOAD.set(Some(load));
+	let result = f();
+	CURRENT_LOAD.set(None);
+	result
+}
+
+/// Log window filter and scrolling.
+pub struct LogView {
+	show_info: bool,
+	show_warnings: bool,
+	auto_scroll: bool,
+}
+
+impl Default for LogView {
+	fn default() -> Self {
+		Self { show_info: true, show_warnings: true, auto_scroll: true }
+	}
+}
+
+impl LogView {
+	fn shows(&self, severity: Severity) -> bool {
+		match severity {
+			Severity::Info => self.show_info,
+			Severity::Warning => self.show_warnings,
+		}
+	}
+	
+	pub fn show(&mut self, ui: &mut egui::Ui) {
+		let mut messages = MESSAGES.lock().unwrap();
+		let start = START.get().copied();
+		let line = |message: &Message| {
+			let secs = start.map_or(0.0, |start| message.time.duration_since(start).as_secs_f32());
+			let load = if message.load.is_some() { " load" } else { "" };
+			format!("[{:8.3}{}] {}", secs, load, message.text)
+		};
+		ui.horizontal(|ui| {
+			ui.label(format!("{} messages", messages.len()));
+			ui.checkbox(&mut self.show_info, "Info");
+			ui.checkbox(&mut self.show_warnings, "Warnings");
+			ui.checkbox(&mut self.auto_scroll, "Auto-scroll");
+			if ui.button("Copy all").clicked() {
+				let mut text = String::new();
+				for message in messages.iter().filter(|message| self.shows(message.severity)) {
+					writeln!(text, "{}", line(message)).unwrap();
+				}
+				ui.output_mut(|o| o.copied_text = text);
+			}
+			if ui.button("Clear").clicked() {
+				messages.clear();
+			}
+		});
+		ui.label("Messages marked load came from loading the current level and are cleared by the next load.");
+		ui.separator();
+		egui::ScrollArea::vertical().stick_to_bottom(self.auto_scroll).show(ui, |ui| {
+			for message in messages.iter().filter(|message| self.shows(message.severity)) {
+				let text = egui::RichText::new(line(message)).monospace();
+				match message.severity {
+					Severity::Info => ui.label(text),
+					Severity::Warning => ui.colored_label(ui.visuals().warn_fg_color, text),
+				};
+			}
+		});
+	}
+}
@@ -0,0 +1,555 @@
+use std::{f32::consts::{FRAC_PI_2, FRAC_PI_4}, io::Result, mem::size_of, ops::Range, sync::Arc};
+use glam::{Mat4, Vec3};
+use shared::min_max::{MinMax, VecMinMaxFromIterator};
+use wgpu::{
+	BindGroup, BindGroupEntry, BindingResource, Buffer, BufferUsages, Color, CommandBuffer, CommandEncoder, Device,
+	Extent3d, LoadOp, Operations, Queue, RenderPassColorAttachment, RenderPassDepthStencilAttachment,
+	RenderPassDescriptor, StoreOp, TextureDimension, TextureFormat, TextureUsages, TextureView,
+	TextureViewDescriptor,
+};
+use super::viewer::ViewerShared;
+use crate::{
+	as_bytes::AsBytes,
+	data_writer::{self, DataWriter, MeshFaceOffsets},
+	face_instance::{page_faces, FaceInstance},
+	geom_buffer::{self, GeomBuffer},
+	level_buffers::{make_model_skeleton, mesh_parents, pose_transforms, write_mesh},
+	gpu::PhysicalSize,
+	make,
+	object_data::ObjectData,
+	tr_traits::{with_level, Animation, Entity, Level, LevelStore, Mesh, Model, Room, RoomStaticMesh},
+	direction, make_camera_transform, make_interact_texture, make_perspective_transform, Projection, SolidMode,
+	Statics, TextureMode, TextureViews, TrToolShared, Viewport, Winding, CAMERA_ENTRY, GEOM_DATA_ENTRY, NO_HIGHLIGHT,
+	NUM_QUAD_VERTICES, NUM_TRI_VERTICES, PERSPECTIVE_ENTRY, RENDER_FLAG_SHINE, SCROLL_OFFSET_ENTRY, STATICS_ENTRY,
+	TIME_ENTRY, VIEWPORT_ENTRY,
+};
+
+/// Side of the square offscreen render in pixels, and of the window's view in points.
+pub const VIEWER_SIZE: u32 = 384;
+const BACKGROUND: Color = Color { r: 0.25, g: 0.25, b: 0.25, a: 1.0 };
+/// Radians per point dragged.
+const ORBIT_SPEED: f32 = 0.01;
+/// Distance factor per point scrolled.
+const ZOOM_SPEED: f32 = 0.002;
+const FOV: f32 = FRAC_PI_4;
+const DEFAULT_YAW: f32 = FRAC_PI_4;
+const DEFAULT_PITCH: f32 = -FRAC_PI_4 / 2.0;
+/// Animations play one game frame per 30th of a second.
+const FPS: f32 = 30.0;
+
+/// What the mesh viewer shows.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MeshViewerSubject {
+	/// Index into the level's models, posed in its first frame until an animation plays.
+	Model(usize),
+	/// Index into the level's static meshes.
+	StaticMesh(usize),
+}
+
+fn model_subjects<L: Level>(level: &L) -> Vec<(MeshViewerSubject, String)> {
+	level
+		.models()
+		.iter()
+		.enumerate()
+		.map(|(index, model)| (MeshViewerSubject::Model(index), format!("Model {}", model.id())))
+		.collect()
+}
+
+/// Every model and static mesh of the level with its label.
+pub fn subjects(level: &LevelStore) -> Vec<(MeshViewerSubject, String)> {
+	let mut subjects = with_level!(level, level => model_subjects(level));
+	let static_meshes = level.as_dyn().static_meshes().iter().enumerate().map(|(index, static_mesh)| {
+		(MeshViewerSubject::StaticMesh(index), format!("Static mesh {}", static_mesh.id))
+	});
+	subjects.extend(static_meshes);
+	subjects
+}
+
+fn selection_subject<L: Level>(level: &L, data: ObjectData) -> Option<MeshViewerSubject> {
+	match data {
+		ObjectData::EntityMeshFace { entity_index, .. } => {
+			let model_id = level.entities()[entity_index as usize].model_id();
+			let index = level.models().iter().position(|model| model.id() as u16 == model_id)?;
+			Some(MeshViewerSubject::Model(index))
+		},
+		ObjectData::RoomStaticMeshFace { room_index, room_static_mesh_index, .. } => {
+			let room = &level.rooms()[room_index as usize];
+			let static_mesh_id = room.room_static_meshes()[room_static_mesh_index as usize].static_mesh_id();
+			let index = level
+				.static_meshes()
+				.iter()
+				.position(|static_mesh| static_mesh.id as u16 == static_mesh_id)?;
+			Some(MeshViewerSubject::StaticMesh(index))
+		},
+		_ => None,
+	}
+}
+
+/// Model or static mesh of a selected face, `None` for other selections.
+pub fn subject_of_selection(level: &LevelStore, data: ObjectData) -> Option<MeshViewerSubject> {
+	with_level!(level, level => selection_subject(level, data))
+}
+
+struct SubjectGeom {
+	output: data_writer::Output,
+	meshes: Vec<MeshFaceOffsets>,
+	bounds: MinMax<Vec3>,
+	object_texture_size: u32,
+}
+
+fn write_subject<L: Level>(level: &L, subject: MeshViewerSubject) -> Result<SubjectGeom> {
+	let placements = match subject {
+		MeshViewerSubject::Model(index) => {
+			let skeleton = make_model_skeleton(level, &level.models()[index])?;
+			let mesh_offsets = &level.mesh_offsets()[skeleton.mesh_offset_index as usize..];
+			mesh_offsets.iter().copied().zip(skeleton.transforms).collect::<Vec<_>>()
+		},
+		MeshViewerSubject::StaticMesh(index) => {
+			let mesh_offset_index = level.static_meshes()[index].mesh_offset_index;
+			vec![(level.mesh_offsets()[mesh_offset_index as usize], Mat4::IDENTITY)]
+		},
+	};
+	let mut geom_buffer = GeomBuffer::new();
+	let written_meshes = placements
+		.iter()
+		.map(|&(mesh_offset, _)| write_mesh::<L>(&mut geom_buffer, level.get_mesh(mesh_offset)))
+		.collect::<Result<Vec<_>>>()?;
+	let bounds = placements
+		.iter()
+		.flat_map(|&(mesh_offset, transform)| {
+			let vertices = level.get_mesh(mesh_offset).vertices();
+			vertices.iter().map(move |v| transform.transform_point3(v.as_vec3()))
+		})
+		.min_max()
+		.unwrap_or(MinMax::new(Vec3::ZERO));
+	//no degenerate tint, and faces aren't selectable so their object data is a placeholder
+	let mut data_writer = DataWriter::new(geom_buffer, vec![]);
+	let meshes = written_meshes.iter().zip(&placements).map(|(mesh, (_, transform))| {
+		let transform_index = data_writer.geom_buffer.write_transform(transform)?;
+		let object_data_maker = |_, _| ObjectData::Reverse { object_data_index: 0 };
+		Ok(data_writer.place_mesh(level, mesh, transform_index, object_data_maker))
+	}).collect::<Result<_>>()?;
+	Ok(SubjectGeom {
+		output: data_writer.done(level.object_textures(), level.sprite_textures())?,
+		meshes,
+		bounds,
+		object_texture_size: size_of::<L::ObjectTexture>() as u32 / 2,
+	})
+}
+
+/// Model mesh transforms `time` game frames into an animation, `None` if its frames are out of bounds. Interpolated
+/// poses blend toward the next stored frame by the time between them, otherwise each is held for its duration.
+fn animation_pose<L: Level>(
+	level: &L, model_index: usize, anim_index: usize, time: f32, interpolate: bool,
+) -> Option<Vec<Mat4>> {
+	let model = &level.models()[model_index];
+	let animation = level.animations().get(anim_index)?;
+	let frame_duration = animation.frame_duration().max(1) as f32;
+	let last_keyframe = animation.frame_end().saturating_sub(animation.frame_start()) as u32 / frame_duration as u32;
+	let keyframe = |keyframe: u32| {
+		let frame_byte_offset = animation.frame_byte_offset() + keyframe * animation.frame_size() as u32 * 2;
+		level.get_frame_at(frame_byte_offset, model.num_meshes())
+	};
+	let position = time / frame_duration;
+	let current = (position as u32).min(last_keyframe);
+	let frame = keyframe(current)?;
+	let next = match interpolate && current < last_keyframe {
+		true => Some((keyframe(current + 1)?, position.fract())),
+		false => None,
+	};
+	let mesh_nodes = level.get_mesh_nodes(model);
+	let parents = mesh_parents(mesh_nodes);
+	let next = next.as_ref().map(|(next, blend)| (next, *blend));
+	Some(pose_transforms(mesh_nodes, &parents, &frame, next))
+}
+
+struct AnimationInfo {
+	/// Game frames, `None` if the animation index is out of bounds.
+	length: Option<f32>,
+	first_anim_index: usize,
+	num_animations: usize,
+}
+
+fn animation_info<L: Level>(level: &L, model_index: usize, anim_index: usize) -> AnimationInfo {
+	let length = level
+		.animations()
+		.get(anim_index)
+		.map(|animation| animation.frame_end().saturating_sub(animation.frame_start()) as f32 + 1.0);
+	AnimationInfo {
+		length,
+		first_anim_index: level.models()[model_index].anim_index() as usize,
+		num_animations: level.animations().len(),
+	}
+}
+
+/// Plays a model subject's animations by rewriting its mesh transforms in the data buffers.
+struct AnimationPlayer {
+	model_index: usize,
+	/// Index into the level's animations.
+	anim_index: usize,
+	/// Game frames since the start of the animation.
+	time: f32,
+	playing: bool,
+	/// Blend between stored frames, rather than stepping to the nearest earlier one as the game does.
+	interpolate: bool,
+	/// Byte offset of the first mesh transform, the same in every data buffer.
+	transforms_byte_offset: u64,
+	data_buffers: Vec<Buffer>,
+}
+
+impl AnimationPlayer {
+	fn write_pose(&self, queue: &Queue, level: &LevelStore) {
+		let Self { model_index, anim_index, time, interpolate, .. } = *self;
+		let transforms =
+			with_level!(level, level => animation_pose(level, model_index, anim_index, time, interpolate));
+		let Some(transforms) = transforms else {
+			return;
+		};
+		for data_buffer in &self.data_buffers {
+			queue.write_buffer(data_buffer, self.transforms_byte_offset, transforms.as_bytes());
+		}
+	}
+	
+	fn show(&mut self, ui: &mut egui::Ui, queue: &Queue, level: &LevelStore) {
+		let AnimationInfo { length, first_anim_index, num_animations } =
+			with_level!(level, level => animation_info(level, self.model_index, self.anim_index));
+		let changed = ui.horizontal(|ui| {
+			let anim_range = 0..=num_animations.saturating_sub(1);
+			let anim_changed = ui
+				.add(egui::DragValue::new(&mut self.anim_index).clamp_range(anim_range).prefix("Animation "))
+				.changed();
+			if ui.button("Model's first").clicked() {
+				self.anim_index = first_anim_index;
+				return true;
+			}
+			anim_changed
+		}).inner;
+		if changed {
+			self.time = 0.0;
+		}
+		let Some(length) = length else {
+			ui.label("No animation");
+			return;
+		};
+		ui.horizontal(|ui| {
+			if ui.button(if self.playing { "Pause" } else { "Play" }).clicked() {
+				self.playing ^= true;
+			}
+			ui.checkbox(&mut self.interpolate, "Interpolate frames");
+		});
+		if self.playing {
+			self.time = (self.time + ui.input(|i| i.stable_dt) * FPS).rem_euclid(length);
+		}
+		ui.add(egui::Slider::new(&mut self.time, 0.0..=length - 1.0).text("Frame"));
+		self.write_pose(queue, level);
+	}
+}
+
+/// Offscreen render target and geometry of one subject.
+struct ViewerRender {
+	color_view: TextureView,
+	interact_view: TextureView,
+	depth_view: TextureView,
+	/// Binds `color_view` for the blit onto the egui pass.
+	blit_bg: BindGroup,
+	geom_page_bgs: Vec<BindGroup>,
+	face_instance_buffer: Buffer,
+	/// Copy of `face_instance_buffer`, for finding each page's faces.
+	face_instances: Vec<FaceInstance>,
+	camera_transform_buffer: Buffer,
+	meshes: Vec<MeshFaceOffsets>,
+	palette_24bit_bg: Option<BindGroup>,
+	texture_16bit_bg: Option<BindGroup>,
+	texture_32bit_bg: Option<BindGroup>,
+	solid_32bit_bg: Option<BindGroup>,
+}
+
+impl ViewerRender {
+	fn draw(
+		&self, encoder: &mut CommandEncoder, shared: &TrToolShared, texture_mode: TextureMode,
+		solid_mode: Option<SolidMode>, winding: Option<Winding>,
+	) {
+		let mut rpass = encoder.begin_render_pass(&RenderPassDescriptor {
+			label: None,
+			color_attachments: &[
+				Some(RenderPassColorAttachment {
+					ops: Operations { load: LoadOp::Clear(BACKGROUND), store: StoreOp::Store },
+					resolve_target: None,
+					view: &self.color_view,
+				}),
+				Some(RenderPassColorAttachment {
+					ops: Operations { load: LoadOp::Clear(Color::TRANSPARENT), store: StoreOp::Discard },
+					resolve_target: None,
+					view: &self.interact_view,
+				}),
+			],
+			depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+				depth_ops: Some(Operations {
+					load: LoadOp::Clear(0.0),//reversed depth
+					store: StoreOp::Discard,
+				}),
+				stencil_ops: None,
+				view: &self.depth_view,
+			}),
+			timestamp_writes: None,
+			occlusion_query_set: None,
+		});
+		let solid = solid_mode.and_then(|solid_mode| {
+			let (solid_pl, solid_bg) = match solid_mode {
+				SolidMode::Bit24 => (&shared.solid_24bit_pl, &self.palette_24bit_bg),
+				SolidMode::Bit32 => (&shared.solid_32bit_pl, &self.solid_32bit_bg),
+			};
+			Some((solid_pl.get(winding), solid_bg.as_ref()?))
+		});
+		let (texture_pls, texture_bg) = match texture_mode {
+			TextureMode::Palette => (&shared.palette_pls, &self.palette_24bit_bg),
+			TextureMode::Bit16 => (&shared.bit16_pls, &self.texture_16bit_bg),
+			TextureMode::Bit32 => (&shared.bit32_pls, &self.texture_32bit_bg),
+		};
+		//unwrap: built for every mode the level has
+		let texture_bg = texture_bg.as_ref().unwrap();
+		rpass.set_vertex_buffer(0, shared.face_vertex_index_buffer.slice(..));
+		rpass.set_vertex_buffer(1, self.face_instance_buffer.slice(..));
+		for (page, geom_page_bg) in self.geom_page_bgs.iter().enumerate() {
+			let faces = |faces: Range<u32>| page_faces(&self.face_instances, faces, page as u8);
+			rpass.set_bind_group(1, geom_page_bg, &[]);
+			if let Some((solid_pl, solid_bg)) = solid {
+				rpass.set_bind_group(0, solid_bg, &[]);
+				rpass.set_pipeline(solid_pl);
+				for mesh in &self.meshes {
+					rpass.draw(0..NUM_QUAD_VERTICES, faces(mesh.solid_quads.clone()));
+					rpass.draw(0..NUM_TRI_VERTICES, faces(mesh.solid_tris.clone()));
+				}
+			}
+			rpass.set_bind_group(0, texture_bg, &[]);
+			rpass.set_pipeline(texture_pls.opaque.get(winding));
+			for mesh in &self.meshes {
+				rpass.draw(0..NUM_QUAD_VERTICES, faces(mesh.textured_quads.opaque()));
+				rpass.draw(0..NUM_TRI_VERTICES, faces(mesh.textured_tris.opaque()));
+			}
+			rpass.set_pipeline(texture_pls.alpha.get(winding));
+			for mesh in &self.meshes {
+				rpass.draw(0..NUM_QUAD_VERTICES, faces(mesh.textured_quads.alpha()));
+				rpass.draw(0..NUM_TRI_VERTICES, faces(mesh.textured_tris.alpha()));
+			}
+			rpass.set_pipeline(texture_pls.additive.get(winding));
+			for mesh in &self.meshes {
+				rpass.draw(0..NUM_QUAD_VERTICES, faces(mesh.textured_quads.additive()));
+				rpass.draw(0..NUM_TRI_VERTICES, faces(mesh.textured_tris.additive()));
+			}
+		}
+	}
+}
+
+/// Renders the subject offscreen before egui's pass, then copies it into the paint callback rect.
+struct MeshViewerCallback {
+	viewer_shared: Arc<ViewerShared>,
+	render: Arc<ViewerRender>,
+	texture_mode: TextureMode,
+	solid_mode: Option<SolidMode>,
+	winding: Option<Winding>,
+}
+
+impl egui_wgpu::CallbackTrait for MeshViewerCallback {
+	fn prepare(
+		&self, _: &Device, _: &Queue, _: &egui_wgpu::ScreenDescriptor, egui_encoder: &mut CommandEncoder,
+		_: &mut egui_wgpu::CallbackResources,
+	) -> Vec<CommandBuffer> {
+		self.render.draw(egui_encoder, &self.viewer_shared.render, self.texture_mode, self.solid_mode, self.winding);
+		vec![]
+	}
+	
+	fn paint<'a>(
+		&'a self, _: egui::PaintCallbackInfo, rpass: &mut wgpu::RenderPass<'a>,
+		_: &'a egui_wgpu::CallbackResources,
+	) {
+		rpass.set_pipeline(&self.viewer_shared.blit_pl);
+		rpass.set_bind_group(0, &self.render.blit_bg, &[]);
+		rpass.set_vertex_buffer(0, self.viewer_shared.render.face_vertex_index_buffer.slice(..));
+		rpass.draw(0..NUM_QUAD_VERTICES, 0..1);
+	}
+}
+
+/// A model or static mesh drawn alone with an orbit camera, independent of the level view.
+pub struct MeshViewer {
+	pub subject: MeshViewerSubject,
+	pub label: String,
+	center: Vec3,
+	yaw: f32,
+	pitch: f32,
+	distance: f32,
+	/// Distance showing the whole subject.
+	fit_distance: f32,
+	render: Arc<ViewerRender>,
+	/// `None` for static meshes.
+	animation_player: Option<AnimationPlayer>,
+}
+
+impl MeshViewer {
+	pub fn new(
+		device: &Device, queue: &Queue, shared: &ViewerShared, texture_views: &TextureViews, level: &LevelStore,
+		subject: MeshViewerSubject, label: String,
+	) -> Result<Self> {
+		let SubjectGeom { output, meshes, bounds, object_texture_size } =
+			with_level!(level, level => write_subject(level, subject))?;
+		let data_writer::Output {
+			geom_output: geom_buffer::Output {
+				data_buffers,
+				transforms_offset,
+				transform_lights_offset,
+				face_array_offsets_offset,
+				object_textures_offset,
+				sprite_textures_offset,
+			},
+			face_buffer,
+			..
+		} = output;
+		let statics = Statics {
+			transforms_offset,
+			transform_lights_offset,
+			face_array_offsets_offset,
+			object_textures_offset,
+			object_texture_size,
+			sprite_textures_offset,
+			num_atlases: level.as_dyn().num_atlases() as u32,
+			render_flags: RENDER_FLAG_SHINE,
+			highlight_object_texture: NO_HIGHLIGHT,
+		};
+		let center = (bounds.min + bounds.max) / 2.0;
+		let radius = ((bounds.max - bounds.min).length() / 2.0).max(1.0);
+		let fit_distance = radius / (FOV / 2.0).sin();
+		let size = PhysicalSize::new(VIEWER_SIZE, VIEWER_SIZE);
+		let projection = Projection { fov: FOV, near: 1.0, far: 0.0, infinite_far: true };
+		let perspective_transform = make_perspective_transform(size, projection);
+		//writable so animations can repose the model
+		let data_buffers = data_buffers
+			.iter()
+			.map(|data_buffer| make::buffer(device, &**data_buffer, BufferUsages::STORAGE | BufferUsages::COPY_DST))
+			.collect::<Vec<_>>();
+		let geom_page_bgs = data_buffers.iter().map(|data_buffer| {
+			let entries = [make::entry(GEOM_DATA_ENTRY, data_buffer.as_entire_binding())];
+			make::bind_group(device, &shared.render.geom_page_layout, &entries)
+		}).collect();
+		let statics_buffer = make::buffer(device, statics.as_bytes(), BufferUsages::UNIFORM);
+		let camera_transform_buffer = make::writable_uniform(device, Mat4::IDENTITY.as_bytes());
+		let perspective_transform_buffer = make::buffer(
+			device,
+			perspective_transform.as_bytes(),
+			BufferUsages::UNIFORM,
+		);
+		//only used by the texture browser
+		let viewport_buffer = make::buffer(device, &[0; size_of::<Viewport>()], BufferUsages::UNIFORM);
+		let scroll_offset_buffer = make::buffer(device, &[0; size_of::<egui::Vec2>()], BufferUsages::UNIFORM);
+		//meshes have no water vertices
+		let time_buffer = make::buffer(device, 0f32.as_bytes(), BufferUsages::UNIFORM);
+		let common_entries = &[
+			make::entry(STATICS_ENTRY, statics_buffer.as_entire_binding()),
+			make::entry(CAMERA_ENTRY, camera_transform_buffer.as_entire_binding()),
+			make::entry(PERSPECTIVE_ENTRY, perspective_transform_buffer.as_entire_binding()),
+			make::entry(VIEWPORT_ENTRY, viewport_buffer.as_entire_binding()),
+			make::entry(SCROLL_OFFSET_ENTRY, scroll_offset_buffer.as_entire_binding()),
+			make::entry(TIME_ENTRY, time_buffer.as_entire_binding()),
+		][..];
+		let make_bind_group = |entries: [BindGroupEntry; 2]| {
+			let entries = [common_entries, &entries].concat();
+			make::bind_group(device, &shared.render.bind_group_layout, &entries)
+		};
+		let [palette_24bit_bg, texture_16bit_bg, texture_32bit_bg] = {
+			[TextureMode::Palette, TextureMode::Bit16, TextureMode::Bit32]
+		}.map(|texture_mode| texture_views.texture_entries(texture_mode).map(make_bind_group));
+		let solid_32bit_bg = texture_views.solid_entries(SolidMode::Bit32).map(make_bind_group);
+		let extent = Extent3d { width: VIEWER_SIZE, height: VIEWER_SIZE, depth_or_array_layers: 1 };
+		let color_view = make::texture(
+			device,
+			extent,
+			TextureDimension::D2,
+			TextureFormat::Bgra8Unorm,
+			TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+		).create_view(&TextureViewDescriptor::default());
+		let interact_view = make_interact_texture(device, size).create_view(&TextureViewDescriptor::default());
+		let blit_bg = make::bind_group(
+			device,
+			&shared.blit_layout,
+			&[make::entry(0, BindingResource::TextureView(&color_view))],
+		);
+		let render = ViewerRender {
+			color_view,
+			interact_view,
+			depth_view: make::depth_view(device, size),
+			blit_bg,
+			geom_page_bgs,
+			face_instance_buffer: make::buffer(device, face_buffer.as_bytes(), BufferUsages::VERTEX),
+			face_instances: face_buffer,
+			camera_transform_buffer,
+			meshes,
+			palette_24bit_bg,
+			texture_16bit_bg,
+			texture_32bit_bg,
+			solid_32bit_bg,
+		};
+		let animation_player = match subject {
+			MeshViewerSubject::Model(model_index) => Some(AnimationPlayer {
+				model_index,
+				anim_index: with_level!(level, level => animation_info(level, model_index, 0)).first_anim_index,
+				time: 0.0,
+				playing: false,
+				interpolate: true,
+				//transforms are written in mesh order from index 0
+				transforms_byte_offset: transforms_offset as u64 * 16,
+				data_buffers,
+			}),
+			MeshViewerSubject::StaticMesh(_) => None,
+		};
+		let mut mesh_viewer = Self {
+			subject,
+			label,
+			center,
+			yaw: DEFAULT_YAW,
+			pitch: DEFAULT_PITCH,
+			distance: fit_distance,
+			fit_distance,
+			render: Arc::new(render),
+			animation_player,
+		};
+		mesh_viewer.update_camera_transform(queue);
+		Ok(mesh_viewer)
+	}
+	
+	fn update_camera_transform(&mut self, queue: &Queue) {
+		let pos = self.center - direction(self.yaw, self.pitch) * self.distance;
+		let camera_transform = make_camera_transform(pos, self.yaw, self.pitch, 0.0);
+		queue.write_buffer(&self.render.camera_transform_buffer, 0, camera_transform.as_bytes());
+	}
+	
+	/// Drag to orbit, scroll to zoom, double-click to reset. Models get animation controls below.
+	#[allow(clippy::too_many_arguments)]
+	pub fn show(
+		&mut self, ui: &mut egui::Ui, queue: &Queue, shared: &Arc<ViewerShared>, level: &LevelStore,
+		texture_mode: TextureMode, solid_mode: Option<SolidMode>, winding: Option<Winding>,
+	) {
+		let size = egui::Vec2::splat(VIEWER_SIZE as f32);
+		let (rect, response) = ui.allocate_exact_size(size, egui::Sense::click_and_drag());
+		let drag = response.drag_delta();
+		let scroll = if response.hovered() { ui.input(|i| i.smooth_scroll_delta.y) } else { 0.0 };
+		if response.double_clicked() {
+			self.yaw = DEFAULT_YAW;
+			self.pitch = DEFAULT_PITCH;
+			self.distance = self.fit_distance;
+		}
+		self.yaw += drag.x * ORBIT_SPEED;
+		self.pitch = (self.pitch + drag.y * ORBIT_SPEED).clamp(-FRAC_PI_2, FRAC_PI_2);
+		self.distance = (self.distance * (-scroll * ZOOM_SPEED).exp()).max(1.0);
+		self.update_camera_transform(queue);
+		let callback = MeshViewerCallback {
+			viewer_shared: shared.clone(),
+			render: self.render.clone(),
+			texture_mode,
+			solid_mode,
+			winding,
+		};
+		ui.painter().add(egui_wgpu::Callback::new_paint_callback(rect, callback));
+		if let Some(animation_player) = &mut self.animation_player {
+			animation_player.show(ui, queue, level);
+		}
+	}
+}
@@ -0,0 +1,229 @@
+use std::{collections::HashMap, f32::consts::TAU, fmt::Write};
+use glam::{Mat4, Vec3};
+use crate::{
+	tr_traits::{
+		vertex_indices, Entity, Face, Level, Mesh, Room, RoomFace, RoomGeom, RoomStaticMesh, RoomVertex, StaticMesh,
+	},
+	level_buffers::{EntityRender, ModelSkeleton},
+};
+use super::export_space::{ExportSettings, ExportSpace};
+
+/// What the viewer currently draws, per the room selector, flip groups and object type toggles.
+pub struct Visible<'a> {
+	pub room_indices: &'a [usize],
+	pub room_mesh: bool,
+	pub static_meshes: bool,
+	pub invisible_static_meshes: bool,
+	pub entity_meshes: bool,
+	pub hidden_entities: bool,
+	pub entity_renders: &'a [Option<EntityRender>],
+	pub model_skeletons: &'a HashMap<u16, ModelSkeleton>,
+}
+
+struct ObjWriter {
+	obj: String,
+	num_vertices: usize,
+	space: ExportSpace,
+}
+
+impl ObjWriter {
+	fn object(&mut self, name: &str) {
+		writeln!(self.obj, "o {}", name).unwrap();
+	}
+	
+	/// Room offset in export space, for room-relative exports.
+	fn room_offset(&mut self, room_index: usize, pos: Vec3) {
+		let [x, y, z] = self.space.point(pos).to_array();
+		writeln!(self.obj, "# room_{} offset {} {} {}", room_index, x, y, z).unwrap();
+	}
+	
+	/// Returns the OBJ index of the first vertex.
+	fn vertices<I: IntoIterator<Item = Vec3>>(&mut self, transform: Mat4, vertices: I) -> usize {
+		let first = self.num_vertices + 1;
+		let transform = self.space.transform * transform;
+		for vertex in vertices {
+			let [x, y, z] = transform.transform_point3(vertex).to_array();
+			writeln!(self.obj, "v {} {} {}", x, y, z).unwrap();
+			self.num_vertices += 1;
+		}
+		first
+	}
+	
+	fn face<I: Iterator<Item = u16>>(&mut self, first: usize, vertex_indices: I) {
+		self.obj.push('f');
+		for vertex_index in vertex_indices {
+			write!(self.obj, " {}", first + vertex_index as usize).unwrap();
+		}
+		self.obj.push('\n');
+	}
+	
+	fn room_faces<F: RoomFace>(&mut self, first: usize, faces: &[F]) {
+		for face in faces {
+			self.face(first, vertex_indices(face));
+			if face.double_sided() {
+				let reversed = vertex_indices(face).collect::<Vec<_>>();
+				self.face(first, reversed.into_iter().rev());
+			}
+		}
+	}
+	
+	fn faces<F: Face>(&mut self, first: usize, faces: &[F]) {
+		for face in faces {
+			self.face(first, vertex_indices(face));
+		}
+	}
+	
+	fn mesh<'a, M: Mesh<'a> + 'a>(&mut self, mesh: M, transform: Mat4) {
+		let first = self.vertices(transform, mesh.vertices().iter().map(|v| v.as_vec3()));
+		self.faces(first, mesh.textured_quads());
+		self.faces(first, mesh.textured_tris());
+		self.faces(first, mesh.solid_quads());
+		self.faces(first, mesh.solid_tris());
+	}
+}
+
+/// Wavefront OBJ of the visible rooms and object types, converted to export space. Faces keep their vertex order.
+pub fn visible_obj<L: Level>(level: &L, visible: &Visible, settings: &ExportSettings) -> String {
+	let space = ExportSpace::new(settings);
+	let mut writer = ObjWriter { obj: String::new(), num_vertices: 0, space };
+	//world to room-relative, identity if baked
+	let room_relative = |room_index: usize| if settings.bake_room_offsets {
+		Mat4::IDENTITY
+	} else {
+		Mat4::from_translation(-level.rooms()[room_index].pos().as_vec3())
+	};
+	for &room_index in visible.room_indices {
+		let room = &level.rooms()[room_index];
+		let room_transform = room_relative(room_index) * Mat4::from_translation(room.pos().as_vec3());
+		if !settings.bake_room_offsets {
+			writer.room_offset(room_index, room.pos().as_vec3());
+		}
+		if visible.room_mesh {
+			writer.object(&format!("room_{}", room_index));
+			for RoomGeom { vertices, quads, tris } in room.geom() {
+				let first = writer.vertices(room_transform, vertices.iter().map(|v| v.pos()));
+				writer.room_faces(first, quads);
+				writer.room_faces(first, tris);
+			}
+		}
+		if visible.static_meshes {
+			for (room_static_mesh_index, room_static_mesh) in room.room_static_meshes().iter().enumerate() {
+				let static_mesh_id = room_static_mesh.static_mesh_id();
+				let Some(static_mesh) = level
+					.static_meshes()
+					.iter()
+					.find(|static_mesh| static_mesh.id() as u16 == static_mesh_id)
+				else {
+					continue;
+				};
+				if !static_mesh.visible() && !visible.invisible_static_meshes {
+					continue;
+				}
+				let translation = Mat4::from_translation(room_static_mesh.pos().as_vec3());
+				let rotation = Mat4::from_rotation_y(room_static_mesh.angle() as f32 / 65536.0 * TAU);
+				let mesh_offset = level.mesh_offsets()[static_mesh.mesh_offset_index() as usize];
+				writer.object(&format!("room_{}_static_mesh_{}", room_index, room_static_mesh_index));
+				writer.mesh(level.get_mesh(mesh_offset), room_relative(room_index) * translation * rotation);
+			}
+		}
+	}
+	if visible.entity_meshes {
+		for (entity_index, entity) in level.entities().iter().enumerate() {
+			if !visible.room_indices.contains(&(entity.room_index() as usize)) {
+				continue;
+			}
+			if entity.initially_invisible() && !visible.hidden_entities {
+				continue;
+			}
+			let Some(EntityRender { model_id, transform }) = visible.entity_renders[entity_index] else {
+				continue;
+			};
+			let skeleton = &visible.model_skeletons[&model_id];
+			let transform = room_relative(entity.room_index() as usize) * transform;
+			writer.object(&format!("entity_{}", entity_index));
+			for (mesh_index, &model_transform) in skeleton.transforms.iter().enumerate() {
+				let mesh_offset = level.mesh_offsets()[skeleton.mesh_offset_index as usize + mesh_index];
+				writer.mesh(level.get_mesh(mesh_offset), transform * model_transform);
+			}
+		}
+	}
+	writer.obj
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::test_level;
+	use super::*;
+	
+	/// Objects of an OBJ with the positions of each of their faces' vertices.
+	fn parse(obj: &str) -> Vec<(String, Vec<Vec<Vec3>>)> {
+		let mut vertices = vec![];
+		let mut objects = vec![];
+		for line in obj.lines() {
+			let mut words = line.split(' ');
+			match words.next() {
+				Some("o") => objects.push((words.next().unwrap().to_string(), vec![])),
+				Some("v") => {
+					let [x, y, z] = [(); 3].map(|_| words.next().unwrap().parse().unwrap());
+					vertices.push(Vec3::new(x, y, z));
+				},
+				Some("f") => {
+					let face = words.map(|index| vertices[index.parse::<usize>().unwrap() - 1]).collect();
+					objects.last_mut().unwrap().1.push(face);
+				},
+				_ => {},
+			}
+		}
+		objects
+	}
+	
+	fn assert_face(face: &[Vec3], expected: &[[f32; 3]]) {
+		assert_eq!(face.len(), expected.len());
+		for (&v, &e) in face.iter().zip(expected) {
+			assert!(v.abs_diff_eq(Vec3::from_array(e), 0.001), "{} != {:?}", v, e);
+		}
+	}
+	
+	fn room_and_static_mesh_obj(settings: &ExportSettings) -> Vec<(String, Vec<Vec<Vec3>>)> {
+		let mut test_level = test_level::tiny();
+		test_level.rooms[0].x = 1024;
+		let level = test_level.level();
+		let visible = Visible {
+			room_indices: &[0],
+			room_mesh: true,
+			static_meshes: true,
+			invisible_static_meshes: true,
+			entity_meshes: false,
+			hidden_entities: false,
+			entity_renders: &[None],
+			model_skeletons: &HashMap::new(),
+		};
+		parse(&visible_obj(level.as_ref(), &visible, settings))
+	}
+	
+	#[test]
+	fn baked_obj_round_trips_room_and_static_mesh_faces() {
+		let objects = room_and_static_mesh_obj(&ExportSettings::default());
+		let names = objects.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>();
+		assert_eq!(names, ["room_0", "room_0_static_mesh_0"]);
+		let room_faces = &objects[0].1;
+		assert_eq!(room_faces.len(), 2);
+		assert_face(
+			&room_faces[0],
+			&[[2048.0, 0.0, 1024.0], [3072.0, 0.0, 1024.0], [3072.0, 0.0, 2048.0], [2048.0, 0.0, 2048.0]],
+		);
+		assert_face(&room_faces[1], &[[2048.0, 0.0, 2048.0], [3072.0, 0.0, 2048.0], [2048.0, 0.0, 3072.0]]);
+		//static mesh turned a quarter about y at (1536, 0, 1536), y flipped
+		let static_mesh_faces = &objects[1].1;
+		assert_eq!(static_mesh_faces.len(), 2);
+		assert_face(&static_mesh_faces[1], &[[1472.0, 0.0, 1600.0], [1472.0, 0.0, 1472.0], [1536.0, 128.0, 1536.0]]);
+	}
+	
+	#[test]
+	fn unbaked_obj_is_room_relative() {
+		let settings = ExportSettings { bake_room_offsets: false, ..ExportSettings::default() };
+		let objects = room_and_static_mesh_obj(&settings);
+		assert_face(&objects[0].1[1], &[[1024.0, 0.0, 2048.0], [2048.0, 0.0, 2048.0], [1024.0, 0.0, 3072.0]]);
+		assert_face(&objects[1].1[1], &[[448.0, 0.0, 1600.0], [448.0, 0.0, 1472.0], [512.0, 128.0, 1536.0]]);
+	}
+}
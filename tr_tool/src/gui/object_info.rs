@@ -0,0 +1,400 @@
+use std::collections::HashMap;
+use glam::Vec3;
+use shared::min_max::MinMax;
+use tr_model::{tr1, tr2};
+use crate::{
+	console,
+	data_writer::FaceBlend,
+	object_data::{MeshFaceType, ObjectData, ObjectOwner, PolyType},
+	room_bounds,
+	tr_traits::{
+		vertex_indices, Entity, Level, Mesh, MeshTexturedFace, Model, ObjectTexture, Room, RoomFace, RoomStaticMesh,
+		RoomVertex, SolidFace, StaticMesh, TexturedFace,
+	},
+	SECTOR_SIZE,
+};
+use super::{footstep, units::UnitMode, viewer::InteractPixel};
+
+/// Object data indices by owner, in object data order.
+pub fn owner_index(object_data: &[ObjectData]) -> HashMap<ObjectOwner, Vec<u32>> {
+	let mut index = HashMap::<ObjectOwner, Vec<u32>>::new();
+	for (object_data_index, data) in object_data.iter().enumerate() {
+		if let Some(owner) = data.owner() {
+			index.entry(owner).or_default().push(object_data_index as u32);
+		}
+	}
+	index
+}
+
+/// Object texture index and shine strength, or 24 bit and 32 bit color indices for solid faces.
+struct MeshFaceInfo {
+	object_texture_index: Option<u16>,
+	/// TR4-5 additive flag of textured faces.
+	additive: bool,
+	shine_strength: u8,
+	color_index_24bit: Option<u8>,
+	color_index_32bit: Option<u8>,
+}
+
+/// Face instance fields recomputed from the level, to check the packed instance against.
+pub struct ExpectedFaceInstance {
+	pub face_index: u16,
+	/// `None` for solid faces.
+	pub object_texture_index: Option<u16>,
+	pub shine_strength: u8,
+}
+
+fn static_mesh_offset<L: Level>(level: &L, room_index: u16, room_static_mesh_index: u16) -> u32 {
+	let room = &level.rooms()[room_index as usize];
+	let room_static_mesh = &room.room_static_meshes()[room_static_mesh_index as usize];
+	let static_mesh_id = room_static_mesh.static_mesh_id();
+	//unwrap: proven in level parse
+	let static_mesh = level
+		.static_meshes()
+		.iter()
+		.find(|static_mesh| static_mesh.id() as u16 == static_mesh_id)
+		.unwrap();
+	level.mesh_offsets()[static_mesh.mesh_offset_index() as usize]
+}
+
+fn entity_mesh_offset<L: Level>(level: &L, entity_index: u16, mesh_index: u16) -> u32 {
+	let model_id = level.entities()[entity_index as usize].model_id();
+	//unwrap: proven in level parse
+	let model = level.models().iter().find(|model| model.id() as u16 == model_id).unwrap();
+	level.mesh_offsets()[(model.mesh_offset_index() + mesh_index) as usize]
+}
+
+fn mesh_face_info<L: Level>(level: &L, mesh_offset: u32, face_type: MeshFaceType, face_index: u16) -> MeshFaceInfo {
+	let mesh = level.get_mesh(mesh_offset);
+	let (object_texture_index, additive, shine_strength, color_index_24bit, color_index_32bit) = match face_type {
+		MeshFaceType::TexturedQuad => {
+			let quad = &mesh.textured_quads()[face_index as usize];
+			(Some(quad.object_texture_index()), quad.additive(), quad.shine_strength(), None, None)
+		},
+		MeshFaceType::TexturedTri => {
+			let tri = &mesh.textured_tris()[face_index as usize];
+			(Some(tri.object_texture_index()), tri.additive(), tri.shine_strength(), None, None)
+		},
+		MeshFaceType::SolidQuad => {
+			let quad = &mesh.solid_quads()[face_index as usize];
+			(None, false, 0, Some(quad.color_index_24bit()), quad.color_index_32bit())
+		},
+		MeshFaceType::SolidTri => {
+			let tri = &mesh.solid_tris()[face_index as usize];
+			(None, false, 0, Some(tri.color_index_24bit()), tri.color_index_32bit())
+		},
+	};
+	MeshFaceInfo { object_texture_index, additive, shine_strength, color_index_24bit, color_index_32bit }
+}
+
+/// `None` for sprites, portals and placeholders, which have no face instance. `data` must not be `Reverse`.
+pub fn expected_face_instance<L: Level>(level: &L, data: ObjectData) -> Option<ExpectedFaceInstance> {
+	let (mesh_offset, face_type, face_index) = match data {
+		ObjectData::RoomFace { room_index, geom_index, face_type, face_index } => {
+			let room = &level.rooms()[room_index as usize];
+			//unwrap: proven in level parse
+			let geom = room.geom().into_iter().nth(geom_index as usize).unwrap();
+			let object_texture_index = match face_type {
+				PolyType::Quad => geom.quads[face_index as usize].object_texture_index(),
+				PolyType::Tri => geom.tris[face_index as usize].object_texture_index(),
+			};
+			return Some(ExpectedFaceInstance {
+				face_index,
+				object_texture_index: Some(object_texture_index),
+				shine_strength: 0,
+			});
+		},
+		ObjectData::RoomStaticMeshFace { room_index, room_static_mesh_index, face_type, face_index } => {
+			(static_mesh_offset(level, room_index, room_static_mesh_index), face_type, face_index)
+		},
+		ObjectData::EntityMeshFace { entity_index, mesh_index, face_type, face_index } => {
+			(entity_mesh_offset(level, entity_index, mesh_index), face_type, face_index)
+		},
+		_ => return None,
+	};
+	let info = mesh_face_info(level, mesh_offset, face_type, face_index);
+	Some(ExpectedFaceInstance {
+		face_index,
+		object_texture_index: info.object_texture_index,
+		shine_strength: info.shine_strength,
+	})
+}
+
+fn color_24bit<L: Level>(level: &L, color_index: Option<u8>) -> Option<u32> {
+	let tr1::Color24Bit { r, g, b } = level.palette_24bit()?[color_index? as usize];
+	let [r, g, b] = [r, g, b].map(|c| (c << 2) as u32);
+	Some((r << 16) | (g << 8) | b)
+}
+
+fn color_32bit<L: Level>(level: &L, color_index: Option<u8>) -> Option<u32> {
+	let &tr2::Color32BitRgb { r, g, b } = &level.palette_32bit()?[color_index? as usize];
+	let [r, g, b] = [r, g, b].map(|c| c as u32);
+	Some((r << 16) | (g << 8) | b)
+}
+
+pub fn print_object_data<L: Level>(level: &L, object_data: &[ObjectData], index: InteractPixel) {
+	console::info!("object data index: {}", index);
+	let data = match object_data.get(index as usize) {
+		Some(&data) => data,
+		None => {
+			console::info!("out of bounds");
+			return;
+		},
+	};
+	console::info!("{:?}", data);
+	let data = match data {
+		ObjectData::Reverse { object_data_index } => {
+			let data = object_data[object_data_index as usize];
+			console::info!("{:?}", data);
+			data
+		},
+		data => data,
+	};
+	let mesh_face = match data {
+		ObjectData::RoomFace { room_index, geom_index, face_type, face_index } => {
+			let room = &level.rooms()[room_index as usize];
+			//unwrap: proven in level parse
+			let geom = room.geom().into_iter().nth(geom_index as usize).unwrap();
+			let (double_sided, object_texture_index) = match face_type {
+				PolyType::Quad => {
+					let quad = &geom.quads[face_index as usize];
+					(quad.double_sided(), quad.object_texture_index())
+				},
+				PolyType::Tri => {
+					let tri = &geom.tris[face_index as usize];
+					(tri.double_sided(), tri.object_texture_index())
+				},
+			};
+			console::info!("double sided: {}", double_sided);
+			let object_texture = &level.object_textures()[object_texture_index as usize];
+			console::info!("blend mode: {}", object_texture.blend_mode());
+			None
+		},
+		ObjectData::RoomStaticMeshFace { room_index, room_static_mesh_index, face_type, face_index } => {
+			let mesh_offset = static_mesh_offset(level, room_index, room_static_mesh_index);
+			Some((mesh_offset, face_type, face_index))
+		},
+		ObjectData::RoomSprite { room_index, sprite_index } => {
+			_ = (room_index, sprite_index);
+			None
+		},
+		ObjectData::EntityMeshFace { entity_index, mesh_index, face_type, face_index } => {
+			let mesh_offset = entity_mesh_offset(level, entity_index, mesh_index);
+			Some((mesh_offset, face_type, face_index))
+		},
+		ObjectData::EntitySprite { entity_index } | ObjectData::UnknownModelEntity { entity_index } => {
+			_ = entity_index;
+			None
+		},
+		ObjectData::Portal { room_index, portal_index } => {
+			let portal = &level.rooms()[room_index as usize].portals()[portal_index as usize];
+			console::info!("adjoining room: {}", portal.adjoining_room_index);
+			None
+		},
+		ObjectData::Reverse { .. } => panic!("reverse points to reverse"),
+	};
+	if let Some((mesh_offset, face_type, face_index)) = mesh_face {
+		console::info!("mesh offset: {}", mesh_offset);
+		let info = mesh_face_info(level, mesh_offset, face_type, face_index);
+		if let Some(object_texture_index) = info.object_texture_index {
+			let object_texture = &level.object_textures()[object_texture_index as usize];
+			console::info!("blend mode: {}", object_texture.blend_mode());
+		}
+		if let Some(color) = color_24bit(level, info.color_index_24bit) {
+			console::info!("color 24 bit: #{:06X}", color);
+		}
+		if let Some(color) = color_32bit(level, info.color_index_32bit) {
+			console::info!("color 32 bit: #{:06X}", color);
+		}
+	}
+}
+
+fn row(ui: &mut egui::Ui, label: &str, value: impl ToString) {
+	ui.label(label);
+	ui.label(value.to_string());
+	ui.end_row();
+}
+
+/// "Entity 3: Rusty Key (model 193)", or only the model id without a script name.
+pub fn entity_label(entity_index: usize, model_id: u16, item_names: &HashMap<u16, String>) -> String {
+	match item_names.get(&model_id) {
+		Some(name) => format!("Entity {}: {} (model {})", entity_index, name, model_id),
+		None => format!("Entity {}: model {}", entity_index, model_id),
+	}
+}
+
+fn entity_rows<E: Entity>(
+	ui: &mut egui::Ui, entity_index: u16, entity: &E, item_names: &HashMap<u16, String>, unit_mode: UnitMode,
+) {
+	row(ui, "Entity", entity_index);
+	if let Some(name) = item_names.get(&entity.model_id()) {
+		row(ui, "Name", name);
+	}
+	row(ui, "Model id", entity.model_id());
+	row(ui, "Room", entity.room_index());
+	row(ui, "Position", unit_mode.format_vec3(entity.pos().as_vec3()));
+	row(ui, "Angle", entity.angle());
+	row(ui, "Flags", format!("0x{:04X}", entity.flags()));
+	row(ui, "Initially invisible", entity.initially_invisible());
+	row(ui, "Activation mask", format!("{:05b}", entity.activation_mask()));
+	if let Some(clear_body) = entity.clear_body() {
+		row(ui, "Clear body", clear_body);
+	}
+	if let Some(ocb) = entity.ocb() {
+		row(ui, "OCB", ocb);
+	}
+}
+
+fn object_texture_rows<L: Level>(ui: &mut egui::Ui, level: &L, object_texture_index: u16, additive_flag: bool) {
+	let object_texture = &level.object_textures()[object_texture_index as usize];
+	row(ui, "Object texture", object_texture_index);
+	row(ui, "Blend mode", object_texture.blend_mode());
+	row(ui, "Drawn as", FaceBlend::of(object_texture.blend_mode(), additive_flag).label());
+}
+
+fn mesh_face_rows<L: Level>(
+	ui: &mut egui::Ui, level: &L, mesh_offset: u32, face_type: MeshFaceType, face_index: u16,
+) {
+	row(ui, "Mesh offset", mesh_offset);
+	row(ui, "Face", format!("{:?} {}", face_type, face_index));
+	let info = mesh_face_info(level, mesh_offset, face_type, face_index);
+	if let Some(object_texture_index) = info.object_texture_index {
+		object_texture_rows(ui, level, object_texture_index, info.additive);
+	}
+	if let Some(color) = color_24bit(level, info.color_index_24bit) {
+		row(ui, "Color 24 bit", format!("#{:06X}", color));
+	}
+	if let Some(color) = color_32bit(level, info.color_index_32bit) {
+		row(ui, "Color 32 bit", format!("#{:06X}", color));
+	}
+}
+
+/// Sector containing room space `pos` horizontally.
+fn sector_at<R: Room>(room: &R, pos: Vec3) -> Option<&tr1::Sector> {
+	let tr1::NumSectors { x, z } = *room.num_sectors();
+	let [sector_x, sector_z] = [pos.x, pos.z].map(|v| (v / SECTOR_SIZE).floor());
+	if sector_x < 0.0 || sector_z < 0.0 || sector_x >= x as f32 || sector_z >= z as f32 {
+		return None;
+	}
+	room.sectors().get(sector_x as usize * z as usize + sector_z as usize)
+}
+
+/// Selection panel contents. `data` must not be `Reverse`. `item_names` are the script's, by model id.
+pub fn show_object_data<L: Level>(
+	ui: &mut egui::Ui, level: &L, data: ObjectData, item_names: &HashMap<u16, String>, unit_mode: UnitMode,
+) {
+	egui::Grid::new("object_data").num_columns(2).show(ui, |ui| match data {
+		ObjectData::RoomFace { room_index, geom_index, face_type, face_index } => {
+			row(ui, "Type", "Room face");
+			row(ui, "Room", room_index);
+			row(ui, "Layer", geom_index);
+			row(ui, "Face", format!("{:?} {}", face_type, face_index));
+			let room = &level.rooms()[room_index as usize];
+			let bounds_label = |MinMax { min, max }: MinMax<Vec3>| {
+				format!("{} to {}", unit_mode.format_vec3(min), unit_mode.format_vec3(max))
+			};
+			row(ui, "Declared extent", bounds_label(room_bounds::declared(room)));
+			if let Some(vertex_bounds) = room_bounds::vertices(room) {
+				row(ui, "Vertex extent", bounds_label(vertex_bounds));
+			}
+			//unwrap: proven in level parse
+			let geom = room.geom().into_iter().nth(geom_index as usize).unwrap();
+			let (double_sided, object_texture_index) = match face_type {
+				PolyType::Quad => {
+					let quad = &geom.quads[face_index as usize];
+					(quad.double_sided(), quad.object_texture_index())
+				},
+				PolyType::Tri => {
+					let tri = &geom.tris[face_index as usize];
+					(tri.double_sided(), tri.object_texture_index())
+				},
+			};
+			row(ui, "Double sided", double_sided);
+			object_texture_rows(ui, level, object_texture_index, false);
+			if level.version().has_footstep_materials() {
+				let vertices = match face_type {
+					PolyType::Quad => vertex_indices(&geom.quads[face_index as usize]).collect::<Vec<_>>(),
+					PolyType::Tri => vertex_indices(&geom.tris[face_index as usize]).collect(),
+				};
+				let center = vertices
+					.iter()
+					.map(|&vertex_index| geom.vertices[vertex_index as usize].pos())
+					.sum::<Vec3>() / vertices.len() as f32;
+				if let Some(sector) = sector_at(room, center) {
+					let material = sector.footstep_material();
+					row(ui, "Footstep material", format!("{} ({})", footstep::label(material), material));
+				}
+			}
+		},
+		ObjectData::RoomStaticMeshFace { room_index, room_static_mesh_index, face_type, face_index } => {
+			row(ui, "Type", "Static mesh face");
+			row(ui, "Room", room_index);
+			row(ui, "Room static mesh", room_static_mesh_index);
+			let room = &level.rooms()[room_index as usize];
+			let room_static_mesh = &room.room_static_meshes()[room_static_mesh_index as usize];
+			row(ui, "Static mesh id", room_static_mesh.static_mesh_id());
+			let Vec3 { x: r, y: g, z: b } = room_static_mesh.light();
+			row(ui, "Light", format!("0x{:04X} ({:.2}, {:.2}, {:.2})", room_static_mesh.light_raw(), r, g, b));
+			let mesh_offset = static_mesh_offset(level, room_index, room_static_mesh_index);
+			mesh_face_rows(ui, level, mesh_offset, face_type, face_index);
+		},
+		ObjectData::RoomSprite { room_index, sprite_index } => {
+			row(ui, "Type", "Room sprite");
+			row(ui, "Room", room_index);
+			row(ui, "Sprite", sprite_index);
+		},
+		ObjectData::EntityMeshFace { entity_index, mesh_index, face_type, face_index } => {
+			row(ui, "Type", "Entity mesh face");
+			entity_rows(ui, entity_index, &level.entities()[entity_index as usize], item_names, unit_mode);
+			row(ui, "Mesh", mesh_index);
+			let mesh_offset = entity_mesh_offset(level, entity_index, mesh_index);
+			mesh_face_rows(ui, level, mesh_offset, face_type, face_index);
+		},
+		ObjectData::EntitySprite { entity_index } => {
+			row(ui, "Type", "Entity sprite");
+			entity_rows(ui, entity_index, &level.entities()[entity_index as usize], item_names, unit_mode);
+		},
+		ObjectData::UnknownModelEntity { entity_index } => {
+			row(ui, "Type", "Entity with unknown model id");
+			entity_rows(ui, entity_index, &level.entities()[entity_index as usize], item_names, unit_mode);
+		},
+		ObjectData::Portal { room_index, portal_index } => {
+			row(ui, "Type", "Portal");
+			row(ui, "Room", room_index);
+			row(ui, "Portal", portal_index);
+			let portal = &level.rooms()[room_index as usize].portals()[portal_index as usize];
+			row(ui, "Adjoining room", portal.adjoining_room_index);
+			let [x, y, z] = portal.normal.to_array();
+			row(ui, "Normal", format!("{}, {}, {}", x, y, z));
+		},
+		ObjectData::Reverse { .. } => panic!("selection is reverse"),
+	});
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{level_buffers, test_level};
+	use super::*;
+	
+	#[test]
+	fn owner_index_matches_scan() {
+		let mut test_level = test_level::tiny();
+		test_level.rooms[0].sprites = vec![tr1::Sprite { vertex_index: 0, sprite_texture_index: 0 }];
+		let level = test_level.level();
+		let object_data = level_buffers::build(level.as_ref()).unwrap().output.object_data;
+		let index = owner_index(&object_data);
+		let owners = [
+			ObjectOwner::Room(0),
+			ObjectOwner::RoomStaticMesh { room_index: 0, room_static_mesh_index: 0 },
+			ObjectOwner::Entity(0),
+		];
+		assert_eq!(index.len(), owners.len());
+		for owner in owners {
+			let scan = (0..object_data.len() as u32)
+				.filter(|&object_data_index| object_data[object_data_index as usize].owner() == Some(owner))
+				.collect::<Vec<_>>();
+			assert!(!scan.is_empty(), "{:?}", owner);
+			assert_eq!(index[&owner], scan, "{:?}", owner);
+		}
+	}
+}
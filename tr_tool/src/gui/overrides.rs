@@ -0,0 +1,70 @@
+use std::{collections::BTreeMap, fs::File, io::{Read, Result}, path::Path};
+use serde_json::{Map, Value};
+use tr_model::fnv1a_hex;
+
+/// Bytes from the start of the level file hashed to recognize it.
+const KEY_LEN: u64 = 64 * 1024;
+
+/// Render option values by key.
+pub type RenderOptionValues = BTreeMap<&'static str, Value>;
+
+/// Hash of the start of the level file, keying its saved overrides.
+pub fn level_key(path: &Path) -> Result<String> {
+	let mut bytes = vec![];
+	File::open(path)?.take(KEY_LEN).read_to_end(&mut bytes)?;
+	Ok(fnv1a_hex(&bytes))
+}
+
+/// Render option that can be saved per level.
+pub trait OptionValue {
+	fn to_value(&self) -> Value;
+	/// Leave the option unchanged and return false if `value` doesn't fit it.
+	fn set_value(&mut self, value: &Value) -> bool;
+}
+
+impl OptionValue for bool {
+	fn to_value(&self) -> Value {
+		Value::Bool(*self)
+	}
+	
+	fn set_value(&mut self, value: &Value) -> bool {
+		value.as_bool().map(|value| *self = value).is_some()
+	}
+}
+
+impl OptionValue for f32 {
+	fn to_value(&self) -> Value {
+		Value::from(*self)
+	}
+	
+	fn set_value(&mut self, value: &Value) -> bool {
+		value.as_f64().filter(|value| value.is_finite()).map(|value| *self = value as f32).is_some()
+	}
+}
+
+/// Implement `OptionValue` for an enum by the labels of `variants`.
+macro_rules! label_option_value {
+	($ty:ty, [$($variant:expr),* $(,)?]) => {
+		impl $crate::gui::overrides::OptionValue for $ty {
+			fn to_value(&self) -> serde_json::Value {
+				self.label().into()
+			}
+			
+			fn set_value(&mut self, value: &serde_json::Value) -> bool {
+				let variant = [$($variant),*].into_iter().find(|variant| value.as_str() == Some(variant.label()));
+				variant.map(|variant| *self = variant).is_some()
+			}
+		}
+	};
+}
+
+pub(crate) use label_option_value;
+
+/// Options of `values` that differ from `defaults`, as saved in settings.
+pub fn diff(values: &RenderOptionValues, defaults: &RenderOptionValues) -> Map<String, Value> {
+	values
+		.iter()
+		.filter(|&(key, value)| defaults.get(key) != Some(value))
+		.map(|(key, value)| (key.to_string(), value.clone()))
+		.collect()
+}
@@ -0,0 +1,192 @@
+use std::{collections::VecDeque, sync::{atomic::{AtomicU8, Ordering}, Arc}, task::Poll};
+use wgpu::{
+	Buffer, BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Device, Extent3d, ImageCopyBuffer,
+	ImageDataLayout, Maintain, MapMode, Origin3d, Queue, Texture, COPY_BYTES_PER_ROW_ALIGNMENT,
+};
+use winit::dpi::PhysicalPosition;
+use super::viewer::{InteractPixel, INTERACT_PIXEL_SIZE};
+
+/// A click whose result may not be available yet.
+pub trait Resolve {
+	type Output;
+	/// `Ready(None)` if the click failed and should be dropped.
+	fn resolve(&mut self) -> Poll<Option<Self::Output>>;
+}
+
+/// Resolves clicks in the order they were made. Stops at the first one still pending so later ones don't jump
+/// ahead, and skips failed ones so they don't block the rest.
+pub struct InOrder<R> {
+	pending: VecDeque<R>,
+}
+
+impl<R> Default for InOrder<R> {
+	fn default() -> Self {
+		Self { pending: VecDeque::new() }
+	}
+}
+
+impl<R: Resolve> InOrder<R> {
+	pub fn push(&mut self, pick: R) {
+		self.pending.push_back(pick);
+	}
+	
+	pub fn is_empty(&self) -> bool {
+		self.pending.is_empty()
+	}
+	
+	pub fn take_ready(&mut self) -> Vec<R::Output> {
+		let mut ready = vec![];
+		while let Some(pick) = self.pending.front_mut() {
+			match pick.resolve() {
+				Poll::Pending => break,
+				Poll::Ready(output) => {
+					ready.extend(output);
+					self.pending.pop_front();
+				},
+			}
+		}
+		ready
+	}
+}
+
+const PENDING: u8 = 0;
+const MAPPED: u8 = 1;
+const FAILED: u8 = 2;
+
+/// Readback of the interact texture row under one click.
+struct PendingPick {
+	buffer: Buffer,
+	x: u32,
+	state: Arc<AtomicU8>,
+}
+
+impl Resolve for PendingPick {
+	type Output = InteractPixel;
+	
+	fn resolve(&mut self) -> Poll<Option<InteractPixel>> {
+		match self.state.load(Ordering::Acquire) {
+			PENDING => Poll::Pending,
+			MAPPED => {
+				let offset = (self.x * INTERACT_PIXEL_SIZE) as usize;
+				let bytes = &*self.buffer.slice(..).get_mapped_range();
+				//unwrap: slice is INTERACT_PIXEL_SIZE bytes
+				let pixel = bytes[offset..offset + INTERACT_PIXEL_SIZE as usize].try_into().unwrap();
+				Poll::Ready(Some(InteractPixel::from_le_bytes(pixel)))
+			},
+			_ => Poll::Ready(None),
+		}
+	}
+}
+
+/// Clicks waiting on the GPU, resolved in the order they were made so none are dropped.
+#[derive(Default)]
+pub struct PickQueue {
+	pending: InOrder<PendingPick>,
+}
+
+impl PickQueue {
+	/// Start reading back the pixel at `pos`. Only the row containing it is copied.
+	pub fn push(&mut self, device: &Device, queue: &Queue, texture: &Texture, pos: PhysicalPosition<u32>) {
+		let width = texture.width();
+		if pos.x >= width || pos.y >= texture.height() {
+			return;
+		}
+		let row_size = (width * INTERACT_PIXEL_SIZE).next_multiple_of(COPY_BYTES_PER_ROW_ALIGNMENT);
+		let buffer = device.create_buffer(&BufferDescriptor {
+			label: None,
+			size: row_size as u64,
+			usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+			mapped_at_creation: false,
+		});
+		let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor::default());
+		let mut source = texture.as_image_copy();
+		source.origin = Origin3d { x: 0, y: pos.y, z: 0 };
+		encoder.copy_texture_to_buffer(
+			source,
+			ImageCopyBuffer {
+				buffer: &buffer,
+				layout: ImageDataLayout { offset: 0, bytes_per_row: Some(row_size), rows_per_image: None },
+			},
+			Extent3d { width, height: 1, depth_or_array_layers: 1 },
+		);
+		queue.submit([encoder.finish()]);
+		let state = Arc::new(AtomicU8::new(PENDING));
+		let set_state = state.clone();
+		buffer.slice(..).map_async(MapMode::Read, move |r| {
+			//a failed map drops the click rather than taking the viewer down
+			set_state.store(if r.is_ok() { MAPPED } else { FAILED }, Ordering::Release);
+		});
+		self.pending.push(PendingPick { buffer, x: pos.x, state });
+	}
+	
+	/// Resolved clicks in click order. Stops at the first click still waiting so later ones don't jump ahead.
+	pub fn poll(&mut self, device: &Device) -> Vec<InteractPixel> {
+		if self.pending.is_empty() {
+			return vec![];
+		}
+		device.poll(Maintain::Poll);
+		self.pending.take_ready()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{cell::Cell, rc::Rc};
+	use super::*;
+	
+	/// Stands in for a GPU readback: pending until the test sets its state.
+	struct FakePick {
+		value: u32,
+		state: Rc<Cell<u8>>,
+	}
+	
+	impl Resolve for FakePick {
+		type Output = u32;
+		
+		fn resolve(&mut self) -> Poll<Option<u32>> {
+			match self.state.get() {
+				PENDING => Poll::Pending,
+				MAPPED => Poll::Ready(Some(self.value)),
+				_ => Poll::Ready(None),
+			}
+		}
+	}
+	
+	fn clicks(queue: &mut InOrder<FakePick>, count: u32) -> Vec<Rc<Cell<u8>>> {
+		(0..count)
+			.map(|value| {
+				let state = Rc::new(Cell::new(PENDING));
+				queue.push(FakePick { value, state: state.clone() });
+				state
+			})
+			.collect()
+	}
+	
+	#[test]
+	fn back_to_back_clicks_resolve_in_click_order() {
+		let mut queue = InOrder::default();
+		let states = clicks(&mut queue, 4);
+		assert!(queue.take_ready().is_empty());
+		//later readbacks finishing first must wait behind the first click
+		states[2].set(MAPPED);
+		states[3].set(MAPPED);
+		assert!(queue.take_ready().is_empty());
+		states[0].set(MAPPED);
+		assert_eq!(queue.take_ready(), [0]);
+		states[1].set(MAPPED);
+		assert_eq!(queue.take_ready(), [1, 2, 3]);
+		assert!(queue.is_empty());
+	}
+	
+	#[test]
+	fn failed_click_is_dropped() {
+		let mut queue = InOrder::default();
+		let states = clicks(&mut queue, 3);
+		states[1].set(FAILED);
+		states[2].set(MAPPED);
+		assert!(queue.take_ready().is_empty());
+		states[0].set(MAPPED);
+		assert_eq!(queue.take_ready(), [0, 2]);
+		assert!(queue.is_empty());
+	}
+}
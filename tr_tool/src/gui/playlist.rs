@@ -0,0 +1,44 @@
+use std::path::PathBuf;
+
+/// Level files queued from the command line, stepped through in order.
+pub struct Playlist {
+	paths: Vec<PathBuf>,
+	index: usize,
+	/// Direction of the last step, continued past files that fail to load.
+	last_step: isize,
+}
+
+impl Playlist {
+	/// `None` for fewer than two paths, which need no navigation.
+	pub fn new(paths: Vec<PathBuf>) -> Option<Self> {
+		(paths.len() > 1).then_some(Self { paths, index: 0, last_step: 1 })
+	}
+	
+	pub fn current(&self) -> &PathBuf {
+		&self.paths[self.index]
+	}
+	
+	/// 1-based position and length, e.g. "2/3".
+	pub fn position(&self) -> String {
+		format!("{}/{}", self.index + 1, self.paths.len())
+	}
+	
+	pub fn has_step(&self, step: isize) -> bool {
+		self.index.checked_add_signed(step).is_some_and(|index| index < self.paths.len())
+	}
+	
+	/// Move by `step` and return the new current path, `None` past either end.
+	pub fn step(&mut self, step: isize) -> Option<&PathBuf> {
+		if !self.has_step(step) {
+			return None;
+		}
+		self.index = self.index.wrapping_add_signed(step);
+		self.last_step = step;
+		Some(self.current())
+	}
+	
+	/// Continue in the direction of the last step after the current file failed to load.
+	pub fn skip(&mut self) -> Option<&PathBuf> {
+		self.step(self.last_step)
+	}
+}
@@ -0,0 +1,183 @@
+use std::{collections::VecDeque, f32::consts::TAU};
+use glam::Vec2;
+
+/// Layout steps run when the graph is built.
+const ITERATIONS: usize = 300;
+/// Preferred edge length in layout units.
+const EDGE_LENGTH: f32 = 1.0;
+const MIN_NODE_RADIUS: f32 = 3.0;
+/// Node radius in points added per square root of the room's face count.
+const NODE_RADIUS_PER_FACE: f32 = 0.25;
+/// Zoom factor per point scrolled.
+const ZOOM_SPEED: f32 = 0.002;
+const ROOM_COLOR: egui::Color32 = egui::Color32::from_rgb(170, 170, 170);
+const WATER_COLOR: egui::Color32 = egui::Color32::from_rgb(60, 130, 230);
+const EDGE_COLOR: egui::Color32 = egui::Color32::from_gray(100);
+
+pub struct RoomNode {
+	pub faces: usize,
+	pub water: bool,
+}
+
+/// Rooms as nodes linked by their portals, laid out by a force-directed simulation.
+pub struct RoomGraph {
+	nodes: Vec<RoomNode>,
+	/// Layout position of each node.
+	positions: Vec<Vec2>,
+	/// Node index pairs, one per pair of adjoining rooms.
+	edges: Vec<(usize, usize)>,
+	/// Screen offset in points.
+	pan: egui::Vec2,
+	zoom: f32,
+}
+
+/// Fruchterman-Reingold layout: all nodes repel, edges attract, and the step size cools linearly to zero.
+/// Starts from a circle so the result is the same on every load.
+fn layout(num_nodes: usize, edges: &[(usize, usize)]) -> Vec<Vec2> {
+	let start_radius = EDGE_LENGTH * (num_nodes as f32).sqrt();
+	let mut positions = (0..num_nodes)
+		.map(|index| Vec2::from_angle(index as f32 / num_nodes as f32 * TAU) * start_radius)
+		.collect::<Vec<_>>();
+	let mut displacements = vec![Vec2::ZERO; num_nodes];
+	for iteration in 0..ITERATIONS {
+		displacements.fill(Vec2::ZERO);
+		for a in 0..num_nodes {
+			for b in a + 1..num_nodes {
+				let delta = positions[a] - positions[b];
+				let distance = delta.length().max(0.01);
+				let push = delta / distance * (EDGE_LENGTH * EDGE_LENGTH / distance);
+				displacements[a] += push;
+				displacements[b] -= push;
+			}
+		}
+		for &(a, b) in edges {
+			let delta = positions[a] - positions[b];
+			let pull = delta * (delta.length() / EDGE_LENGTH);
+			displacements[a] -= pull;
+			displacements[b] += pull;
+		}
+		let max_step = start_radius * 0.1 * (1.0 - iteration as f32 / ITERATIONS as f32);
+		for (position, displacement) in positions.iter_mut().zip(&displacements) {
+			*position += displacement.clamp_length_max(max_step);
+		}
+	}
+	positions
+}
+
+impl RoomGraph {
+	/// `adjoining` yields the rooms each room's portals lead to, by room index.
+	pub fn new(nodes: Vec<RoomNode>, adjoining: impl IntoIterator<Item = (usize, usize)>) -> Self {
+		let mut edges = adjoining
+			.into_iter()
+			.filter(|&(a, b)| a != b && a < nodes.len() && b < nodes.len())
+			.map(|(a, b)| (a.min(b), a.max(b)))
+			.collect::<Vec<_>>();
+		edges.sort_unstable();
+		edges.dedup();
+		let positions = layout(nodes.len(), &edges);
+		Self {
+			nodes,
+			positions,
+			edges,
+			pan: egui::Vec2::ZERO,
+			zoom: 1.0,
+		}
+	}
+	
+	/// Fewest-portal route between two rooms, both included, found breadth-first. `None` if unreachable.
+	pub fn path(&self, start: usize, end: usize) -> Option<Vec<usize>> {
+		let num_nodes = self.nodes.len();
+		if start >= num_nodes || end >= num_nodes {
+			return None;
+		}
+		let mut neighbors = vec![vec![]; num_nodes];
+		for &(a, b) in &self.edges {
+			neighbors[a].push(b);
+			neighbors[b].push(a);
+		}
+		let mut previous = vec![None; num_nodes];
+		previous[start] = Some(start);
+		let mut queue = VecDeque::from([start]);
+		while let Some(node) = queue.pop_front() {
+			if node == end {
+				break;
+			}
+			for &neighbor in &neighbors[node] {
+				if previous[neighbor].is_none() {
+					previous[neighbor] = Some(node);
+					queue.push_back(neighbor);
+				}
+			}
+		}
+		previous[end]?;
+		let mut path = vec![end];
+		while let Some(&node) = path.last().filter(|&&node| node != start) {
+			//unwrap: every node reached has a previous node
+			path.push(previous[node].unwrap());
+		}
+		path.reverse();
+		Some(path)
+	}
+	
+	fn node_radius(&self, node_index: usize) -> f32 {
+		(MIN_NODE_RADIUS + (self.nodes[node_index].faces as f32).sqrt() * NODE_RADIUS_PER_FACE) * self.zoom.sqrt()
+	}
+	
+	/// Drag to pan, scroll to zoom, double-click to reset. `room_ui` fills the tooltip of the hovered room.
+	/// Clicked room index.
+	pub fn show(
+		&mut self, ui: &mut egui::Ui, selected: Option<usize>, room_ui: impl FnOnce(&mut egui::Ui, usize),
+	) -> Option<usize> {
+		let size = ui.available_size().max(egui::vec2(200.0, 200.0));
+		let (response, painter) = ui.allocate_painter(size, egui::Sense::click_and_drag());
+		let rect = response.rect;
+		if response.double_clicked() {
+			self.pan = egui::Vec2::ZERO;
+			self.zoom = 1.0;
+		}
+		self.pan += response.drag_delta();
+		if let (true, Some(pointer)) = (response.hovered(), response.hover_pos()) {
+			let scroll = ui.input(|i| i.smooth_scroll_delta.y);
+			let factor = (scroll * ZOOM_SPEED).exp();
+			//keep the point under the pointer in place
+			let anchor = pointer - rect.center() - self.pan;
+			self.pan -= anchor * (factor - 1.0);
+			self.zoom *= factor;
+		}
+		let (min, max) = self.positions.iter().fold((Vec2::MAX, Vec2::MIN), |(min, max), &p| (min.min(p), max.max(p)));
+		let center = if self.positions.is_empty() { Vec2::ZERO } else { (min + max) / 2.0 };
+		let extent = (max - min).max_element().max(EDGE_LENGTH);
+		let scale = rect.width().min(rect.height()) * 0.9 / extent * self.zoom;
+		let to_screen = |p: Vec2| {
+			let p = (p - center) * scale;
+			rect.center() + self.pan + egui::vec2(p.x, p.y)
+		};
+		let painter = painter.with_clip_rect(rect);
+		for &(a, b) in &self.edges {
+			let points = [to_screen(self.positions[a]), to_screen(self.positions[b])];
+			painter.line_segment(points, (1.0, EDGE_COLOR));
+		}
+		let pointer = response.hover_pos();
+		let mut hovered = None;
+		for (node_index, (node, &position)) in self.nodes.iter().zip(&self.positions).enumerate() {
+			let screen_pos = to_screen(position);
+			let radius = self.node_radius(node_index);
+			let color = if node.water { WATER_COLOR } else { ROOM_COLOR };
+			painter.circle_filled(screen_pos, radius, color);
+			if selected == Some(node_index) {
+				painter.circle_stroke(screen_pos, radius + 2.0, (2.0, egui::Color32::YELLOW));
+			}
+			if pointer.is_some_and(|pointer| pointer.distance(screen_pos) <= radius.max(4.0)) {
+				hovered = Some(node_index);
+			}
+		}
+		let clicked = response.clicked();
+		if let Some(node_index) = hovered {
+			response.on_hover_ui_at_pointer(|ui| {
+				ui.label(format!("Room {}", node_index));
+				room_ui(ui, node_index);
+			});
+		}
+		hovered.filter(|_| clicked)
+	}
+}
@@ -0,0 +1,47 @@
+use glam::{Vec2, Vec3, Vec3Swizzles};
+use crate::{snapshot::CameraSpec, yaw_pitch};
+use super::camera_path::{CameraPath, CameraPathPlayback, Keyframe};
+
+/// Camera speed along a room path in units per second.
+const SPEED: f32 = 4096.0;
+
+/// Camera moving along a chain of rooms, interpolated as a recorded camera path.
+pub struct RoomPath {
+	path: CameraPath,
+	/// Room the camera is in from each keyframe on.
+	rooms: Vec<usize>,
+	playback: CameraPathPlayback,
+}
+
+impl RoomPath {
+	/// `points` are the positions to pass through with the room each is in. The camera faces the next point.
+	pub fn new(points: &[(Vec3, usize)]) -> Self {
+		let mut keyframes = Vec::<Keyframe>::with_capacity(points.len());
+		for (index, &(pos, _)) in points.iter().enumerate() {
+			let previous = keyframes.last();
+			let time = previous.map_or(0.0, |previous| previous.time + previous.camera.pos.distance(pos) / SPEED);
+			let toward = match (points.get(index + 1), previous) {
+				(Some(&(next, _)), _) => next - pos,
+				(None, Some(previous)) => pos - previous.camera.pos,
+				(None, None) => Vec3::NEG_Z,
+			};
+			let (mut yaw, pitch) = yaw_pitch(toward);
+			if toward.xz() == Vec2::ZERO {
+				//straight up or down, keep facing the same way
+				yaw = previous.map_or(0.0, |previous| previous.camera.yaw);
+			}
+			keyframes.push(Keyframe { time, camera: CameraSpec { pos, yaw, pitch } });
+		}
+		let rooms = points.iter().map(|&(_, room_index)| room_index).collect();
+		let playback = CameraPathPlayback { playing: true, ..CameraPathPlayback::default() };
+		Self { path: CameraPath { keyframes }, rooms, playback }
+	}
+	
+	/// Advance by `delta_secs`. Camera to show and the room it is in, `None` once past the end.
+	pub fn advance(&mut self, delta_secs: f32) -> Option<(CameraSpec, usize)> {
+		let camera = self.playback.advance(&self.path, delta_secs)?;
+		let time = self.playback.time;
+		let keyframe_index = self.path.keyframes.partition_point(|keyframe| keyframe.time <= time).max(1) - 1;
+		Some((camera, self.rooms[keyframe_index]))
+	}
+}
@@ -0,0 +1,480 @@
+use std::{
+	collections::HashMap, fs, io::{Cursor, Error, Read, Result, Seek, SeekFrom}, ops::Range, path::{Path, PathBuf},
+};
+use crate::version::Version;
+
+/// TR2/3 `TOMBPC.DAT` offsets and flags.
+const TR23_NUM_LEVELS_OFFSET: u64 = 326;
+const TR23_FLAGS_OFFSET: u64 = 374;
+const TR23_STRINGS_OFFSET: u64 = 390;
+const TR23_FLAG_USE_XOR: u16 = 0x100;
+/// Menu and message strings between the game strings and the item strings.
+const TR23_NUM_PC_STRINGS: usize = 41;
+
+/// TR4 `SCRIPT.DAT` offsets and language file XOR key.
+const TR4_NUM_LEVELS_OFFSET: u64 = 9;
+const TR4_PATHS_OFFSET: u64 = 56;
+const TR4_LANGUAGE_XOR: u8 = 0xA5;
+const TR4_DEFAULT_LANGUAGE: &str = "ENGLISH.DAT";
+
+/// TR4 level block opcodes.
+const TR4_OP_LEVEL: u8 = 0x81;
+const TR4_OP_END: u8 = 0x83;
+
+/// TR2 model ids of the puzzle, pickup and key items lying in levels, by inventory slot.
+const TR2_PUZZLE_ITEM_IDS: Range<u16> = 174..178;
+const TR2_PICKUP_ITEM_IDS: Range<u16> = 205..207;
+const TR2_KEY_ITEM_IDS: Range<u16> = 193..197;
+
+/// Per level names of the inventory items, by slot.
+#[derive(Default)]
+pub struct ItemNames {
+	pub puzzles: Vec<String>,
+	pub pickups: Vec<String>,
+	pub keys: Vec<String>,
+}
+
+pub struct ScriptLevel {
+	pub name: String,
+	/// Lowercase file name without directory or extension, to match level files.
+	pub file_stem: String,
+	/// Empty if the script's item strings couldn't be read or the format has none.
+	pub item_names: ItemNames,
+}
+
+/// Level list read from a game script.
+pub struct Script {
+	pub levels: Vec<ScriptLevel>,
+}
+
+fn read_u8<R: Read>(reader: &mut R) -> Result<u8> {
+	let mut bytes = [0; 1];
+	reader.read_exact(&mut bytes)?;
+	Ok(bytes[0])
+}
+
+fn read_u16<R: Read>(reader: &mut R) -> Result<u16> {
+	let mut bytes = [0; 2];
+	reader.read_exact(&mut bytes)?;
+	Ok(u16::from_le_bytes(bytes))
+}
+
+fn read_u16s<R: Read>(reader: &mut R, len: usize) -> Result<Vec<u16>> {
+	(0..len).map(|_| read_u16(reader)).collect()
+}
+
+fn read_bytes<R: Read>(reader: &mut R, len: usize) -> Result<Vec<u8>> {
+	let mut bytes = vec![0; len];
+	reader.read_exact(&mut bytes)?;
+	Ok(bytes)
+}
+
+/// Null-terminated string at `offset` in `block`, with each byte XORed by `xor`.
+fn get_string(block: &[u8], offset: u16, xor: u8) -> Result<String> {
+	let bytes = block.get(offset as usize..).ok_or_else(|| Error::other("String offset out of bounds"))?;
+	let bytes = bytes.iter().map(|&b| b ^ xor).take_while(|&b| b != 0).collect::<Vec<_>>();
+	Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Offsets, block size, then block.
+fn read_tr23_strings<R: Read>(reader: &mut R, len: usize, xor: u8) -> Result<Vec<String>> {
+	let offsets = read_u16s(reader, len)?;
+	let block_len = read_u16(reader)?;
+	let block = read_bytes(reader, block_len as usize)?;
+	offsets.into_iter().map(|offset| get_string(&block, offset, xor)).collect()
+}
+
+/// `data\wall.TR2` -> `wall`.
+fn path_stem(path: &str) -> String {
+	let file_name = path.rsplit(['\\', '/']).next().unwrap_or_default();
+	let stem = file_name.split('.').next().unwrap_or_default();
+	stem.to_lowercase()
+}
+
+/// Item strings per level, after the level paths. Cutscene paths, sequences, demo levels, game strings and PC
+/// strings come first and are skipped.
+fn read_tr23_items<R: Read>(
+	reader: &mut R, num_levels: usize, num_cutscenes: usize, num_demo_levels: usize, xor: u8,
+) -> Result<Vec<ItemNames>> {
+	read_tr23_strings(reader, num_cutscenes, xor)?;
+	//sequence offsets, including one for the title
+	read_u16s(reader, num_levels + 1)?;
+	let sequences_len = read_u16(reader)?;
+	read_bytes(reader, sequences_len as usize)?;
+	read_u16s(reader, num_demo_levels)?;
+	let num_game_strings = read_u16(reader)? as usize;
+	read_tr23_strings(reader, num_game_strings, xor)?;
+	read_tr23_strings(reader, TR23_NUM_PC_STRINGS, xor)?;
+	//one table per slot, each with a string per level
+	let mut read_slots = |num_slots| {
+		(0..num_slots).map(|_| read_tr23_strings(reader, num_levels, xor)).collect::<Result<Vec<_>>>()
+	};
+	let puzzles = read_slots(4)?;
+	let pickups = read_slots(2)?;
+	let keys = read_slots(4)?;
+	let level_names = |slots: &[Vec<String>], level_index: usize| {
+		slots.iter().map(|slot| slot[level_index].clone()).collect()
+	};
+	let item_names = (0..num_levels)
+		.map(|level_index| ItemNames {
+			puzzles: level_names(&puzzles, level_index),
+			pickups: level_names(&pickups, level_index),
+			keys: level_names(&keys, level_index),
+		})
+		.collect();
+	Ok(item_names)
+}
+
+/// Item strings that can't be read are left out rather than failing the level list.
+fn read_tr23(bytes: &[u8]) -> Result<Script> {
+	let mut reader = Cursor::new(bytes);
+	reader.seek(SeekFrom::Start(TR23_NUM_LEVELS_OFFSET))?;
+	let num_levels = read_u16(&mut reader)? as usize;
+	let num_chapter_screens = read_u16(&mut reader)? as usize;
+	let num_titles = read_u16(&mut reader)? as usize;
+	let num_fmvs = read_u16(&mut reader)? as usize;
+	let num_cutscenes = read_u16(&mut reader)? as usize;
+	let num_demo_levels = read_u16(&mut reader)? as usize;
+	reader.seek(SeekFrom::Start(TR23_FLAGS_OFFSET))?;
+	let flags = read_u16(&mut reader)?;
+	reader.seek(SeekFrom::Current(6))?;
+	let xor = read_u8(&mut reader)?;
+	let xor = if flags & TR23_FLAG_USE_XOR != 0 { xor } else { 0 };
+	reader.seek(SeekFrom::Start(TR23_STRINGS_OFFSET))?;
+	let names = read_tr23_strings(&mut reader, num_levels, xor)?;
+	for len in [num_chapter_screens, num_titles, num_fmvs] {
+		read_tr23_strings(&mut reader, len, xor)?;
+	}
+	let paths = read_tr23_strings(&mut reader, num_levels, xor)?;
+	let mut item_names = read_tr23_items(&mut reader, num_levels, num_cutscenes, num_demo_levels, xor)
+		.unwrap_or_default()
+		.into_iter();
+	let levels = names
+		.into_iter()
+		.zip(paths)
+		.map(|(name, path)| ScriptLevel {
+			name,
+			file_stem: path_stem(&path),
+			item_names: item_names.next().unwrap_or_default(),
+		})
+		.collect();
+	Ok(Script { levels })
+}
+
+/// Arg size of each level block opcode, `None` if unknown. Needed to walk a block.
+fn tr4_arg_size(opcode: u8) -> Option<usize> {
+	match opcode {
+		0x80 => Some(1),//fmv
+		0x81 => Some(5),//level
+		0x82 => Some(4),//title
+		0x83 => Some(0),//end
+		0x84..=0x88 => Some(1),//cut, resident cuts
+		0x89 | 0x8A => Some(4),//layers
+		0x8B | 0x8C => Some(1),//uv rotate, legend
+		0x8D => Some(9),//lens flare
+		0x8E => Some(5),//mirror
+		0x8F => Some(3),//fog
+		0x90 => Some(2),//animating mip
+		0x91 => Some(25),//load camera
+		0x92 => Some(1),//reset hub
+		0x93..=0xD9 => Some(14),//key, puzzle, pickup, examine and combo item names
+		_ => None,
+	}
+}
+
+/// Name string index and path index of the level opcode in a level block, if any.
+fn find_tr4_level(block: &[u8]) -> Option<(u8, u8)> {
+	let mut pos = 0;
+	loop {
+		let opcode = *block.get(pos)?;
+		if opcode == TR4_OP_LEVEL {
+			return Some((*block.get(pos + 1)?, *block.get(pos + 4)?));
+		}
+		if opcode == TR4_OP_END {
+			return None;
+		}
+		pos += 1 + tr4_arg_size(opcode)?;
+	}
+}
+
+/// Three string counts, three block sizes, offsets, then one block of all strings.
+fn read_tr4_language(bytes: &[u8]) -> Result<Vec<String>> {
+	let mut reader = Cursor::new(bytes);
+	let counts = read_u16s(&mut reader, 3)?;
+	let block_lens = read_u16s(&mut reader, 3)?;
+	let offsets = read_u16s(&mut reader, counts.iter().map(|&c| c as usize).sum())?;
+	let block = read_bytes(&mut reader, block_lens.iter().map(|&l| l as usize).sum())?;
+	offsets.into_iter().map(|offset| get_string(&block, offset, TR4_LANGUAGE_XOR)).collect()
+}
+
+/// Level names are indices into a separate language file, named after the level blocks.
+fn read_tr4(bytes: &[u8], script_dir: &Path) -> Result<Script> {
+	let mut reader = Cursor::new(bytes);
+	reader.seek(SeekFrom::Start(TR4_NUM_LEVELS_OFFSET))?;
+	let num_levels = read_u8(&mut reader)? as usize;
+	let num_paths = read_u16(&mut reader)? as usize;
+	let paths_len = read_u16(&mut reader)? as usize;
+	let level_blocks_len = read_u16(&mut reader)? as usize;
+	reader.seek(SeekFrom::Start(TR4_PATHS_OFFSET))?;
+	let path_offsets = read_u16s(&mut reader, num_paths)?;
+	let paths_block = read_bytes(&mut reader, paths_len)?;
+	let paths = path_offsets
+		.into_iter()
+		.map(|offset| get_string(&paths_block, offset, 0))
+		.collect::<Result<Vec<_>>>()?;
+	let mut level_offsets = read_u16s(&mut reader, num_levels)?;
+	let level_blocks = read_bytes(&mut reader, level_blocks_len)?;
+	let language = bytes
+		.get(reader.position() as usize..)
+		.and_then(|rest| rest.split(|&b| b == 0).next())
+		.filter(|name| !name.is_empty())
+		.map(|name| String::from_utf8_lossy(name).into_owned())
+		.unwrap_or_else(|| TR4_DEFAULT_LANGUAGE.to_string());
+	let language_path = find_file(script_dir, &language).ok_or_else(|| Error::other("Language file not found"))?;
+	let strings = read_tr4_language(&fs::read(language_path)?)?;
+	level_offsets.push(level_blocks_len as u16);
+	let levels = level_offsets
+		.windows(2)
+		.filter_map(|w| find_tr4_level(level_blocks.get(w[0] as usize..w[1] as usize)?))
+		.filter_map(|(string_index, path_index)| {
+			Some(ScriptLevel {
+				name: strings.get(string_index as usize)?.clone(),
+				file_stem: path_stem(paths.get(path_index as usize)?),
+				item_names: ItemNames::default(),
+			})
+		})
+		.collect();
+	Ok(Script { levels })
+}
+
+/// Case-insensitive lookup of a file in `dir`.
+fn find_file(dir: &Path, file_name: &str) -> Option<PathBuf> {
+	fs::read_dir(dir)
+		.ok()?
+		.filter_map(|entry| entry.ok())
+		.map(|entry| entry.path())
+		.find(|path| path.file_name().is_some_and(|n| n.to_string_lossy().eq_ignore_ascii_case(file_name)))
+}
+
+/// `SCRIPT.DAT` is TR4, anything else is read as TR2/3 `TOMBPC.DAT`.
+pub fn read_script(path: &Path) -> Result<Script> {
+	let bytes = fs::read(path)?;
+	let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+	if file_name.eq_ignore_ascii_case("script.dat") {
+		read_tr4(&bytes, path.parent().unwrap_or(Path::new(".")))
+	} else {
+		read_tr23(&bytes)
+	}
+}
+
+/// Look for a script in the level's directory and its parent. Missing or unreadable scripts give `None`.
+pub fn find_script(level_path: &Path) -> Option<Script> {
+	let level_dir = level_path.parent()?;
+	let dirs = [Some(level_dir), level_dir.parent()];
+	dirs
+		.into_iter()
+		.flatten()
+		.flat_map(|dir| ["TOMBPC.DAT", "SCRIPT.DAT"].map(|name| find_file(dir, name)))
+		.flatten()
+		.find_map(|path| read_script(&path).ok())
+}
+
+impl Script {
+	/// Script entry for a level file, matched by file name.
+	pub fn level(&self, level_path: &Path) -> Option<&ScriptLevel> {
+		let stem = level_path.file_stem()?.to_string_lossy().to_lowercase();
+		self.levels.iter().find(|level| level.file_stem == stem)
+	}
+}
+
+impl ScriptLevel {
+	/// Item names by the model ids of the items lying in the level. Only TR2's item model ids are known, so other
+	/// versions give none.
+	pub fn item_names_by_model(&self, version: Version) -> HashMap<u16, String> {
+		let ItemNames { puzzles, pickups, keys } = &self.item_names;
+		let slots = match version {
+			Version::Tr2 => [(TR2_PUZZLE_ITEM_IDS, puzzles), (TR2_PICKUP_ITEM_IDS, pickups), (TR2_KEY_ITEM_IDS, keys)],
+			_ => return HashMap::new(),
+		};
+		slots
+			.into_iter()
+			.flat_map(|(model_ids, names)| model_ids.zip(names.iter().cloned()))
+			.filter(|(_, name)| !name.is_empty())
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{gui::object_info::entity_label, test_level::TempDir};
+	use super::*;
+	
+	const TR23_XOR: u8 = 0xA6;
+	
+	fn put_u16(bytes: &mut Vec<u8>, value: u16) {
+		bytes.extend(value.to_le_bytes());
+	}
+	
+	/// Offsets, block size, then block of null-terminated strings, each byte XORed by `xor`.
+	fn string_table(bytes: &mut Vec<u8>, strings: &[&str], xor: u8) {
+		let mut block = vec![];
+		for string in strings {
+			put_u16(bytes, block.len() as u16);
+			block.extend(string.bytes().chain([0]).map(|b| b ^ xor));
+		}
+		put_u16(bytes, block.len() as u16);
+		bytes.extend(block);
+	}
+	
+	/// TR2 `TOMBPC.DAT` with the given levels as (name, path, key 1 name), and a title and cutscene.
+	fn tombpc(levels: &[(&str, &str, &str)], use_xor: bool) -> Vec<u8> {
+		let xor = if use_xor { TR23_XOR } else { 0 };
+		let num_levels = levels.len();
+		let mut bytes = vec![0; TR23_STRINGS_OFFSET as usize];
+		//levels, chapter screens, titles, fmvs, cutscenes, demo levels
+		for (index, count) in [num_levels, 1, 1, 0, 1, 1].into_iter().enumerate() {
+			let offset = TR23_NUM_LEVELS_OFFSET as usize + index * 2;
+			bytes[offset..offset + 2].copy_from_slice(&(count as u16).to_le_bytes());
+		}
+		if use_xor {
+			let flags_offset = TR23_FLAGS_OFFSET as usize;
+			bytes[flags_offset..flags_offset + 2].copy_from_slice(&TR23_FLAG_USE_XOR.to_le_bytes());
+			bytes[flags_offset + 8] = TR23_XOR;
+		}
+		let names = levels.iter().map(|&(name, _, _)| name).collect::<Vec<_>>();
+		let paths = levels.iter().map(|&(_, path, _)| path).collect::<Vec<_>>();
+		string_table(&mut bytes, &names, xor);
+		string_table(&mut bytes, &["pix\\legal.pcx"], xor);
+		string_table(&mut bytes, &["data\\title.TR2"], xor);
+		string_table(&mut bytes, &[], xor);
+		string_table(&mut bytes, &paths, xor);
+		string_table(&mut bytes, &["data\\cut1.TR2"], xor);
+		//sequence offsets, then sequences of one end opcode each
+		for index in 0..=num_levels {
+			put_u16(&mut bytes, index as u16 * 2);
+		}
+		put_u16(&mut bytes, (num_levels as u16 + 1) * 2);
+		for _ in 0..=num_levels {
+			put_u16(&mut bytes, 9);
+		}
+		put_u16(&mut bytes, 0);//demo level
+		put_u16(&mut bytes, 2);//game strings
+		string_table(&mut bytes, &["INVENTORY", "OPTION"], xor);
+		string_table(&mut bytes, &["pc"; TR23_NUM_PC_STRINGS], xor);
+		let puzzle_names = levels.iter().map(|_| "P1").collect::<Vec<_>>();
+		let key_names = levels.iter().map(|&(_, _, key)| key).collect::<Vec<_>>();
+		for slot in 0..10 {
+			match slot {
+				0 => string_table(&mut bytes, &puzzle_names, xor),
+				6 => string_table(&mut bytes, &key_names, xor),
+				_ => string_table(&mut bytes, &vec![""; num_levels], xor),
+			}
+		}
+		bytes
+	}
+	
+	fn level_entry(script: &Script, index: usize) -> (&str, &str) {
+		let level = &script.levels[index];
+		(level.name.as_str(), level.file_stem.as_str())
+	}
+	
+	#[test]
+	fn tr2_levels_and_item_names() {
+		let levels = [
+			("Great Wall", "data\\wall.TR2", "Guardhouse Key"),
+			("Venice", "data\\boat.TR2", "Boathouse Key"),
+		];
+		for use_xor in [false, true] {
+			let script = read_tr23(&tombpc(&levels, use_xor)).unwrap();
+			assert_eq!(script.levels.len(), 2);
+			assert_eq!(level_entry(&script, 0), ("Great Wall", "wall"));
+			assert_eq!(level_entry(&script, 1), ("Venice", "boat"));
+			let venice = script.level(Path::new("levels/BOAT.TR2")).unwrap();
+			let item_names = venice.item_names_by_model(Version::Tr2);
+			assert_eq!(item_names.get(&TR2_KEY_ITEM_IDS.start).map(String::as_str), Some("Boathouse Key"));
+			assert_eq!(item_names.get(&TR2_PUZZLE_ITEM_IDS.start).map(String::as_str), Some("P1"));
+			//unnamed slots are left out
+			assert_eq!(item_names.len(), 2);
+			assert!(venice.item_names_by_model(Version::Tr3).is_empty());
+		}
+	}
+	
+	/// `SCRIPT.DAT` with a title block and the given levels as (name string index, path index), and
+	/// `ENGLISH.DAT` with `strings` as its first group.
+	fn write_tr4_script(dir: &TempDir, paths: &[&str], levels: &[(u8, u8)], strings: &[&str]) -> PathBuf {
+		let mut path_offsets = vec![];
+		let mut paths_block = vec![];
+		for path in paths {
+			path_offsets.push(paths_block.len() as u16);
+			paths_block.extend(path.bytes().chain([0]));
+		}
+		let mut level_offsets = vec![];
+		let mut level_blocks = vec![];
+		level_offsets.push(0);
+		level_blocks.extend([0x82, 0, 0, 0, 0, TR4_OP_END]);
+		for &(name_index, path_index) in levels {
+			level_offsets.push(level_blocks.len() as u16);
+			//fog before the level opcode, to walk past
+			level_blocks.extend([0x8F, 0, 0, 0]);
+			level_blocks.extend([TR4_OP_LEVEL, name_index, 0, 0, path_index, 0, TR4_OP_END]);
+		}
+		let mut bytes = vec![0; TR4_PATHS_OFFSET as usize];
+		bytes[TR4_NUM_LEVELS_OFFSET as usize] = level_offsets.len() as u8;
+		for (index, value) in [paths.len(), paths_block.len(), level_blocks.len()].into_iter().enumerate() {
+			let offset = TR4_NUM_LEVELS_OFFSET as usize + 1 + index * 2;
+			bytes[offset..offset + 2].copy_from_slice(&(value as u16).to_le_bytes());
+		}
+		path_offsets.into_iter().for_each(|offset| put_u16(&mut bytes, offset));
+		bytes.extend(paths_block);
+		level_offsets.into_iter().for_each(|offset| put_u16(&mut bytes, offset));
+		bytes.extend(level_blocks);
+		bytes.extend(b"english.dat\0");
+		let script_path = dir.join("SCRIPT.DAT");
+		fs::write(&script_path, bytes).unwrap();
+		let mut language = vec![];
+		let mut offsets = vec![];
+		let mut block = vec![];
+		for string in strings {
+			offsets.push(block.len() as u16);
+			block.extend(string.bytes().chain([0]).map(|b| b ^ TR4_LANGUAGE_XOR));
+		}
+		[strings.len() as u16, 0, 0, block.len() as u16, 0, 0].into_iter().for_each(|v| put_u16(&mut language, v));
+		offsets.into_iter().for_each(|offset| put_u16(&mut language, offset));
+		language.extend(block);
+		fs::write(dir.join("ENGLISH.DAT"), language).unwrap();
+		script_path
+	}
+	
+	#[test]
+	fn tr4_levels_from_language_file() {
+		let dir = TempDir::new("tr4_script");
+		let paths = ["data\\title", "data\\angkor1", "data\\ang_race"];
+		let strings = ["Lara's Home", "Angkor Wat", "Race For The Iris"];
+		let script_path = write_tr4_script(&dir, &paths, &[(1, 1), (2, 2)], &strings);
+		let script = read_script(&script_path).unwrap();
+		assert_eq!(script.levels.len(), 2);
+		assert_eq!(level_entry(&script, 0), ("Angkor Wat", "angkor1"));
+		assert_eq!(level_entry(&script, 1), ("Race For The Iris", "ang_race"));
+		let level_path = dir.join("ANG_RACE.TR4");
+		assert_eq!(find_script(&level_path).unwrap().level(&level_path).unwrap().name, "Race For The Iris");
+	}
+	
+	#[test]
+	fn corrupt_script_degrades_to_numeric_ids() {
+		let levels = [("Great Wall", "data\\wall.TR2", "Guardhouse Key")];
+		let bytes = tombpc(&levels, true);
+		//cut inside the item strings: the level list is still read, without item names
+		let script = read_tr23(&bytes[..bytes.len() - 8]).unwrap();
+		let wall = script.level(Path::new("wall.tr2")).unwrap();
+		assert_eq!(wall.name, "Great Wall");
+		let item_names = wall.item_names_by_model(Version::Tr2);
+		assert!(item_names.is_empty());
+		assert_eq!(entity_label(3, TR2_KEY_ITEM_IDS.start, &item_names), "Entity 3: model 193");
+		//cut inside the level list: no script is found, so the level has no names at all
+		let dir = TempDir::new("corrupt_script");
+		fs::write(dir.join("TOMBPC.DAT"), &bytes[..TR23_STRINGS_OFFSET as usize + 4]).unwrap();
+		assert!(find_script(&dir.join("wall.tr2")).is_none());
+		fs::write(dir.join("TOMBPC.DAT"), [0xFF; 16]).unwrap();
+		assert!(find_script(&dir.join("wall.tr2")).is_none());
+	}
+}
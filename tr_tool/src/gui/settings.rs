@@ -0,0 +1,94 @@
+use std::{fs, io::{Error, Result}};
+use serde_json::{Map, Value};
+use crate::{console, settings::{Settings, PATH}, version::{LearnedVersion, ReadMode, Version}};
+
+impl Settings {
+	pub fn save(&self) -> Result<()> {
+		let json = serde_json::to_string_pretty(self).map_err(Error::other)?;
+		fs::write(PATH, json)
+	}
+	
+	/// Remember `version` for files with this magic and extension, replacing any earlier choice.
+	pub fn learn_version(&mut self, magic: u32, extension: &str, version: Version) {
+		let extension = extension.to_ascii_lowercase();
+		self.learned_versions.retain(|learned| learned.magic != magic || learned.extension != extension);
+		self.learned_versions.push(LearnedVersion { magic, extension, version });
+	}
+	
+	/// Saved render options of the level with this key. A malformed entry is reported and ignored.
+	pub fn level_overrides(&self, key: &str) -> Option<&Map<String, Value>> {
+		let entry = self.level_overrides.get(key)?;
+		let overrides = entry.as_object();
+		if overrides.is_none() {
+			console::log_warn!("ignoring malformed render overrides for level {}", key);
+		}
+		overrides
+	}
+	
+	/// Replace the saved render options of the level with this key, forgetting them if empty. True if changed.
+	pub fn set_level_overrides(&mut self, key: &str, overrides: Map<String, Value>) -> bool {
+		if overrides.is_empty() {
+			return self.level_overrides.remove(key).is_some();
+		}
+		let overrides = Value::Object(overrides);
+		if self.level_overrides.get(key) == Some(&overrides) {
+			return false;
+		}
+		self.level_overrides.insert(key.to_string(), overrides);
+		true
+	}
+	
+	/// List of learned versions with a button to forget each. True if one was forgotten.
+	pub fn show_learned_versions(&mut self, ui: &mut egui::Ui) -> bool {
+		if self.learned_versions.is_empty() {
+			ui.label("None yet. Versions picked for unknown file types are remembered here.");
+			return false;
+		}
+		let mut forget = None;
+		egui::Grid::new("learned_versions").show(ui, |ui| {
+			for (index, learned) in self.learned_versions.iter().enumerate() {
+				ui.label(format!("0x{:X}", learned.magic));
+				ui.label(format!(".{}", learned.extension));
+				ui.label(learned.version.label());
+				if ui.button("Forget").clicked() {
+					forget = Some(index);
+				}
+				ui.end_row();
+			}
+		});
+		if let Some(index) = forget {
+			self.learned_versions.remove(index);
+		}
+		forget.is_some()
+	}
+}
+
+impl ReadMode {
+	pub const ALL: [ReadMode; 2] = [ReadMode::Buffered, ReadMode::Mapped];
+	
+	pub fn label(&self) -> &'static str {
+		match self {
+			ReadMode::Buffered => "Buffered reads",
+			ReadMode::Mapped => "Memory-map file",
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn learned_version_replaces_earlier_choice() {
+		let mut settings = Settings::default();
+		settings.learn_version(0x20, "PHD", Version::Tr2);
+		settings.learn_version(0x345254, "DAT", Version::Tr5);
+		settings.learn_version(0x345254, "dat", Version::Tr4);
+		let learned = settings
+			.learned_versions
+			.iter()
+			.map(|learned| (learned.magic, learned.extension.as_str(), learned.version))
+			.collect::<Vec<_>>();
+		assert_eq!(learned, [(0x20, "phd", Version::Tr2), (0x345254, "dat", Version::Tr4)]);
+	}
+}
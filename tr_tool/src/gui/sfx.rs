@@ -0,0 +1,163 @@
+use std::{fs, io::{Error, Result}, path::{Path, PathBuf}};
+
+const RIFF: &[u8; 4] = b"RIFF";
+const WAVE: &[u8; 4] = b"WAVE";
+/// Chunk id and length.
+const CHUNK_HEADER_LEN: usize = 8;
+
+/// Samples of a TR2-3 MAIN.SFX, which is RIFF WAVE files back to back. Levels index them through
+/// `sample_indices`.
+pub struct Sfx {
+	pub path: PathBuf,
+	/// Each a whole WAV file.
+	pub samples: Vec<Box<[u8]>>,
+}
+
+/// Format of a WAV file, from its `fmt ` and `data` chunks.
+#[derive(Clone, Copy)]
+pub struct WavInfo {
+	pub channels: u16,
+	pub sample_rate: u32,
+	pub bits_per_sample: u16,
+	/// Bytes of sample data.
+	pub data_len: u32,
+}
+
+impl WavInfo {
+	pub fn secs(&self) -> f32 {
+		let bytes_per_sec = self.sample_rate * self.channels as u32 * (self.bits_per_sample as u32 / 8);
+		self.data_len as f32 / bytes_per_sec.max(1) as f32
+	}
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> Option<u16> {
+	Some(u16::from_le_bytes(bytes.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> Option<u32> {
+	Some(u32::from_le_bytes(bytes.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+/// Split the concatenated RIFF files. Trailing bytes too short for a RIFF header are ignored.
+pub fn parse(bytes: &[u8]) -> Result<Vec<Box<[u8]>>> {
+	let mut samples = vec![];
+	let mut offset = 0;
+	while bytes.len() - offset >= CHUNK_HEADER_LEN + WAVE.len() {
+		let header = &bytes[offset..];
+		if &header[..4] != RIFF || &header[CHUNK_HEADER_LEN..][..4] != WAVE {
+			return Err(Error::other(format!("no RIFF WAVE header at offset {}", offset)));
+		}
+		//unwrap: length checked by loop condition
+		let len = CHUNK_HEADER_LEN + u32_at(header, 4).unwrap() as usize;
+		let Some(sample) = header.get(..len) else {
+			return Err(Error::other(format!("RIFF at offset {} runs past the end of the file", offset)));
+		};
+		samples.push(sample.into());
+		offset += len;
+	}
+	Ok(samples)
+}
+
+/// `None` if the file lacks a `fmt ` or `data` chunk.
+pub fn wav_info(wav: &[u8]) -> Option<WavInfo> {
+	let mut offset = CHUNK_HEADER_LEN + WAVE.len();
+	let mut format = None;
+	while offset + CHUNK_HEADER_LEN <= wav.len() {
+		let id = &wav[offset..offset + 4];
+		let len = u32_at(wav, offset + 4)?;
+		let body = offset + CHUNK_HEADER_LEN;
+		match id {
+			b"fmt " => {
+				//audio format, channels, sample rate, byte rate, block align, bits per sample
+				format = Some((u16_at(wav, body + 2)?, u32_at(wav, body + 4)?, u16_at(wav, body + 14)?));
+			},
+			b"data" => {
+				let (channels, sample_rate, bits_per_sample) = format?;
+				return Some(WavInfo { channels, sample_rate, bits_per_sample, data_len: len });
+			},
+			_ => {},
+		}
+		//chunks are padded to even length
+		offset = body + (len as usize).next_multiple_of(2);
+	}
+	None
+}
+
+impl Sfx {
+	pub fn load(path: PathBuf) -> Result<Self> {
+		let samples = parse(&fs::read(&path)?)?;
+		Ok(Self { path, samples })
+	}
+}
+
+/// File in `dir` named `name` in any case.
+fn find_file(dir: &Path, name: &str) -> Option<PathBuf> {
+	fs::read_dir(dir).ok()?.flatten().map(|entry| entry.path()).find(|path| {
+		path.file_name().and_then(|file_name| file_name.to_str()).is_some_and(|file_name| {
+			file_name.eq_ignore_ascii_case(name)
+		})
+	})
+}
+
+/// MAIN.SFX next to the level, or in a `data` folder beside the level's folder.
+pub fn find_main_sfx(level_path: &Path) -> Option<PathBuf> {
+	let level_dir = level_path.parent()?;
+	find_file(level_dir, "main.sfx").or_else(|| find_file(&find_file(level_dir.parent()?, "data")?, "main.sfx"))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	/// Chunk with its even padding.
+	fn chunk(id: &[u8; 4], body: &[u8]) -> Vec<u8> {
+		let mut chunk = [id.as_slice(), &(body.len() as u32).to_le_bytes(), body].concat();
+		chunk.resize(chunk.len().next_multiple_of(2), 0);
+		chunk
+	}
+	
+	fn wav(channels: u16, sample_rate: u32, bits_per_sample: u16, extra: &[u8], data: &[u8]) -> Vec<u8> {
+		let block_align = channels * bits_per_sample / 8;
+		let fmt = [
+			&1u16.to_le_bytes()[..],
+			&channels.to_le_bytes(),
+			&sample_rate.to_le_bytes(),
+			&(sample_rate * block_align as u32).to_le_bytes(),
+			&block_align.to_le_bytes(),
+			&bits_per_sample.to_le_bytes(),
+		].concat();
+		let body = [WAVE.as_slice(), &chunk(b"LIST", extra), &chunk(b"fmt ", &fmt), &chunk(b"data", data)].concat();
+		chunk(RIFF, &body)
+	}
+	
+	fn two_samples() -> (Vec<u8>, Vec<u8>) {
+		//an odd length chunk ahead of `fmt ` to check the padding is skipped
+		(wav(1, 11025, 16, &[1, 2, 3], &[0; 22050]), wav(2, 22050, 8, &[], &[0; 11025]))
+	}
+	
+	#[test]
+	fn two_riff_samples() {
+		let (first, second) = two_samples();
+		let samples = parse(&[&first[..], &second, &[0; 4]].concat()).unwrap();
+		assert_eq!(samples.len(), 2);
+		assert_eq!(*samples[0], *first);
+		assert_eq!(*samples[1], *second);
+		let info = wav_info(&samples[0]).unwrap();
+		assert_eq!((info.channels, info.sample_rate, info.bits_per_sample, info.data_len), (1, 11025, 16, 22050));
+		assert_eq!(info.secs(), 1.0);
+		let info = wav_info(&samples[1]).unwrap();
+		assert_eq!((info.channels, info.sample_rate, info.bits_per_sample, info.data_len), (2, 22050, 8, 11025));
+		assert_eq!(info.secs(), 0.25);
+	}
+	
+	#[test]
+	fn truncated_riff() {
+		let (first, second) = two_samples();
+		let bytes = [&first[..], &second].concat();
+		let error = parse(&bytes[..bytes.len() - 1]).err().unwrap();
+		assert_eq!(error.to_string(), format!("RIFF at offset {} runs past the end of the file", first.len()));
+		//a sample cut off before its `data` chunk has no format
+		assert!(wav_info(&first[..first.len() - 22050 - CHUNK_HEADER_LEN]).is_none());
+		assert!(parse(&[&first[..], b"junk and more junk"].concat()).is_err());
+	}
+}
@@ -0,0 +1,124 @@
+use std::path::{Path, PathBuf};
+use crate::{console, tr_traits::{Level, SoundDetails}, version::Version};
+use super::sfx::{self, Sfx};
+
+/// One `sound_map` entry in use.
+pub struct Sound {
+	/// Index into `sound_map`, as used by animations and sound sources.
+	pub id: usize,
+	pub details_index: u16,
+	/// Entries of the level's sample indices, one per variation the game picks from.
+	pub samples: Vec<u32>,
+}
+
+/// Sound map entries that point at sound details.
+pub fn sounds<L: Level>(level: &L) -> Vec<Sound> {
+	let sample_indices = level.sample_indices();
+	level.sound_map().iter().enumerate().filter_map(|(id, &details_index)| {
+		let details = level.sound_details().get(details_index as usize)?;
+		let num_samples = details.num_samples().max(1) as usize;
+		let samples = sample_indices.get(details.sample_index() as usize..)?;
+		let samples = samples.iter().take(num_samples).copied().collect();
+		Some(Sound { id, details_index, samples })
+	}).collect()
+}
+
+pub enum SoundsAction {
+	LoadSfx,
+	/// Save the MAIN.SFX sample with this index as a WAV file.
+	Export(u32),
+}
+
+/// Sounds window. Shows sample formats and offers export once MAIN.SFX is loaded.
+pub struct SoundsView {
+	sounds: Vec<Sound>,
+	uses_main_sfx: bool,
+	sfx: Option<Sfx>,
+}
+
+impl SoundsView {
+	pub fn new(sounds: Vec<Sound>, version: Version) -> Self {
+		Self { sounds, uses_main_sfx: version.uses_main_sfx(), sfx: None }
+	}
+	
+	/// Load MAIN.SFX found near the level, if the version uses one. Missing is fine, unreadable is reported.
+	pub fn find_sfx(&mut self, level_path: &Path) {
+		let Some(path) = sfx::find_main_sfx(level_path).filter(|_| self.uses_main_sfx) else {
+			return;
+		};
+		if let Err(e) = self.load_sfx(path.clone()) {
+			console::log_warn!("failed to read {}: {}", path.display(), e);
+		}
+	}
+	
+	pub fn load_sfx(&mut self, path: PathBuf) -> std::io::Result<()> {
+		self.sfx = Some(Sfx::load(path)?);
+		Ok(())
+	}
+	
+	/// WAV file of a MAIN.SFX sample.
+	pub fn sample(&self, sample: u32) -> Option<&[u8]> {
+		Some(&self.sfx.as_ref()?.samples.get(sample as usize)?[..])
+	}
+	
+	pub fn show(&self, ui: &mut egui::Ui) -> Option<SoundsAction> {
+		let mut action = None;
+		if self.uses_main_sfx {
+			ui.horizontal(|ui| {
+				if ui.button("Load SFX…").clicked() {
+					action = Some(SoundsAction::LoadSfx);
+				}
+				match &self.sfx {
+					Some(sfx) => ui.label(format!("{}: {} samples", sfx.path.display(), sfx.samples.len())),
+					None => ui.label("No MAIN.SFX loaded, showing sample indices only"),
+				};
+			});
+		} else {
+			ui.label("Samples of this version are not browsable, showing sample indices only");
+		}
+		ui.separator();
+		egui::ScrollArea::vertical().show(ui, |ui| {
+			egui::Grid::new("sounds").striped(true).show(ui, |ui| {
+				for header in ["Sound", "Details", "Sample", "Format", ""] {
+					ui.strong(header);
+				}
+				ui.end_row();
+				for sound in &self.sounds {
+					for (variation, &sample) in sound.samples.iter().enumerate() {
+						if variation == 0 {
+							ui.label(sound.id.to_string());
+							ui.label(sound.details_index.to_string());
+						} else {
+							ui.label("");
+							ui.label("");
+						}
+						ui.label(sample.to_string());
+						match self.sample(sample) {
+							Some(wav) => {
+								match sfx::wav_info(wav) {
+									Some(info) => ui.label(format!(
+										"{} Hz, {}-bit, {} ch, {:.2} s",
+										info.sample_rate,
+										info.bits_per_sample,
+										info.channels,
+										info.secs(),
+									)),
+									None => ui.label("unknown format"),
+								};
+								if ui.button("Export WAV").clicked() {
+									action = Some(SoundsAction::Export(sample));
+								}
+							},
+							None => {
+								ui.label("");
+								ui.label("");
+							},
+						}
+						ui.end_row();
+					}
+				}
+			});
+		});
+		action
+	}
+}
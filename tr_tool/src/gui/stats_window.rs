@@ -0,0 +1,96 @@
+use std::{collections::VecDeque, time::Duration};
+use crate::stats::{DrawKind, DrawStats};
+
+/// Frames in the rolling frame time window.
+const FRAME_WINDOW: usize = 120;
+
+impl DrawKind {
+	pub fn label(&self) -> &'static str {
+		match self {
+			DrawKind::Sky => "Sky",
+			DrawKind::Solid => "Solid",
+			DrawKind::Opaque => "Opaque",
+			DrawKind::Additive => "Additive",
+			DrawKind::Alpha => "Alpha blend",
+			DrawKind::Ghost => "Ghost",
+			DrawKind::Sprite => "Sprite",
+			DrawKind::Portal => "Portal",
+		}
+	}
+}
+
+/// Sizes of GPU resources created for a level, in bytes.
+#[derive(Default)]
+pub struct GpuMemory {
+	pub buffers: Vec<(&'static str, u64)>,
+	/// Labelled by texture mode, so the active one can be marked.
+	pub textures: Vec<(&'static str, u64)>,
+}
+
+pub struct FrameTimes {
+	times: VecDeque<Duration>,
+}
+
+impl FrameTimes {
+	pub fn new() -> Self {
+		Self { times: VecDeque::with_capacity(FRAME_WINDOW) }
+	}
+	
+	pub fn push(&mut self, time: Duration) {
+		if self.times.len() == FRAME_WINDOW {
+			self.times.pop_front();
+		}
+		self.times.push_back(time);
+	}
+	
+	/// Average, min and max over the window.
+	pub fn summary(&self) -> Option<(Duration, Duration, Duration)> {
+		let min = *self.times.iter().min()?;
+		let max = *self.times.iter().max()?;
+		let average = self.times.iter().sum::<Duration>() / self.times.len() as u32;
+		Some((average, min, max))
+	}
+}
+
+fn bytes_label(bytes: u64) -> String {
+	format!("{:.2} MB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+fn ms_label(time: Duration) -> String {
+	format!("{:.2} ms", time.as_secs_f64() * 1000.0)
+}
+
+/// Stats window contents. `draw` is `None` without a loaded level.
+pub fn show(
+	ui: &mut egui::Ui, frame_times: &FrameTimes, draw: Option<(&DrawStats, &GpuMemory, &'static str)>,
+) {
+	egui::Grid::new("stats").show(ui, |ui| {
+		if let Some((average, min, max)) = frame_times.summary() {
+			ui.label("Frame time");
+			ui.label(format!("{} (min {}, max {})", ms_label(average), ms_label(min), ms_label(max)));
+			ui.end_row();
+		}
+		let Some((draw_stats, gpu_memory, active_texture)) = draw else {
+			return;
+		};
+		ui.label("Draw calls");
+		ui.label(draw_stats.draw_calls.to_string());
+		ui.end_row();
+		for kind in DrawKind::ALL {
+			ui.label(format!("{} instances", kind.label()));
+			ui.label(draw_stats.instances[kind as usize].to_string());
+			ui.end_row();
+		}
+		for &(label, size) in &gpu_memory.buffers {
+			ui.label(format!("{} buffer", label));
+			ui.label(bytes_label(size));
+			ui.end_row();
+		}
+		for &(label, size) in &gpu_memory.textures {
+			let active = if label == active_texture { " (active)" } else { "" };
+			ui.label(format!("{} textures{}", label, active));
+			ui.label(bytes_label(size));
+			ui.end_row();
+		}
+	});
+}
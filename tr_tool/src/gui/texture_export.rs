@@ -0,0 +1,163 @@
+use std::{
+	fs, io::{Error, Result}, path::PathBuf, sync::{atomic::{AtomicUsize, Ordering}, Arc}, thread::{self, JoinHandle},
+};
+use serde::Serialize;
+use crate::{texture_export::{save_png, SIDE}, tr_traits::{Level, ObjectTexture}};
+use super::atlas_usage::{object_texture_rect, sprite_texture_rect, Rect};
+
+#[derive(Clone, Copy)]
+pub struct ExportOptions {
+	/// Each atlas as `atlas_N.png`.
+	pub atlases: bool,
+	/// Each object texture cropped to its UV bounds as `objtex_NNNN.png`, listed in `object_textures.json`.
+	pub object_textures: bool,
+	/// Each sprite texture as `sprite_NNNN.png`, listed in `sprite_textures.json`.
+	pub sprite_textures: bool,
+}
+
+impl Default for ExportOptions {
+	fn default() -> Self {
+		Self { atlases: true, object_textures: true, sprite_textures: true }
+	}
+}
+
+#[derive(Serialize)]
+struct ManifestEntry {
+	index: usize,
+	file: String,
+	atlas: usize,
+	/// Pixel bounds of the crop in the atlas.
+	x: u32,
+	y: u32,
+	width: u32,
+	height: u32,
+	/// Original UVs in 1/256 pixels, the crop being their bounding box. Absent for sprite textures.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	uvs: Option<[[u16; 2]; 4]>,
+}
+
+/// Pixels of `rect` out of the RGBA atlases, `None` if its atlas is missing or it is empty.
+fn crop(atlases_rgba: &[u8], rect: &Rect) -> Option<Vec<u8>> {
+	const ATLAS_BYTES: usize = SIDE * SIDE * 4;
+	let atlas = atlases_rgba.get(rect.atlas_index * ATLAS_BYTES..(rect.atlas_index + 1) * ATLAS_BYTES)?;
+	let [x, y, width, height] = [rect.min.x, rect.min.y, rect.max.x - rect.min.x, rect.max.y - rect.min.y]
+		.map(|v| v as usize);
+	if width == 0 || height == 0 {
+		return None;
+	}
+	let pixels = (y..y + height)
+		.flat_map(|row| &atlas[(row * SIDE + x) * 4..(row * SIDE + x + width) * 4])
+		.copied()
+		.collect();
+	Some(pixels)
+}
+
+/// Textures gathered from the level, so the cropping and encoding can run on a worker thread.
+struct Job {
+	options: ExportOptions,
+	atlases_rgba: Vec<u8>,
+	object_textures: Vec<(Rect, [[u16; 2]; 4])>,
+	sprite_textures: Vec<Rect>,
+	out_dir: PathBuf,
+}
+
+impl Job {
+	fn num_files(&self) -> usize {
+		let num_atlases = self.atlases_rgba.len() / (SIDE * SIDE * 4);
+		[
+			(self.options.atlases, num_atlases),
+			(self.options.object_textures, self.object_textures.len()),
+			(self.options.sprite_textures, self.sprite_textures.len()),
+		].into_iter().filter_map(|(enabled, count)| enabled.then_some(count)).sum()
+	}
+	
+	/// Write each crop as `{prefix}_NNNN.png` and the manifest as `manifest_name`. Empty crops and crops in
+	/// missing atlases are skipped.
+	fn export_crops(
+		&self, prefix: &str, manifest_name: &str, crops: impl Iterator<Item = (Rect, Option<[[u16; 2]; 4]>)>,
+		progress: &AtomicUsize,
+	) -> Result<()> {
+		let mut manifest = vec![];
+		for (index, (rect, uvs)) in crops.enumerate() {
+			progress.fetch_add(1, Ordering::Relaxed);
+			let Some(pixels) = crop(&self.atlases_rgba, &rect) else {
+				continue;
+			};
+			let file = format!("{}_{:04}.png", prefix, index);
+			let [width, height] = (rect.max - rect.min).to_array();
+			save_png(&self.out_dir.join(&file), &pixels, width, height)?;
+			manifest.push(ManifestEntry {
+				index,
+				file,
+				atlas: rect.atlas_index,
+				x: rect.min.x,
+				y: rect.min.y,
+				width,
+				height,
+				uvs,
+			});
+		}
+		let json = serde_json::to_string_pretty(&manifest).map_err(Error::other)?;
+		fs::write(self.out_dir.join(manifest_name), json)
+	}
+	
+	fn run(self, progress: &AtomicUsize) -> Result<()> {
+		fs::create_dir_all(&self.out_dir)?;
+		if self.options.atlases {
+			for (index, atlas) in self.atlases_rgba.chunks_exact(SIDE * SIDE * 4).enumerate() {
+				progress.fetch_add(1, Ordering::Relaxed);
+				save_png(&self.out_dir.join(format!("atlas_{}.png", index)), atlas, SIDE as u32, SIDE as u32)?;
+			}
+		}
+		if self.options.object_textures {
+			let crops = self.object_textures.iter().map(|&(rect, uvs)| (rect, Some(uvs)));
+			self.export_crops("objtex", "object_textures.json", crops, progress)?;
+		}
+		if self.options.sprite_textures {
+			let crops = self.sprite_textures.iter().map(|&rect| (rect, None));
+			self.export_crops("sprite", "sprite_textures.json", crops, progress)?;
+		}
+		Ok(())
+	}
+}
+
+/// Export running on a worker thread.
+pub struct TextureExport {
+	progress: Arc<AtomicUsize>,
+	num_files: usize,
+	out_dir: PathBuf,
+	handle: JoinHandle<Result<()>>,
+}
+
+impl TextureExport {
+	/// `atlases_rgba` holds the level's atlases in one texture mode, as the Textures window saves them.
+	pub fn start<L: Level>(level: &L, atlases_rgba: Vec<u8>, options: ExportOptions, out_dir: PathBuf) -> Self {
+		let object_textures = level
+			.object_textures()
+			.iter()
+			.map(|object_texture| (object_texture_rect(object_texture), object_texture.uvs().map(|uv| uv.to_array())))
+			.collect();
+		let sprite_textures = level.sprite_textures().iter().map(sprite_texture_rect).collect();
+		let job = Job { options, atlases_rgba, object_textures, sprite_textures, out_dir: out_dir.clone() };
+		let num_files = job.num_files();
+		let progress = Arc::new(AtomicUsize::new(0));
+		let worker_progress = progress.clone();
+		let handle = thread::spawn(move || job.run(&worker_progress));
+		Self { progress, num_files, out_dir, handle }
+	}
+	
+	/// Fraction of files written.
+	pub fn progress(&self) -> f32 {
+		self.progress.load(Ordering::Relaxed) as f32 / self.num_files.max(1) as f32
+	}
+	
+	pub fn is_finished(&self) -> bool {
+		self.handle.is_finished()
+	}
+	
+	/// Wait for the worker. On success, the folder written to.
+	pub fn join(self) -> Result<PathBuf> {
+		self.handle.join().expect("join texture export")?;
+		Ok(self.out_dir)
+	}
+}
@@ -0,0 +1,171 @@
+use std::{
+	collections::VecDeque, f32::consts::TAU, fs, io::{Error, Result}, path::PathBuf,
+	sync::{atomic::{AtomicBool, Ordering}, mpsc::{self, Receiver, SyncSender, TrySendError}, Arc},
+	thread::{self, JoinHandle},
+};
+use glam::Vec3;
+use image::RgbaImage;
+use crate::{direction, snapshot::{CameraSpec, PendingImage}};
+
+/// Frames being read back from the GPU before further frames are skipped.
+const MAX_READBACKS: usize = 3;
+/// Frames waiting to be written before further frames are skipped.
+const MAX_QUEUED_FRAMES: usize = 4;
+
+#[derive(Clone, Copy)]
+pub struct TurntableOptions {
+	/// Distance from the target.
+	pub radius: f32,
+	/// Seconds per orbit while previewing.
+	pub revolution_secs: f32,
+	/// Frames saved over one orbit.
+	pub num_frames: u32,
+	pub save_frames: bool,
+}
+
+impl Default for TurntableOptions {
+	fn default() -> Self {
+		Self { radius: 4096.0, revolution_secs: 10.0, num_frames: 120, save_frames: false }
+	}
+}
+
+/// Writes each frame as `frame_NNNN.png` to `out_dir`, stopping at the first error.
+fn write_frames(receiver: Receiver<(u32, RgbaImage)>, out_dir: PathBuf) -> Result<u32> {
+	fs::create_dir_all(&out_dir)?;
+	let mut written = 0;
+	for (frame, image) in receiver {
+		image.save(out_dir.join(format!("frame_{:04}.png", frame))).map_err(Error::other)?;
+		written += 1;
+	}
+	Ok(written)
+}
+
+/// Saving one orbit of frames. Frames are rendered one per displayed frame at evenly spaced angles, so the
+/// orbit loops regardless of frame rate. Frames the readback or writer can't keep up with are skipped.
+struct Capture {
+	next_frame: u32,
+	/// Set once each image is mapped.
+	readbacks: VecDeque<(u32, PendingImage, Arc<AtomicBool>)>,
+	/// `None` once the writer has stopped early.
+	sender: Option<SyncSender<(u32, RgbaImage)>>,
+	writer: JoinHandle<Result<u32>>,
+	skipped: u32,
+	out_dir: PathBuf,
+}
+
+/// Result of a finished or stopped capture.
+pub struct CaptureSummary {
+	pub written: u32,
+	pub skipped: u32,
+	pub out_dir: PathBuf,
+}
+
+/// Camera orbiting a target at a fixed pitch, optionally saving each frame.
+pub struct Turntable {
+	target: Vec3,
+	pitch: f32,
+	options: TurntableOptions,
+	start_yaw: f32,
+	yaw: f32,
+	capture: Option<Capture>,
+}
+
+impl Turntable {
+	/// Starts at `camera`'s yaw and pitch. Frames are saved to `out_dir` if `options.save_frames`.
+	pub fn start(target: Vec3, camera: CameraSpec, options: TurntableOptions, out_dir: Option<PathBuf>) -> Self {
+		let capture = out_dir.filter(|_| options.save_frames).map(|out_dir| {
+			let (sender, receiver) = mpsc::sync_channel(MAX_QUEUED_FRAMES);
+			let writer_dir = out_dir.clone();
+			Capture {
+				next_frame: 0,
+				readbacks: VecDeque::new(),
+				sender: Some(sender),
+				writer: thread::spawn(move || write_frames(receiver, writer_dir)),
+				skipped: 0,
+				out_dir,
+			}
+		});
+		Self { target, pitch: camera.pitch, options, start_yaw: camera.yaw, yaw: camera.yaw, capture }
+	}
+	
+	/// Move along the orbit, by time while previewing or to the next frame's angle while capturing.
+	pub fn advance(&mut self, delta_secs: f32) {
+		self.yaw = match &self.capture {
+			Some(capture) => {
+				self.start_yaw + TAU * capture.next_frame as f32 / self.options.num_frames.max(1) as f32
+			},
+			None => self.yaw + TAU * delta_secs / self.options.revolution_secs.max(0.1),
+		};
+	}
+	
+	/// Frames rendered for saving so far and the total, `None` while previewing.
+	pub fn capture_progress(&self) -> Option<(u32, u32)> {
+		self.capture.as_ref().map(|capture| (capture.next_frame, self.options.num_frames))
+	}
+	
+	pub fn camera(&self) -> CameraSpec {
+		let pos = self.target - direction(self.yaw, self.pitch) * self.options.radius;
+		CameraSpec { pos, yaw: self.yaw, pitch: self.pitch }
+	}
+	
+	/// Number of the frame just rendered if it should be read back. Counts it as skipped if too many readbacks
+	/// are in flight.
+	pub fn frame_to_capture(&mut self) -> Option<u32> {
+		let num_frames = self.options.num_frames;
+		let capture = self.capture.as_mut().filter(|capture| capture.next_frame < num_frames)?;
+		let frame = capture.next_frame;
+		capture.next_frame += 1;
+		if capture.readbacks.len() >= MAX_READBACKS {
+			capture.skipped += 1;
+			return None;
+		}
+		Some(frame)
+	}
+	
+	/// `mapped` is set by the image's map callback.
+	pub fn queue_readback(&mut self, frame: u32, image: PendingImage, mapped: Arc<AtomicBool>) {
+		if let Some(capture) = &mut self.capture {
+			capture.readbacks.push_back((frame, image, mapped));
+		}
+	}
+	
+	/// Hand finished readbacks to the writer, in frame order. The device must have been polled.
+	pub fn poll_readbacks(&mut self) {
+		let Some(capture) = &mut self.capture else {
+			return;
+		};
+		while capture.readbacks.front().is_some_and(|(_, _, mapped)| mapped.load(Ordering::Acquire)) {
+			//unwrap: front is some
+			let (frame, image, _) = capture.readbacks.pop_front().unwrap();
+			let Some(sender) = &capture.sender else {
+				capture.skipped += 1;
+				continue;
+			};
+			match sender.try_send((frame, image.take())) {
+				Ok(()) => {},
+				Err(TrySendError::Full(_)) => capture.skipped += 1,
+				Err(TrySendError::Disconnected(_)) => {
+					capture.sender = None;
+					capture.skipped += 1;
+				},
+			}
+		}
+	}
+	
+	/// All frames of the orbit are saved or skipped. Never true while previewing.
+	pub fn capture_done(&self) -> bool {
+		self.capture.as_ref().is_some_and(|capture| {
+			capture.next_frame >= self.options.num_frames && capture.readbacks.is_empty()
+		})
+	}
+	
+	/// Wait for queued frames to be written. `None` if no frames were being saved. Frames still being read back
+	/// count as skipped.
+	pub fn finish(self) -> Option<Result<CaptureSummary>> {
+		let Capture { readbacks, sender, writer, skipped, out_dir, .. } = self.capture?;
+		drop(sender);
+		let skipped = skipped + readbacks.len() as u32;
+		let result = writer.join().expect("join turntable writer");
+		Some(result.map(|written| CaptureSummary { written, skipped, out_dir }))
+	}
+}
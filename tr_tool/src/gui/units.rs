@@ -0,0 +1,55 @@
+use glam::Vec3;
+
+const SECTOR_UNITS: f32 = 1024.0;
+/// Lara is about 768 units tall, taken as 1.8 m.
+const METER_UNITS: f32 = 768.0 / 1.8;
+
+/// Unit that distances and positions are shown in.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum UnitMode {
+	TrUnits,
+	Sectors,
+	Meters,
+}
+
+impl UnitMode {
+	pub fn label(&self) -> &'static str {
+		match self {
+			UnitMode::TrUnits => "TR units",
+			UnitMode::Sectors => "Sectors",
+			UnitMode::Meters => "Meters (approx.)",
+		}
+	}
+	
+	/// TR units per displayed unit.
+	pub fn scale(&self) -> f32 {
+		match self {
+			UnitMode::TrUnits => 1.0,
+			UnitMode::Sectors => SECTOR_UNITS,
+			UnitMode::Meters => METER_UNITS,
+		}
+	}
+	
+	/// Decimals shown in labels and drag values.
+	pub fn decimals(&self) -> usize {
+		match self {
+			UnitMode::TrUnits => 0,
+			UnitMode::Sectors | UnitMode::Meters => 2,
+		}
+	}
+	
+	pub fn suffix(&self) -> &'static str {
+		match self {
+			UnitMode::TrUnits => "",
+			UnitMode::Sectors => " sec",
+			UnitMode::Meters => " m",
+		}
+	}
+	
+	/// `pos` in TR units.
+	pub fn format_vec3(&self, pos: Vec3) -> String {
+		let [x, y, z] = (pos / self.scale()).to_array();
+		let decimals = self.decimals();
+		format!("{:.*}, {:.*}, {:.*}{}", decimals, x, decimals, y, decimals, z, self.suffix())
+	}
+}
@@ -0,0 +1,3534 @@
+use std::{
+	borrow::Cow, collections::HashMap, f32::consts::{FRAC_PI_2, FRAC_PI_4, PI, TAU}, fs, io::{Error, Result}, mem,
+	path::{Path, PathBuf}, sync::{atomic::{AtomicBool, Ordering}, Arc}, thread::{self, JoinHandle}, time::Duration,
+};
+use glam::{DVec2, Mat4, UVec2, Vec2, Vec3, Vec3Swizzles, Vec4Swizzles};
+use shared::min_max::{MinMax, VecMinMaxFromIterator};
+use tr_model::{tr1, tr2, tr3, tr4, tr5};
+use wgpu::{
+	BindGroup, BindGroupLayout, BindingResource, BindingType, CommandEncoder, Device, FrontFace, Maintain,
+	Queue, RenderPipeline, ShaderStages, Texture, TextureFormat, TextureSampleType, TextureView,
+	TextureViewDescriptor, TextureViewDimension,
+};
+use winit::{
+	dpi::PhysicalPosition, event::{ElementState, MouseButton, MouseScrollDelta}, event_loop::EventLoopWindowTarget,
+	keyboard::{KeyCode, ModifiersState}, window::{CursorGrabMode, Icon, Window},
+};
+use crate::{
+	as_bytes::{AsBytes, ReinterpretAsBytes}, buffer_dump, common_entries, console,
+	data_writer::{portal_color, SpriteInstance}, demo, detect_version, direction, dump, face_instance,
+	gpu::PhysicalSize,
+	level_buffers::{
+		self, AnimatedSprite, EntityRender, FlipGroup, LevelIssues, ModelSkeleton, PortalInfo, RenderRoom, RoomStats,
+		SectorOutline, UnknownModelEntity,
+	},
+	make, make_atlases_view, make_camera_transform, make_interact_texture, make_mesh_shader, make_perspective_transform,
+	make_pipeline, make_tr_tool_shared, read_level, render_level, rgba, snapshot::{self, CameraSpec},
+	settings::Settings, stats::DrawStats,
+	tr_traits::{with_level, Entity, Level, LevelDyn, LevelStore, Room, RoomStaticMesh},
+	upload_level, version::{self, LevelReader, ReadMode}, yaw_pitch, BlendFilter, FaceColoring, HiddenEntities,
+	LoadedLevel, LoadedLevelShared, Projection, SolidMode, TextureMode, TextureViews, TrToolShared, Version,
+	Statics, ViewBuffers, Viewport, Winding, ATLASES_ENTRY, CAMERA_VEC, DEFAULT_PROJECTION, MAX_FOV, MIN_FOV,
+	NO_HIGHLIGHT, NUM_QUAD_VERTICES, PALETTE_ENTRY, RENDER_FLAG_SHINE, RENDER_FLAG_WATER_WAVES, SECTOR_SIZE,
+};
+use super::{
+	atlas_usage::{self, AtlasUsage}, camera_path::{CameraPath, CameraPathPlayback},
+	cinematic::{self, CinematicFrame, CinematicPlayback}, compare::{self, LevelDiff},
+	demo_playback::{self, DemoFrame, DemoPlayback, Input}, export_space::ExportSettings, file_dialog::FileDialogWrapper,
+	flyby::{self, FlybyPlayback, FlybySequence}, footstep, keys::{KeyGroup, KeyStates}, log::{self, LogView},
+	mesh_viewer::{self, MeshViewer, MeshViewerSubject}, obj,
+	object_info::{entity_label, expected_face_instance, owner_index, print_object_data, show_object_data},
+	overrides::{self, OptionValue, RenderOptionValues}, pick::PickQueue, playlist::Playlist,
+	room_graph::{RoomGraph, RoomNode}, room_path::RoomPath, script::{self, Script},
+	sounds::{self, SoundsAction, SoundsView}, stats_window::{self, FrameTimes, GpuMemory},
+	texture_export::{ExportOptions, TextureExport}, turntable::{Turntable, TurntableOptions}, units::UnitMode,
+	walk::{WalkRoom, WalkState, EYE_HEIGHT}, Gui,
+};
+use crate::object_data::{ObjectData, ObjectOwner};
+
+const WINDOW_TITLE: &str = "TR Tool";
+
+/// Longer file names and error lines are elided in the middle.
+const MAX_TITLE_FILE_NAME_CHARS: usize = 64;
+
+const MAX_ERROR_LINE_CHARS: usize = 160;
+
+/// Working directory folder F9 dumps the level's GPU buffers to.
+const BUFFER_DUMP_DIR: &str = "buffer_dump";
+
+pub type InteractPixel = u32;
+
+pub const INTERACT_PIXEL_SIZE: u32 = size_of::<InteractPixel>() as u32;
+
+const FORWARD: Vec3 = Vec3::NEG_Z;
+
+const BACKWARD: Vec3 = Vec3::Z;
+
+const LEFT: Vec3 = Vec3::X;
+
+const RIGHT: Vec3 = Vec3::NEG_X;
+
+const DOWN: Vec3 = Vec3::Y;
+
+const UP: Vec3 = Vec3::NEG_Y;
+
+struct ActionMap {
+	forward: KeyGroup,
+	backward: KeyGroup,
+	left: KeyGroup,
+	right: KeyGroup,
+	up: KeyGroup,
+	down: KeyGroup,
+	fast: KeyGroup,
+	slow: KeyGroup,
+	roll_left: KeyGroup,
+	roll_right: KeyGroup,
+	zoom_in: KeyGroup,
+	zoom_out: KeyGroup,
+}
+
+overrides::label_option_value!(TextureMode, [TextureMode::Palette, TextureMode::Bit16, TextureMode::Bit32]);
+
+overrides::label_option_value!(HiddenEntities, [HiddenEntities::Hide, HiddenEntities::Ghost, HiddenEntities::Show]);
+
+overrides::label_option_value!(BlendFilter, [BlendFilter::All, BlendFilter::Opaque, BlendFilter::Blended]);
+
+impl FaceColoring {
+	fn label(&self) -> &'static str {
+		match self {
+			FaceColoring::Textured => "Textured",
+			FaceColoring::TextureId => "By texture id",
+			FaceColoring::Atlas => "By atlas",
+			FaceColoring::BlendMode => "By blend mode",
+			FaceColoring::UvCheck => "UV check",
+		}
+	}
+	
+	/// Value at `RENDER_FACE_COLORING_SHIFT` in render flags.
+	fn render_flags(&self) -> u32 {
+		match self {
+			FaceColoring::Textured | FaceColoring::UvCheck => 0,
+			FaceColoring::TextureId => 1,
+			FaceColoring::Atlas => 2,
+			FaceColoring::BlendMode => 3,
+		}
+	}
+}
+
+overrides::label_option_value!(FaceColoring, [
+	FaceColoring::Textured,
+	FaceColoring::TextureId,
+	FaceColoring::Atlas,
+	FaceColoring::BlendMode,
+	FaceColoring::UvCheck,
+]);
+
+/// Light from the camera, for shape on faces whose lighting is baked or missing.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Shading {
+	Off,
+	/// Face normals.
+	Flat,
+	/// Vertex normals averaged over the faces around them, for meshes and TR5 rooms. Other faces shade flat.
+	Smooth,
+}
+
+impl Shading {
+	fn label(&self) -> &'static str {
+		match self {
+			Shading::Off => "Off",
+			Shading::Flat => "Flat",
+			Shading::Smooth => "Smooth",
+		}
+	}
+	
+	/// Value at `RENDER_SHADING_SHIFT` in render flags.
+	fn render_flags(&self) -> u32 {
+		match self {
+			Shading::Off => 0,
+			Shading::Flat => 1,
+			Shading::Smooth => 2,
+		}
+	}
+}
+
+overrides::label_option_value!(Shading, [Shading::Off, Shading::Flat, Shading::Smooth]);
+
+impl RoomStats {
+	fn show(&self, ui: &mut egui::Ui) {
+		let RoomStats { vertices, faces, sprites, static_meshes, entities, water, water_wave_vertices } = *self;
+		egui::Grid::new("room_stats").show(ui, |ui| {
+			for (label, count) in [
+				("Vertices", vertices),
+				("Faces", faces),
+				("Sprites", sprites),
+				("Static meshes", static_meshes),
+				("Entities", entities),
+				("Water wave vertices", water_wave_vertices),
+			] {
+				ui.label(label);
+				ui.label(count.to_string());
+				ui.end_row();
+			}
+			ui.label("Water");
+			ui.label(if water { "Yes" } else { "No" });
+			ui.end_row();
+		});
+	}
+}
+
+/// Saved as the numbers of flip groups shown flipped.
+impl OptionValue for Vec<FlipGroup> {
+	fn to_value(&self) -> serde_json::Value {
+		self.iter().filter(|flip_group| flip_group.show_flipped).map(|flip_group| flip_group.number).collect()
+	}
+	
+	fn set_value(&mut self, value: &serde_json::Value) -> bool {
+		let Some(numbers) = value.as_array().and_then(|numbers| {
+			numbers.iter().map(|number| number.as_u64()).collect::<Option<Vec<_>>>()
+		}) else {
+			return false;
+		};
+		for flip_group in self {
+			flip_group.show_flipped = numbers.contains(&(flip_group.number as u64));
+		}
+		true
+	}
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum TexturesTab {
+	Textures(TextureMode),
+	Misc,
+}
+
+impl TexturesTab {
+	fn label(&self) -> &'static str {
+		match self {
+			TexturesTab::Textures(texture_mode) => texture_mode.label(),
+			TexturesTab::Misc => "Misc",
+		}
+	}
+}
+
+overrides::label_option_value!(Winding, [Winding::Cw, Winding::Ccw]);
+
+type FileDialog = FileDialogWrapper<TexturesTab>;
+
+struct TrTool {
+	//gui resources
+	window: Arc<Window>,
+	device: Arc<Device>,
+	queue: Arc<Queue>,
+	//static
+	shared: Arc<ViewerShared>,
+	//state
+	window_size: PhysicalSize<u32>,
+	modifiers: ModifiersState,
+	file_dialog: FileDialog,
+	error: Option<String>,
+	print: bool,
+	loaded_level: Option<LevelView>,
+	level_path: Option<PathBuf>,
+	level_load: Option<LevelLoad>,
+	/// Levels given on the command line.
+	playlist: Option<Playlist>,
+	/// Game script for friendly level names, found next to the level or loaded manually.
+	script: Option<Script>,
+	/// Kept open since on some platforms copied data is lost when the clipboard is dropped.
+	clipboard: Option<arboard::Clipboard>,
+	export_settings: ExportSettings,
+	settings: Settings,
+	frame_times: FrameTimes,
+	version_prompt: Option<VersionPrompt>,
+	//windows
+	show_render_options_window: bool,
+	show_textures_window: bool,
+	show_level_issues_window: bool,
+	show_mesh_nodes_window: bool,
+	show_mesh_viewer_window: bool,
+	show_room_graph_window: bool,
+	show_turntable_window: bool,
+	show_sounds_window: bool,
+	show_find_entity_window: bool,
+	show_mini_map: bool,
+	show_stats: bool,
+	show_log_window: bool,
+	log_view: LogView,
+}
+
+/// A level being parsed and uploaded on a worker thread.
+struct LevelLoad {
+	path: PathBuf,
+	window_size: PhysicalSize<u32>,
+	handle: JoinHandle<Result<LevelView>>,
+	/// Magic of a file whose version was picked in the prompt, remembered if the load succeeds.
+	prompted_magic: Option<u32>,
+}
+
+impl LevelLoad {
+	fn show(&self, ui: &mut egui::Ui) {
+		let file_name = self.path.file_name().map(|f| f.to_string_lossy()).unwrap_or_default();
+		ui.horizontal(|ui| {
+			ui.spinner();
+			ui.label(format!("Loading {}", elide_middle(&file_name, MAX_TITLE_FILE_NAME_CHARS)));
+		});
+	}
+}
+
+/// A level file whose version could not be determined, awaiting manual selection.
+struct VersionPrompt {
+	path: PathBuf,
+	magic: u32,
+}
+
+/// Tint faces packed with `degenerate_texture`, see face_instance.rs.
+const RENDER_FLAG_TINT_DEGENERATE: u32 = 1;
+
+/// Two bits selecting the key colored by `texture_debug_fs_main`.
+const RENDER_FACE_COLORING_SHIFT: u32 = 2;
+
+/// Two bits selecting the headlight shading.
+const RENDER_SHADING_SHIFT: u32 = 4;
+
+const SPRITE_FPS: f32 = 10.0;
+
+const MIN_NEAR: f32 = 1.0;
+
+const MAX_NEAR: f32 = 1000.0;
+
+const MIN_FAR: f32 = 1000.0;
+
+const MAX_FAR: f32 = 1000000.0;
+
+/// Smallest gap kept between the near and far planes.
+const CLIP_GAP: f32 = 1.0;
+
+/// Radians per second.
+const ROLL_SPEED: f32 = FRAC_PI_2;
+
+const FOV_SPEED: f32 = FRAC_PI_4;
+
+/// Largest mini-map side as a fraction of the smaller window dimension.
+const MINI_MAP_SCALE: f32 = 0.2;
+
+const MINI_MAP_MARGIN: f32 = 8.0;
+
+const MINI_MAP_WEDGE_LEN: f32 = 16.0;
+
+/// How far past a clicked portal the camera is placed.
+const PORTAL_JUMP_DISTANCE: f32 = 512.0;
+
+/// How far back from an entity found in the Find Entity window the camera is placed.
+const ENTITY_VIEW_DISTANCE: f32 = 2048.0;
+
+const MAX_LOD_THRESHOLD: f32 = 0.5;
+
+const SECTOR_FLOOR_COLOR: egui::Color32 = egui::Color32::from_gray(160);
+
+const SECTOR_WALL_COLOR: egui::Color32 = egui::Color32::from_rgb(200, 60, 60);
+
+const DECLARED_BOUNDS_COLOR: egui::Color32 = egui::Color32::from_rgb(80, 160, 255);
+
+const VERTEX_BOUNDS_COLOR: egui::Color32 = egui::Color32::from_rgb(120, 220, 120);
+
+/// Screen radius of room light markers.
+const LIGHT_MARKER_RADIUS: f32 = 5.0;
+
+const FOOTSTEP_MATERIAL_OPACITY: f32 = 0.5;
+
+/// Distance of overlay legends from the window corner.
+const LEGEND_MARGIN: f32 = 8.0;
+
+const CINEMATIC_PATH_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 200, 0);
+
+const DEMO_PATH_COLOR: egui::Color32 = egui::Color32::from_rgb(0, 220, 120);
+
+const DEMO_GHOST_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 255, 255);
+
+const DEMO_GHOST_RADIUS: f32 = 6.0;
+
+/// World units of the line showing the demo ghost's heading.
+const DEMO_HEADING_LENGTH: f32 = 256.0;
+
+const DIFF_MOVE_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 80, 200);
+
+/// Points drawn per flyby spline segment.
+const FLYBY_SPLINE_STEPS: usize = 8;
+
+const CINEMATIC_TARGET_COLOR: egui::Color32 = egui::Color32::from_rgba_premultiplied(128, 128, 128, 128);
+
+/// Cinematic frames between look target lines, one second of playback.
+const CINEMATIC_TARGET_INTERVAL: usize = 30;
+
+/// Half the height of the octahedron marking an entity with an unknown model id.
+const PLACEHOLDER_SIZE: f32 = 128.0;
+
+const PLACEHOLDER_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 0, 255);
+
+const DEFAULT_KEYFRAME_INTERVAL: f32 = 2.0;
+
+/// Level open in the viewer: the rendered level and the state of its windows and overlays.
+struct LevelView {
+	//render
+	render: LoadedLevel,
+	/// Its view is `render.interact_view`, read back for picking.
+	interact_texture: Texture,
+	view_shared: Arc<ViewShared>,
+	gpu_memory: GpuMemory,
+	/// From the last main pass.
+	draw_stats: DrawStats,
+	//camera
+	projection: Projection,
+	//rooms
+	/// XZ bounds of all rooms.
+	map_bounds: MinMax<Vec2>,
+	room_graph: RoomGraph,
+	//object data
+	level: LevelStore,
+	object_data: Vec<ObjectData>,
+	/// Object data indices of each room, room static mesh and entity.
+	object_data_by_owner: HashMap<ObjectOwner, Vec<u32>>,
+	picks: PickQueue,
+	selected: Option<ObjectData>,
+	/// Show the selected face's instance decoded beside the values recomputed from the level.
+	show_face_instance: bool,
+	/// Script names of the items lying in the level, by model id.
+	item_names: HashMap<u16, String>,
+	/// Find Entity window filter.
+	entity_query: String,
+	//skeletons
+	model_skeletons: HashMap<u16, ModelSkeleton>,
+	entity_renders: Vec<Option<EntityRender>>,//by entity index, None for sprite entities
+	hovered_mesh_index: Option<usize>,
+	mesh_viewer: Option<MeshViewer>,
+	//walk mode
+	walk_rooms: Vec<WalkRoom>,
+	walk: Option<WalkState>,
+	//flyby cameras
+	flyby_sequences: Vec<FlybySequence>,
+	flyby_playback: Option<FlybyPlayback>,
+	//cutscene camera
+	cinematic_frames: Vec<CinematicFrame>,
+	cinematic_playback: Option<CinematicPlayback>,
+	//recorded demo
+	demo_data_len: usize,
+	/// Decoded TR1 demo, `None` for other versions or if too short for the header.
+	demo: Option<demo::Demo>,
+	demo_frames: Vec<DemoFrame>,
+	demo_playback: Option<DemoPlayback>,
+	/// Move the camera with the demo ghost during playback.
+	follow_demo: bool,
+	//recorded fly-through
+	camera_path: CameraPath,
+	camera_path_playback: CameraPathPlayback,
+	/// Seconds between recorded keyframes.
+	keyframe_interval: f32,
+	//walk between rooms through portals
+	room_path_ends: [usize; 2],
+	room_path: Option<RoomPath>,
+	/// Result of the last route search.
+	room_path_status: String,
+	//sprite animation
+	animated_sprites: Vec<AnimatedSprite>,
+	sprite_time: f32,
+	sprite_frame: u32,
+	/// Seconds of water wave animation.
+	water_time: f32,
+	/// Sprite and water clocks stop, holding their current frame.
+	animations_paused: bool,
+	/// Seconds to advance paused animations by on the next frame.
+	animation_step: f32,
+	//input state
+	mouse_pos: PhysicalPosition<f64>,
+	locked_mouse_pos: PhysicalPosition<f64>,
+	mouse_control: bool,
+	key_states: KeyStates,
+	action_map: ActionMap,
+	frame_update_queue: Vec<Box<dyn FnOnce(&mut Self) + Sync + Send>>,
+	/// Camera string being edited in Render Options > Camera.
+	camera_paste: String,
+	//render options
+	show_portal_labels: bool,
+	show_sector_grid: bool,
+	show_room_bounds: bool,
+	show_lights: bool,
+	show_footstep_materials: bool,
+	show_flyby_paths: bool,
+	show_flyby_targets: bool,
+	show_cinematic_path: bool,
+	show_demo_path: bool,
+	animate_sprites: bool,
+	animate_water: bool,
+	tint_degenerate_textures: bool,
+	mesh_shine: bool,
+	shading: Shading,
+	unit_mode: UnitMode,
+	/// Hash of the start of the level file, keying its saved render options. `None` if it couldn't be read.
+	level_key: Option<String>,
+	/// Render options before saved overrides were applied. Options differing from these at close are saved.
+	render_option_defaults: RenderOptionValues,
+	/// Saved overrides were applied on load.
+	using_saved_overrides: bool,
+	//textures
+	textures_tab: TexturesTab,
+	num_atlases: u32,
+	num_misc_images: Option<u32>,
+	atlas_usage: AtlasUsage,
+	/// Object texture clicked in the Textures window, its faces highlighted.
+	highlighted_object_texture: Option<u16>,
+	texture_export_options: ExportOptions,
+	/// Running export, polled each frame.
+	texture_export: Option<TextureExport>,
+	//turntable
+	turntable_options: TurntableOptions,
+	/// Folder frames are saved to.
+	turntable_dir: Option<PathBuf>,
+	/// Orbit in progress, moving the camera each frame.
+	turntable: Option<Turntable>,
+	//issues
+	issues: LevelIssues,
+	//comparison
+	level_diff: Option<LevelDiff>,
+	/// Draw lines from moved entities to their positions in the compared level.
+	show_diff_moves: bool,
+	//sounds
+	sounds: SoundsView,
+}
+
+/// Bindings of the open level kept for views drawn in egui paint callbacks, like the Textures window.
+struct ViewShared {
+	view_buffers: ViewBuffers,
+	misc_images_bg: Option<BindGroup>,
+	texture_views: TextureViews,
+}
+
+/// Level-independent resources of the viewer.
+pub struct ViewerShared {
+	pub render: TrToolShared,
+	/// Draw a texture mode's atlases flat for the Textures window.
+	flat_palette_pl: RenderPipeline,
+	flat_16bit_pl: RenderPipeline,
+	flat_32bit_pl: RenderPipeline,
+	/// Binds a single offscreen render for `blit_pl`.
+	pub blit_layout: BindGroupLayout,
+	/// Copies an offscreen render into an egui paint callback.
+	pub blit_pl: RenderPipeline,
+}
+
+impl ReinterpretAsBytes for egui::Vec2 {}
+
+impl TextureMode {
+	fn label(&self) -> &'static str {
+		match self {
+			TextureMode::Palette => "Palette",
+			TextureMode::Bit16 => "16 Bit",
+			TextureMode::Bit32 => "32 Bit",
+		}
+	}
+}
+
+impl SolidMode {
+	fn label(&self) -> &'static str {
+		match self {
+			SolidMode::Bit24 => "24 Bit",
+			SolidMode::Bit32 => "32 Bit",
+		}
+	}
+}
+
+impl HiddenEntities {
+	fn label(&self) -> &'static str {
+		match self {
+			HiddenEntities::Hide => "Hide",
+			HiddenEntities::Ghost => "Ghost",
+			HiddenEntities::Show => "Show",
+		}
+	}
+}
+
+impl BlendFilter {
+	fn label(&self) -> &'static str {
+		match self {
+			BlendFilter::All => "All",
+			BlendFilter::Opaque => "Opaque only",
+			BlendFilter::Blended => "Blended only",
+		}
+	}
+}
+
+impl Projection {
+	/// Keeps near strictly below far, also when far is at its minimum.
+	fn max_near(&self) -> f32 {
+		if self.infinite_far {
+			MAX_NEAR
+		} else {
+			MAX_NEAR.min(self.far - CLIP_GAP)
+		}
+	}
+}
+
+impl LoadedLevelShared {
+	/// `None` if the level lacks the mode.
+	fn texture_bg(&self, texture_mode: TextureMode) -> Option<&BindGroup> {
+		match texture_mode {
+			TextureMode::Palette => self.palette_24bit_bg.as_ref(),
+			TextureMode::Bit16 => self.texture_16bit_bg.as_ref(),
+			TextureMode::Bit32 => self.texture_32bit_bg.as_ref(),
+		}
+	}
+}
+
+impl Winding {
+	fn label(&self) -> &'static str {
+		match self {
+			Winding::Cw => "Clockwise",
+			Winding::Ccw => "Counter-clockwise",
+		}
+	}
+}
+
+impl LevelView {
+	fn set_mouse_control(&mut self, window: &Window, mouse_control: bool) {
+		match (self.mouse_control, mouse_control) {
+			(true, false) => {
+				window.set_cursor_visible(true);
+				window.set_cursor_grab(CursorGrabMode::None).expect("cursor ungrab");
+			},
+			(false, true) => {
+				window.set_cursor_visible(false);
+				window
+					.set_cursor_grab(CursorGrabMode::Confined)
+					.or_else(|_| window.set_cursor_grab(CursorGrabMode::Locked))
+					.expect("cursor grab");
+			},
+			_ => {},
+		}
+		self.mouse_control = mouse_control;
+	}
+	
+	fn resize(&mut self, device: &Device, queue: &Queue, window_size: PhysicalSize<u32>) {
+		self.render.depth_view = make::depth_view(device, window_size);
+		self.interact_texture = make_interact_texture(device, window_size);
+		self.render.interact_view = self.interact_texture.create_view(&TextureViewDescriptor::default());
+		self.update_perspective_transform(queue, window_size);
+	}
+	
+	fn camera_spec(&self) -> CameraSpec {
+		CameraSpec { pos: self.render.pos, yaw: self.render.yaw, pitch: self.render.pitch }
+	}
+	
+	fn set_camera_spec(&mut self, CameraSpec { pos, yaw, pitch }: CameraSpec) {
+		self.render.pos = pos;
+		self.render.yaw = yaw;
+		self.render.pitch = pitch.clamp(-FRAC_PI_2, FRAC_PI_2);
+	}
+	
+	/// Take level-independent render options from the previously loaded level.
+	fn copy_render_options(&mut self, other: &Self) {
+		self.render.show_room_mesh = other.render.show_room_mesh;
+		self.render.show_static_meshes = other.render.show_static_meshes;
+		self.render.show_invisible_static_meshes = other.render.show_invisible_static_meshes;
+		self.render.show_entity_meshes = other.render.show_entity_meshes;
+		self.render.show_room_sprites = other.render.show_room_sprites;
+		self.render.show_entity_sprites = other.render.show_entity_sprites;
+		self.render.show_portals = other.render.show_portals;
+		self.show_portal_labels = other.show_portal_labels;
+		self.show_sector_grid = other.show_sector_grid;
+		self.show_room_bounds = other.show_room_bounds;
+		self.show_lights = other.show_lights;
+		self.show_footstep_materials = other.show_footstep_materials;
+		self.show_flyby_paths = other.show_flyby_paths;
+		self.show_flyby_targets = other.show_flyby_targets;
+		self.show_cinematic_path = other.show_cinematic_path;
+		self.show_demo_path = other.show_demo_path;
+		self.animate_sprites = other.animate_sprites;
+		self.animate_water = other.animate_water;
+		self.render.bias_reverse_faces = other.render.bias_reverse_faces;
+		self.tint_degenerate_textures = other.tint_degenerate_textures;
+		self.mesh_shine = other.mesh_shine;
+		self.render.background = other.render.background;
+		self.render.hidden_entities = other.render.hidden_entities;
+		self.render.blend_filter = other.render.blend_filter;
+		self.render.winding = other.render.winding;
+		self.render.disable_culling = other.render.disable_culling;
+		self.render.face_coloring = other.render.face_coloring;
+		self.shading = other.shading;
+		self.unit_mode = other.unit_mode;
+		self.render.lod_threshold = other.render.lod_threshold;
+		self.projection.fov = other.projection.fov;
+		self.render.show_both_flip_states = other.render.show_both_flip_states;
+	}
+	
+	/// Render options that can be saved per level, by key.
+	fn render_option_fields(&mut self) -> Vec<(&'static str, &mut dyn OptionValue)> {
+		vec![
+			("show_room_mesh", &mut self.render.show_room_mesh),
+			("show_static_meshes", &mut self.render.show_static_meshes),
+			("show_invisible_static_meshes", &mut self.render.show_invisible_static_meshes),
+			("show_entity_meshes", &mut self.render.show_entity_meshes),
+			("show_room_sprites", &mut self.render.show_room_sprites),
+			("show_entity_sprites", &mut self.render.show_entity_sprites),
+			("show_portals", &mut self.render.show_portals),
+			("show_portal_labels", &mut self.show_portal_labels),
+			("show_sector_grid", &mut self.show_sector_grid),
+			("show_room_bounds", &mut self.show_room_bounds),
+			("show_lights", &mut self.show_lights),
+			("show_footstep_materials", &mut self.show_footstep_materials),
+			("show_flyby_paths", &mut self.show_flyby_paths),
+			("show_flyby_targets", &mut self.show_flyby_targets),
+			("show_cinematic_path", &mut self.show_cinematic_path),
+			("show_demo_path", &mut self.show_demo_path),
+			("animate_sprites", &mut self.animate_sprites),
+			("animate_water", &mut self.animate_water),
+			("bias_reverse_faces", &mut self.render.bias_reverse_faces),
+			("tint_degenerate_textures", &mut self.tint_degenerate_textures),
+			("mesh_shine", &mut self.mesh_shine),
+			("hidden_entities", &mut self.render.hidden_entities),
+			("blend_filter", &mut self.render.blend_filter),
+			("winding", &mut self.render.winding),
+			("disable_culling", &mut self.render.disable_culling),
+			("face_coloring", &mut self.render.face_coloring),
+			("shading", &mut self.shading),
+			("lod_threshold", &mut self.render.lod_threshold),
+			("fov", &mut self.projection.fov),
+			("texture_mode", &mut self.render.texture_mode),
+			("flip_groups", &mut self.render.flip_groups),
+			("show_both_flip_states", &mut self.render.show_both_flip_states),
+		]
+	}
+	
+	fn render_option_values(&mut self) -> RenderOptionValues {
+		self.render_option_fields().into_iter().map(|(key, field)| (key, field.to_value())).collect()
+	}
+	
+	/// Set options found in `values`. Unknown keys and values that don't fit are reported and skipped.
+	fn set_render_options<'a>(&mut self, values: impl IntoIterator<Item = (&'a str, &'a serde_json::Value)>) {
+		let texture_mode = self.render.texture_mode;
+		let mut fields = self.render_option_fields();
+		for (key, value) in values {
+			match fields.iter_mut().find(|(field_key, _)| *field_key == key) {
+				Some((_, field)) => if !field.set_value(value) {
+					console::log_warn!("ignoring saved render option {}: {}", key, value);
+				},
+				None => console::log_warn!("ignoring unknown saved render option {}", key),
+			}
+		}
+		if self.render.shared.texture_bg(self.render.texture_mode).is_none() {
+			self.render.texture_mode = texture_mode;
+		}
+	}
+	
+	/// Remember the current options as defaults, then apply saved overrides if any.
+	fn apply_render_overrides(&mut self, overrides: Option<&serde_json::Map<String, serde_json::Value>>) {
+		self.render_option_defaults = self.render_option_values();
+		if let Some(overrides) = overrides {
+			self.set_render_options(overrides.iter().map(|(key, value)| (key.as_str(), value)));
+			self.using_saved_overrides = true;
+		}
+	}
+	
+	/// Return to the options from before saved overrides were applied.
+	fn reset_render_overrides(&mut self) {
+		let defaults = mem::take(&mut self.render_option_defaults);
+		self.set_render_options(defaults.iter().map(|(&key, value)| (key, value)));
+		self.render_option_defaults = defaults;
+		self.using_saved_overrides = false;
+	}
+	
+	/// Options differing from those at load, to save for this level.
+	fn render_overrides(&mut self) -> serde_json::Map<String, serde_json::Value> {
+		overrides::diff(&self.render_option_values(), &self.render_option_defaults)
+	}
+	
+	fn update_render_flags(&self, queue: &Queue) {
+		let mut render_flags = 0;
+		if self.tint_degenerate_textures {
+			render_flags |= RENDER_FLAG_TINT_DEGENERATE;
+		}
+		if self.mesh_shine {
+			render_flags |= RENDER_FLAG_SHINE;
+		}
+		if self.animate_water {
+			render_flags |= RENDER_FLAG_WATER_WAVES;
+		}
+		render_flags |= self.render.face_coloring.render_flags() << RENDER_FACE_COLORING_SHIFT;
+		render_flags |= self.shading.render_flags() << RENDER_SHADING_SHIFT;
+		let offset = mem::offset_of!(Statics, render_flags) as u64;
+		queue.write_buffer(&self.render.statics_buffer, offset, render_flags.as_bytes());
+	}
+	
+	fn update_highlight(&self, queue: &Queue) {
+		let highlight = self.highlighted_object_texture.map_or(NO_HIGHLIGHT, |index| index as u32);
+		let offset = mem::offset_of!(Statics, highlight_object_texture) as u64;
+		queue.write_buffer(&self.render.statics_buffer, offset, highlight.as_bytes());
+	}
+	
+	fn update_background(&self, queue: &Queue) {
+		queue.write_buffer(&self.render.sky_instance_buffer, 0, self.render.background.sky_instance().as_bytes());
+	}
+	
+	/// `projection` with the field of view of the playing cinematic frame.
+	fn current_projection(&self, window_size: PhysicalSize<u32>) -> Projection {
+		let mut projection = self.projection;
+		if let Some(playback) = &self.cinematic_playback {
+			if let Some(frame) = self.cinematic_frames.get(playback.frame_index()) {
+				projection.fov = cinematic::vertical_fov(frame.fov, window_size);
+			}
+		}
+		projection
+	}
+	
+	fn update_perspective_transform(&self, queue: &Queue, window_size: PhysicalSize<u32>) {
+		let perspective_transform = make_perspective_transform(window_size, self.current_projection(window_size));
+		queue.write_buffer(&self.view_shared.view_buffers.perspective_transform, 0, perspective_transform.as_bytes());
+	}
+	
+	/// Advance multi-frame entity sprite sequences. Shows the first frame when animation is off.
+	fn update_sprite_frame(&mut self, queue: &Queue, delta_secs: f32) {
+		let frame = if self.animate_sprites {
+			self.sprite_time += delta_secs;
+			(self.sprite_time * SPRITE_FPS) as u32
+		} else {
+			self.sprite_time = 0.0;
+			0
+		};
+		if frame == self.sprite_frame {
+			return;
+		}
+		self.sprite_frame = frame;
+		for &AnimatedSprite {
+			instance_index,
+			first_sprite_texture_index,
+			num_frames,
+		} in &self.animated_sprites {
+			let sprite_texture_index = first_sprite_texture_index as u32 + frame % num_frames as u32;
+			let offset = instance_index as u64 * size_of::<SpriteInstance>() as u64
+				+ SpriteInstance::SPRITE_TEXTURE_INDEX_OFFSET;
+			queue.write_buffer(&self.render.sprite_instance_buffer, offset, sprite_texture_index.as_bytes());
+		}
+	}
+	
+	/// Advance water waves, which hold still when animation is off.
+	fn update_water_time(&mut self, queue: &Queue, delta_secs: f32) {
+		if self.animate_water {
+			self.water_time += delta_secs;
+			queue.write_buffer(&self.view_shared.view_buffers.time, 0, self.water_time.as_bytes());
+		}
+	}
+	
+	fn toggle_animations_paused(&mut self) {
+		self.animations_paused ^= true;
+		self.animation_step = 0.0;
+	}
+	
+	/// Advance paused animations by one sprite frame.
+	fn step_animations(&mut self) {
+		if self.animations_paused {
+			self.animation_step += 1.0 / SPRITE_FPS;
+		}
+	}
+	
+	/// Print and select the object under a resolved click.
+	fn pick(&mut self, o_idx: InteractPixel) {
+		with_level!(&self.level, level => print_object_data(level, &self.object_data, o_idx));
+		self.selected = self.object_data.get(o_idx as usize).map(|&data| match data {
+			ObjectData::Reverse { object_data_index } => self.object_data[object_data_index as usize],
+			data => data,
+		});
+		if let Some(ObjectData::Portal { room_index, portal_index }) = self.selected {
+			self.move_through_portal(room_index, portal_index);
+		}
+	}
+	
+	fn frame_update(&mut self, device: &Device, queue: &Queue, window_size: PhysicalSize<u32>, delta_time: Duration) {
+		for o_idx in self.picks.poll(device) {
+			self.pick(o_idx);
+		}
+		for update_fn in mem::take(&mut self.frame_update_queue) {
+			update_fn(self);
+		}
+		let movement = [
+			(self.action_map.forward, FORWARD),
+			(self.action_map.backward, BACKWARD),
+			(self.action_map.left, LEFT),
+			(self.action_map.right, RIGHT),
+			(self.action_map.up, UP),
+			(self.action_map.down, DOWN),
+		];
+		let movement = movement
+			.into_iter()
+			.filter_map(|(key_group, vector)| self.key_states.any(key_group).then_some(vector))
+			.reduce(|a, b| a + b);
+		let movement = movement.map_or(Vec3::ZERO, |movement| {
+			5000.0
+				* if self.key_states.any(self.action_map.fast) { 5.0 } else { 1.0 }
+				* if self.key_states.any(self.action_map.slow) { 0.2 } else { 1.0 }
+				* delta_time.as_secs_f32()
+				* Mat4::from_rotation_y(self.render.yaw).transform_point3(movement)
+		});
+		let delta_secs = delta_time.as_secs_f32();
+		match &mut self.walk {
+			Some(walk) => match walk.step(&self.walk_rooms, self.render.pos, movement, delta_secs) {
+				Some(pos) => self.render.pos = pos,
+				None => self.walk = None,
+			},
+			None => self.render.pos += movement,
+		}
+		for (key_group, sign) in [(self.action_map.roll_left, -1.0), (self.action_map.roll_right, 1.0)] {
+			if self.key_states.any(key_group) {
+				self.render.roll = (self.render.roll + sign * ROLL_SPEED * delta_secs).clamp(-PI, PI);
+			}
+		}
+		let fov = self.projection.fov;
+		for (key_group, sign) in [(self.action_map.zoom_in, -1.0), (self.action_map.zoom_out, 1.0)] {
+			if self.key_states.any(key_group) {
+				let fov = self.projection.fov + sign * FOV_SPEED * delta_secs;
+				self.projection.fov = fov.clamp(MIN_FOV, MAX_FOV);
+			}
+		}
+		if self.projection.fov != fov {
+			self.update_perspective_transform(queue, window_size);
+		}
+		if let Some(playback) = &mut self.flyby_playback {
+			match playback.advance(&self.flyby_sequences[playback.sequence_index], delta_secs) {
+				Some((pos, target)) => {
+					self.render.pos = pos;
+					(self.render.yaw, self.render.pitch) = yaw_pitch(target - pos);
+				},
+				None => self.flyby_playback = None,
+			}
+		}
+		if let Some(playback) = &mut self.cinematic_playback {
+			match playback.advance(&self.cinematic_frames, delta_secs) {
+				Some(frame) => {
+					self.render.pos = frame.pos;
+					(self.render.yaw, self.render.pitch) = yaw_pitch(frame.target - frame.pos);
+					self.render.roll = frame.roll;
+				},
+				None => {
+					self.cinematic_playback = None;
+					self.render.roll = 0.0;
+				},
+			}
+			self.update_perspective_transform(queue, window_size);
+		}
+		if let Some(playback) = &mut self.demo_playback {
+			match playback.advance(&self.demo_frames, delta_secs) {
+				Some(frame) => {
+					if self.follow_demo {
+						self.render.pos = frame.pos - Vec3::Y * EYE_HEIGHT;
+						self.render.yaw = yaw_pitch(frame.forward()).0;
+					}
+				},
+				None => self.demo_playback = None,
+			}
+		}
+		if let Some(camera) = self.camera_path_playback.advance(&self.camera_path, delta_secs) {
+			self.set_camera_spec(camera);
+		}
+		if let Some(room_path) = &mut self.room_path {
+			match room_path.advance(delta_secs) {
+				Some((camera, room_index)) => {
+					self.set_camera_spec(camera);
+					if self.render.render_room_index.is_some() {
+						self.render.render_room_index = Some(room_index);
+					}
+				},
+				None => self.room_path = None,
+			}
+		}
+		let animation_secs = if self.animations_paused { mem::take(&mut self.animation_step) } else { delta_secs };
+		self.update_sprite_frame(queue, animation_secs);
+		self.update_water_time(queue, animation_secs);
+		if let Some(turntable) = &mut self.turntable {
+			turntable.advance(delta_secs);
+			let camera = turntable.camera();
+			self.set_camera_spec(camera);
+		}
+		self.render.update_camera_transform(queue);
+	}
+	
+	/// Switch between flying and walking on the floor. Returns false if walking can't start with no floor
+	/// under the camera.
+	fn toggle_walk(&mut self) -> bool {
+		self.walk = match self.walk {
+			Some(_) => None,
+			None => match WalkState::start(&self.walk_rooms, self.render.pos) {
+				Some(walk) => Some(walk),
+				None => return false,
+			},
+		};
+		true
+	}
+	
+	/// Move the camera just past the portal into the adjoining room, facing through it. If a single room is
+	/// selected, select the adjoining room.
+	fn move_through_portal(&mut self, room_index: u16, portal_index: u16) {
+		let PortalInfo { center, normal, adjoining_room_index } = {
+			self.render.render_rooms[room_index as usize].portals[portal_index as usize]
+		};
+		let through = -normal;
+		self.render.pos = center + through * PORTAL_JUMP_DISTANCE;
+		let (yaw, pitch) = yaw_pitch(through);
+		if through.xz() != Vec2::ZERO {
+			self.render.yaw = yaw;
+		}
+		self.render.pitch = pitch;
+		if self.render.render_room_index.is_some() {
+			self.render.render_room_index = Some(adjoining_room_index as usize);
+		}
+	}
+	
+	/// Place the camera along `CAMERA_VEC` so the bounds of all rooms fit the view.
+	fn fit_level(&mut self, window_size: PhysicalSize<u32>) {
+		let Some(MinMax { min, max }) = self
+			.render.render_rooms
+			.iter()
+			.flat_map(|&RenderRoom { center, radius, .. }| {
+				let half = Vec3::splat(radius / 2.0);
+				[center - half, center + half]
+			})
+			.min_max()
+		else {
+			return;
+		};
+		let center = (max + min) / 2.0;
+		let radius = (max - min).length() / 2.0;
+		let aspect_ratio = window_size.width.max(1) as f32 / window_size.height.max(1) as f32;
+		let half_fov = self.projection.fov.clamp(MIN_FOV, MAX_FOV) / 2.0;
+		let half_fov = half_fov.min((half_fov.tan() * aspect_ratio).atan());
+		let distance = radius / half_fov.sin();
+		(self.render.yaw, self.render.pitch) = yaw_pitch(CAMERA_VEC);
+		self.render.roll = 0.0;
+		self.render.pos = center - direction(self.render.yaw, self.render.pitch) * distance;
+		self.projection.far = self.projection.far.max(distance + radius);
+	}
+	
+	/// OBJ of what is currently drawn.
+	fn visible_obj(&self, settings: &ExportSettings) -> String {
+		let room_indices = self.render.visible_room_indices();
+		let visible = obj::Visible {
+			room_indices: &room_indices,
+			room_mesh: self.render.show_room_mesh,
+			static_meshes: self.render.show_static_meshes,
+			invisible_static_meshes: self.render.show_invisible_static_meshes,
+			entity_meshes: self.render.show_entity_meshes,
+			hidden_entities: self.render.hidden_entities != HiddenEntities::Hide,
+			entity_renders: &self.entity_renders,
+			model_skeletons: &self.model_skeletons,
+		};
+		with_level!(&self.level, level => obj::visible_obj(level, &visible, settings))
+	}
+	
+	/// Move the camera back from the room's center to see all of it.
+	fn focus_room(&mut self, render_room_index: usize) {
+		let RenderRoom { center, radius, .. } = self.render.render_rooms[render_room_index];
+		let move_camera = move |loaded_level: &mut Self| {
+			loaded_level.render.pos = center - direction(loaded_level.render.yaw, loaded_level.render.pitch) * radius;
+		};
+		self.frame_update_queue.push(Box::new(move_camera));
+	}
+	
+	/// Render only the room `step` away from the rendered one, wrapping around, and move the camera to it.
+	/// Starts at room 0 when all rooms are rendered.
+	fn step_room(&mut self, step: isize) {
+		if self.render.render_rooms.is_empty() {
+			return;
+		}
+		let render_room_index = match self.render.render_room_index {
+			Some(index) => (index as isize + step).rem_euclid(self.render.render_rooms.len() as isize) as usize,
+			None => 0,
+		};
+		self.render.render_room_index = Some(render_room_index);
+		self.focus_room(render_room_index);
+	}
+	
+	/// Step through the selection's owner's faces if there are any, otherwise through rooms.
+	fn step_face_or_room(&mut self, step: isize) {
+		match self.owner_faces() {
+			Some(_) => self.step_owner_face(step),
+			None => self.step_room(step),
+		}
+	}
+}
+
+/// Windows and overlays of the level.
+impl LevelView {
+	/// Clicking a room renders only that room, as picking it in Render Options does.
+	fn room_graph(&mut self, ui: &mut egui::Ui) {
+		ui.label("Drag to pan, scroll to zoom, double-click to reset. Rooms are sized by face count, water is blue.");
+		let render_rooms = &self.render.render_rooms;
+		let clicked = self.room_graph.show(ui, self.render.render_room_index, |ui, room_index| {
+			render_rooms[room_index].stats.show(ui)
+		});
+		if let Some(room_index) = clicked {
+			self.render.render_room_index = Some(room_index);
+			self.focus_room(room_index);
+		}
+	}
+	
+	/// True if saved overrides were reset, to forget them.
+	fn render_options(&mut self, ui: &mut egui::Ui, window_size: PhysicalSize<u32>) -> bool {
+		let mut reset_overrides = false;
+		if self.using_saved_overrides {
+			ui.horizontal(|ui| {
+				ui.label("Using saved overrides for this level");
+				reset_overrides = ui.button("Reset").clicked();
+			});
+			ui.separator();
+		}
+		if reset_overrides {
+			self.reset_render_overrides();
+		}
+		if !self.render.flip_groups.is_empty() {
+			ui.horizontal(|ui| {
+				ui.label("Flip groups");
+				for flip_group in &mut self.render.flip_groups {
+					ui.toggle_value(&mut flip_group.show_flipped, flip_group.number.to_string());
+				}
+				ui.checkbox(&mut self.render.show_both_flip_states, "Show both")
+					.on_hover_text("Draw the other state of each flip group translucent");
+			});
+		}
+		let old_render_room = self.render.render_room_index;
+		egui::ComboBox::from_label("Room")
+			.selected_text(selected_room_text(self.render.render_room_index))
+			.show_ui(ui, |ui| {
+				ui.selectable_value(&mut self.render.render_room_index, None, selected_room_text(None));
+				for (render_room_index, render_room) in self.render.render_rooms.iter().enumerate() {
+					ui.selectable_value(
+						&mut self.render.render_room_index,
+						Some(render_room_index),
+						selected_room_text(Some(render_room_index)),
+					).on_hover_ui(|ui| render_room.stats.show(ui));
+				}
+			});
+		if let (true, Some(render_room_index)) = {
+			(self.render.render_room_index != old_render_room, self.render.render_room_index)
+		} {
+			self.focus_room(render_room_index);
+		}
+		if [
+			&self.render.shared.palette_24bit_bg,
+			&self.render.shared.texture_16bit_bg,
+			&self.render.shared.texture_32bit_bg,
+		].into_iter().filter(|bg| bg.is_some()).count() > 1 {
+			egui::ComboBox::from_label("Texture mode")
+				.selected_text(self.render.texture_mode.label())
+				.show_ui(ui, |ui| {
+					for (bg, mode) in [
+						(&self.render.shared.palette_24bit_bg, TextureMode::Palette),
+						(&self.render.shared.texture_16bit_bg, TextureMode::Bit16),
+						(&self.render.shared.texture_32bit_bg, TextureMode::Bit32),
+					] {
+						if bg.is_some() {
+							ui.selectable_value(&mut self.render.texture_mode, mode, mode.label());
+						}
+					}
+				});
+		}
+		if let (Some(solid_mode), Some(_), Some(_)) = {
+			(&mut self.render.solid_mode, &self.render.shared.palette_24bit_bg, &self.render.solid_32bit_bg)
+		} {
+			egui::ComboBox::from_label("Solid color mode")
+				.selected_text(solid_mode.label())
+				.show_ui(ui, |ui| {
+					for mode in [SolidMode::Bit24, SolidMode::Bit32] {
+						ui.selectable_value(solid_mode, mode, mode.label());
+					}
+				});
+		}
+		egui::ComboBox::from_label("Hidden entities")
+			.selected_text(self.render.hidden_entities.label())
+			.show_ui(ui, |ui| {
+				for mode in [HiddenEntities::Hide, HiddenEntities::Ghost, HiddenEntities::Show] {
+					ui.selectable_value(&mut self.render.hidden_entities, mode, mode.label());
+				}
+			});
+		ui.horizontal(|ui| {
+			for blend_filter in [BlendFilter::All, BlendFilter::Opaque, BlendFilter::Blended] {
+				ui.radio_value(&mut self.render.blend_filter, blend_filter, blend_filter.label());
+			}
+		});
+		egui::ComboBox::from_label("Winding")
+			.selected_text(self.render.winding.label())
+			.show_ui(ui, |ui| {
+				for winding in [Winding::Cw, Winding::Ccw] {
+					ui.selectable_value(&mut self.render.winding, winding, winding.label());
+				}
+			});
+		ui.checkbox(&mut self.render.disable_culling, "Disable culling");
+		egui::ComboBox::from_label("Face coloring")
+			.selected_text(self.render.face_coloring.label())
+			.show_ui(ui, |ui| {
+				let modes = [
+					FaceColoring::Textured,
+					FaceColoring::TextureId,
+					FaceColoring::Atlas,
+					FaceColoring::BlendMode,
+					FaceColoring::UvCheck,
+				];
+				for mode in modes {
+					ui.selectable_value(&mut self.render.face_coloring, mode, mode.label());
+				}
+			});
+		egui::ComboBox::from_label("Shading")
+			.selected_text(self.shading.label())
+			.show_ui(ui, |ui| {
+				for shading in [Shading::Off, Shading::Flat, Shading::Smooth] {
+					ui.selectable_value(&mut self.shading, shading, shading.label());
+				}
+			});
+		egui::ComboBox::from_label("Units")
+			.selected_text(self.unit_mode.label())
+			.show_ui(ui, |ui| {
+				for mode in [UnitMode::TrUnits, UnitMode::Sectors, UnitMode::Meters] {
+					ui.selectable_value(&mut self.unit_mode, mode, mode.label());
+				}
+			});
+		ui.add(egui::Slider::new(&mut self.render.lod_threshold, 0.0..=MAX_LOD_THRESHOLD).text("Room LOD threshold"))
+			.on_hover_text("Distant rooms draw only their largest faces. 0 to disable.");
+		ui.collapsing("Camera", |ui| {
+			let mut fov = self.projection.fov.to_degrees();
+			let fov_range = MIN_FOV.to_degrees()..=MAX_FOV.to_degrees();
+			ui.add(egui::Slider::new(&mut fov, fov_range).suffix("°").text("FOV"));
+			self.projection.fov = fov.to_radians();
+			let mut roll = self.render.roll.to_degrees();
+			ui.add(egui::Slider::new(&mut roll, -180.0..=180.0).suffix("°").text("Roll"));
+			self.render.roll = roll.to_radians();
+			if ui.button("Reset").clicked() {
+				self.projection.fov = FRAC_PI_4;
+				self.render.roll = 0.0;
+			}
+			if ui.button("Fit level to view").clicked() {
+				self.fit_level(window_size);
+			}
+			ui.separator();
+			let unit_mode = self.unit_mode;
+			ui.horizontal(|ui| {
+				ui.label("Position");
+				for value in [&mut self.render.pos.x, &mut self.render.pos.y, &mut self.render.pos.z] {
+					let mut shown = *value / unit_mode.scale();
+					let drag_value = egui::DragValue::new(&mut shown)
+						.speed(16.0 / unit_mode.scale())
+						.max_decimals(unit_mode.decimals())
+						.suffix(unit_mode.suffix());
+					if ui.add(drag_value).changed() {
+						*value = shown * unit_mode.scale();
+					}
+				}
+			});
+			ui.horizontal(|ui| {
+				ui.label("Yaw");
+				ui.drag_angle(&mut self.render.yaw);
+				ui.label("Pitch");
+				ui.drag_angle(&mut self.render.pitch);
+			});
+			self.render.pitch = self.render.pitch.clamp(-FRAC_PI_2, FRAC_PI_2);
+			if ui.button("Copy camera").clicked() {
+				let camera = self.camera_spec().to_string();
+				ui.output_mut(|output| output.copied_text = camera);
+			}
+			ui.horizontal(|ui| {
+				ui.add(egui::TextEdit::singleline(&mut self.camera_paste).hint_text("camera x y z yaw pitch"));
+				let camera = self.camera_paste.parse::<CameraSpec>();
+				if ui.add_enabled(camera.is_ok(), egui::Button::new("Apply")).clicked() {
+					//unwrap: button only enabled if parsed
+					self.set_camera_spec(camera.unwrap());
+				}
+			});
+		});
+		ui.collapsing("Clip planes", |ui| {
+			let projection = &mut self.projection;
+			let max_near = projection.max_near();
+			ui.add(egui::Slider::new(&mut projection.near, MIN_NEAR..=max_near).logarithmic(true).text("Near"));
+			ui.add_enabled(
+				!projection.infinite_far,
+				egui::Slider::new(&mut projection.far, MIN_FAR..=MAX_FAR).logarithmic(true).text("Far"),
+			);
+			ui.checkbox(&mut projection.infinite_far, "Infinite far plane");
+			//far or infinite far may have changed
+			projection.near = projection.near.min(projection.max_near());
+		});
+		ui.collapsing("Object type toggles", |ui| {
+			for (val, label) in [
+				(&mut self.render.show_room_mesh, "Room mesh"),
+				(&mut self.render.show_static_meshes, "Static meshes"),
+				(&mut self.render.show_invisible_static_meshes, "Invisible static meshes"),
+				(&mut self.render.show_entity_meshes, "Entity meshes"),
+				(&mut self.render.show_room_sprites, "Room sprites"),
+				(&mut self.render.show_entity_sprites, "Entity sprites"),
+				(&mut self.render.show_portals, "Portals"),
+				(&mut self.show_sector_grid, "Sector grid"),
+				(&mut self.show_room_bounds, "Room bounds"),
+				(&mut self.show_lights, "Lights"),
+				(&mut self.animate_sprites, "Animate sprite sequences"),
+				(&mut self.animate_water, "Animate water vertices"),
+				(&mut self.render.bias_reverse_faces, "Offset reverse faces"),
+				(&mut self.tint_degenerate_textures, "Tint degenerate textures"),
+				(&mut self.mesh_shine, "Mesh shine"),
+			] {
+				ui.checkbox(val, label);
+			}
+			ui.horizontal(|ui| {
+				let mut paused = self.animations_paused;
+				if ui.checkbox(&mut paused, "Pause animations").changed() {
+					self.toggle_animations_paused();
+				}
+				if ui.add_enabled(paused, egui::Button::new("Step frame")).clicked() {
+					self.step_animations();
+				}
+			});
+			let portal_labels = egui::Checkbox::new(&mut self.show_portal_labels, "Portal labels");
+			ui.add_enabled(self.render.show_portals, portal_labels);
+			if self.level.version().has_footstep_materials() {
+				ui.checkbox(&mut self.show_footstep_materials, "Footstep materials");
+			}
+		});
+		if !self.flyby_sequences.is_empty() {
+			ui.collapsing("Flyby cameras", |ui| {
+				ui.checkbox(&mut self.show_flyby_paths, "Show paths");
+				ui.add_enabled(
+					self.show_flyby_paths,
+					egui::Checkbox::new(&mut self.show_flyby_targets, "Show targets"),
+				);
+				for (sequence_index, sequence) in self.flyby_sequences.iter().enumerate() {
+					ui.horizontal(|ui| {
+						if ui.button("Play").clicked() {
+							self.flyby_playback = Some(FlybyPlayback::new(sequence_index));
+							self.cinematic_playback = None;
+							self.camera_path_playback.playing = false;
+							self.room_path = None;
+						}
+						ui.label(format!("Sequence {} ({} nodes)", sequence.id, sequence.nodes.len()));
+					});
+				}
+				if ui.add_enabled(self.flyby_playback.is_some(), egui::Button::new("Stop")).clicked() {
+					self.flyby_playback = None;
+				}
+			});
+		}
+		if !self.cinematic_frames.is_empty() {
+			ui.collapsing("Cinematic", |ui| {
+				ui.checkbox(&mut self.show_cinematic_path, "Show path");
+				ui.horizontal(|ui| {
+					if ui.button("Play cinematic").clicked() {
+						self.cinematic_playback = Some(CinematicPlayback::default());
+						self.flyby_playback = None;
+						self.camera_path_playback.playing = false;
+						self.room_path = None;
+					}
+					if ui.add_enabled(self.cinematic_playback.is_some(), egui::Button::new("Stop")).clicked() {
+						self.cinematic_playback = None;
+						self.render.roll = 0.0;
+					}
+				});
+				let frame_index = self.cinematic_playback.as_ref().map_or(0, |playback| playback.frame_index());
+				ui.label(format!("Frame {}/{}", frame_index, self.cinematic_frames.len()));
+			});
+		}
+		if self.demo_data_len != 0 {
+			ui.collapsing("Demo", |ui| self.demo_options(ui));
+		}
+		ui.collapsing("Camera path", |ui| self.camera_path_options(ui));
+		ui.collapsing("Room path", |ui| self.room_path_options(ui));
+		ui.collapsing("Background", |ui| {
+			let background = &mut self.render.background;
+			ui.horizontal(|ui| {
+				ui.color_edit_button_srgb(&mut background.top);
+				ui.label(if background.gradient { "Top" } else { "Color" });
+			});
+			ui.checkbox(&mut background.gradient, "Gradient");
+			ui.add_enabled_ui(background.gradient, |ui| {
+				ui.horizontal(|ui| {
+					ui.color_edit_button_srgb(&mut background.bottom);
+					ui.label("Bottom");
+				});
+			});
+		});
+		reset_overrides
+	}
+	
+	/// Returns the model or static mesh to open in the mesh viewer if requested.
+	fn selection(&mut self, ui: &mut egui::Ui) -> Option<MeshViewerSubject> {
+		//unwrap: window only shown with selection
+		let data = self.selected.unwrap();
+		with_level!(&self.level, level => show_object_data(ui, level, data, &self.item_names, self.unit_mode));
+		self.owner_faces_summary(ui);
+		ui.separator();
+		ui.checkbox(&mut self.show_face_instance, "Face instance");
+		if self.show_face_instance {
+			self.face_instance_rows(ui, data);
+		}
+		let subject = mesh_viewer::subject_of_selection(&self.level, data)?;
+		ui.button("View in mesh viewer").clicked().then_some(subject)
+	}
+	
+	/// Selected face's instance as the shader decodes it, beside the values recomputed from the level.
+	/// Mismatches are red.
+	fn face_instance_rows(&self, ui: &mut egui::Ui, data: ObjectData) {
+		let expected = with_level!(&self.level, level => expected_face_instance(level, data));
+		let Some(expected) = expected else {
+			ui.label("Not a face");
+			return;
+		};
+		let Some(object_data_index) = self.object_data.iter().position(|&other| other == data) else {
+			ui.label("Not in object data");
+			return;
+		};
+		let object_data_index = object_data_index as u32;
+		let instance = self
+			.render.face_instances
+			.iter()
+			.find(|instance| instance.object_data_index() == object_data_index);
+		let Some(instance) = instance else {
+			ui.label("No face instance (culled or on a skipped layer)");
+			return;
+		};
+		let words = instance.words();
+		let decoded = face_instance::decode(words);
+		ui.label(format!("Words: 0x{:08X} 0x{:08X} 0x{:08X}", words[0], words[1], words[2]));
+		let degenerate = expected
+			.object_texture_index
+			.is_some_and(|object_texture_index| self.issues.object_textures.has_issue(object_texture_index));
+		let shine_strength = expected.shine_strength & face_instance::MAX_SHINE_STRENGTH;
+		egui::Grid::new("face_instance").num_columns(3).show(ui, |ui| {
+			ui.label("Field");
+			ui.label("Instance");
+			ui.label("Level");
+			ui.end_row();
+			let fields = [
+				("Face", decoded.face_index.to_string(), expected.face_index.to_string()),
+				("Degenerate texture", decoded.degenerate_texture.to_string(), degenerate.to_string()),
+				("Shine strength", decoded.shine_strength.to_string(), shine_strength.to_string()),
+				("Object data", decoded.object_data_index.to_string(), object_data_index.to_string()),
+			];
+			for (label, packed, recomputed) in fields {
+				let color = if packed == recomputed { ui.visuals().text_color() } else { egui::Color32::RED };
+				ui.label(label);
+				ui.colored_label(color, packed);
+				ui.colored_label(color, recomputed);
+				ui.end_row();
+			}
+			for (label, value) in [
+				("Face array", decoded.face_array_index),
+				("Transform", decoded.transform_index),
+				("Page", decoded.page as u16),
+			] {
+				ui.label(label);
+				ui.label(value.to_string());
+				ui.end_row();
+			}
+		});
+	}
+	
+	/// Object data indices sharing the selection's owner, and the selection's position among them.
+	fn owner_faces(&self) -> Option<(&[u32], usize)> {
+		let selected = self.selected?;
+		let faces = self.object_data_by_owner.get(&selected.owner()?)?;
+		let position = faces.iter().position(|&index| self.object_data[index as usize] == selected)?;
+		Some((faces, position))
+	}
+	
+	fn owner_faces_summary(&self, ui: &mut egui::Ui) {
+		let Some((faces, position)) = self.owner_faces() else {
+			return;
+		};
+		ui.separator();
+		ui.label(format!("Face {} of {} on this object, [ and ] to cycle", position + 1, faces.len()));
+		let mut counts = Vec::<(&str, usize)>::new();
+		for &index in faces {
+			let kind = self.object_data[index as usize].kind_label();
+			match counts.iter_mut().find(|(other, _)| *other == kind) {
+				Some((_, count)) => *count += 1,
+				None => counts.push((kind, 1)),
+			}
+		}
+		for (kind, count) in counts {
+			ui.label(format!("{}: {}", kind, count));
+		}
+	}
+	
+	/// Center of the selected object's room, static mesh or entity, else of the room rendered alone.
+	fn turntable_target(&self) -> Option<Vec3> {
+		let pos = |owner| with_level!(&self.level, level => owner_pos(level, &self.render.render_rooms, owner));
+		match self.selected.and_then(|selected| selected.owner()) {
+			Some(owner) => pos(owner),
+			None => self.render.render_room_index.map(|index| self.render.render_rooms[index].center),
+		}
+	}
+	
+	/// Stop orbiting, waiting for saved frames to be written.
+	fn stop_turntable(&mut self) -> Result<()> {
+		let Some(turntable) = self.turntable.take() else {
+			return Ok(());
+		};
+		if let Some(summary) = turntable.finish().transpose()? {
+			console::info!(
+				"turntable: {} frames written to {}, {} skipped",
+				summary.written,
+				summary.out_dir.display(),
+				summary.skipped,
+			);
+		}
+		Ok(())
+	}
+	
+	/// Orbit options and start, or progress and stop while orbiting. True if Choose folder was clicked.
+	fn turntable(&mut self, ui: &mut egui::Ui) -> Result<bool> {
+		if let Some(turntable) = &self.turntable {
+			match turntable.capture_progress() {
+				Some((frame, num_frames)) => {
+					ui.add(egui::ProgressBar::new(frame as f32 / num_frames.max(1) as f32).text("Saving frames"));
+				},
+				None => {
+					ui.label("Orbiting");
+				},
+			}
+			if ui.button("Stop").clicked() {
+				self.stop_turntable()?;
+			}
+			return Ok(false);
+		}
+		let options = &mut self.turntable_options;
+		let turntable_dir = &self.turntable_dir;
+		ui.add(egui::Slider::new(&mut options.radius, 256.0..=65536.0).logarithmic(true).text("Radius"));
+		ui.add(egui::Slider::new(&mut options.revolution_secs, 1.0..=120.0).suffix(" s").text("Revolution time"));
+		ui.checkbox(&mut options.save_frames, "Save frames");
+		let mut choose_folder = false;
+		ui.add_enabled_ui(options.save_frames, |ui| {
+			ui.add(egui::Slider::new(&mut options.num_frames, 2..=3600).text("Frames per revolution"));
+			ui.horizontal(|ui| {
+				choose_folder = ui.button("Choose folder").clicked();
+				match turntable_dir {
+					Some(dir) => ui.label(dir.display().to_string()),
+					None => ui.label("No folder"),
+				};
+			});
+		});
+		let options = self.turntable_options;
+		let target = self.turntable_target();
+		let ready = target.is_some() && (!options.save_frames || self.turntable_dir.is_some());
+		if ui.add_enabled(ready, egui::Button::new("Start")).clicked() {
+			//unwrap: ready
+			let turntable = Turntable::start(target.unwrap(), self.camera_spec(), options, self.turntable_dir.clone());
+			self.turntable = Some(turntable);
+		}
+		if target.is_none() {
+			ui.label("Select an object or render a single room to orbit it.");
+		}
+		Ok(choose_folder)
+	}
+	
+	/// Export options, or progress while an export runs. True if Export was clicked.
+	fn texture_export(&mut self, ui: &mut egui::Ui) -> bool {
+		if let Some(export) = &self.texture_export {
+			ui.add(egui::ProgressBar::new(export.progress()).show_percentage());
+			return false;
+		}
+		let options = &mut self.texture_export_options;
+		ui.checkbox(&mut options.atlases, "Atlases");
+		ui.checkbox(&mut options.object_textures, "Object textures, cropped to their UVs");
+		ui.checkbox(&mut options.sprite_textures, "Sprite textures");
+		ui.label("Atlases are read in the current tab's texture mode, or the render texture mode for Misc.");
+		ui.button("Export to folder").clicked()
+	}
+	
+	fn start_texture_export(&mut self, out_dir: PathBuf) {
+		let texture_mode = match self.textures_tab {
+			TexturesTab::Textures(texture_mode) => texture_mode,
+			TexturesTab::Misc => self.render.texture_mode,
+		};
+		let rgba = textures_tab_rgba(self.level.as_dyn(), TexturesTab::Textures(texture_mode));
+		let options = self.texture_export_options;
+		let export = with_level!(&self.level, level => TextureExport::start(level, rgba, options, out_dir));
+		self.texture_export = Some(export);
+	}
+	
+	/// Select the face `step` away among the selection's owner's faces, wrapping around.
+	fn step_owner_face(&mut self, step: isize) {
+		let Some((faces, position)) = self.owner_faces() else {
+			return;
+		};
+		let position = (position as isize + step).rem_euclid(faces.len() as isize) as usize;
+		self.selected = Some(self.object_data[faces[position] as usize]);
+	}
+	
+	fn open_mesh_viewer(
+		&mut self, device: &Device, queue: &Queue, shared: &ViewerShared, subject: MeshViewerSubject,
+	) {
+		let label = mesh_viewer::subjects(&self.level)
+			.into_iter()
+			.find_map(|(other, label)| (other == subject).then_some(label))
+			.unwrap_or_default();
+		match MeshViewer::new(device, queue, shared, &self.view_shared.texture_views, &self.level, subject, label) {
+			Ok(mesh_viewer) => self.mesh_viewer = Some(mesh_viewer),
+			Err(e) => console::log_warn!("failed to open mesh viewer: {}", e),
+		}
+	}
+	
+	fn mesh_viewer(&mut self, ui: &mut egui::Ui, device: &Device, queue: &Queue, shared: &Arc<ViewerShared>) {
+		let current = self.mesh_viewer.as_ref().map(|mesh_viewer| mesh_viewer.subject);
+		let selected_text = self.mesh_viewer.as_ref().map_or("None", |mesh_viewer| mesh_viewer.label.as_str());
+		let mut picked = None;
+		egui::ComboBox::from_label("Subject").selected_text(selected_text).show_ui(ui, |ui| {
+			for (subject, label) in mesh_viewer::subjects(&self.level) {
+				if ui.selectable_label(current == Some(subject), label).clicked() {
+					picked = Some(subject);
+				}
+			}
+		});
+		if let Some(subject) = picked {
+			self.open_mesh_viewer(device, queue, shared, subject);
+		}
+		let winding = self.render.cull_winding();
+		match &mut self.mesh_viewer {
+			Some(mesh_viewer) => {
+				ui.label("Drag to orbit, scroll to zoom, double-click to reset");
+				let LoadedLevel { texture_mode, solid_mode, .. } = self.render;
+				mesh_viewer.show(ui, queue, shared, &self.level, texture_mode, solid_mode, winding);
+			},
+			None => {
+				ui.label("Pick a model or static mesh, or use the Selection window on a mesh face");
+			},
+		}
+	}
+	
+	fn level_issues(&mut self, ui: &mut egui::Ui) {
+		let LevelIssues {
+			missing_static_meshes,
+			truncated_sprite_sequences,
+			ref malformed_rooms,
+			ref mismatched_room_bounds,
+			ref object_textures,
+			ref unknown_model_entities,
+		} = self.issues;
+		if missing_static_meshes == 0
+			&& truncated_sprite_sequences == 0
+			&& malformed_rooms.is_empty()
+			&& mismatched_room_bounds.is_empty()
+			&& object_textures.is_empty()
+			&& unknown_model_entities.is_empty()
+		{
+			ui.label("No issues found");
+		}
+		if missing_static_meshes != 0 {
+			ui.label(format!("Skipped room static meshes with missing id: {}", missing_static_meshes));
+		}
+		if truncated_sprite_sequences != 0 {
+			ui.label(format!("Truncated out of bounds sprite sequences: {}", truncated_sprite_sequences));
+		}
+		for (indices, label) in [
+			(malformed_rooms, "Malformed rooms"),
+			(mismatched_room_bounds, "Rooms whose declared extent doesn't match their vertices"),
+			(&object_textures.zero_area, "Object textures with zero area UVs"),
+			(&object_textures.flipped, "Object textures with flipped UVs"),
+			(&object_textures.tri_flag_mismatch, "Object textures with mismatched tri flag"),
+		] {
+			if !indices.is_empty() {
+				ui.collapsing(format!("{}: {}", label, indices.len()), |ui| {
+					let indices = indices.iter().map(|index| index.to_string()).collect::<Vec<_>>();
+					ui.label(indices.join(", "));
+				});
+			}
+		}
+		let mut picked = None;
+		if !unknown_model_entities.is_empty() {
+			let label = format!("Entities with unknown model id: {}", unknown_model_entities.len());
+			ui.collapsing(label, |ui| {
+				for unknown in unknown_model_entities {
+					let UnknownModelEntity { entity_index, model_id, .. } = *unknown;
+					let label = format!("Entity {}: model id {}", entity_index, model_id);
+					if ui.selectable_label(self.selected_entity_index() == Some(entity_index), label).clicked() {
+						picked = Some(entity_index);
+					}
+				}
+			});
+		}
+		if let Some(entity_index) = picked {
+			self.selected = Some(ObjectData::UnknownModelEntity { entity_index });
+		}
+	}
+	
+	/// Entities whose label contains the query. Click one to select it and move the camera back from it.
+	fn find_entity(&mut self, ui: &mut egui::Ui) {
+		ui.add(egui::TextEdit::singleline(&mut self.entity_query).hint_text("name, model id or index"));
+		let query = self.entity_query.to_lowercase();
+		let entities = with_level!(&self.level, level => {
+			level.entities().iter().map(|entity| (entity.model_id(), entity.pos().as_vec3())).collect::<Vec<_>>()
+		});
+		let selected_entity_index = self.selected_entity_index();
+		let mut picked = None;
+		egui::ScrollArea::vertical().show(ui, |ui| {
+			for (entity_index, &(model_id, pos)) in entities.iter().enumerate() {
+				let label = entity_label(entity_index, model_id, &self.item_names);
+				if !label.to_lowercase().contains(&query) {
+					continue;
+				}
+				let entity_index = entity_index as u16;
+				if ui.selectable_label(selected_entity_index == Some(entity_index), label).clicked() {
+					picked = Some((entity_index, pos));
+				}
+			}
+		});
+		if let Some((entity_index, pos)) = picked {
+			let faces = self.object_data_by_owner.get(&ObjectOwner::Entity(entity_index));
+			self.selected = faces.and_then(|faces| faces.first()).map(|&index| self.object_data[index as usize]);
+			let move_camera = move |loaded_level: &mut Self| {
+				let view_direction = direction(loaded_level.render.yaw, loaded_level.render.pitch);
+				loaded_level.render.pos = pos - view_direction * ENTITY_VIEW_DISTANCE;
+			};
+			self.frame_update_queue.push(Box::new(move_camera));
+		}
+	}
+	
+	/// Side length and number of the images shown in a textures tab, as uploaded.
+	fn textures_tab_extent(&self, textures_tab: TexturesTab) -> (u32, u32) {
+		let num_images = match textures_tab {
+			TexturesTab::Textures(_) => self.num_atlases,
+			TexturesTab::Misc => self.num_misc_images.unwrap_or(0),
+		};
+		(tr1::ATLAS_SIDE_LEN as u32, num_images)
+	}
+	
+	fn atlas_usage(&self, ui: &mut egui::Ui) {
+		let AtlasUsage { ref occupied, packed_atlases } = self.atlas_usage;
+		let num_atlases = occupied.len();
+		let reclaimable = num_atlases.saturating_sub(packed_atlases) as f32 / num_atlases.max(1) as f32;
+		ui.label(format!(
+			"Current: {} atlases, achievable: {} ({:.0}% waste)",
+			num_atlases,
+			packed_atlases.min(num_atlases),
+			reclaimable * 100.0,
+		));
+		ui.label("Estimate from repacking texture bounds, overlapping ones together");
+		for atlas_index in 0..num_atlases {
+			let occupancy = self.atlas_usage.occupancy(atlas_index);
+			ui.horizontal(|ui| {
+				ui.label(format!("{:>3}", atlas_index));
+				ui.add(egui::ProgressBar::new(occupancy).text(format!("{:.0}%", occupancy * 100.0)));
+			});
+		}
+	}
+	
+	/// Object texture under `pixel` of the atlases stacked as in the Textures window.
+	fn object_texture_at(&self, pixel: UVec2) -> Option<u16> {
+		let side = tr1::ATLAS_SIDE_LEN as u32;
+		let atlas_index = (pixel.y / side) as usize;
+		let pixel = UVec2::new(pixel.x, pixel.y % side);
+		let index = with_level!(&self.level, level => atlas_usage::object_texture_at(level, atlas_index, pixel));
+		index.map(|index| index as u16)
+	}
+	
+	/// Bounds of the highlighted object texture in the atlases stacked as in the Textures window.
+	fn highlighted_texture_rect(&self) -> Option<MinMax<UVec2>> {
+		let index = self.highlighted_object_texture? as usize;
+		let rect = with_level!(&self.level, level => {
+			level.object_textures().get(index).map(atlas_usage::object_texture_rect)
+		})?;
+		let offset = UVec2::new(0, rect.atlas_index as u32 * tr1::ATLAS_SIDE_LEN as u32);
+		Some(MinMax { min: rect.min + offset, max: rect.max + offset })
+	}
+	
+	/// Highlight faces using the object texture clicked at `pixel` in the Textures window, or none if empty.
+	fn pick_texture(&mut self, queue: &Queue, pixel: UVec2) {
+		self.highlighted_object_texture = self.object_texture_at(pixel);
+		self.update_highlight(queue);
+	}
+	
+	fn selected_entity_index(&self) -> Option<u16> {
+		match self.selected?.owner()? {
+			ObjectOwner::Entity(entity_index) => Some(entity_index),
+			_ => None,
+		}
+	}
+	
+	fn selected_entity_mesh(&self) -> Option<(u16, u16, &EntityRender, &ModelSkeleton)> {
+		let Some(ObjectData::EntityMeshFace { entity_index, mesh_index, .. }) = self.selected else {
+			return None;
+		};
+		let entity_render = self.entity_renders[entity_index as usize].as_ref()?;
+		let skeleton = &self.model_skeletons[&entity_render.model_id];
+		Some((entity_index, mesh_index, entity_render, skeleton))
+	}
+	
+	fn mesh_nodes(&mut self, ui: &mut egui::Ui) {
+		let mut hovered_mesh_index = None;
+		match self.selected_entity_mesh() {
+			Some((entity_index, mesh_index, entity_render, skeleton)) => {
+				ui.label(format!("Entity {}, model {}", entity_index, entity_render.model_id));
+				let mesh_offsets = self.level.as_dyn().mesh_offsets();
+				egui::ScrollArea::vertical().show(ui, |ui| {
+					mesh_node_tree(
+						ui,
+						skeleton,
+						mesh_offsets,
+						0,
+						mesh_index as usize,
+						&mut hovered_mesh_index,
+						self.unit_mode,
+					);
+				});
+			},
+			None => {
+				ui.label("Select an entity mesh face");
+			},
+		}
+		self.hovered_mesh_index = hovered_mesh_index;
+	}
+	
+	/// Maps world positions to screen positions, `None` if behind the camera.
+	fn screen_projection(
+		&self, ctx: &egui::Context, window_size: PhysicalSize<u32>,
+	) -> impl Fn(Vec3) -> Option<egui::Pos2> {
+		let view_proj = {
+			let perspective_transform = make_perspective_transform(window_size, self.current_projection(window_size));
+			let LoadedLevel { pos, yaw, pitch, roll, .. } = self.render;
+			perspective_transform * make_camera_transform(pos, yaw, pitch, roll)
+		};
+		let screen_size = ctx.screen_rect().size();
+		move |pos: Vec3| {
+			let clip = view_proj * pos.extend(1.0);
+			(clip.w > 0.0).then(|| {
+				let ndc = clip.xy() / clip.w;
+				egui::pos2((ndc.x + 1.0) / 2.0 * screen_size.x, (1.0 - ndc.y) / 2.0 * screen_size.y)
+			})
+		}
+	}
+	
+	/// Play the TR1 demo's reconstructed path with a ghost, and list how long each input is held.
+	fn demo_options(&mut self, ui: &mut egui::Ui) {
+		ui.label(format!("Demo data: {} bytes", self.demo_data_len));
+		if self.demo.is_none() {
+			ui.label(match self.level.version() {
+				Version::Tr1 => "Too short for the demo header",
+				_ => "Only TR1 demos are decoded",
+			});
+			return;
+		}
+		ui.checkbox(&mut self.show_demo_path, "Show path");
+		ui.checkbox(&mut self.follow_demo, "Follow with camera");
+		ui.horizontal(|ui| {
+			if ui.button("Play demo").clicked() {
+				self.demo_playback = Some(DemoPlayback::default());
+				self.flyby_playback = None;
+				self.cinematic_playback = None;
+				self.camera_path_playback.playing = false;
+				self.room_path = None;
+			}
+			if ui.add_enabled(self.demo_playback.is_some(), egui::Button::new("Stop")).clicked() {
+				self.demo_playback = None;
+			}
+		});
+		let Some(demo) = &self.demo else {
+			return;
+		};
+		let frame_index = self.demo_playback.as_ref().map_or(0, |playback| playback.frame_index());
+		ui.label(format!("Frame {}/{}", frame_index.min(demo.inputs.len()), demo.inputs.len()));
+		let pos = demo.pos;
+		ui.label(format!("Start ({}, {}, {}) in room {}", pos.x, pos.y, pos.z, demo.room_index));
+		egui::Grid::new("demo_inputs").show(ui, |ui| {
+			for (input, frames) in Input::ALL.iter().zip(demo_playback::histogram(&demo.inputs)) {
+				ui.label(input.label());
+				ui.label(format!("{} frames", frames));
+				ui.end_row();
+			}
+		});
+	}
+	
+	/// Record keyframes and play them back on a timeline. Saved and loaded from the File menu.
+	fn camera_path_options(&mut self, ui: &mut egui::Ui) {
+		ui.horizontal(|ui| {
+			if ui.button("Add keyframe").clicked() {
+				self.camera_path.record(self.camera_spec(), self.keyframe_interval);
+			}
+			ui.add(egui::DragValue::new(&mut self.keyframe_interval).speed(0.1).clamp_range(0.1..=60.0).suffix(" s"));
+			ui.label("after last");
+		});
+		let duration = self.camera_path.duration();
+		ui.label(format!("{} keyframes, {:.1} s", self.camera_path.keyframes.len(), duration));
+		let playback = &mut self.camera_path_playback;
+		ui.horizontal(|ui| {
+			let label = if playback.playing { "Pause" } else { "Play" };
+			if ui.add_enabled(self.camera_path.keyframes.len() > 1, egui::Button::new(label)).clicked() {
+				if !playback.playing && playback.time >= duration {
+					playback.time = 0.0;
+				}
+				playback.playing ^= true;
+				self.flyby_playback = None;
+				self.cinematic_playback = None;
+				self.room_path = None;
+			}
+			if ui.button("Clear").clicked() {
+				self.camera_path = CameraPath::default();
+				*playback = CameraPathPlayback { speed: playback.speed, ..CameraPathPlayback::default() };
+			}
+		});
+		ui.add(egui::Slider::new(&mut playback.speed, 0.1..=4.0).logarithmic(true).text("Speed"));
+		let timeline = egui::Slider::new(&mut playback.time, 0.0..=duration).suffix(" s").text("Time");
+		if ui.add(timeline).changed() {
+			if let Some(camera) = self.camera_path.sample(playback.time) {
+				self.set_camera_spec(camera);
+			}
+		}
+	}
+	
+	/// Move the camera from one room to another along the route through the fewest portals.
+	fn room_path_options(&mut self, ui: &mut egui::Ui) {
+		let max_room_index = self.render.render_rooms.len().saturating_sub(1);
+		ui.horizontal(|ui| {
+			let [start, end] = &mut self.room_path_ends;
+			ui.label("From room");
+			ui.add(egui::DragValue::new(start).clamp_range(0..=max_room_index));
+			ui.label("to room");
+			ui.add(egui::DragValue::new(end).clamp_range(0..=max_room_index));
+		});
+		ui.horizontal(|ui| {
+			if ui.add_enabled(!self.render.render_rooms.is_empty(), egui::Button::new("Walk")).clicked() {
+				self.start_room_path();
+			}
+			if ui.add_enabled(self.room_path.is_some(), egui::Button::new("Stop")).clicked() {
+				self.room_path = None;
+			}
+		});
+		ui.label(&self.room_path_status);
+	}
+	
+	/// Path through room centers and the portals between them, played from the start room.
+	fn start_room_path(&mut self) {
+		let [start, end] = self.room_path_ends;
+		let Some(route) = self.room_graph.path(start, end) else {
+			self.room_path_status = format!("No portal route from room {} to room {}", start, end);
+			self.room_path = None;
+			return;
+		};
+		let mut points = vec![(self.render.render_rooms[start].center, start)];
+		for pair in route.windows(2) {
+			let [from, to] = [pair[0], pair[1]];
+			let portals = &self.render.render_rooms[from].portals;
+			if let Some(portal) = portals.iter().find(|portal| portal.adjoining_room_index as usize == to) {
+				points.push((portal.center, to));
+			}
+			points.push((self.render.render_rooms[to].center, to));
+		}
+		self.room_path_status = format!("{} portals", route.len() - 1);
+		self.room_path = Some(RoomPath::new(&points));
+		self.flyby_playback = None;
+		self.cinematic_playback = None;
+		self.camera_path_playback.playing = false;
+		if self.render.render_room_index.is_some() {
+			self.render.render_room_index = Some(start);
+		}
+	}
+	
+	/// Label each visible portal with its adjoining room, in the portal's color.
+	fn portal_labels(&self, ctx: &egui::Context, window_size: PhysicalSize<u32>) {
+		if !(self.render.show_portals && self.show_portal_labels) {
+			return;
+		}
+		let project = self.screen_projection(ctx, window_size);
+		let painter = ctx.layer_painter(egui::LayerId::background());
+		for room_index in self.render.visible_room_indices() {
+			for portal in &self.render.render_rooms[room_index].portals {
+				let Some(pos) = project(portal.center) else {
+					continue;
+				};
+				let [r, g, b, _] = portal_color(portal.adjoining_room_index).to_le_bytes();
+				painter.text(
+					pos,
+					egui::Align2::CENTER_CENTER,
+					format!("→ room {}", portal.adjoining_room_index),
+					egui::FontId::proportional(14.0),
+					egui::Color32::from_rgb(r, g, b),
+				);
+			}
+		}
+	}
+	
+	/// Outline each sector of the visible rooms at its floor height, walls in a different color.
+	fn sector_grid(&self, ctx: &egui::Context, window_size: PhysicalSize<u32>) {
+		if !self.show_sector_grid {
+			return;
+		}
+		let project = self.screen_projection(ctx, window_size);
+		let painter = ctx.layer_painter(egui::LayerId::background());
+		for room_index in self.render.visible_room_indices() {
+			for &SectorOutline { corner, wall, .. } in &self.render.render_rooms[room_index].sectors {
+				let corners = [Vec3::ZERO, Vec3::X, Vec3::new(1.0, 0.0, 1.0), Vec3::Z]
+					.map(|offset| project(corner + offset * SECTOR_SIZE));
+				let color = if wall { SECTOR_WALL_COLOR } else { SECTOR_FLOOR_COLOR };
+				for index in 0..4 {
+					if let (Some(a), Some(b)) = (corners[index], corners[(index + 1) % 4]) {
+						painter.line_segment([a, b], egui::Stroke::new(1.0, color));
+					}
+				}
+			}
+		}
+	}
+	
+	/// Wireframe boxes of visible rooms, declared extent and vertex bounds in different colors.
+	fn room_bounds(&self, ctx: &egui::Context, window_size: PhysicalSize<u32>) {
+		if !self.show_room_bounds {
+			return;
+		}
+		let project = self.screen_projection(ctx, window_size);
+		let painter = ctx.layer_painter(egui::LayerId::background());
+		for room_index in self.render.visible_room_indices() {
+			let render_room = &self.render.render_rooms[room_index];
+			let boxes = [
+				(Some(&render_room.declared_bounds), DECLARED_BOUNDS_COLOR),
+				(render_room.vertex_bounds.as_ref(), VERTEX_BOUNDS_COLOR),
+			];
+			for (bounds, color) in boxes {
+				for [a, b] in bounds.map(box_edges).into_iter().flatten() {
+					if let (Some(a), Some(b)) = (project(a), project(b)) {
+						painter.line_segment([a, b], egui::Stroke::new(1.0, color));
+					}
+				}
+			}
+		}
+	}
+	
+	/// Dot at each light of visible rooms in its color.
+	fn lights(&self, ctx: &egui::Context, window_size: PhysicalSize<u32>) {
+		if !self.show_lights {
+			return;
+		}
+		let project = self.screen_projection(ctx, window_size);
+		let painter = ctx.layer_painter(egui::LayerId::background());
+		for room_index in self.render.visible_room_indices() {
+			for light in &self.render.render_rooms[room_index].lights {
+				if let Some(pos) = project(light.pos) {
+					let [r, g, b] = light.color;
+					let color = egui::Color32::from_rgb(r, g, b);
+					painter.circle(pos, LIGHT_MARKER_RADIUS, color, egui::Stroke::new(1.0, egui::Color32::BLACK));
+				}
+			}
+		}
+	}
+	
+	/// Fill floor sectors of visible rooms by footstep material, with a legend of the materials shown.
+	fn footstep_materials(&self, ctx: &egui::Context, window_size: PhysicalSize<u32>) {
+		if !self.show_footstep_materials || !self.level.version().has_footstep_materials() {
+			return;
+		}
+		let project = self.screen_projection(ctx, window_size);
+		let painter = ctx.layer_painter(egui::LayerId::background());
+		let mut counts = [0usize; 16];
+		for room_index in self.render.visible_room_indices() {
+			for &SectorOutline { corner, wall, material } in &self.render.render_rooms[room_index].sectors {
+				if wall {
+					continue;
+				}
+				let corners = [Vec3::ZERO, Vec3::X, Vec3::new(1.0, 0.0, 1.0), Vec3::Z]
+					.map(|offset| project(corner + offset * SECTOR_SIZE));
+				let Some(corners) = corners.into_iter().collect::<Option<Vec<_>>>() else {
+					continue;
+				};
+				let color = footstep::color(material).gamma_multiply(FOOTSTEP_MATERIAL_OPACITY);
+				painter.add(egui::Shape::convex_polygon(corners, color, egui::Stroke::NONE));
+				counts[material as usize] += 1;
+			}
+		}
+		egui::Area::new(egui::Id::new("footstep_materials"))
+			.anchor(egui::Align2::LEFT_BOTTOM, [LEGEND_MARGIN, -LEGEND_MARGIN])
+			.show(ctx, |ui| {
+				egui::Frame::popup(ui.style()).show(ui, |ui| {
+					for (material, &count) in counts.iter().enumerate().filter(|&(_, &count)| count > 0) {
+						let material = material as u8;
+						ui.horizontal(|ui| {
+							let (rect, _) = ui.allocate_exact_size(egui::vec2(12.0, 12.0), egui::Sense::hover());
+							ui.painter().rect_filled(rect, 0.0, footstep::color(material));
+							ui.label(format!("{} {}: {}", material, footstep::label(material), count));
+						});
+					}
+				});
+			});
+	}
+	
+	/// Spline through the nodes of each flyby sequence with numbered node markers, colored by sequence.
+	/// Optionally a line from each node to its target.
+	fn flyby_paths(&self, ctx: &egui::Context, window_size: PhysicalSize<u32>) {
+		if !self.show_flyby_paths {
+			return;
+		}
+		let project = self.screen_projection(ctx, window_size);
+		let painter = ctx.layer_painter(egui::LayerId::background());
+		for sequence in &self.flyby_sequences {
+			let [r, g, b, _] = portal_color(sequence.id as u16).to_le_bytes();
+			let color = egui::Color32::from_rgb(r, g, b);
+			let spline = sequence.spline(FLYBY_SPLINE_STEPS).into_iter().map(&project).collect::<Vec<_>>();
+			for pair in spline.windows(2) {
+				if let [Some(a), Some(b)] = *pair {
+					painter.line_segment([a, b], egui::Stroke::new(2.0, color));
+				}
+			}
+			if self.show_flyby_targets {
+				for node in &sequence.nodes {
+					if let (Some(pos), Some(target)) = (project(node.pos), project(node.target)) {
+						painter.line_segment([pos, target], egui::Stroke::new(1.0, color.gamma_multiply(0.6)));
+						painter.circle_stroke(target, 4.0, egui::Stroke::new(1.5, color));
+					}
+				}
+			}
+			let nodes = sequence.nodes.iter().map(|node| project(node.pos)).collect::<Vec<_>>();
+			for (node_index, pos) in nodes.into_iter().enumerate() {
+				let Some(pos) = pos else {
+					continue;
+				};
+				painter.circle_filled(pos, 4.0, color);
+				painter.text(
+					pos + egui::vec2(6.0, -6.0),
+					egui::Align2::LEFT_BOTTOM,
+					format!("{}.{}", sequence.id, node_index),
+					egui::FontId::proportional(12.0),
+					color,
+				);
+			}
+		}
+	}
+	
+	/// Line from each moved entity's position in this level to a dot at its position in the compared level.
+	fn diff_moves(&self, ctx: &egui::Context, window_size: PhysicalSize<u32>) {
+		let Some(level_diff) = self.level_diff.as_ref().filter(|_| self.show_diff_moves) else {
+			return;
+		};
+		let project = self.screen_projection(ctx, window_size);
+		let painter = ctx.layer_painter(egui::LayerId::background());
+		for entity_move in &level_diff.entities_moved {
+			let [old, new] = entity_move.pos.map(|pos| project(pos.as_vec3()));
+			if let (Some(old), Some(new)) = (old, new) {
+				painter.line_segment([old, new], egui::Stroke::new(2.0, DIFF_MOVE_COLOR));
+			}
+			if let Some(new) = new {
+				painter.circle_filled(new, 4.0, DIFF_MOVE_COLOR);
+			}
+			if let Some(old) = old {
+				painter.text(
+					old + egui::vec2(6.0, -6.0),
+					egui::Align2::LEFT_BOTTOM,
+					format!("Entity {}", entity_move.old_index),
+					egui::FontId::proportional(12.0),
+					DIFF_MOVE_COLOR,
+				);
+			}
+		}
+	}
+	
+	/// Polyline through the cinematic camera positions, with a line to the look target every second.
+	fn cinematic_path(&self, ctx: &egui::Context, window_size: PhysicalSize<u32>) {
+		if !self.show_cinematic_path || self.cinematic_playback.is_some() {
+			return;
+		}
+		let project = self.screen_projection(ctx, window_size);
+		let painter = ctx.layer_painter(egui::LayerId::background());
+		let positions = self.cinematic_frames.iter().map(|frame| project(frame.pos)).collect::<Vec<_>>();
+		for pair in positions.windows(2) {
+			if let [Some(a), Some(b)] = *pair {
+				painter.line_segment([a, b], egui::Stroke::new(2.0, CINEMATIC_PATH_COLOR));
+			}
+		}
+		for (frame, &pos) in self.cinematic_frames.iter().zip(&positions).step_by(CINEMATIC_TARGET_INTERVAL) {
+			if let (Some(pos), Some(target)) = (pos, project(frame.target)) {
+				painter.line_segment([pos, target], egui::Stroke::new(1.0, CINEMATIC_TARGET_COLOR));
+				painter.circle_filled(pos, 3.0, CINEMATIC_PATH_COLOR);
+			}
+		}
+	}
+	
+	/// Reconstructed TR1 demo path, and the ghost with a line along its heading during playback.
+	fn demo_path(&self, ctx: &egui::Context, window_size: PhysicalSize<u32>) {
+		let ghost = self.demo_playback.as_ref().and_then(|playback| self.demo_frames.get(playback.frame_index()));
+		if !self.show_demo_path && ghost.is_none() {
+			return;
+		}
+		let project = self.screen_projection(ctx, window_size);
+		let painter = ctx.layer_painter(egui::LayerId::background());
+		if self.show_demo_path {
+			let positions = self.demo_frames.iter().map(|frame| project(frame.pos)).collect::<Vec<_>>();
+			for pair in positions.windows(2) {
+				if let [Some(a), Some(b)] = *pair {
+					painter.line_segment([a, b], egui::Stroke::new(2.0, DEMO_PATH_COLOR));
+				}
+			}
+			if let Some(&Some(start)) = positions.first() {
+				painter.circle_filled(start, 4.0, DEMO_PATH_COLOR);
+			}
+		}
+		if let Some(frame) = ghost {
+			let heading = project(frame.pos + frame.forward() * DEMO_HEADING_LENGTH);
+			if let (Some(pos), Some(heading)) = (project(frame.pos), heading) {
+				painter.line_segment([pos, heading], egui::Stroke::new(2.0, DEMO_GHOST_COLOR));
+				painter.circle_filled(pos, DEMO_GHOST_RADIUS, DEMO_GHOST_COLOR);
+			}
+		}
+	}
+	
+	/// Wireframe octahedron at each entity with an unknown model id in the visible rooms, labeled with the id.
+	fn placeholders(&self, ctx: &egui::Context, window_size: PhysicalSize<u32>) {
+		if !self.render.show_entity_meshes || self.issues.unknown_model_entities.is_empty() {
+			return;
+		}
+		let project = self.screen_projection(ctx, window_size);
+		let painter = ctx.layer_painter(egui::LayerId::background());
+		let visible_room_indices = self.render.visible_room_indices();
+		let selected_entity_index = self.selected_entity_index();
+		for unknown in &self.issues.unknown_model_entities {
+			if !visible_room_indices.contains(&(unknown.room_index as usize)) {
+				continue;
+			}
+			let [px, nx, py, ny, pz, nz] = [Vec3::X, -Vec3::X, Vec3::Y, -Vec3::Y, Vec3::Z, -Vec3::Z]
+				.map(|offset| project(unknown.pos + offset * PLACEHOLDER_SIZE));
+			let width = if selected_entity_index == Some(unknown.entity_index) { 3.0 } else { 1.5 };
+			for [a, b] in [
+				[px, pz], [pz, nx], [nx, nz], [nz, px],
+				[py, px], [py, pz], [py, nx], [py, nz],
+				[ny, px], [ny, pz], [ny, nx], [ny, nz],
+			] {
+				if let (Some(a), Some(b)) = (a, b) {
+					painter.line_segment([a, b], egui::Stroke::new(width, PLACEHOLDER_COLOR));
+				}
+			}
+			if let Some(pos) = project(unknown.pos + Vec3::NEG_Y * PLACEHOLDER_SIZE) {
+				painter.text(
+					pos,
+					egui::Align2::CENTER_BOTTOM,
+					format!("model {}", unknown.model_id),
+					egui::FontId::proportional(12.0),
+					PLACEHOLDER_COLOR,
+				);
+			}
+		}
+	}
+	
+	/// Draw lines between parent and child mesh pivots of the selected entity, and the bounds of the
+	/// hovered mesh.
+	fn skeleton_overlay(&self, ctx: &egui::Context, window_size: PhysicalSize<u32>) {
+		let Some((_, _, entity_render, skeleton)) = self.selected_entity_mesh() else {
+			return;
+		};
+		let project = self.screen_projection(ctx, window_size);
+		let painter = ctx.layer_painter(egui::LayerId::background());
+		let transforms = skeleton
+			.transforms
+			.iter()
+			.map(|&transform| entity_render.transform * transform)
+			.collect::<Vec<_>>();
+		let pivots = transforms.iter().map(|t| project(t.w_axis.xyz())).collect::<Vec<_>>();
+		for (mesh_index, &parent) in skeleton.parents.iter().enumerate() {
+			if let (Some(parent), Some(child)) = (parent.and_then(|p| pivots[p]), pivots[mesh_index]) {
+				painter.line_segment([parent, child], egui::Stroke::new(2.0, egui::Color32::YELLOW));
+			}
+		}
+		for pivot in pivots.iter().flatten() {
+			painter.circle_filled(*pivot, 3.0, egui::Color32::YELLOW);
+		}
+		if let Some(mesh_index) = self.hovered_mesh_index {
+			let MinMax { min, max } = skeleton.bounds[mesh_index];
+			let corners = (0..8).map(|corner| {
+				let pos = Vec3::select(
+					glam::BVec3::new(corner & 1 != 0, corner & 2 != 0, corner & 4 != 0),
+					max,
+					min,
+				);
+				project(transforms[mesh_index].transform_point3(pos))
+			}).collect::<Vec<_>>();
+			for corner in 0..8 {
+				for bit in [1, 2, 4] {
+					if corner & bit == 0 {
+						if let (Some(a), Some(b)) = (corners[corner], corners[corner | bit]) {
+							painter.line_segment([a, b], egui::Stroke::new(1.5, egui::Color32::LIGHT_BLUE));
+						}
+					}
+				}
+			}
+		}
+	}
+	
+	/// Top-down view of room bounds and the camera in the bottom right corner. Click to move the camera
+	/// to that XZ position.
+	fn mini_map(&mut self, ctx: &egui::Context) {
+		let MinMax { min, max } = self.map_bounds;
+		let extent = (max - min).max(Vec2::ONE);
+		let scale = ctx.screen_rect().size().min_elem() * MINI_MAP_SCALE / extent.max_element();
+		let visible_room_indices = self.render.visible_room_indices();
+		egui::Area::new(egui::Id::new("mini_map"))
+			.anchor(egui::Align2::RIGHT_BOTTOM, [-MINI_MAP_MARGIN; 2])
+			.order(egui::Order::Foreground)
+			.show(ctx, |ui| {
+				let size = egui::vec2(extent.x, extent.y) * scale;
+				let (response, painter) = ui.allocate_painter(size, egui::Sense::click());
+				let rect = response.rect;
+				//seen from above, -X is right and +Z is down
+				let to_screen = |pos: Vec2| rect.min + egui::vec2(max.x - pos.x, pos.y - min.y) * scale;
+				painter.rect_filled(rect, 0.0, egui::Color32::from_black_alpha(160));
+				for (render_room_index, render_room) in self.render.render_rooms.iter().enumerate() {
+					let MinMax { min, max } = render_room.bounds;
+					let room_rect = egui::Rect::from_two_pos(to_screen(min), to_screen(max));
+					if visible_room_indices.contains(&render_room_index) {
+						painter.rect_filled(room_rect, 0.0, egui::Color32::from_gray(110));
+					} else {
+						painter.rect_stroke(room_rect, 0.0, egui::Stroke::new(1.0, egui::Color32::from_gray(80)));
+					}
+				}
+				let camera = to_screen(self.render.pos.xz());
+				let half_fov = self.projection.fov / 2.0;
+				let wedge = [-half_fov, half_fov].map(|angle| {
+					camera + egui::Vec2::angled(self.render.yaw - FRAC_PI_2 + angle) * MINI_MAP_WEDGE_LEN
+				});
+				painter.add(egui::Shape::convex_polygon(
+					vec![camera, wedge[0], wedge[1]],
+					egui::Color32::from_rgba_unmultiplied(255, 0, 0, 96),
+					egui::Stroke::NONE,
+				));
+				painter.circle_filled(camera, 3.0, egui::Color32::RED);
+				if let (true, Some(click_pos)) = (response.clicked(), response.interact_pointer_pos()) {
+					let offset = (click_pos - rect.min) / scale;
+					self.render.pos.x = max.x - offset.x;
+					self.render.pos.z = min.y + offset.y;
+				}
+			});
+	}
+}
+
+fn mesh_node_tree(
+	ui: &mut egui::Ui, skeleton: &ModelSkeleton, mesh_offsets: &[u32], mesh_index: usize,
+	selected_mesh_index: usize, hovered_mesh_index: &mut Option<usize>, unit_mode: UnitMode,
+) {
+	let text = egui::RichText::new(format!("Mesh {}", mesh_index));
+	let text = if mesh_index == selected_mesh_index { text.strong() } else { text };
+	let response = egui::CollapsingHeader::new(text).id_source(mesh_index).default_open(true).show(ui, |ui| {
+		let mesh_offset = mesh_offsets[skeleton.mesh_offset_index as usize + mesh_index];
+		ui.label(format!("Mesh offset: {}", mesh_offset));
+		//root mesh has no node
+		if let Some(mesh_node) = mesh_index.checked_sub(1).map(|index| &skeleton.mesh_nodes[index]) {
+			ui.label(format!("Node offset: {}", unit_mode.format_vec3(mesh_node.offset.as_vec3())));
+			ui.label(format!("Push: {}, pop: {}", mesh_node.flags.push(), mesh_node.flags.pop()));
+		}
+		for (child_index, &parent) in skeleton.parents.iter().enumerate() {
+			if parent == Some(mesh_index) {
+				mesh_node_tree(
+					ui,
+					skeleton,
+					mesh_offsets,
+					child_index,
+					selected_mesh_index,
+					hovered_mesh_index,
+					unit_mode,
+				);
+			}
+		}
+	});
+	if response.header_response.hovered() {
+		*hovered_mesh_index = Some(mesh_index);
+	}
+}
+
+/// Room center, or room static mesh or entity position.
+fn owner_pos<L: Level>(level: &L, render_rooms: &[RenderRoom], owner: ObjectOwner) -> Option<Vec3> {
+	let pos = match owner {
+		ObjectOwner::Room(room_index) => render_rooms.get(room_index as usize)?.center,
+		ObjectOwner::RoomStaticMesh { room_index, room_static_mesh_index } => {
+			let room = level.rooms().get(room_index as usize)?;
+			room.room_static_meshes().get(room_static_mesh_index as usize)?.pos().as_vec3()
+		},
+		ObjectOwner::Entity(entity_index) => level.entities().get(entity_index as usize)?.pos().as_vec3(),
+	};
+	Some(pos)
+}
+
+/// Replace the middle of `text` with "…" if longer than `max_chars`, keeping both ends readable.
+fn elide_middle(text: &str, max_chars: usize) -> Cow<'_, str> {
+	let len = text.chars().count();
+	if len <= max_chars {
+		return Cow::Borrowed(text);
+	}
+	let keep = max_chars.saturating_sub(1);
+	let head = text.chars().take(keep - keep / 2);
+	let tail = text.chars().skip(len - keep / 2);
+	Cow::Owned(head.chain(['…']).chain(tail).collect())
+}
+
+fn draw_window<R, F>(
+	ctx: &egui::Context, title: &str, resizable: bool, open: &mut bool, contents: F,
+) -> Option<R> where F: FnOnce(&mut egui::Ui) -> R {
+	egui::Window::new(title).resizable(resizable).open(open).show(ctx, contents)?.inner
+}
+
+fn selected_room_text(render_room_index: Option<usize>) -> String {
+	match render_room_index {
+		Some(render_room_index) => format!("Room {}", render_room_index),
+		None => "All".to_string(),
+	}
+}
+
+struct TexturesCallback {
+	queue: Arc<Queue>,
+	viewer_shared: Arc<ViewerShared>,
+	loaded_level_shared: Arc<LoadedLevelShared>,
+	view_shared: Arc<ViewShared>,
+	textures_tab: TexturesTab,
+	num_images: u32,
+}
+
+impl egui_wgpu::CallbackTrait for TexturesCallback {
+	fn paint<'a>(
+		&'a self, info: egui::PaintCallbackInfo, rpass: &mut wgpu::RenderPass<'a>,
+		_: &'a egui_wgpu::CallbackResources,
+	) {
+		let cp = info.clip_rect_in_pixels();
+		let vp = info.viewport_in_pixels();
+		let viewport = Viewport {
+			clip: [cp.left_px, cp.top_px, cp.width_px, cp.height_px],
+			view: [vp.left_px, vp.top_px, vp.width_px, vp.height_px],
+			pixels_per_point: info.pixels_per_point,
+			num_images: self.num_images,
+		};
+		self.queue.write_buffer(&self.view_shared.view_buffers.viewport, 0, viewport.as_bytes());
+		let vs = &self.viewer_shared;
+		let ll = &self.loaded_level_shared;
+		rpass.set_vertex_buffer(0, vs.render.face_vertex_index_buffer.slice(..));
+		let (flat_pl, bind_group) = match self.textures_tab {
+			TexturesTab::Textures(TextureMode::Palette) => (&vs.flat_palette_pl, &ll.palette_24bit_bg),
+			TexturesTab::Textures(TextureMode::Bit16) => (&vs.flat_16bit_pl, &ll.texture_16bit_bg),
+			TexturesTab::Textures(TextureMode::Bit32) => (&vs.flat_32bit_pl, &ll.texture_32bit_bg),
+			TexturesTab::Misc => (&vs.flat_32bit_pl, &self.view_shared.misc_images_bg),
+		};
+		let bind_group = bind_group.as_ref().unwrap();//texture can't be selected unless it exists
+		rpass.set_pipeline(flat_pl);
+		rpass.set_bind_group(0, bind_group, &[]);
+		rpass.draw(0..NUM_QUAD_VERTICES, 0..1);
+	}
+}
+
+/// Images of a Textures window tab stacked vertically. The tab must be available for the level.
+fn textures_tab_rgba(level: &dyn LevelDyn, textures_tab: TexturesTab) -> Vec<u8> {
+	match textures_tab {
+		TexturesTab::Textures(TextureMode::Palette) => {
+			let palette = level.palette_24bit().unwrap();
+			let atlases = level.atlases_palette().unwrap();
+			rgba::palette_images_to_rgba(palette, atlases)
+		},
+		TexturesTab::Textures(TextureMode::Bit16) => rgba::bit16_images_to_rgba(level.atlases_16bit().unwrap()),
+		TexturesTab::Textures(TextureMode::Bit32) => rgba::bit32_images_to_rgba(level.atlases_32bit().unwrap()),
+		TexturesTab::Misc => rgba::bit32_images_to_rgba(level.misc_images().unwrap()),
+	}
+}
+
+impl TrTool {
+	/// Parse and upload on a worker thread so the window stays responsive. `Device` and `Queue` are
+	/// thread-safe, and queued writes are only submitted with the main thread's next frame.
+	fn load_level(&mut self, path: PathBuf, version: Version, prompted_magic: Option<u32>) {
+		let device = self.device.clone();
+		let queue = self.queue.clone();
+		let shared = self.shared.clone();
+		let window_size = self.window_size;
+		let large_file_mode = self.settings.large_file_mode;
+		let thread_path = path.clone();
+		let load = log::begin_load();
+		let handle = thread::spawn(move || {
+			log::with_load(load, || {
+				let mut loaded_level =
+					load_level_view(&device, &queue, window_size, &shared, &thread_path, version, large_file_mode)?;
+				loaded_level.sounds.find_sfx(&thread_path);
+				loaded_level.level_key = overrides::level_key(&thread_path)
+					.map_err(|e| {
+						console::log_warn!("failed to hash {} for saved render options: {}", thread_path.display(), e)
+					})
+					.ok();
+				Ok(loaded_level)
+			})
+		});
+		//a previous load still running is detached and its result discarded
+		self.level_load = Some(LevelLoad { path, window_size, handle, prompted_magic });
+	}
+	
+	fn poll_level_load(&mut self) {
+		let Some(level_load) = self.level_load.take() else {
+			return;
+		};
+		if !level_load.handle.is_finished() {
+			self.level_load = Some(level_load);
+			return;
+		}
+		let LevelLoad { path, window_size, handle, prompted_magic } = level_load;
+		let result = handle
+			.join()
+			.unwrap_or_else(|_| Err(Error::other("level load panicked")));
+		match result {
+			Ok(mut loaded_level) => {
+				if let Some(magic) = prompted_magic {
+					let version = loaded_level.level.version();
+					self.settings.learn_version(magic, version::level_extension(&path), version);
+					self.save_settings();
+				}
+				if window_size != self.window_size {
+					loaded_level.resize(&self.device, &self.queue, self.window_size);
+				}
+				self.store_render_overrides();
+				if let Some(previous) = &self.loaded_level {
+					loaded_level.copy_render_options(previous);
+				}
+				let overrides = loaded_level.level_key.as_deref().and_then(|key| self.settings.level_overrides(key));
+				loaded_level.apply_render_overrides(overrides);
+				loaded_level.update_render_flags(&self.queue);
+				loaded_level.update_background(&self.queue);
+				loaded_level.update_perspective_transform(&self.queue, self.window_size);
+				if let Some(script) = script::find_script(&path) {
+					self.script = Some(script);
+				}
+				self.level_path = Some(path);
+				self.update_title();
+				self.loaded_level = Some(loaded_level);
+				self.update_item_names();
+			},
+			Err(e) => self.load_failed(&path, e),
+		}
+	}
+	
+	/// Save render options changed on the open level, applied the next time it's loaded.
+	fn store_render_overrides(&mut self) {
+		let Some(loaded_level) = &mut self.loaded_level else {
+			return;
+		};
+		let overrides = loaded_level.render_overrides();
+		let Some(key) = loaded_level.level_key.clone() else {
+			return;
+		};
+		if self.settings.set_level_overrides(&key, overrides) {
+			self.save_settings();
+		}
+	}
+	
+	fn save_settings(&mut self) {
+		if let Err(e) = self.settings.save() {
+			self.error = Some(format!("Failed to save settings: {}", e));
+		}
+	}
+	
+	/// Show the error, and move on to the next queued level if `path` is the current one.
+	fn load_failed(&mut self, path: &PathBuf, e: Error) {
+		let file_name = path.file_name().map(|f| f.to_string_lossy()).unwrap_or_default();
+		self.error = Some(format!("{}: {}", file_name, e));
+		let next = self
+			.playlist
+			.as_mut()
+			.filter(|playlist| playlist.current() == path)
+			.and_then(|playlist| playlist.skip().cloned());
+		if let Some(next) = next {
+			self.open_level(next);
+		}
+	}
+	
+	/// Open the previous (-1) or next (1) queued level.
+	fn step_playlist(&mut self, step: isize) {
+		if let Some(path) = self.playlist.as_mut().and_then(|playlist| playlist.step(step).cloned()) {
+			self.open_level(path);
+		}
+	}
+	
+	/// "TR Tool - Great Wall – 2/3 – WALL.TR2", without the level name if there is no script entry, and without
+	/// the queue position unless the level is the current queued one.
+	fn update_title(&self) {
+		let Some(path) = &self.level_path else {
+			return;
+		};
+		let Some(file_name) = path.file_name().map(|f| f.to_string_lossy()) else {
+			return;
+		};
+		let mut file_name = elide_middle(&file_name, MAX_TITLE_FILE_NAME_CHARS);
+		if let Some(playlist) = self.playlist.as_ref().filter(|playlist| playlist.current() == path) {
+			file_name = Cow::Owned(format!("{} – {}", playlist.position(), file_name));
+		}
+		let title = match self.script.as_ref().and_then(|script| script.level(path)) {
+			Some(script_level) => format!("{} - {} – {}", WINDOW_TITLE, script_level.name, file_name),
+			None => format!("{} - {}", WINDOW_TITLE, file_name),
+		};
+		self.window.set_title(&title);
+	}
+	
+	/// Name the open level's items from its script entry, or clear the names if there is none.
+	fn update_item_names(&mut self) {
+		let (Some(path), Some(loaded_level)) = (&self.level_path, &mut self.loaded_level) else {
+			return;
+		};
+		let script_level = self.script.as_ref().and_then(|script| script.level(path));
+		let version = loaded_level.level.as_dyn().version();
+		loaded_level.item_names =
+			script_level.map_or_else(HashMap::new, |script_level| script_level.item_names_by_model(version));
+	}
+	
+	/// Load level if version is recognized or was picked before for such files, otherwise prompt for version.
+	fn open_level(&mut self, path: PathBuf) {
+		match detect_version(&path, &self.settings.learned_versions) {
+			Ok((_, Some(version))) => self.load_level(path, version, None),
+			Ok((magic, None)) => self.version_prompt = Some(VersionPrompt { path, magic }),
+			Err(e) => self.load_failed(&path, e),
+		}
+	}
+	
+	fn version_prompt(&mut self, ctx: &egui::Context) {
+		let Some(version_prompt) = self.version_prompt.take() else {
+			return;
+		};
+		let mut open = true;
+		let mut selected = None;
+		draw_window(ctx, "Unknown File Type", false, &mut open, |ui| {
+			ui.label(format!("Version: 0x{:X}", version_prompt.magic));
+			ui.label("Load as (remembered for files with this version and extension):");
+			ui.horizontal(|ui| {
+				for version in Version::ALL {
+					if ui.button(version.label()).clicked() {
+						selected = Some(version);
+					}
+				}
+			});
+		});
+		match (selected, open) {
+			(Some(version), _) => self.load_level(version_prompt.path, version, Some(version_prompt.magic)),
+			(None, true) => self.version_prompt = Some(version_prompt),
+			(None, false) => {},
+		}
+	}
+	
+	/// Render the level view at window resolution and copy it to the clipboard.
+	fn copy_screenshot(&mut self) {
+		let Some(loaded_level) = &self.loaded_level else {
+			return;
+		};
+		let image = snapshot::render_image(
+			&self.device,
+			&self.queue,
+			&self.shared.render,
+			&loaded_level.render,
+			self.window_size,
+		);
+		let image_data = arboard::ImageData {
+			width: image.width() as usize,
+			height: image.height() as usize,
+			bytes: image.into_raw().into(),
+		};
+		let clipboard = match self.clipboard.take() {
+			Some(clipboard) => Ok(clipboard),
+			None => arboard::Clipboard::new(),
+		};
+		let result = clipboard.and_then(|mut clipboard| {
+			let result = clipboard.set_image(image_data);
+			self.clipboard = Some(clipboard);
+			result
+		});
+		if let Err(e) = result {
+			self.error = Some(format!("Failed to copy screenshot: {}", e));
+		}
+	}
+	
+	/// Start reading back the frame just rendered if the turntable is saving it, without waiting, and pass
+	/// finished frames to its writer.
+	fn capture_turntable_frame(&mut self) {
+		let Some(loaded_level) = &mut self.loaded_level else {
+			return;
+		};
+		let Some(frame) = loaded_level.turntable.as_mut().map(Turntable::frame_to_capture) else {
+			return;
+		};
+		if let Some(frame) = frame {
+			let mapped = Arc::new(AtomicBool::new(false));
+			let set_mapped = mapped.clone();
+			let image = snapshot::start_render_image(
+				&self.device,
+				&self.queue,
+				&self.shared.render,
+				&loaded_level.render,
+				self.window_size,
+				move || set_mapped.store(true, Ordering::Release),
+			);
+			//unwrap: checked above
+			loaded_level.turntable.as_mut().unwrap().queue_readback(frame, image, mapped);
+		}
+		self.device.poll(Maintain::Poll);
+		//unwrap: checked above
+		let turntable = loaded_level.turntable.as_mut().unwrap();
+		turntable.poll_readbacks();
+		if turntable.capture_done() {
+			if let Err(e) = loaded_level.stop_turntable() {
+				self.error = Some(format!("Failed to save turntable frames: {}", e));
+			}
+		}
+	}
+}
+
+impl Gui for TrTool {
+	fn resize(&mut self, window_size: winit::dpi::PhysicalSize<u32>) {
+		let window_size = window_size.into();
+		self.window_size = window_size;
+		if let Some(loaded_level) = &mut self.loaded_level {
+			loaded_level.resize(&self.device, &self.queue, window_size);
+		}
+	}
+	
+	fn modifiers(&mut self, modifers: ModifiersState) {
+		self.modifiers = modifers;
+	}
+	
+	fn key(
+		&mut self, target: &EventLoopWindowTarget<()>, key_code: KeyCode, state: ElementState, repeat: bool,
+	) {
+		if let Some(loaded_level) = &mut self.loaded_level {
+			loaded_level.key_states.set(key_code, state.is_pressed());
+		}
+		match (self.modifiers, state, key_code, repeat, &mut self.loaded_level) {
+			(_, ElementState::Pressed, KeyCode::Escape, false, _) => target.exit(),
+			(_, ElementState::Pressed, KeyCode::KeyP, _, _) => self.print = true,
+			(ModifiersState::CONTROL, ElementState::Pressed, KeyCode::PageUp, false, _) => {
+				self.step_playlist(-1);
+			},
+			(ModifiersState::CONTROL, ElementState::Pressed, KeyCode::PageDown, false, _) => {
+				self.step_playlist(1);
+			},
+			(ModifiersState::CONTROL, ElementState::Pressed, KeyCode::KeyO, false, _) => {
+				if let Some(loaded_level) = &mut self.loaded_level {
+					loaded_level.set_mouse_control(&self.window, false);
+				}
+				self.file_dialog.select_level();
+			},
+			(_, ElementState::Pressed, KeyCode::KeyR, false, Some(_)) => {
+				self.show_render_options_window ^= true;
+			},
+			(_, ElementState::Pressed, KeyCode::KeyT, false, Some(_)) => self.show_textures_window ^= true,
+			(_, ElementState::Pressed, KeyCode::KeyI, false, Some(_)) => {
+				self.show_level_issues_window ^= true;
+			},
+			(_, ElementState::Pressed, KeyCode::KeyN, false, Some(_)) => {
+				self.show_mesh_nodes_window ^= true;
+			},
+			(_, ElementState::Pressed, KeyCode::KeyM, false, Some(_)) => {
+				self.show_mesh_viewer_window ^= true;
+			},
+			(_, ElementState::Pressed, KeyCode::KeyH, false, Some(_)) => {
+				self.show_room_graph_window ^= true;
+			},
+			(_, ElementState::Pressed, KeyCode::KeyO, false, Some(_)) => {
+				self.show_turntable_window ^= true;
+			},
+			(_, ElementState::Pressed, KeyCode::KeyU, false, Some(_)) => {
+				self.show_sounds_window ^= true;
+			},
+			(_, ElementState::Pressed, KeyCode::Tab, false, Some(_)) => self.show_mini_map ^= true,
+			(_, ElementState::Pressed, KeyCode::KeyG, false, Some(loaded_level)) => {
+				let toggled = loaded_level.toggle_walk();
+				if !toggled {
+					self.error = Some("No floor under the camera to walk on".to_string());
+				}
+			},
+			(_, ElementState::Pressed, KeyCode::BracketLeft, _, Some(loaded_level)) => {
+				loaded_level.step_face_or_room(-1);
+			},
+			(_, ElementState::Pressed, KeyCode::BracketRight, _, Some(loaded_level)) => {
+				loaded_level.step_face_or_room(1);
+			},
+			(_, ElementState::Pressed, KeyCode::F3, false, _) => self.show_stats ^= true,
+			(_, ElementState::Pressed, KeyCode::KeyL, false, _) => self.show_log_window ^= true,
+			(_, ElementState::Pressed, KeyCode::F12, false, Some(_)) => self.copy_screenshot(),
+			(_, ElementState::Pressed, KeyCode::F9, false, Some(loaded_level)) => {
+				let out_dir = Path::new(BUFFER_DUMP_DIR);
+				match buffer_dump::dump_buffers(&self.device, &self.queue, &loaded_level.render, out_dir) {
+					Ok(()) => console::info!("buffers dumped to {}", BUFFER_DUMP_DIR),
+					Err(e) => self.error = Some(format!("Failed to dump buffers: {}", e)),
+				}
+			},
+			(_, ElementState::Pressed, KeyCode::Space, false, Some(loaded_level)) => {
+				loaded_level.toggle_animations_paused();
+			},
+			(_, ElementState::Pressed, KeyCode::Period, _, Some(loaded_level)) => loaded_level.step_animations(),
+			(ModifiersState::CONTROL, ElementState::Pressed, KeyCode::KeyF, false, Some(_)) => {
+				self.show_find_entity_window ^= true;
+			},
+			(_, ElementState::Pressed, KeyCode::KeyF, false, Some(loaded_level)) => {
+				loaded_level.fit_level(self.window_size);
+				loaded_level.update_perspective_transform(&self.queue, self.window_size);
+			},
+			_ => {},
+		}
+	}
+	
+	fn mouse_button(&mut self, button: MouseButton, state: ElementState) {
+		if let Some(loaded_level) = &mut self.loaded_level {
+			match (state, button) {
+				(ElementState::Pressed, MouseButton::Right) => {
+					if self.file_dialog.is_closed() {
+						loaded_level.locked_mouse_pos = loaded_level.mouse_pos;
+						loaded_level.set_mouse_control(&self.window, !loaded_level.mouse_control);
+					}
+				},
+				(ElementState::Pressed, MouseButton::Left) => {
+					let pos = loaded_level.mouse_pos.cast::<u32>();
+					loaded_level.picks.push(&self.device, &self.queue, &loaded_level.interact_texture, pos);
+				},
+				_ => {},
+			}
+		}
+	}
+	
+	fn mouse_motion(&mut self, delta: DVec2) {
+		if let Some(loaded_level) = &mut self.loaded_level {
+			if loaded_level.mouse_control {
+				loaded_level.render.yaw += delta.x as f32 / 150.0;
+				let pitch = (loaded_level.render.pitch + delta.y as f32 / 150.0).clamp(-FRAC_PI_2, FRAC_PI_2);
+				loaded_level.render.pitch = pitch;
+			}
+		}
+	}
+	
+	fn cursor_moved(&mut self, pos: PhysicalPosition<f64>) {
+		if let Some(loaded_level) = &mut self.loaded_level {
+			loaded_level.mouse_pos = pos;
+			if loaded_level.mouse_control {
+				self.window.set_cursor_position(loaded_level.locked_mouse_pos).expect("set cursor pos");
+			}
+		}
+	}
+	
+	fn mouse_wheel(&mut self, _: MouseScrollDelta) {}
+	
+	fn render(
+		&mut self, encoder: &mut CommandEncoder, color_view: &TextureView, delta_time: Duration,
+		last_render_time: Duration,
+	) {
+		if let Some(loaded_level) = &mut self.loaded_level {
+			loaded_level.frame_update(&self.device, &self.queue, self.window_size, delta_time);
+			loaded_level.draw_stats = render_level(encoder, color_view, &self.shared.render, &loaded_level.render);
+		}
+		self.capture_turntable_frame();
+		self.frame_times.push(delta_time);
+		if self.print {
+			console::info!("render time: {}us", last_render_time.as_micros());
+		}
+	}
+	
+	fn gui(&mut self, ctx: &egui::Context) {
+		self.file_dialog.update(ctx);
+		if let Some(path) = self.file_dialog.get_level_path() {
+			self.open_level(path);
+		}
+		self.version_prompt(ctx);
+		self.poll_level_load();
+		let mut playlist_step = None;
+		let mut script_loaded = false;
+		match &mut self.loaded_level {
+			None => {
+				egui::panel::CentralPanel::default().show(ctx, |ui| {
+					ui.centered_and_justified(|ui| {
+						if let Some(level_load) = &self.level_load {
+							level_load.show(ui);
+						} else if ui.label("Ctrl+O or click to open file").clicked() {
+							self.file_dialog.select_level();
+						}
+					});
+				});
+			},
+			Some(loaded_level) => {
+				egui::TopBottomPanel::top("menu").show(ctx, |ui| {
+					egui::menu::bar(ui, |ui| {
+						ui.menu_button("File", |ui| {
+							if ui.button("Open").clicked() {
+								self.file_dialog.select_level();
+								ui.close_menu();
+							}
+							if ui.button("Load script…").clicked() {
+								self.file_dialog.select_script();
+								ui.close_menu();
+							}
+							if ui.button("Compare with…").clicked() {
+								self.file_dialog.select_comparison();
+								ui.close_menu();
+							}
+							if ui.button("Export metadata JSON").clicked() {
+								self.file_dialog.save_metadata();
+								ui.close_menu();
+							}
+							if ui.button("Export visible geometry OBJ").clicked() {
+								self.file_dialog.save_visible_obj();
+								ui.close_menu();
+							}
+							if ui.button("Load camera path…").clicked() {
+								self.file_dialog.select_camera_path();
+								ui.close_menu();
+							}
+							if ui.button("Save camera path…").clicked() {
+								self.file_dialog.save_camera_path();
+								ui.close_menu();
+							}
+							ui.menu_button("Export settings", |ui| self.export_settings.show(ui));
+							ui.menu_button("Learned versions", |ui| {
+								if self.settings.show_learned_versions(ui) {
+									if let Err(e) = self.settings.save() {
+										self.error = Some(format!("Failed to save settings: {}", e));
+									}
+								}
+							});
+							ui.menu_button("Large files", |ui| {
+								for read_mode in ReadMode::ALL {
+									let label = read_mode.label();
+									if ui.radio_value(&mut self.settings.large_file_mode, read_mode, label).changed() {
+										if let Err(e) = self.settings.save() {
+											self.error = Some(format!("Failed to save settings: {}", e));
+										}
+									}
+								}
+							});
+						});
+						if let Some(playlist) = &self.playlist {
+							if ui.add_enabled(playlist.has_step(-1), egui::Button::new("◀")).clicked() {
+								playlist_step = Some(-1);
+							}
+							ui.label(playlist.position());
+							if ui.add_enabled(playlist.has_step(1), egui::Button::new("▶")).clicked() {
+								playlist_step = Some(1);
+							}
+						}
+						if let Some(level_load) = &self.level_load {
+							level_load.show(ui);
+						}
+					});
+				});
+				if let Some(path) = self.file_dialog.get_metadata_path() {
+					let result = dump::metadata_json(&loaded_level.level).and_then(|json| fs::write(path, json));
+					if let Err(e) = result {
+						self.error = Some(e.to_string());
+					}
+				}
+				if let Some(path) = self.file_dialog.get_comparison_path() {
+					//other level is dropped once diffed
+					let result = dump::read_level_store(&path)
+						.and_then(|other| compare::compare(&loaded_level.level, &other));
+					match result {
+						Ok(level_diff) => loaded_level.level_diff = Some(level_diff),
+						Err(e) => self.error = Some(e.to_string()),
+					}
+				}
+				if let Some(path) = self.file_dialog.get_script_path() {
+					match script::read_script(&path) {
+						Ok(script) => {
+							self.script = Some(script);
+							script_loaded = true;
+						},
+						Err(e) => self.error = Some(e.to_string()),
+					}
+				}
+				if let Some(path) = self.file_dialog.get_visible_obj_path() {
+					if let Err(e) = fs::write(path, loaded_level.visible_obj(&self.export_settings)) {
+						self.error = Some(e.to_string());
+					}
+				}
+				if let Some(path) = self.file_dialog.get_camera_path_to_load() {
+					match fs::read_to_string(path).and_then(|text| text.parse::<CameraPath>()) {
+						Ok(camera_path) => {
+							loaded_level.camera_path = camera_path;
+							loaded_level.camera_path_playback.time = 0.0;
+							loaded_level.camera_path_playback.playing = false;
+						},
+						Err(e) => self.error = Some(e.to_string()),
+					}
+				}
+				if let Some(path) = self.file_dialog.get_camera_path_to_save() {
+					if let Err(e) = fs::write(path, loaded_level.camera_path.to_string()) {
+						self.error = Some(e.to_string());
+					}
+				}
+				let projection = loaded_level.projection;
+				let render_flags = (
+					loaded_level.tint_degenerate_textures,
+					loaded_level.mesh_shine,
+					loaded_level.animate_water,
+					loaded_level.render.face_coloring,
+					loaded_level.shading,
+				);
+				let background = loaded_level.render.background;
+				let window_size = self.window_size;
+				let reset_overrides = draw_window(
+					ctx, "Render Options", false, &mut self.show_render_options_window,
+					|ui| loaded_level.render_options(ui, window_size),
+				);
+				if let (Some(true), Some(key)) = (reset_overrides, &loaded_level.level_key) {
+					if self.settings.set_level_overrides(key, serde_json::Map::new()) {
+						if let Err(e) = self.settings.save() {
+							self.error = Some(format!("Failed to save settings: {}", e));
+						}
+					}
+				}
+				if loaded_level.projection != projection {
+					loaded_level.update_perspective_transform(&self.queue, self.window_size);
+				}
+				if (
+					loaded_level.tint_degenerate_textures,
+					loaded_level.mesh_shine,
+					loaded_level.animate_water,
+					loaded_level.render.face_coloring,
+					loaded_level.shading,
+				) != render_flags {
+					loaded_level.update_render_flags(&self.queue);
+				}
+				if loaded_level.render.background != background {
+					loaded_level.update_background(&self.queue);
+				}
+				if loaded_level.selected.is_some() {
+					let mut open = true;
+					let mut subject = None;
+					draw_window(ctx, "Selection", false, &mut open, |ui| subject = loaded_level.selection(ui));
+					if !open {
+						loaded_level.selected = None;
+					}
+					if let Some(subject) = subject {
+						loaded_level.open_mesh_viewer(&self.device, &self.queue, &self.shared, subject);
+						self.show_mesh_viewer_window = true;
+					}
+				}
+				if let Some(level_diff) = &loaded_level.level_diff {
+					let mut open = true;
+					let show_moves = &mut loaded_level.show_diff_moves;
+					draw_window(ctx, "Level Comparison", true, &mut open, |ui| {
+						egui::ScrollArea::vertical().show(ui, |ui| level_diff.show(ui, show_moves));
+					});
+					if !open {
+						loaded_level.level_diff = None;
+					}
+				}
+				draw_window(ctx, "Level Issues", false, &mut self.show_level_issues_window, |ui| {
+					loaded_level.level_issues(ui)
+				});
+				draw_window(ctx, "Find Entity", true, &mut self.show_find_entity_window, |ui| {
+					loaded_level.find_entity(ui)
+				});
+				draw_window(ctx, "Mesh Nodes", true, &mut self.show_mesh_nodes_window, |ui| {
+					loaded_level.mesh_nodes(ui)
+				});
+				draw_window(ctx, "Mesh Viewer", false, &mut self.show_mesh_viewer_window, |ui| {
+					loaded_level.mesh_viewer(ui, &self.device, &self.queue, &self.shared)
+				});
+				draw_window(ctx, "Graph", true, &mut self.show_room_graph_window, |ui| loaded_level.room_graph(ui));
+				draw_window(ctx, "Turntable", false, &mut self.show_turntable_window, |ui| {
+					match loaded_level.turntable(ui) {
+						Ok(true) => self.file_dialog.select_frames_dir(),
+						Ok(false) => {},
+						Err(e) => self.error = Some(format!("Failed to save turntable frames: {}", e)),
+					}
+				});
+				if let Some(dir) = self.file_dialog.get_frames_dir() {
+					loaded_level.turntable_dir = Some(dir);
+				}
+				let sounds_action = draw_window(ctx, "Sounds", true, &mut self.show_sounds_window, |ui| {
+					loaded_level.sounds.show(ui)
+				}).flatten();
+				match sounds_action {
+					Some(SoundsAction::LoadSfx) => self.file_dialog.select_sfx(),
+					Some(SoundsAction::Export(sample)) => self.file_dialog.save_sample(sample),
+					None => {},
+				}
+				if let Some(path) = self.file_dialog.get_sfx_path() {
+					if let Err(e) = loaded_level.sounds.load_sfx(path) {
+						self.error = Some(format!("Failed to read SFX: {}", e));
+					}
+				}
+				if let Some((path, sample)) = self.file_dialog.get_sample_path() {
+					if let Some(Err(e)) = loaded_level.sounds.sample(sample).map(|wav| fs::write(path, wav)) {
+						self.error = Some(e.to_string());
+					}
+				}
+				if self.show_mesh_nodes_window {
+					loaded_level.skeleton_overlay(ctx, self.window_size);
+				} else {
+					loaded_level.hovered_mesh_index = None;
+				}
+				loaded_level.portal_labels(ctx, self.window_size);
+				loaded_level.sector_grid(ctx, self.window_size);
+				loaded_level.room_bounds(ctx, self.window_size);
+				loaded_level.lights(ctx, self.window_size);
+				loaded_level.footstep_materials(ctx, self.window_size);
+				loaded_level.flyby_paths(ctx, self.window_size);
+				loaded_level.cinematic_path(ctx, self.window_size);
+				loaded_level.demo_path(ctx, self.window_size);
+				loaded_level.diff_moves(ctx, self.window_size);
+				loaded_level.placeholders(ctx, self.window_size);
+				if self.show_mini_map {
+					loaded_level.mini_map(ctx);
+				}
+				draw_window(ctx, "Textures", true, &mut self.show_textures_window, |ui| {
+					let ll = &loaded_level.render.shared;
+					let bind_groups = [
+						&ll.palette_24bit_bg,
+						&ll.texture_16bit_bg,
+						&ll.texture_32bit_bg,
+						&loaded_level.view_shared.misc_images_bg,
+					];
+					if bind_groups.into_iter().filter(|bg| bg.is_some()).count() > 1 {
+						ui.horizontal(|ui| {
+							for (bg, tab) in [
+								(&ll.palette_24bit_bg, TexturesTab::Textures(TextureMode::Palette)),
+								(&ll.texture_16bit_bg, TexturesTab::Textures(TextureMode::Bit16)),
+								(&ll.texture_32bit_bg, TexturesTab::Textures(TextureMode::Bit32)),
+								(&loaded_level.view_shared.misc_images_bg, TexturesTab::Misc),
+							] {
+								if bg.is_some() {
+									ui.selectable_value(&mut loaded_level.textures_tab, tab, tab.label());
+								}
+							}
+						});
+					}
+					if ui.button("Save").clicked() {
+						self.file_dialog.save_texture(loaded_level.textures_tab);
+					}
+					ui.collapsing("Export", |ui| {
+						if loaded_level.texture_export(ui) {
+							self.file_dialog.select_export_dir();
+						}
+					});
+					if let TexturesTab::Textures(_) = loaded_level.textures_tab {
+						ui.collapsing("Atlas usage", |ui| loaded_level.atlas_usage(ui));
+					}
+					let (side, num_images) = loaded_level.textures_tab_extent(loaded_level.textures_tab);
+					ui.label(format!("{} images, {}×{}", num_images, side, side));
+					let object_textures = matches!(loaded_level.textures_tab, TexturesTab::Textures(_));
+					if object_textures {
+						match loaded_level.highlighted_object_texture {
+							Some(index) => {
+								ui.horizontal(|ui| {
+									ui.label(format!("Highlighting faces using object texture {}", index));
+									if ui.button("Clear").clicked() {
+										loaded_level.highlighted_object_texture = None;
+										loaded_level.update_highlight(&self.queue);
+									}
+								});
+							},
+							None => {
+								ui.label("Click a texture to highlight the faces using it");
+							},
+						}
+					}
+					ui.add_space(2.0);
+					let id: u8 = match loaded_level.textures_tab {
+						TexturesTab::Textures(_) => 0,
+						TexturesTab::Misc => 1,
+					};
+					let scroll_output = egui::ScrollArea::vertical().id_source(id).show(ui, |ui| {
+						let size = egui::vec2(side as f32, (side * num_images) as f32);
+						let (rect, response) = ui.allocate_exact_size(size, egui::Sense::click());
+						let textures_cb = TexturesCallback {
+							queue: self.queue.clone(),
+							viewer_shared: self.shared.clone(),
+							loaded_level_shared: loaded_level.render.shared.clone(),
+							view_shared: loaded_level.view_shared.clone(),
+							textures_tab: loaded_level.textures_tab,
+							num_images,
+						};
+						ui.painter().add(egui_wgpu::Callback::new_paint_callback(rect, textures_cb));
+						if !object_textures {
+							return;
+						}
+						if let Some(pos) = response.interact_pointer_pos().filter(|_| response.clicked()) {
+							let pixel = pos - rect.min;
+							loaded_level.pick_texture(&self.queue, UVec2::new(pixel.x as u32, pixel.y as u32));
+						}
+						if let Some(MinMax { min, max }) = loaded_level.highlighted_texture_rect() {
+							let [min, max] = [min, max].map(|p| rect.min + egui::vec2(p.x as f32, p.y as f32));
+							let stroke = egui::Stroke::new(1.0, egui::Color32::from_rgb(0, 255, 255));
+							ui.painter().rect_stroke(egui::Rect::from_min_max(min, max), 0.0, stroke);
+						}
+					});
+					let scroll_offset_bytes = scroll_output.state.offset.as_bytes();
+					let scroll_offset_buffer = &loaded_level.view_shared.view_buffers.scroll_offset;
+					self.queue.write_buffer(scroll_offset_buffer, 0, scroll_offset_bytes);
+				});
+				if let Some(out_dir) = self.file_dialog.get_export_dir() {
+					loaded_level.start_texture_export(out_dir);
+				}
+				if loaded_level.texture_export.as_ref().is_some_and(TextureExport::is_finished) {
+					//unwrap: checked above
+					match loaded_level.texture_export.take().unwrap().join() {
+						Ok(out_dir) => console::info!("textures exported to {}", out_dir.display()),
+						Err(e) => self.error = Some(format!("Failed to export textures: {}", e)),
+					}
+				}
+				if let Some((path, texture)) = self.file_dialog.get_texture_path() {
+					let rgba = textures_tab_rgba(loaded_level.level.as_dyn(), texture);
+					let (side, num_images) = loaded_level.textures_tab_extent(texture);
+					let result = image::save_buffer(path, &rgba, side, side * num_images, image::ColorType::Rgba8);
+					if let Err(e) = result {
+						self.error = Some(e.to_string());
+					}
+				}
+			}
+		}
+		if let Some(step) = playlist_step {
+			self.step_playlist(step);
+		}
+		if script_loaded {
+			self.update_title();
+			self.update_item_names();
+		}
+		let draw = self.loaded_level.as_ref().map(|loaded_level| {
+			(&loaded_level.draw_stats, &loaded_level.gpu_memory, loaded_level.render.texture_mode.label())
+		});
+		draw_window(ctx, "Stats", false, &mut self.show_stats, |ui| stats_window::show(ui, &self.frame_times, draw));
+		draw_window(ctx, "Log", true, &mut self.show_log_window, |ui| self.log_view.show(ui));
+		if let Some(error) = &self.error {
+			let mut show = true;
+			draw_window(ctx, "Error", false, &mut show, |ui| {
+				for line in error.lines() {
+					ui.label(elide_middle(line, MAX_ERROR_LINE_CHARS));
+				}
+			});
+			if !show {
+				self.error = None;
+			}
+		}
+		self.print = false;
+	}
+	
+	fn exiting(&mut self) {
+		self.store_render_overrides();
+	}
+}
+
+fn make_gui(
+	window: Arc<Window>, device: Arc<Device>, queue: Arc<Queue>, window_size: PhysicalSize<u32>,
+	level_paths: Vec<PathBuf>,
+) -> TrTool {
+	let shared = Arc::new(make_viewer_shared(&device));
+	let mut tr_tool = TrTool {
+		window,
+		device,
+		queue,
+		shared,
+		window_size,
+		modifiers: ModifiersState::empty(),
+		file_dialog: FileDialog::new(),
+		error: None,
+		print: false,
+		loaded_level: None,
+		level_path: None,
+		level_load: None,
+		playlist: None,
+		script: None,
+		clipboard: None,
+		export_settings: ExportSettings::default(),
+		settings: Settings::load(),
+		frame_times: FrameTimes::new(),
+		version_prompt: None,
+		show_render_options_window: true,
+		show_textures_window: false,
+		show_level_issues_window: false,
+		show_mesh_nodes_window: false,
+		show_mesh_viewer_window: false,
+		show_room_graph_window: false,
+		show_turntable_window: false,
+		show_sounds_window: false,
+		show_find_entity_window: false,
+		show_mini_map: true,
+		show_stats: false,
+		show_log_window: false,
+		log_view: LogView::default(),
+	};
+	let first = level_paths.first().cloned();
+	tr_tool.playlist = Playlist::new(level_paths);
+	if let Some(path) = first {
+		tr_tool.open_level(path);
+	}
+	tr_tool
+}
+
+/// Open the viewer window, queueing `level_paths` and opening the first. Returns when the window is closed.
+pub fn run_viewer(level_paths: Vec<PathBuf>) {
+	let window_icon_bytes = include_bytes!("../res/icon16.data");
+	let taskbar_icon_bytes = include_bytes!("../res/icon24.data");
+	let window_icon = Icon::from_rgba(window_icon_bytes.to_vec(), 16, 16).expect("window icon");
+	let taskbar_icon = Icon::from_rgba(taskbar_icon_bytes.to_vec(), 24, 24).expect("taskbar icon");
+	super::run(WINDOW_TITLE, window_icon, taskbar_icon, |window, device, queue, window_size| {
+		make_gui(window, device, queue, window_size.into(), level_paths)
+	});
+}
+
+/// The 12 edges of a box.
+fn box_edges(bounds: &MinMax<Vec3>) -> [[Vec3; 2]; 12] {
+	let MinMax { min, max } = *bounds;
+	let corner = |x: bool, y: bool, z: bool| {
+		Vec3::new(if x { max.x } else { min.x }, if y { max.y } else { min.y }, if z { max.z } else { min.z })
+	};
+	let mut edges = [[Vec3::ZERO; 2]; 12];
+	for i in 0..4 {
+		let [a, b] = [i & 1 != 0, i & 2 != 0];
+		edges[i] = [corner(false, a, b), corner(true, a, b)];
+		edges[i + 4] = [corner(a, false, b), corner(a, true, b)];
+		edges[i + 8] = [corner(a, b, false), corner(a, b, true)];
+	}
+	edges
+}
+
+fn make_viewer_shared(device: &Device) -> ViewerShared {
+	let render = make_tr_tool_shared(device);
+	let shader = make_mesh_shader(device);
+	let [flat_palette_pl, flat_16bit_pl, flat_32bit_pl] = {
+		["flat_palette_fs_main", "flat_16bit_fs_main", "flat_32bit_fs_main"]
+	}.map(|fs_entry| {
+		make_pipeline(
+			device,
+			&[&render.bind_group_layout],
+			&shader,
+			"flat_vs_main",
+			fs_entry,
+			&[],
+			None,
+			FrontFace::Cw,
+			None,
+			None,
+			None,
+		)
+	});
+	let blit_shader = make::shader(device, include_str!("../shader/blit.wgsl"));
+	let blit_image_entry = BindingType::Texture {
+		sample_type: TextureSampleType::Float { filterable: false },
+		view_dimension: TextureViewDimension::D2,
+		multisampled: false,
+	};
+	let blit_layout = make::bind_group_layout(device, &[(0, blit_image_entry, ShaderStages::FRAGMENT)]);
+	let blit_pl = make_pipeline(
+		device,
+		&[&blit_layout],
+		&blit_shader,
+		"blit_vs_main",
+		"blit_fs_main",
+		&[],
+		None,
+		FrontFace::Cw,
+		None,
+		None,
+		None,
+	);
+	ViewerShared { render, flat_palette_pl, flat_16bit_pl, flat_32bit_pl, blit_layout, blit_pl }
+}
+
+fn parse_level_view<L: Level>(
+	device: &Device,
+	queue: &Queue,
+	shared: &ViewerShared,
+	window_size: PhysicalSize<u32>,
+	reader: &mut LevelReader,
+) -> Result<LevelView> {
+	let level = read_level::<L>(reader)?;
+	let mut level_buffers = level_buffers::build(level.as_ref())?;
+	let object_data = mem::take(&mut level_buffers.output.object_data);
+	let model_skeletons = mem::take(&mut level_buffers.model_skeletons);
+	let entity_renders = mem::take(&mut level_buffers.entity_renders);
+	let animated_sprites = mem::take(&mut level_buffers.animated_sprites);
+	let issues = mem::take(&mut level_buffers.issues);
+	let geom_size = level_buffers.output.geom_output.data_buffers.iter().map(|data| data.len() as u64).sum();
+	let render_rooms = &level_buffers.render_rooms;
+	let map_bounds = render_rooms
+		.iter()
+		.flat_map(|render_room| [render_room.bounds.min, render_room.bounds.max])
+		.min_max()
+		.unwrap_or(MinMax::new(Vec2::ZERO));
+	let room_graph = RoomGraph::new(
+		render_rooms
+			.iter()
+			.map(|render_room| RoomNode { faces: render_room.stats.faces, water: render_room.stats.water })
+			.collect(),
+		render_rooms.iter().enumerate().flat_map(|(room_index, render_room)| {
+			render_room.portals.iter().map(move |portal| (room_index, portal.adjoining_room_index as usize))
+		}),
+	);
+	let sounds = SoundsView::new(sounds::sounds(level.as_ref()), level.version());
+	let view_buffers = ViewBuffers::new(device, window_size);
+	let texture_views = TextureViews::new(device, queue, level.as_ref());
+	let interact_texture = make_interact_texture(device, window_size);
+	let render = upload_level(
+		device,
+		&shared.render,
+		level.as_ref(),
+		level_buffers,
+		&view_buffers,
+		&texture_views,
+		&interact_texture,
+	);
+	let mut gpu_memory = GpuMemory::default();
+	gpu_memory.buffers.extend([
+		("Geometry", geom_size),
+		("Face instance", render.face_instance_buffer.size()),
+		("Sprite instance", render.sprite_instance_buffer.size()),
+		("Portal instance", render.portal_instance_buffer.size()),
+	]);
+	let texture_sizes = [
+		(TextureMode::Palette.label(), level.atlases_palette().map(AsBytes::as_bytes)),
+		(TextureMode::Bit16.label(), level.atlases_16bit().map(AsBytes::as_bytes)),
+		(TextureMode::Bit32.label(), level.atlases_32bit().map(AsBytes::as_bytes)),
+	];
+	for (label, bytes) in texture_sizes {
+		if let Some(bytes) = bytes {
+			gpu_memory.textures.push((label, bytes.len() as u64));
+		}
+	}
+	let (misc_images_bg, num_misc_images) = level.misc_images().map(|misc_images| {
+		let atlases_view = make_atlases_view(device, queue, misc_images, TextureFormat::R32Uint);
+		gpu_memory.textures.push(("Misc", misc_images.as_bytes().len() as u64));
+		let common_entries = common_entries(&render.statics_buffer, &render.camera_transform_buffer, &view_buffers);
+		let dummy_palette = BindingResource::TextureView(&texture_views.dummy_palette);
+		let entries = [
+			make::entry(PALETTE_ENTRY, dummy_palette),
+			make::entry(ATLASES_ENTRY, BindingResource::TextureView(&atlases_view)),
+		];
+		let entries = [&common_entries[..], &entries].concat();
+		let bind_group = make::bind_group(device, &shared.render.bind_group_layout, &entries);
+		(Some(bind_group), Some(misc_images.len() as u32))
+	}).unwrap_or_default();
+	let view_shared = Arc::new(ViewShared { view_buffers, misc_images_bg, texture_views });
+	let walk_rooms = level.rooms().iter().map(|room| WalkRoom::new(room, level.floor_data())).collect::<Vec<_>>();
+	let flyby_sequences = flyby::sequences(level.flyby_cameras());
+	//TR2-3 cutscenes are anchored at Lara, TR1's at positions built into the game
+	let cinematic_origin = level.entities().iter().find(|entity| entity.model_id() == 0).map_or(
+		Mat4::IDENTITY,
+		|lara| {
+			Mat4::from_translation(lara.pos().as_vec3())
+				* Mat4::from_rotation_y(lara.angle() as f32 / 65536.0 * TAU)
+		},
+	);
+	let cinematic_frames = cinematic::frames(level.cinematic_frames(), cinematic_origin);
+	let demo_data_len = level.demo_data().len();
+	let demo = match level.version() {
+		Version::Tr1 => demo::decode(level.demo_data()),
+		_ => None,
+	};
+	let demo_frames = demo.as_ref().map_or(vec![], |demo| demo_playback::path(demo, &walk_rooms));
+	let action_map = ActionMap {
+		forward: KeyGroup::new(&[KeyCode::KeyW, KeyCode::ArrowUp]),
+		backward: KeyGroup::new(&[KeyCode::KeyS, KeyCode::ArrowDown]),
+		left: KeyGroup::new(&[KeyCode::KeyA, KeyCode::ArrowLeft]),
+		right: KeyGroup::new(&[KeyCode::KeyD, KeyCode::ArrowRight]),
+		up: KeyGroup::new(&[KeyCode::KeyQ, KeyCode::PageUp]),
+		down: KeyGroup::new(&[KeyCode::KeyE, KeyCode::PageDown]),
+		fast: KeyGroup::new(&[KeyCode::ShiftLeft, KeyCode::ShiftRight]),
+		slow: KeyGroup::new(&[KeyCode::ControlLeft, KeyCode::ControlRight]),
+		roll_left: KeyGroup::new(&[KeyCode::KeyZ]),
+		roll_right: KeyGroup::new(&[KeyCode::KeyC]),
+		zoom_in: KeyGroup::new(&[KeyCode::Equal, KeyCode::NumpadAdd]),
+		zoom_out: KeyGroup::new(&[KeyCode::Minus, KeyCode::NumpadSubtract]),
+	};
+	let num_atlases = level.num_atlases() as u32;
+	let atlas_usage = atlas_usage::atlas_usage(level.as_ref());
+	let texture_mode = render.texture_mode;
+	Ok(LevelView {
+		render,
+		interact_texture,
+		view_shared,
+		gpu_memory,
+		draw_stats: DrawStats::default(),
+		projection: DEFAULT_PROJECTION,
+		map_bounds,
+		room_graph,
+		object_data_by_owner: owner_index(&object_data),
+		object_data,
+		level: level.store(),
+		picks: PickQueue::default(),
+		selected: None,
+		show_face_instance: false,
+		item_names: HashMap::new(),
+		entity_query: String::new(),
+		model_skeletons,
+		entity_renders,
+		hovered_mesh_index: None,
+		mesh_viewer: None,
+		walk_rooms,
+		walk: None,
+		flyby_sequences,
+		flyby_playback: None,
+		cinematic_frames,
+		cinematic_playback: None,
+		demo_data_len,
+		demo,
+		demo_frames,
+		demo_playback: None,
+		follow_demo: false,
+		camera_path: CameraPath::default(),
+		camera_path_playback: CameraPathPlayback::default(),
+		keyframe_interval: DEFAULT_KEYFRAME_INTERVAL,
+		room_path_ends: [0; 2],
+		room_path: None,
+		room_path_status: String::new(),
+		animated_sprites,
+		sprite_time: 0.0,
+		sprite_frame: 0,
+		water_time: 0.0,
+		animations_paused: false,
+		animation_step: 0.0,
+		mouse_pos: PhysicalPosition::default(),
+		locked_mouse_pos: PhysicalPosition::default(),
+		mouse_control: false,
+		key_states: KeyStates::new(),
+		action_map,
+		frame_update_queue: vec![],
+		camera_paste: String::new(),
+		show_portal_labels: true,
+		show_sector_grid: false,
+		show_room_bounds: false,
+		show_lights: false,
+		show_footstep_materials: false,
+		show_flyby_paths: true,
+		show_flyby_targets: false,
+		show_cinematic_path: true,
+		show_demo_path: true,
+		animate_sprites: true,
+		animate_water: true,
+		tint_degenerate_textures: false,
+		mesh_shine: true,
+		shading: Shading::Off,
+		unit_mode: UnitMode::TrUnits,
+		level_key: None,
+		render_option_defaults: RenderOptionValues::new(),
+		using_saved_overrides: false,
+		textures_tab: TexturesTab::Textures(texture_mode),
+		num_atlases,
+		num_misc_images,
+		atlas_usage,
+		highlighted_object_texture: None,
+		texture_export_options: ExportOptions::default(),
+		texture_export: None,
+		turntable_options: TurntableOptions::default(),
+		turntable_dir: None,
+		turntable: None,
+		issues,
+		level_diff: None,
+		show_diff_moves: true,
+		sounds,
+	})
+}
+
+fn load_level_view(
+	device: &Device,
+	queue: &Queue,
+	win_size: PhysicalSize<u32>,
+	shared: &ViewerShared,
+	path: &Path,
+	version: Version,
+	large_file_mode: ReadMode,
+) -> Result<LevelView> {
+	let mut reader = version::open_level(path, large_file_mode)?;
+	match version {
+		Version::Tr1 => parse_level_view::<tr1::Level>(device, queue, shared, win_size, &mut reader),
+		Version::Tr2 => parse_level_view::<tr2::Level>(device, queue, shared, win_size, &mut reader),
+		Version::Tr3 => parse_level_view::<tr3::Level>(device, queue, shared, win_size, &mut reader),
+		Version::Tr4 => parse_level_view::<tr4::Level>(device, queue, shared, win_size, &mut reader),
+		Version::Tr5 => parse_level_view::<tr5::Level>(device, queue, shared, win_size, &mut reader),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn elide_middle_counts_chars() {
+		//more bytes than `max_chars` but few enough chars
+		assert!(matches!(elide_middle("日本語.phd", 7), Cow::Borrowed("日本語.phd")));
+		assert_eq!(elide_middle("ÄÖÜäöüßÄÖÜäöüß", 7), "ÄÖÜ…öüß");
+		assert_eq!(elide_middle("レベル名がとても長い.tr4", 8), "レベル名…tr4");
+	}
+}
@@ -0,0 +1,332 @@
+use glam::{Vec2, Vec3, Vec3Swizzles};
+use crate::{tr_traits::Room, CLICK_SIZE, NO_HEIGHT, SECTOR_SIZE};
+
+/// Sector room below or above when absent.
+const NO_ROOM: u8 = 0xFF;
+/// Eye height above the floor.
+pub const EYE_HEIGHT: f32 = 768.0;
+/// Highest floor rise walked onto, higher floors stop movement.
+const STEP_HEIGHT: f32 = 256.0;
+/// Units per second squared, y is down.
+const GRAVITY: f32 = 9000.0;
+/// Limit on rooms followed through portals and pits in one lookup, in case of portal cycles.
+const MAX_ROOM_HOPS: usize = 16;
+
+const FLOOR_DATA_FUNCTION_MASK: u16 = 0x1F;
+const FLOOR_DATA_END: u16 = 0x8000;
+const FLOOR_DATA_PORTAL: u16 = 1;
+const FLOOR_DATA_FLOOR_SLANT: u16 = 2;
+const FLOOR_DATA_CEILING_SLANT: u16 = 3;
+
+#[derive(Clone, Copy)]
+struct WalkSector {
+	/// Clicks.
+	floor: i8,
+	ceiling: i8,
+	/// Floor slant along x and z, a quarter click per unit across the sector.
+	slant: [i8; 2],
+	/// Adjoining room through a wall portal.
+	portal: Option<usize>,
+	room_below: Option<usize>,
+	room_above: Option<usize>,
+}
+
+impl WalkSector {
+	fn wall(&self) -> bool {
+		self.floor == NO_HEIGHT || self.floor == self.ceiling
+	}
+	
+	/// Flat floor height, ignoring slant.
+	fn floor(&self) -> f32 {
+		self.floor as f32 * CLICK_SIZE
+	}
+	
+	fn ceiling(&self) -> f32 {
+		self.ceiling as f32 * CLICK_SIZE
+	}
+	
+	/// Floor height at `pos` with slant applied, `None` for walls.
+	fn floor_at(&self, pos: Vec3) -> Option<f32> {
+		if self.wall() {
+			return None;
+		}
+		let offset = pos.xz().rem_euclid(Vec2::splat(SECTOR_SIZE));
+		let rise = |slant: i8, offset: f32| {
+			let slant = slant as f32;
+			if slant < 0.0 {
+				-slant * offset / 4.0
+			} else {
+				slant * (SECTOR_SIZE - offset) / 4.0
+			}
+		};
+		Some(self.floor() + rise(self.slant[0], offset.x) + rise(self.slant[1], offset.y))
+	}
+}
+
+/// Wall portal and floor slant from a sector's floor data. Reading stops at the first other function, whose
+/// argument sizes vary, so floors triangulated in TR3+ are flat here.
+fn read_floor_data(floor_data: &[u16], floor_data_index: u16) -> (Option<usize>, [i8; 2]) {
+	let mut portal = None;
+	let mut slant = [0; 2];
+	//index 0 is the shared empty list
+	let mut index = floor_data_index as usize;
+	while index != 0 {
+		let (Some(&function), Some(&arg)) = (floor_data.get(index), floor_data.get(index + 1)) else {
+			break;
+		};
+		match function & FLOOR_DATA_FUNCTION_MASK {
+			FLOOR_DATA_PORTAL => portal = Some(arg as usize),
+			FLOOR_DATA_FLOOR_SLANT => slant = [arg as i8, (arg >> 8) as i8],
+			FLOOR_DATA_CEILING_SLANT => {},
+			_ => break,
+		}
+		if function & FLOOR_DATA_END != 0 {
+			break;
+		}
+		index += 2;
+	}
+	(portal, slant)
+}
+
+/// Sector grid of a room for walk mode.
+pub struct WalkRoom {
+	/// World XZ of the grid corner.
+	pos: Vec2,
+	num_x: usize,
+	num_z: usize,
+	sectors: Vec<WalkSector>,
+}
+
+impl WalkRoom {
+	pub fn new<R: Room>(room: &R, floor_data: &[u16]) -> Self {
+		let room_index = |index: u8| (index != NO_ROOM).then_some(index as usize);
+		let sectors = room.sectors().iter().map(|sector| {
+			let (portal, slant) = read_floor_data(floor_data, sector.floor_data_index);
+			WalkSector {
+				floor: sector.floor,
+				ceiling: sector.ceiling,
+				slant,
+				portal,
+				room_below: room_index(sector.room_below_index),
+				room_above: room_index(sector.room_above_index),
+			}
+		}).collect();
+		Self {
+			pos: room.pos().as_vec3().xz(),
+			num_x: room.num_sectors().x as usize,
+			num_z: room.num_sectors().z as usize,
+			sectors,
+		}
+	}
+	
+	fn sector(&self, pos: Vec3) -> Option<WalkSector> {
+		let local = (pos.xz() - self.pos) / SECTOR_SIZE;
+		if local.x < 0.0 || local.y < 0.0 {
+			return None;
+		}
+		let (x, z) = (local.x as usize, local.y as usize);
+		if x >= self.num_x || z >= self.num_z {
+			return None;
+		}
+		self.sectors.get(x * self.num_z + z).copied()
+	}
+}
+
+/// Room holding `pos`, following wall portals from `room_index`, and the floor under `pos`, following pits
+/// into rooms below. `None` if outside all rooms or in a wall.
+fn floor_under(rooms: &[WalkRoom], mut room_index: usize, pos: Vec3) -> Option<(usize, f32)> {
+	let mut sector = rooms.get(room_index)?.sector(pos)?;
+	for _ in 0..MAX_ROOM_HOPS {
+		let Some(portal) = sector.portal else {
+			break;
+		};
+		room_index = portal;
+		sector = rooms.get(room_index)?.sector(pos)?;
+	}
+	for _ in 0..MAX_ROOM_HOPS {
+		let Some(room_below) = sector.room_below else {
+			break;
+		};
+		sector = rooms.get(room_below)?.sector(pos)?;
+	}
+	Some((room_index, sector.floor_at(pos)?))
+}
+
+/// Move through floor and ceiling openings to the room vertically containing `pos`.
+fn track_vertical(rooms: &[WalkRoom], mut room_index: usize, pos: Vec3) -> usize {
+	for _ in 0..MAX_ROOM_HOPS {
+		let Some(sector) = rooms.get(room_index).and_then(|room| room.sector(pos)) else {
+			break;
+		};
+		room_index = match (sector.room_above, sector.room_below) {
+			(Some(room_above), _) if pos.y < sector.ceiling() => room_above,
+			(_, Some(room_below)) if pos.y > sector.floor() => room_below,
+			_ => break,
+		};
+	}
+	room_index
+}
+
+/// Camera constrained to the floor at eye height.
+pub struct WalkState {
+	room_index: usize,
+	fall_speed: f32,
+	/// Eye after the last step, to detect the camera being moved by other means.
+	last_eye: Vec3,
+}
+
+impl WalkState {
+	/// Start in the room whose sector at `eye` spans its height, or otherwise any room with a floor below it.
+	pub fn start(rooms: &[WalkRoom], eye: Vec3) -> Option<Self> {
+		let below = |room: &WalkRoom| room.sector(eye).filter(|sector| !sector.wall() && sector.floor() >= eye.y);
+		let room_index = rooms
+			.iter()
+			.position(|room| below(room).is_some_and(|sector| sector.ceiling() <= eye.y))
+			.or_else(|| rooms.iter().position(|room| below(room).is_some()))?;
+		Some(Self { room_index, fall_speed: 0.0, last_eye: eye })
+	}
+	
+	/// Move `eye` horizontally by `movement` unless blocked by a wall or a rise above `STEP_HEIGHT`, then fall or
+	/// settle onto the floor. `None` if the camera is no longer over a floor.
+	pub fn step(&mut self, rooms: &[WalkRoom], eye: Vec3, movement: Vec3, delta_secs: f32) -> Option<Vec3> {
+		if eye != self.last_eye {
+			*self = Self::start(rooms, eye)?;
+		}
+		let mut feet = eye + Vec3::Y * EYE_HEIGHT;
+		let target = feet + Vec3::new(movement.x, 0.0, movement.z);
+		if let Some((room_index, floor)) = floor_under(rooms, self.room_index, target) {
+			if floor >= target.y - STEP_HEIGHT {
+				self.room_index = room_index;
+				feet = target;
+			}
+		}
+		let (room_index, floor) = floor_under(rooms, self.room_index, feet)?;
+		self.room_index = room_index;
+		if feet.y < floor {
+			self.fall_speed += GRAVITY * delta_secs;
+			feet.y = (feet.y + self.fall_speed * delta_secs).min(floor);
+		} else {
+			feet.y = floor;
+		}
+		if feet.y == floor {
+			self.fall_speed = 0.0;
+		}
+		let eye = feet - Vec3::Y * EYE_HEIGHT;
+		self.room_index = track_vertical(rooms, self.room_index, eye);
+		self.last_eye = eye;
+		Some(eye)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	fn open(floor: i8) -> WalkSector {
+		WalkSector { floor, ceiling: -16, slant: [0; 2], portal: None, room_below: None, room_above: None }
+	}
+	
+	fn wall() -> WalkSector {
+		WalkSector { floor: NO_HEIGHT, ceiling: NO_HEIGHT, ..open(0) }
+	}
+	
+	/// 3x3 sector room at sector `(x, z)`, `sector(x, z)` gives each sector.
+	fn room(x: f32, z: f32, sector: impl Fn(usize, usize) -> WalkSector) -> WalkRoom {
+		let sectors = (0..3).flat_map(|x| (0..3).map(move |z| (x, z))).map(|(x, z)| sector(x, z)).collect();
+		WalkRoom { pos: Vec2::new(x, z) * SECTOR_SIZE, num_x: 3, num_z: 3, sectors }
+	}
+	
+	/// Room 0 at the origin and room 1 one click lower, overlapping by two sector columns as TR rooms do: each room's
+	/// edge column is a wall portal over the other's floor. Room 1's far column is wall.
+	fn side_by_side() -> [WalkRoom; 2] {
+		let room0 = room(0.0, 0.0, |x, _| if x == 2 { WalkSector { portal: Some(1), ..open(0) } } else { open(0) });
+		let room1 = room(1.0, 0.0, |x, _| match x {
+			0 => WalkSector { portal: Some(0), ..open(1) },
+			1 => open(1),
+			_ => wall(),
+		});
+		[room0, room1]
+	}
+	
+	/// Room 0 with a pit in its middle sector into room 1, 8 clicks below.
+	fn stacked() -> [WalkRoom; 2] {
+		let pit = |x, z| x == 1 && z == 1;
+		let room0 = room(0.0, 0.0, |x, z| WalkSector { room_below: pit(x, z).then_some(1), ..open(0) });
+		let room1 = room(0.0, 0.0, |x, z| WalkSector { ceiling: 0, room_above: pit(x, z).then_some(0), ..open(8) });
+		[room0, room1]
+	}
+	
+	/// Eye standing on a flat floor at sector center `(x, z)`.
+	fn eye(x: f32, z: f32, floor_clicks: f32) -> Vec3 {
+		Vec3::new(x * SECTOR_SIZE + 512.0, floor_clicks * CLICK_SIZE - EYE_HEIGHT, z * SECTOR_SIZE + 512.0)
+	}
+	
+	#[test]
+	fn floor_data_portal_and_slant() {
+		let floor_data = [0, FLOOR_DATA_PORTAL, 1, FLOOR_DATA_FLOOR_SLANT | FLOOR_DATA_END, 0x02FC];
+		assert_eq!(read_floor_data(&floor_data, 1), (Some(1), [-4, 2]));
+		assert_eq!(read_floor_data(&floor_data, 3), (None, [-4, 2]));
+		assert_eq!(read_floor_data(&floor_data, 0), (None, [0, 0]));
+	}
+	
+	#[test]
+	fn sector_lookup() {
+		let rooms = side_by_side();
+		assert_eq!(rooms[1].sector(eye(2.0, 2.0, 0.0)).map(|sector| sector.floor), Some(1));
+		assert!(rooms[1].sector(eye(3.0, 0.0, 0.0)).unwrap().wall());
+		assert!(rooms[1].sector(eye(4.0, 0.0, 0.0)).is_none());
+		assert!(rooms[0].sector(eye(0.0, 3.0, 0.0)).is_none());
+		assert!(rooms[0].sector(Vec3::new(-1.0, 0.0, 0.0)).is_none());
+		let sloped = WalkSector { slant: [-4, 0], ..open(0) };
+		assert_eq!(sloped.floor_at(Vec3::new(512.0, 0.0, 0.0)), Some(512.0));
+		assert_eq!(wall().floor_at(Vec3::ZERO), None);
+	}
+	
+	#[test]
+	fn floor_under_follows_portals_and_pits() {
+		let rooms = side_by_side();
+		assert_eq!(floor_under(&rooms, 0, eye(1.0, 1.0, 0.0)), Some((0, 0.0)));
+		assert_eq!(floor_under(&rooms, 0, eye(2.0, 1.0, 0.0)), Some((1, CLICK_SIZE)));
+		assert_eq!(floor_under(&rooms, 1, eye(1.0, 1.0, 0.0)), Some((0, 0.0)));
+		assert_eq!(floor_under(&rooms, 1, eye(3.0, 1.0, 0.0)), None);
+		let rooms = stacked();
+		assert_eq!(floor_under(&rooms, 0, eye(1.0, 1.0, 0.0)), Some((0, 8.0 * CLICK_SIZE)));
+		assert_eq!(track_vertical(&rooms, 0, eye(1.0, 1.0, 8.0)), 1);
+		assert_eq!(track_vertical(&rooms, 1, eye(1.0, 1.0, 0.0)), 0);
+		assert_eq!(track_vertical(&rooms, 0, eye(0.0, 0.0, 0.0)), 0);
+	}
+	
+	#[test]
+	fn walk_into_adjoining_room() {
+		let rooms = side_by_side();
+		let start = eye(1.0, 1.0, 0.0);
+		let mut state = WalkState::start(&rooms, start).unwrap();
+		assert_eq!(state.room_index, 0);
+		let mut eye = state.step(&rooms, start, Vec3::new(SECTOR_SIZE, -100.0, 0.0), 0.05).unwrap();
+		assert_eq!(state.room_index, 1);
+		//drops the click down
+		for _ in 0..10 {
+			eye = state.step(&rooms, eye, Vec3::ZERO, 0.05).unwrap();
+		}
+		assert_eq!(eye, Vec3::new(start.x + SECTOR_SIZE, CLICK_SIZE - EYE_HEIGHT, start.z));
+		//the next column is wall
+		let blocked = state.step(&rooms, eye, Vec3::new(SECTOR_SIZE, 0.0, 0.0), 0.0).unwrap();
+		assert_eq!(blocked, eye);
+		assert_eq!(state.room_index, 1);
+	}
+	
+	#[test]
+	fn fall_into_room_below() {
+		let rooms = stacked();
+		let start = eye(0.0, 1.0, 0.0);
+		let mut state = WalkState::start(&rooms, start).unwrap();
+		assert_eq!(state.room_index, 0);
+		let mut eye = state.step(&rooms, start, Vec3::new(SECTOR_SIZE, 0.0, 0.0), 0.05).unwrap();
+		assert!(eye.y < 0.0);
+		for _ in 0..100 {
+			eye = state.step(&rooms, eye, Vec3::ZERO, 0.05).unwrap();
+		}
+		assert_eq!(eye, self::eye(1.0, 1.0, 8.0));
+		assert_eq!(state.room_index, 1);
+	}
+}
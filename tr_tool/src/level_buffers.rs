@@ -0,0 +1,916 @@
+//! Level geometry written to the buffers the shaders read, independent of the viewer's GPU resources.
+//!
+//! Buffer layout:
+//! - Geometry pages (`geom_buffer::Output::data_buffers`): storage buffers of `GEOM_BUFFER_SIZE` bytes, one bound
+//!   per draw. Each holds its page's vertex and face arrays, then the tables shared by all pages (transforms, face
+//!   array offsets, object textures, sprite textures) at the offsets in `geom_buffer::Output`.
+//! - Face instances (`Output::face_buffer`): one `FaceInstance` per drawn face, sorted by page within each write, so
+//!   `face_instance::page_faces` splits a range into per-page draws.
+//! - Sprite and portal instances (`Output::sprite_buffer`, `Output::portal_buffer`): `SpriteInstance` and
+//!   `PortalInstance`, one per drawn sprite or portal.
+//! - Object data (`Output::object_data`): what each instance index refers to, for picking.
+//!
+//! Ranges into the instance buffers are kept per room in `RenderRoom`.
+use std::{collections::{hash_map::Entry, HashMap}, f32::consts::TAU, io::Result, iter, ops::Range};
+use glam::{I16Vec3, Mat4, Quat, Vec2, Vec3, Vec3Swizzles};
+use shared::min_max::{MinMax, VecMinMaxFromIterator};
+use tr_model::{tr1, TrError};
+use crate::{
+	analysis::{self, ObjectTextureIssues}, as_bytes::ReinterpretAsBytes, console,
+	data_writer::{DataWriter, MeshBatch, MeshFaceOffsets, Output, RoomFaceOffsets},
+	geom_buffer::{GeomBuffer, VertexArray},
+	object_data::{ObjectData, PolyType},
+	room_bounds,
+	tr_traits::{
+		vertex_indices, Entity, Face, Frame, Level, Light, Mesh, Model, Room, RoomGeom, RoomStaticMesh, RoomVertex,
+		StaticMesh,
+	},
+	CLICK_SIZE, NO_HEIGHT, SECTOR_SIZE,
+};
+
+/// CPU side buffers of a level and what the viewer needs to draw them.
+pub struct LevelBuffers {
+	pub output: Output,
+	pub render_rooms: Vec<RenderRoom>,
+	/// Rooms that aren't part of a flip pair.
+	pub static_room_indices: Vec<usize>,
+	/// Sorted by number.
+	pub flip_groups: Vec<FlipGroup>,
+	pub model_skeletons: HashMap<u16, ModelSkeleton>,
+	pub entity_renders: Vec<Option<EntityRender>>,
+	pub animated_sprites: Vec<AnimatedSprite>,
+	pub issues: LevelIssues,
+}
+
+pub struct RoomMesh {
+	pub quads: RoomFaceOffsets,
+	pub tris: RoomFaceOffsets,
+}
+
+/// Counts shown in the room selector tooltip.
+pub struct RoomStats {
+	pub vertices: usize,
+	pub faces: usize,
+	pub sprites: usize,
+	pub static_meshes: usize,
+	pub entities: usize,
+	pub water: bool,
+	/// Vertices flagged to bob as a water or quicksand surface.
+	pub water_wave_vertices: usize,
+}
+
+/// World space portal placement, for labels and moving through.
+#[derive(Clone, Copy)]
+pub struct PortalInfo {
+	pub center: Vec3,
+	/// Points into the room the portal belongs to.
+	pub normal: Vec3,
+	pub adjoining_room_index: u16,
+}
+
+/// World space sector square for the sector grid overlay.
+#[derive(Clone, Copy)]
+pub struct SectorOutline {
+	/// Corner with the least x and z, at floor height.
+	pub corner: Vec3,
+	/// No floor or ceiling. Drawn at the lowest floor of the room.
+	pub wall: bool,
+	/// TR3+ footstep sound material.
+	pub material: u8,
+}
+
+pub struct LightMarker {
+	pub pos: Vec3,
+	pub color: [u8; 3],
+}
+
+pub struct RenderRoom {
+	pub geom: Vec<RoomMesh>,
+	/// All static meshes of the room in one batch.
+	pub static_meshes: MeshFaceOffsets,
+	/// Static meshes whose `StaticMesh` is not flagged visible.
+	pub invisible_static_meshes: MeshFaceOffsets,
+	pub entity_meshes: Vec<Vec<MeshFaceOffsets>>,
+	/// Meshes of entities flagged initially invisible.
+	pub hidden_entity_meshes: Vec<Vec<MeshFaceOffsets>>,
+	pub room_sprites: Range<u32>,
+	pub entity_sprites: Range<u32>,
+	pub hidden_entity_sprites: Range<u32>,
+	pub portal_instances: Range<u32>,
+	pub portals: Vec<PortalInfo>,
+	pub sectors: Vec<SectorOutline>,
+	pub center: Vec3,
+	pub radius: f32,
+	/// World space XZ bounds of the room vertices.
+	pub bounds: MinMax<Vec2>,
+	/// World space box from the sector grid and declared y extent.
+	pub declared_bounds: MinMax<Vec3>,
+	/// World space box of the room vertices, `None` if it has none.
+	pub vertex_bounds: Option<MinMax<Vec3>>,
+	pub lights: Vec<LightMarker>,
+	pub stats: RoomStats,
+}
+
+impl RenderRoom {
+	pub fn static_meshes(&self, show_invisible: bool) -> impl Iterator<Item = &MeshFaceOffsets> {
+		iter::once(&self.static_meshes).chain(show_invisible.then_some(&self.invisible_static_meshes))
+	}
+	
+	pub fn entity_meshes(&self, show_hidden: bool) -> impl Iterator<Item = &MeshFaceOffsets> {
+		let hidden = if show_hidden { &self.hidden_entity_meshes[..] } else { &[] };
+		self.entity_meshes.iter().chain(hidden).flatten()
+	}
+	
+	pub fn entity_sprites(&self, show_hidden: bool) -> Range<u32> {
+		//hidden entity sprites are written directly after visible ones
+		let end = if show_hidden { self.hidden_entity_sprites.end } else { self.entity_sprites.end };
+		self.entity_sprites.start..end
+	}
+}
+
+pub struct FlipRoomIndices {
+	pub original: usize,
+	pub flipped: usize,
+}
+
+impl FlipRoomIndices {
+	pub fn get(&self, flipped: bool) -> usize {
+		if flipped {
+			self.flipped
+		} else {
+			self.original
+		}
+	}
+}
+
+pub struct FlipGroup {
+	pub number: u8,
+	pub rooms: Vec<FlipRoomIndices>,
+	pub show_flipped: bool,
+}
+
+/// Mesh hierarchy of a model, computed once and shared by all entities of that model.
+pub struct ModelSkeleton {
+	pub mesh_offset_index: u16,
+	pub mesh_nodes: Vec<tr1::MeshNode>,
+	/// Parent mesh index of each mesh, `None` for the root mesh.
+	pub parents: Vec<Option<usize>>,
+	/// Model space transform of each mesh.
+	pub transforms: Vec<Mat4>,
+	/// Mesh space bounds of each mesh.
+	pub bounds: Vec<MinMax<Vec3>>,
+}
+
+#[derive(Clone, Copy)]
+pub struct EntityRender {
+	pub model_id: u16,
+	pub transform: Mat4,
+}
+
+/// Problems found while parsing that don't prevent the level from loading.
+#[derive(Default)]
+pub struct LevelIssues {
+	/// Room static meshes whose id matches no `StaticMesh`.
+	pub missing_static_meshes: usize,
+	/// Sprite sequences extending past the end of `sprite_textures`.
+	pub truncated_sprite_sequences: usize,
+	/// Rooms loaded with filler or inconsistent layer data treated as empty.
+	pub malformed_rooms: Vec<u16>,
+	/// Rooms whose declared extent is more than a sector from their vertices on some side.
+	pub mismatched_room_bounds: Vec<u16>,
+	pub object_textures: ObjectTextureIssues,
+	/// Entities whose model id matches no model or sprite sequence, drawn as placeholders.
+	pub unknown_model_entities: Vec<UnknownModelEntity>,
+}
+
+pub struct UnknownModelEntity {
+	pub entity_index: u16,
+	pub model_id: u16,
+	pub room_index: u16,
+	pub pos: Vec3,
+}
+
+/// Entity sprite whose sequence has more than one frame.
+pub struct AnimatedSprite {
+	pub instance_index: u32,
+	pub first_sprite_texture_index: u16,
+	pub num_frames: u16,
+}
+
+#[derive(Clone, Copy)]
+enum ModelRef<'a, M> {
+	Model(&'a M),
+	SpriteSequence(&'a tr1::SpriteSequence),
+}
+
+pub struct WrittenFaceArray<'a, F> {
+	pub index: u16,
+	pub faces: &'a [F],
+}
+
+pub struct WrittenMesh<'a, L: Level + 'a> {
+	pub textured_quads: WrittenFaceArray<'a, <L::Mesh<'a> as Mesh<'a>>::TexturedQuad>,
+	pub textured_tris: WrittenFaceArray<'a, <L::Mesh<'a> as Mesh<'a>>::TexturedTri>,
+	pub solid_quads: WrittenFaceArray<'a, <L::Mesh<'a> as Mesh<'a>>::SolidQuad>,
+	pub solid_tris: WrittenFaceArray<'a, <L::Mesh<'a> as Mesh<'a>>::SolidTri>,
+}
+
+fn write_face_array<'a, F: Face>(
+	geom_buffer: &mut GeomBuffer,
+	vertex_array: VertexArray,
+	faces: &'a [F],
+	reversed: bool,
+) -> Result<WrittenFaceArray<'a, F>> {
+	Ok(WrittenFaceArray { index: geom_buffer.write_face_array(faces, vertex_array, reversed)?, faces })
+}
+
+/// Unit normals are stored as i16 scaled by this.
+const NORMAL_SCALE: f32 = i16::MAX as f32;
+
+/// Mesh vertex with its smooth normal. 8 2-byte units, a size no room vertex has, so the shader can tell them
+/// apart.
+#[repr(C)]
+struct MeshVertex {
+	pos: I16Vec3,
+	normal: I16Vec3,
+	padding: u32,
+}
+
+impl ReinterpretAsBytes for MeshVertex {}
+
+/// Vertex indices of a face and its normal, weighted by area. `None` if an index is out of bounds.
+fn face_normal<F: Face>(positions: &[Vec3], face: &F) -> Option<(Vec<usize>, Vec3)> {
+	let indices = vertex_indices(face).map(|index| index as usize).collect::<Vec<_>>();
+	if indices.iter().any(|&index| index >= positions.len()) {
+		return None;
+	}
+	let first = positions[indices[0]];
+	//sum over the fan triangles of the face
+	let normal = indices[1..]
+		.windows(2)
+		.map(|pair| (positions[pair[0]] - first).cross(positions[pair[1]] - first))
+		.sum::<Vec3>();
+	Some((indices, normal))
+}
+
+/// Add each face's normal to its vertices, weighted by face area. The sign follows the winding, which doesn't
+/// matter to the headlight.
+fn add_face_normals<F: Face>(positions: &[Vec3], faces: &[F], normals: &mut [Vec3]) {
+	for (indices, normal) in faces.iter().filter_map(|face| face_normal(positions, face)) {
+		for index in indices {
+			normals[index] += normal;
+		}
+	}
+}
+
+/// Faces whose normal points away from `center` minus those pointing toward it. Clockwise front faces have
+/// normals pointing into a closed mesh, so a positive count means the mesh is wound the other way.
+fn outward_faces<F: Face>(positions: &[Vec3], center: Vec3, faces: &[F]) -> i32 {
+	faces
+		.iter()
+		.filter_map(|face| face_normal(positions, face))
+		.map(|(indices, normal)| {
+			let face_center = indices.iter().map(|&index| positions[index]).sum::<Vec3>() / indices.len() as f32;
+			//distance of the face's plane from the center, faces through the center count for neither
+			let distance = normal.normalize_or_zero().dot(face_center - center);
+			(distance > 1.0) as i32 - (distance < -1.0) as i32
+		})
+		.sum()
+}
+
+/// Most faces are wound counter-clockwise, as in some TR4 entity meshes. Such meshes are drawn reversed so one
+/// culled draw is right for every mesh. Flat meshes have no inside and are left as they are.
+fn mesh_inside_out<'a, M: Mesh<'a> + 'a>(mesh: &M) -> bool {
+	let positions = mesh.vertices().iter().map(|v| v.as_vec3()).collect::<Vec<_>>();
+	if positions.is_empty() {
+		return false;
+	}
+	let center = positions.iter().sum::<Vec3>() / positions.len() as f32;
+	let outward = outward_faces(&positions, center, mesh.textured_quads())
+		+ outward_faces(&positions, center, mesh.textured_tris())
+		+ outward_faces(&positions, center, mesh.solid_quads())
+		+ outward_faces(&positions, center, mesh.solid_tris());
+	outward > 0
+}
+
+/// Vertices with the average normal of the faces around each, so shading is smooth across edges.
+fn mesh_vertices<'a, M: Mesh<'a> + 'a>(mesh: &M) -> Vec<MeshVertex> {
+	let positions = mesh.vertices().iter().map(|v| v.as_vec3()).collect::<Vec<_>>();
+	let mut normals = vec![Vec3::ZERO; positions.len()];
+	add_face_normals(&positions, mesh.textured_quads(), &mut normals);
+	add_face_normals(&positions, mesh.textured_tris(), &mut normals);
+	add_face_normals(&positions, mesh.solid_quads(), &mut normals);
+	add_face_normals(&positions, mesh.solid_tris(), &mut normals);
+	mesh.vertices()
+		.iter()
+		.zip(normals)
+		.map(|(&pos, normal)| MeshVertex {
+			pos,
+			normal: (normal.normalize_or_zero() * NORMAL_SCALE).as_i16vec3(),
+			padding: 0,
+		})
+		.collect()
+}
+
+/// Parent mesh index of each mesh of a model, `None` for the root mesh.
+pub fn mesh_parents(mesh_nodes: &[tr1::MeshNode]) -> Vec<Option<usize>> {
+	let mut parents = Vec::with_capacity(mesh_nodes.len() + 1);
+	parents.push(None);
+	let mut parent_stack = vec![];
+	for (mesh_node_index, mesh_node) in mesh_nodes.iter().enumerate() {
+		//mesh node n belongs to mesh n + 1, so the last mesh is mesh_node_index
+		let parent = if mesh_node.flags.pop() {
+			parent_stack.pop().expect("mesh transform stack empty")
+		} else {
+			mesh_node_index
+		};
+		if mesh_node.flags.push() {
+			parent_stack.push(parent);
+		}
+		parents.push(Some(parent));
+	}
+	parents
+}
+
+/// Model space transform of each mesh posed in `frame`, or blended toward `next` by its factor in 0..1.
+/// Rotations are slerped and the root offset lerped. Meshes past the end of a frame's rotations are unrotated.
+pub fn pose_transforms<F: Frame>(
+	mesh_nodes: &[tr1::MeshNode], parents: &[Option<usize>], frame: &F, next: Option<(&F, f32)>,
+) -> Vec<Mat4> {
+	let mut offset = frame.offset().as_vec3();
+	let rotations = frame.iter_rotations().chain(iter::repeat(Quat::IDENTITY));
+	let mut rotations = rotations.take(parents.len()).collect::<Vec<_>>();
+	if let Some((next, blend)) = next {
+		offset = offset.lerp(next.offset().as_vec3(), blend);
+		for (rotation, next_rotation) in rotations.iter_mut().zip(next.iter_rotations()) {
+			*rotation = rotation.slerp(next_rotation, blend);
+		}
+	}
+	let mut transforms = Vec::<Mat4>::with_capacity(parents.len());
+	for (mesh_index, (&parent, rotation)) in parents.iter().zip(rotations).enumerate() {
+		let translation = match parent {
+			None => Mat4::from_translation(offset),
+			Some(parent) => transforms[parent] * Mat4::from_translation(mesh_nodes[mesh_index - 1].offset.as_vec3()),
+		};
+		transforms.push(translation * Mat4::from_quat(rotation));
+	}
+	transforms
+}
+
+/// Fails if the model's first frame runs past the frame data.
+pub fn make_model_skeleton<L: Level>(level: &L, model: &L::Model) -> Result<ModelSkeleton> {
+	let mesh_offset_index = model.mesh_offset_index();
+	let mesh_nodes = level.get_mesh_nodes(model);
+	let parents = mesh_parents(mesh_nodes);
+	let frame = level.get_frame(model).ok_or(TrError::Invalid("model frame out of bounds"))?;
+	let transforms = pose_transforms(mesh_nodes, &parents, &frame, None);
+	let bounds = level.mesh_offsets()[mesh_offset_index as usize..][..transforms.len()]
+		.iter()
+		.map(|&mesh_offset| {
+			let vertices = level.get_mesh(mesh_offset).vertices();
+			vertices.iter().map(|v| v.as_vec3()).min_max().unwrap_or(MinMax::new(Vec3::ZERO))
+		})
+		.collect();
+	Ok(ModelSkeleton {
+		mesh_offset_index,
+		mesh_nodes: mesh_nodes.to_vec(),
+		parents,
+		transforms,
+		bounds,
+	})
+}
+
+/// Vertices with computed normals, then the face arrays, reversed if the mesh is inside out.
+pub fn write_mesh<'a, L: Level>(geom_buffer: &mut GeomBuffer, mesh: L::Mesh<'a>) -> Result<WrittenMesh<'a, L>> {
+	let vao = geom_buffer.write_vertex_array(&mesh_vertices(&mesh));
+	let reversed = mesh_inside_out(&mesh);
+	Ok(WrittenMesh {
+		textured_quads: write_face_array(geom_buffer, vao, mesh.textured_quads(), reversed)?,
+		textured_tris: write_face_array(geom_buffer, vao, mesh.textured_tris(), reversed)?,
+		solid_quads: write_face_array(geom_buffer, vao, mesh.solid_quads(), reversed)?,
+		solid_tris: write_face_array(geom_buffer, vao, mesh.solid_tris(), reversed)?,
+	})
+}
+
+/// Write the rooms, static meshes, entities, sprites and portals of `level` to buffers.
+pub fn build<L: Level>(level: &L) -> Result<LevelBuffers> {
+	assert!(level.entities().len() <= 65536);
+	//map model and sprite sequence ids to model and sprite sequence refs
+	let model_id_map = level
+		.models()
+		.iter()
+		.map(|model| (model.id() as u16, ModelRef::Model(model)))
+		.chain(level.sprite_sequences().iter().map(|ss| (ss.id as u16, ModelRef::SpriteSequence(ss))))
+		.collect::<HashMap<_, _>>();
+	//group entities by room
+	let mut room_entity_indices = vec![vec![]; level.rooms().len()];
+	for (entity_index, entity) in level.entities().iter().enumerate() {
+		room_entity_indices[entity.room_index() as usize].push(entity_index);
+	}
+	//write meshes, map tr mesh offets to meshes indices
+	let mut geom_buffer = GeomBuffer::new();
+	let mut written_meshes = vec![];
+	let mut mesh_offset_map = HashMap::new();
+	for &mesh_offset in level.mesh_offsets() {
+		if let Entry::Vacant(entry) = mesh_offset_map.entry(mesh_offset) {
+			entry.insert(written_meshes.len());
+			written_meshes.push(write_mesh::<L>(&mut geom_buffer, level.get_mesh(mesh_offset))?);
+		}
+	}
+	//write sprites (do first to ensure obj ids fit in u16)
+	let mut issues = LevelIssues {
+		object_textures: analysis::object_texture_issues(level),
+		..LevelIssues::default()
+	};
+	for (room_index, room) in level.rooms().iter().enumerate() {
+		if room.malformed() {
+			console::log_warn!("room {} malformed", room_index);
+			issues.malformed_rooms.push(room_index as u16);
+		}
+		if let Some(vertex_bounds) = room_bounds::vertices(room) {
+			if room_bounds::mismatched(&room_bounds::declared(room), &vertex_bounds) {
+				issues.mismatched_room_bounds.push(room_index as u16);
+			}
+		}
+	}
+	for (entity_index, entity) in level.entities().iter().enumerate() {
+		if !model_id_map.contains_key(&entity.model_id()) {
+			issues.unknown_model_entities.push(UnknownModelEntity {
+				entity_index: entity_index as u16,
+				model_id: entity.model_id(),
+				room_index: entity.room_index(),
+				pos: entity.pos().as_vec3(),
+			});
+		}
+	}
+	if !issues.unknown_model_entities.is_empty() {
+		let mut model_ids = issues.unknown_model_entities.iter().map(|e| e.model_id).collect::<Vec<_>>();
+		model_ids.sort_unstable();
+		model_ids.dedup();
+		let model_ids = model_ids.iter().map(|model_id| model_id.to_string()).collect::<Vec<_>>();
+		console::log_warn!(
+			"{} entities reference unknown model ids: {}",
+			issues.unknown_model_entities.len(),
+			model_ids.join(", "),
+		);
+	}
+	let degenerate_object_textures = issues.object_textures.degenerate(level.object_textures().len());
+	let mut animated_sprites = vec![];
+	let mut data_writer = DataWriter::new(geom_buffer, degenerate_object_textures);
+	let room_sprite_ranges = level.rooms().iter().enumerate().map(|(room_index, room)| {
+		let room_index = room_index as u16;
+		let room_sprites = data_writer.write_room_sprites(
+			room.pos(),
+			room.vertices(),
+			room.sprites(),
+			|sprite_index| ObjectData::RoomSprite { room_index, sprite_index },
+		);
+		//hidden entity sprites directly after visible ones
+		let [entity_sprites, hidden_entity_sprites] = [false, true].map(|hidden| {
+			let start = data_writer.sprite_offset();
+			for &entity_index in &room_entity_indices[room_index as usize] {
+				let entity = &level.entities()[entity_index];
+				if entity.initially_invisible() != hidden {
+					continue;
+				}
+				let Some(&ModelRef::SpriteSequence(ss)) = model_id_map.get(&entity.model_id()) else {
+					continue;
+				};
+				let first = ss.sprite_texture_index;
+				let num_frames = ss.neg_length.saturating_neg().max(1) as u16;
+				let available = level.sprite_textures().len().saturating_sub(first as usize);
+				let num_frames = if available < num_frames as usize {
+					console::log_warn!("sprite sequence {} out of bounds: {} + {}", ss.id, first, num_frames);
+					issues.truncated_sprite_sequences += 1;
+					available as u16
+				} else {
+					num_frames
+				};
+				if num_frames == 0 {
+					continue;
+				}
+				let instance_index = data_writer.write_entity_sprite(entity_index as u16, entity.pos(), first);
+				if num_frames > 1 {
+					animated_sprites.push(AnimatedSprite {
+						instance_index,
+						first_sprite_texture_index: first,
+						num_frames,
+					});
+				}
+			}
+			start..data_writer.sprite_offset()
+		});
+		(room_sprites, entity_sprites, hidden_entity_sprites)
+	}).collect::<Vec<_>>();
+	//geom
+	let mut model_skeletons = HashMap::new();
+	let mut entity_renders = vec![None; level.entities().len()];
+	let mut static_room_indices = (0..level.rooms().len()).collect::<Vec<_>>();//flip rooms will be removed
+	let mut flip_groups = HashMap::<u8, Vec<FlipRoomIndices>>::new();
+	let render_rooms = {
+		level.rooms().iter().enumerate().zip(room_entity_indices).zip(room_sprite_ranges)
+	}.map(|(((room_index, room), entity_indices), (room_sprites, entity_sprites, hidden_entity_sprites))| {
+		let room_index = room_index as u16;
+		let room_pos = room.pos();
+		let stats = RoomStats {
+			vertices: room.vertices().len(),
+			faces: room.geom().into_iter().map(|RoomGeom { quads, tris, .. }| quads.len() + tris.len()).sum(),
+			sprites: room.sprites().len(),
+			static_meshes: room.room_static_meshes().len(),
+			entities: entity_indices.len(),
+			water: room.water(),
+			water_wave_vertices: room.vertices().iter().filter(|vertex| vertex.attrs().water_wave).count(),
+		};
+		//room geom
+		let geom = {
+			room.geom().into_iter().enumerate()
+		}.map(|(geom_index, RoomGeom { vertices, quads, tris })| {
+			let geom_index = geom_index as u16;
+			let vertex_array = data_writer.geom_buffer.write_vertex_array(vertices);
+			let transform = Mat4::from_translation(room_pos.as_vec3());
+			let transform_index = data_writer.geom_buffer.write_transform(&transform)?;
+			let quads = data_writer.write_room_face_array(
+				level,
+				vertices,
+				vertex_array,
+				quads,
+				transform_index,
+				|face_index| {
+					ObjectData::RoomFace {
+						room_index,
+						geom_index,
+						face_type: PolyType::Quad,
+						face_index,
+					}
+				},
+			)?;
+			let tris = data_writer.write_room_face_array(
+				level,
+				vertices,
+				vertex_array,
+				tris,
+				transform_index,
+				|face_index| {
+					ObjectData::RoomFace {
+						room_index,
+						geom_index,
+						face_type: PolyType::Tri,
+						face_index,
+					}
+				},
+			)?;
+			Ok(RoomMesh { quads, tris })
+		}).collect::<Result<Vec<_>>>()?;
+		//portals
+		let portal_instances = data_writer.write_room_portals(
+			room_pos,
+			room.portals(),
+			|portal_index| ObjectData::Portal { room_index, portal_index },
+		);
+		let portals = room.portals().iter().map(|portal| {
+			let vertices = portal.vertices.map(|v| (room_pos + v.as_ivec3()).as_vec3());
+			PortalInfo {
+				center: vertices.iter().sum::<Vec3>() / 4.0,
+				normal: portal.normal.as_vec3().normalize_or_zero(),
+				adjoining_room_index: portal.adjoining_room_index,
+			}
+		}).collect();
+		//static meshes
+		let mut static_meshes = MeshBatch::default();
+		let mut invisible_static_meshes = MeshBatch::default();
+		for (room_static_mesh_index, room_static_mesh) in room.room_static_meshes().iter().enumerate() {
+			let room_static_mesh_index = room_static_mesh_index as u16;
+			let static_mesh_id = room_static_mesh.static_mesh_id();
+			let maybe_static_mesh = level
+				.static_meshes()
+				.iter()
+				.find(|static_mesh| static_mesh.id() as u16 == static_mesh_id);
+			let static_mesh = match maybe_static_mesh {
+				Some(static_mesh) => static_mesh,
+				None => {
+					console::log_warn!("static mesh id missing: {}", static_mesh_id);
+					issues.missing_static_meshes += 1;
+					continue;
+				},
+			};
+			let mesh_offset = level.mesh_offsets()[static_mesh.mesh_offset_index() as usize];
+			let written_mesh = &written_meshes[mesh_offset_map[&mesh_offset]];
+			let translation = Mat4::from_translation(room_static_mesh.pos().as_vec3());
+			let rotation = Mat4::from_rotation_y(room_static_mesh.angle() as f32 / 65536.0 * TAU);
+			let transform = translation * rotation;
+			let transform_index =
+				data_writer.geom_buffer.write_tinted_transform(&transform, room_static_mesh.light())?;
+			let batch = if static_mesh.visible() { &mut static_meshes } else { &mut invisible_static_meshes };
+			data_writer.add_mesh(
+				batch,
+				level,
+				written_mesh,
+				transform_index,
+				|face_type, face_index| {
+					ObjectData::RoomStaticMeshFace {
+						room_index,
+						room_static_mesh_index,
+						face_type,
+						face_index,
+					}
+				},
+			);
+		}
+		let static_meshes = data_writer.write_batch(static_meshes);
+		let invisible_static_meshes = data_writer.write_batch(invisible_static_meshes);
+		//entities
+		let mut entity_meshes = vec![];
+		let mut hidden_entity_meshes = vec![];
+		for entity_index in entity_indices {
+			let entity = &level.entities()[entity_index];
+			let Some(&ModelRef::Model(model)) = model_id_map.get(&entity.model_id()) else {
+				continue;
+			};
+			let model_id = model.id() as u16;
+			let skeleton = match model_skeletons.entry(model_id) {
+				Entry::Occupied(entry) => entry.into_mut(),
+				Entry::Vacant(entry) => entry.insert(make_model_skeleton(level, model)?),
+			};
+			let entity_translation = Mat4::from_translation(entity.pos().as_vec3());
+			let entity_rotation = Mat4::from_rotation_y(entity.angle() as f32 / 65536.0 * TAU);
+			let entity_transform = entity_translation * entity_rotation;
+			entity_renders[entity_index] = Some(EntityRender { model_id, transform: entity_transform });
+			let entity_index = entity_index as u16;
+			let mut meshes = Vec::with_capacity(skeleton.transforms.len());
+			for (mesh_index, &model_transform) in skeleton.transforms.iter().enumerate() {
+				let mesh_offset = level.mesh_offsets()[skeleton.mesh_offset_index as usize + mesh_index];
+				let mesh = &written_meshes[mesh_offset_map[&mesh_offset]];
+				let transform = entity_transform * model_transform;
+				let transform_index = data_writer.geom_buffer.write_transform(&transform)?;
+				let mesh_index = mesh_index as u16;
+				meshes.push(
+					data_writer.place_mesh(
+						level,
+						mesh,
+						transform_index,
+						|face_type, face_index| {
+							ObjectData::EntityMeshFace {
+								entity_index,
+								mesh_index,
+								face_type,
+								face_index,
+							}
+						},
+					),
+				);
+			}
+			if entity.initially_invisible() {
+				hidden_entity_meshes.push(meshes);
+			} else {
+				entity_meshes.push(meshes);
+			}
+		}
+		let room_index = room_index as usize;
+		if room.flip_room_index() != u16::MAX {
+			let flip_room_index = room.flip_room_index() as usize;
+			//unwrap: static_room_indices contains room_index until removed
+			static_room_indices.remove(static_room_indices.binary_search(&room_index).unwrap());
+			static_room_indices.remove(
+				static_room_indices
+					.binary_search(&flip_room_index)
+					.expect("flip room index missing"),
+			);
+			flip_groups
+				.entry(room.flip_group())
+				.or_default()
+				.push(FlipRoomIndices { original: room_index, flipped: flip_room_index });
+		}
+		let MinMax { min, max } = room
+			.vertices()
+			.iter()
+			.map(|v| v.pos())
+			.min_max()
+			.unwrap_or(MinMax::new(Vec3::ZERO));
+		let room_pos = room_pos.as_vec3();
+		let center = (max + min) / 2.0 + room_pos;
+		let radius = (max - min).max_element();
+		let bounds = MinMax { min: (min + room_pos).xz(), max: (max + room_pos).xz() };
+		let declared_bounds = room_bounds::declared(room);
+		let vertex_bounds = room_bounds::vertices(room);
+		let lights = room.lights().iter().map(|light| {
+			LightMarker { pos: light.pos(), color: (light.color() * 255.0).to_array().map(|c| c as u8) }
+		}).collect();
+		//sectors
+		let is_wall = |sector: &tr1::Sector| sector.floor == NO_HEIGHT && sector.ceiling == NO_HEIGHT;
+		let lowest_floor = room
+			.sectors()
+			.iter()
+			.filter(|sector| !is_wall(sector))
+			.map(|sector| sector.floor)
+			.max()
+			.map_or(center.y, |floor| floor as f32 * CLICK_SIZE);
+		let num_z = room.num_sectors().z as usize;
+		let sectors = room.sectors().iter().enumerate().map(|(sector_index, sector)| {
+			let wall = is_wall(sector);
+			let x = (sector_index / num_z) as f32 * SECTOR_SIZE;
+			let z = (sector_index % num_z) as f32 * SECTOR_SIZE;
+			let y = if wall { lowest_floor } else { sector.floor as f32 * CLICK_SIZE };
+			SectorOutline {
+				corner: Vec3::new(room_pos.x + x, y, room_pos.z + z),
+				wall,
+				material: sector.footstep_material(),
+			}
+		}).collect();
+		Ok(RenderRoom {
+			geom,
+			static_meshes,
+			invisible_static_meshes,
+			entity_meshes,
+			hidden_entity_meshes,
+			room_sprites,
+			entity_sprites,
+			hidden_entity_sprites,
+			portal_instances,
+			portals,
+			sectors,
+			center,
+			radius,
+			bounds,
+			declared_bounds,
+			vertex_bounds,
+			lights,
+			stats,
+		})
+	}).collect::<Result<Vec<_>>>()?;
+	let mut flip_groups = flip_groups
+		.into_iter()
+		.map(|(number, rooms)| FlipGroup { number, rooms, show_flipped: false })
+		.collect::<Vec<_>>();
+	flip_groups.sort_by_key(|f| f.number);
+	let output = data_writer.done(level.object_textures(), level.sprite_textures())?;
+	Ok(LevelBuffers {
+		output,
+		render_rooms,
+		static_room_indices,
+		flip_groups,
+		model_skeletons,
+		entity_renders,
+		animated_sprites,
+		issues,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use glam::IVec3;
+	use crate::{face_instance::decode, test_level, tr_traits::LevelDyn};
+	use super::*;
+	
+	fn normal(vertex: &MeshVertex) -> Vec3 {
+		vertex.normal.as_vec3() / NORMAL_SCALE
+	}
+	
+	#[test]
+	fn mesh_vertex_normals_average_adjacent_faces() {
+		//floor quad at y 0 with a solid tri rising from its back edge to the apex at y -128
+		let level = test_level::tiny().level();
+		let vertices = mesh_vertices(&level.get_mesh(level.mesh_offsets()[0]));
+		assert_eq!(vertices.len(), 5);
+		assert_eq!(vertices[4].pos, I16Vec3::new(0, -128, 0));
+		let quad_normal = Vec3::NEG_Y;
+		let tri_normal = Vec3::new(0.0, -1.0, -2.0).normalize();
+		//vertices of only the quad or only the tri take its normal
+		for index in [2, 3] {
+			assert!(normal(&vertices[index]).abs_diff_eq(quad_normal, 0.001));
+		}
+		assert!(normal(&vertices[4]).abs_diff_eq(tri_normal, 0.001));
+		//shared vertices weight by area, the quad being twice the tri
+		let shared_normal = (quad_normal * 2.0 + tri_normal * Vec3::new(0.0, -8192.0, -16384.0).length() / 16384.0)
+			.normalize();
+		for index in [0, 1] {
+			assert!(normal(&vertices[index]).abs_diff_eq(shared_normal, 0.001));
+		}
+	}
+	
+	#[test]
+	fn face_normal_is_area_weighted_and_skips_bad_indices() {
+		let positions = [Vec3::ZERO, Vec3::X * 2.0, Vec3::new(2.0, 0.0, 2.0), Vec3::Z * 2.0];
+		let quad = tr1::SolidQuad { vertex_indices: [0, 1, 2, 3], color_index: 0 };
+		let (indices, normal) = face_normal(&positions, &quad).unwrap();
+		assert_eq!(indices, [0, 1, 2, 3]);
+		assert_eq!(normal, Vec3::new(0.0, -8.0, 0.0));
+		let bad = tr1::SolidTri { vertex_indices: [0, 1, 4], color_index: 0 };
+		assert!(face_normal(&positions, &bad).is_none());
+	}
+	
+	fn solid_tris(vertex_indices: &[[u16; 3]]) -> Vec<tr1::SolidTri> {
+		vertex_indices.iter().map(|&vertex_indices| tr1::SolidTri { vertex_indices, color_index: 0 }).collect()
+	}
+	
+	/// Whether a mesh of `tris` is detected inside out and its face array written with the reversed flag.
+	fn written_reversed(vertices: &[I16Vec3], tris: &[tr1::SolidTri]) -> (bool, bool) {
+		let mut test_level = test_level::tiny();
+		test_level.mesh_data = test_level::mesh_data(I16Vec3::ZERO, 256, vertices, &[], &[], &[], tris);
+		let level = test_level.level();
+		let mut geom_buffer = GeomBuffer::new();
+		let inside_out = mesh_inside_out(&level.get_mesh(0));
+		let index = write_mesh::<tr1::Level>(&mut geom_buffer, level.get_mesh(0)).unwrap().solid_tris.index as usize;
+		let output = geom_buffer.into_buffer::<tr1::ObjectTexture>(&[], &[]).unwrap();
+		let data_buffer = &*output.data_buffers[0];
+		let u32_at = |offset: usize| u32::from_le_bytes(data_buffer[offset..][..4].try_into().unwrap()) as usize;
+		let face_array_offset = u32_at((output.face_array_offsets_offset as usize + index) * 4) * 4;
+		//the face size follows the vertex array offset, its top bit is the reversed flag
+		let face_size = u16::from_le_bytes(data_buffer[face_array_offset + 4..][..2].try_into().unwrap());
+		(inside_out, face_size & 0x8000 != 0)
+	}
+	
+	#[test]
+	fn inside_out_mesh_is_written_reversed() {
+		let tetrahedron = [
+			I16Vec3::new(0, -256, 0), I16Vec3::new(-256, 0, -256), I16Vec3::new(256, 0, -256), I16Vec3::new(0, 0, 256),
+		];
+		let clockwise = solid_tris(&[[0, 2, 1], [0, 3, 2], [0, 1, 3], [1, 2, 3]]);
+		let counter_clockwise = solid_tris(&[[0, 1, 2], [0, 2, 3], [0, 3, 1], [1, 3, 2]]);
+		assert_eq!(written_reversed(&tetrahedron, &clockwise), (false, false));
+		assert_eq!(written_reversed(&tetrahedron, &counter_clockwise), (true, true));
+		//a flat mesh has no inside, either winding is left as it is
+		let square = [[-256, -256], [256, -256], [256, 256], [-256, 256]].map(|[x, z]| I16Vec3::new(x, 0, z));
+		for tris in [[[0, 1, 2], [0, 2, 3]], [[0, 2, 1], [0, 3, 2]]] {
+			assert_eq!(written_reversed(&square, &solid_tris(&tris)), (false, false));
+		}
+	}
+	
+	#[test]
+	fn build_writes_every_face_of_a_tiny_level() {
+		let level = test_level::tiny().level();
+		let buffers = build(level.as_ref()).unwrap();
+		assert_eq!(buffers.render_rooms.len(), 1);
+		assert_eq!(buffers.static_room_indices, [0]);
+		assert!(buffers.flip_groups.is_empty());
+		assert!(buffers.entity_renders[0].is_some());
+		assert_eq!(buffers.model_skeletons.len(), 1);
+		assert_eq!(buffers.issues.missing_static_meshes, 0);
+		//room quad and tri, static mesh and entity mesh quad and tri each
+		assert_eq!(buffers.output.face_buffer.len(), 6);
+		assert_eq!(buffers.output.geom_output.data_buffers.len(), 1);
+	}
+	
+	#[test]
+	fn face_object_data_past_u16_range() {
+		//room sprites are written before faces, pushing the faces' object data indices from 65535 up
+		let mut test_level = test_level::tiny();
+		let sprite = tr1::Sprite { vertex_index: 0, sprite_texture_index: 0 };
+		test_level.rooms[0].sprites = vec![sprite; u16::MAX as usize];
+		let level = test_level.level();
+		let output = build(level.as_ref()).unwrap().output;
+		assert!(output.object_data.len() > 65536);
+		assert_eq!(output.face_buffer.len(), 6);
+		let object_data_indices = output.face_buffer.iter().map(|face| decode(face.words()).object_data_index);
+		assert_eq!(object_data_indices.clone().max(), Some(u16::MAX as u32 + 5));
+		for object_data_index in object_data_indices {
+			assert!(object_data_index >= u16::MAX as u32);
+			assert!(matches!(
+				output.object_data[object_data_index as usize],
+				ObjectData::RoomFace { .. } | ObjectData::RoomStaticMeshFace { .. } | ObjectData::EntityMeshFace { .. },
+			));
+		}
+	}
+	
+	#[test]
+	fn model_frame_out_of_bounds_is_an_error() {
+		let mut test_level = test_level::tiny();
+		test_level.models[0].frame_byte_offset = test_level.frame_data.len() as u32 * 2;
+		let level = test_level.level();
+		let error = build(level.as_ref()).err().unwrap();
+		assert_eq!(TrError::of(&error), Some(&TrError::Invalid("model frame out of bounds")));
+	}
+	
+	#[test]
+	fn unknown_model_id_gets_one_placeholder() {
+		let mut test_level = test_level::tiny();
+		let bogus = tr1::Entity { model_id: 999, pos: IVec3::new(1536, 0, 1536), ..test_level.entities[0] };
+		test_level.entities.push(bogus);
+		let level = test_level.level();
+		let buffers = build(level.as_ref()).unwrap();
+		let [ref unknown] = buffers.issues.unknown_model_entities[..] else {
+			panic!("{} unknown model entities", buffers.issues.unknown_model_entities.len());
+		};
+		assert_eq!((unknown.entity_index, unknown.model_id, unknown.room_index), (1, 999, 0));
+		assert_eq!(unknown.pos, Vec3::new(1536.0, 0.0, 1536.0));
+		assert!(buffers.entity_renders[0].is_some());
+		assert!(buffers.entity_renders[1].is_none());
+	}
+	
+	#[test]
+	fn blended_pose_midpoint() {
+		//second frame raised and moved forward, its one mesh turned a quarter turn about y
+		let mut test_level = test_level::tiny();
+		let mut second = test_level::one_mesh_frame();
+		second[6..9].copy_from_slice(&[0, -512i16 as u16, 256]);
+		second[11] = 4;//y angle 256 of 1024 in the packed rotation
+		test_level.frame_data.extend(second);
+		let level = test_level.level();
+		let [first, second] = [0, 24].map(|frame_byte_offset| level.get_frame_at(frame_byte_offset, 1).unwrap());
+		let pose = |blend| pose_transforms(&[], &[None], &first, Some((&second, blend)))[0];
+		let posed = |angle, offset| Mat4::from_rotation_translation(Quat::from_rotation_y(angle), offset);
+		assert!(pose(0.0).abs_diff_eq(Mat4::IDENTITY, 0.001));
+		//halfway is an eighth turn, slerped rather than an average of the matrices
+		assert!(pose(0.5).abs_diff_eq(posed(TAU / 8.0, Vec3::new(0.0, -256.0, 128.0)), 0.001));
+		assert!(pose(1.0).abs_diff_eq(posed(TAU / 4.0, Vec3::new(0.0, -512.0, 256.0)), 0.001));
+	}
+}
@@ -0,0 +1,1365 @@
+//! Level viewer. The binary is a thin front to this library, which other tools can use to render levels
+//! offscreen (`render_snapshot`) or to build the level buffers for their own renderer (`level_buffers`).
+
+mod as_bytes;
+pub mod cli;
+mod console;
+#[cfg(feature = "gui")]
+pub mod gui;
+mod gpu;
+mod make;
+mod tr_traits;
+mod vec_tail;
+mod geom_buffer;
+mod data_writer;
+mod face_instance;
+pub mod level_buffers;
+mod object_data;
+mod version;
+mod dump;
+mod snapshot;
+mod analysis;
+mod texture_export;
+mod room_bounds;
+mod settings;
+mod buffer_dump;
+mod stats;
+mod demo;
+mod rgba;
+#[cfg(test)]
+mod test_level;
+
+#[cfg(feature = "headless")]
+pub use snapshot::{render_snapshot, render_snapshot_with, CameraSpec};
+#[cfg(not(feature = "headless"))]
+use snapshot::CameraSpec;
+pub use version::{LearnedVersion, Version};
+
+use std::{f32::consts::{FRAC_PI_4, PI}, io::Result, mem::{size_of, MaybeUninit}, ops::Range, path::Path, sync::Arc};
+use data_writer::{FaceBlend, Output};
+use face_instance::{page_faces, FaceInstance};
+use level_buffers::{FlipGroup, LevelBuffers, RenderRoom, RoomMesh};
+use geom_buffer::GEOM_BUFFER_SIZE;
+use as_bytes::{AsBytes, ReinterpretAsBytes};
+use glam::{EulerRot, Mat4, Vec2, Vec3, Vec3Swizzles};
+use tr_model::{tr1, tr2, tr3, tr4, tr5, TrError};
+use tr_traits::Level;
+use stats::{CountingPass, DrawKind, DrawStats};
+use version::{LevelReader, ReadMode};
+use wgpu::{
+	BindGroup, BindGroupEntry, BindGroupLayout, BindingResource, BlendComponent, BlendFactor,
+	BlendOperation, BlendState, Buffer, BufferUsages, Color, ColorTargetState, ColorWrites, CommandEncoder,
+	DepthBiasState, Device, Extent3d, FragmentState, FrontFace, IndexFormat, LoadOp, MultisampleState,
+	Operations, PipelineLayoutDescriptor, PrimitiveState, PrimitiveTopology, Queue, RenderPassColorAttachment,
+	RenderPassDepthStencilAttachment, RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, ShaderModule,
+	ShaderStages, StoreOp, Texture, TextureDimension, TextureFormat, TextureUsages, TextureView,
+	TextureViewDescriptor, TextureViewDimension, VertexFormat, VertexState, VertexStepMode,
+};
+use gpu::PhysicalSize;
+
+/*
+This ordering creates a "Z" so triangle strip mode may be used for quads, and the first three indices used
+for tris.
+*/
+const FACE_VERTEX_INDICES: [u32; 4] = [1, 2, 0, 3];
+const REVERSE_INDICES: [u16; 4] = [0, 2, 1, 3];//yields face vertex indices [1, 0, 2, 3]
+const NUM_QUAD_VERTICES: u32 = 4;
+const NUM_TRI_VERTICES: u32 = 3;
+
+#[repr(C)]
+struct Viewport {
+	clip: [i32; 4],
+	view: [i32; 4],
+	/// Texture pixels and the scroll offset are in points, scaled by this to physical pixels.
+	pixels_per_point: f32,
+	/// Images stacked in the bound texture.
+	num_images: u32,
+}
+
+impl ReinterpretAsBytes for Viewport {}
+
+//group 1, one bind group per geometry page
+const GEOM_DATA_ENTRY: u32 = 0;
+const STATICS_ENTRY: u32 = 1;
+const CAMERA_ENTRY: u32 = 2;
+const PERSPECTIVE_ENTRY: u32 = 3;
+const PALETTE_ENTRY: u32 = 4;
+const ATLASES_ENTRY: u32 = 5;
+const VIEWPORT_ENTRY: u32 = 6;
+const SCROLL_OFFSET_ENTRY: u32 = 7;
+const TIME_ENTRY: u32 = 8;
+
+const INTERACT_TEXTURE_FORMAT: TextureFormat = TextureFormat::R32Uint;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum TextureMode {
+	Palette,
+	Bit16,
+	Bit32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SolidMode {
+	Bit24,
+	Bit32,
+}
+
+/// How to render entities flagged initially invisible.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum HiddenEntities {
+	Hide,
+	Ghost,
+	Show,
+}
+
+/// Which face blend passes to draw, for diagnosing faces wrongly classified as blended.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum BlendFilter {
+	All,
+	Opaque,
+	/// Additive and alpha blended.
+	Blended,
+}
+
+impl BlendFilter {
+	fn opaque(&self) -> bool {
+		*self != BlendFilter::Blended
+	}
+	
+	fn blended(&self) -> bool {
+		*self != BlendFilter::Opaque
+	}
+}
+
+/// Face coloring for texture hunting. Solid faces are colored by palette index in every debug mode.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum FaceColoring {
+	Textured,
+	TextureId,
+	Atlas,
+	BlendMode,
+	/// Checkerboard over atlas UVs, for spotting stretched and seamed mapping.
+	UvCheck,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct Projection {
+	/// Vertical field of view in radians.
+	fov: f32,
+	near: f32,
+	far: f32,
+	infinite_far: bool,
+}
+
+/// Projection on load.
+const DEFAULT_PROJECTION: Projection = Projection { fov: FRAC_PI_4, near: 100.0, far: 100000.0, infinite_far: false };
+
+/// Solid clear color, or a vertical gradient drawn behind all geometry.
+#[derive(Clone, Copy, PartialEq)]
+struct Background {
+	top: [u8; 3],
+	bottom: [u8; 3],
+	gradient: bool,
+}
+
+impl Background {
+	fn clear_color(&self) -> Color {
+		let [r, g, b] = self.top.map(|c| c as f64 / 255.0);
+		Color { r, g, b, a: 1.0 }
+	}
+	
+	/// Top and bottom as RGBA8 for the sky instance.
+	fn sky_instance(&self) -> [u32; 2] {
+		[self.top, self.bottom].map(|[r, g, b]| u32::from_le_bytes([r, g, b, u8::MAX]))
+	}
+}
+
+struct LoadedLevelShared {
+	palette_24bit_bg: Option<BindGroup>,
+	texture_16bit_bg: Option<BindGroup>,
+	texture_32bit_bg: Option<BindGroup>,
+}
+
+/// Kept to bind the level's textures in other views, like the mesh viewer.
+struct TextureViews {
+	/// With `atlases_palette`.
+	palette_24bit: Option<TextureView>,
+	palette_32bit: Option<TextureView>,
+	atlases_palette: Option<TextureView>,
+	atlases_16bit: Option<TextureView>,
+	atlases_32bit: Option<TextureView>,
+	/// Bound where the mode doesn't use a palette or atlases.
+	dummy_palette: TextureView,
+	dummy_atlases: TextureView,
+}
+
+impl TextureViews {
+	/// Palette and atlases entries for textured faces, `None` if the level lacks the mode.
+	fn texture_entries(&self, texture_mode: TextureMode) -> Option<[BindGroupEntry<'_>; 2]> {
+		let (palette, atlases) = match texture_mode {
+			TextureMode::Palette => (self.palette_24bit.as_ref()?, self.atlases_palette.as_ref()?),
+			TextureMode::Bit16 => (&self.dummy_palette, self.atlases_16bit.as_ref()?),
+			TextureMode::Bit32 => (&self.dummy_palette, self.atlases_32bit.as_ref()?),
+		};
+		Some([
+			make::entry(PALETTE_ENTRY, BindingResource::TextureView(palette)),
+			make::entry(ATLASES_ENTRY, BindingResource::TextureView(atlases)),
+		])
+	}
+	
+	/// Palette and atlases entries for solid faces, `None` if the level lacks the mode.
+	fn solid_entries(&self, solid_mode: SolidMode) -> Option<[BindGroupEntry<'_>; 2]> {
+		match solid_mode {
+			SolidMode::Bit24 => self.texture_entries(TextureMode::Palette),
+			SolidMode::Bit32 => Some([
+				make::entry(PALETTE_ENTRY, BindingResource::TextureView(self.palette_32bit.as_ref()?)),
+				make::entry(ATLASES_ENTRY, BindingResource::TextureView(&self.dummy_atlases)),
+			]),
+		}
+	}
+}
+
+struct LoadedLevel {
+	//render
+	depth_view: TextureView,
+	/// Bind group of each geometry page.
+	geom_page_bgs: Vec<BindGroup>,
+	/// Data buffer of each geometry page, bound by `geom_page_bgs`.
+	geom_data_buffers: Vec<Buffer>,
+	interact_view: TextureView,
+	face_instance_buffer: Buffer,
+	/// Copy of `face_instance_buffer`, for the Selection window's face instance check.
+	face_instances: Vec<FaceInstance>,
+	sprite_instance_buffer: Buffer,
+	portal_instance_buffer: Buffer,
+	statics_buffer: Buffer,
+	camera_transform_buffer: Buffer,
+	sky_instance_buffer: Buffer,
+	solid_32bit_bg: Option<BindGroup>,
+	shared: Arc<LoadedLevelShared>,
+	solid_mode: Option<SolidMode>,
+	texture_mode: TextureMode,
+	//camera
+	pos: Vec3,
+	yaw: f32,
+	pitch: f32,
+	roll: f32,
+	//rooms
+	render_rooms: Vec<RenderRoom>,
+	static_room_indices: Vec<usize>,
+	flip_groups: Vec<FlipGroup>,
+	/// Also draw the flip state not selected in each flip group, ghosted.
+	show_both_flip_states: bool,
+	render_room_index: Option<usize>,//if None, render all
+	//render options
+	show_room_mesh: bool,
+	show_static_meshes: bool,
+	show_invisible_static_meshes: bool,
+	show_entity_meshes: bool,
+	show_room_sprites: bool,
+	show_entity_sprites: bool,
+	show_portals: bool,
+	/// Draw the reverse side of double-sided room faces with `REVERSE_DEPTH_BIAS`.
+	bias_reverse_faces: bool,
+	background: Background,
+	hidden_entities: HiddenEntities,
+	blend_filter: BlendFilter,
+	winding: Winding,
+	disable_culling: bool,
+	face_coloring: FaceColoring,
+	/// Rooms whose size over distance is below this draw only their largest faces. 0 disables LOD.
+	lod_threshold: f32,
+}
+
+/// How front faces are wound. Original levels are clockwise.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Winding {
+	Cw,
+	Ccw,
+}
+
+/// Pipeline variants for each winding, and one culling nothing.
+struct Windings<T> {
+	cw: T,
+	ccw: T,
+	no_cull: T,
+}
+
+impl<T> Windings<T> {
+	fn new<F: Fn(FrontFace, Option<wgpu::Face>) -> T>(f: F) -> Self {
+		Self {
+			cw: f(FrontFace::Cw, Some(wgpu::Face::Back)),
+			ccw: f(FrontFace::Ccw, Some(wgpu::Face::Back)),
+			no_cull: f(FrontFace::Cw, None),
+		}
+	}
+	
+	/// `None` for the variant culling nothing.
+	fn get(&self, winding: Option<Winding>) -> &T {
+		match winding {
+			Some(Winding::Cw) => &self.cw,
+			Some(Winding::Ccw) => &self.ccw,
+			None => &self.no_cull,
+		}
+	}
+}
+
+struct TexturePipelines {
+	opaque: Windings<RenderPipeline>,
+	additive: Windings<RenderPipeline>,
+	alpha: Windings<RenderPipeline>,
+	/// Variants with `REVERSE_DEPTH_BIAS` for the reverse side of double-sided room faces.
+	opaque_reverse: Windings<RenderPipeline>,
+	additive_reverse: Windings<RenderPipeline>,
+	alpha_reverse: Windings<RenderPipeline>,
+	sprite: RenderPipeline,
+	ghost: Windings<RenderPipeline>,
+	sprite_ghost: RenderPipeline,
+}
+
+/// Level-independent render resources.
+struct TrToolShared {
+	bind_group_layout: BindGroupLayout,
+	geom_page_layout: BindGroupLayout,
+	solid_24bit_pl: Windings<RenderPipeline>,
+	solid_32bit_pl: Windings<RenderPipeline>,
+	solid_24bit_debug_pl: Windings<RenderPipeline>,
+	solid_32bit_debug_pl: Windings<RenderPipeline>,
+	/// Flat face coloring, usable with any texture bind group.
+	texture_debug_pl: Windings<RenderPipeline>,
+	texture_debug_reverse_pl: Windings<RenderPipeline>,
+	texture_uv_check_pl: Windings<RenderPipeline>,
+	texture_uv_check_reverse_pl: Windings<RenderPipeline>,
+	palette_pls: TexturePipelines,
+	bit16_pls: TexturePipelines,
+	bit32_pls: TexturePipelines,
+	portal_pl: RenderPipeline,
+	sky_pl: RenderPipeline,
+	face_vertex_index_buffer: Buffer,
+	reverse_indices_buffer: Buffer,
+}
+
+#[repr(C)]
+struct Statics {
+	transforms_offset: u32,
+	transform_lights_offset: u32,
+	face_array_offsets_offset: u32,
+	object_textures_offset: u32,
+	object_texture_size: u32,
+	sprite_textures_offset: u32,
+	num_atlases: u32,
+	/// Written separately when render options change.
+	render_flags: u32,
+	/// Faces using this object texture are tinted, `NO_HIGHLIGHT` for none. Written separately when picked.
+	highlight_object_texture: u32,
+}
+
+const NO_HIGHLIGHT: u32 = u32::MAX;
+
+const RENDER_FLAG_SHINE: u32 = 2;
+/// Bob room vertices flagged `water_wave` with the time uniform.
+const RENDER_FLAG_WATER_WAVES: u32 = 64;
+
+impl ReinterpretAsBytes for Statics {}
+
+const MIN_FOV: f32 = PI / 6.0;//30°
+const MAX_FOV: f32 = PI * 2.0 / 3.0;//120°
+const SECTOR_SIZE: f32 = 1024.0;
+/// Sector floor and ceiling heights are in clicks.
+const CLICK_SIZE: f32 = 256.0;
+/// Sector floor or ceiling height when absent.
+const NO_HEIGHT: i8 = -127;
+/// Camera look direction on load and when fitting the level to view.
+const CAMERA_VEC: Vec3 = Vec3::ONE;
+
+fn make_camera_transform(pos: Vec3, yaw: f32, pitch: f32, roll: f32) -> Mat4 {
+	Mat4::from_rotation_z(roll)
+		* Mat4::from_euler(EulerRot::XYZ, pitch, yaw, PI)
+		* Mat4::from_translation(-pos)
+}
+
+/// Depth is reversed: near plane maps to 1, far plane to 0.
+fn make_perspective_transform(window_size: PhysicalSize<u32>, projection: Projection) -> Mat4 {
+	let aspect_ratio = window_size.width.max(1) as f32 / window_size.height.max(1) as f32;
+	let Projection { fov, near, far, infinite_far } = projection;
+	let fov = fov.clamp(MIN_FOV, MAX_FOV);
+	if infinite_far {
+		Mat4::perspective_infinite_reverse_rh(fov, aspect_ratio, near)
+	} else {
+		Mat4::perspective_rh(fov, aspect_ratio, far, near)
+	}
+}
+
+impl LoadedLevel {
+	fn update_camera_transform(&self, queue: &Queue) {
+		let camera_transform = make_camera_transform(self.pos, self.yaw, self.pitch, self.roll);
+		queue.write_buffer(&self.camera_transform_buffer, 0, camera_transform.as_bytes());
+	}
+	
+	/// Front face winding to cull by, `None` if culling is disabled.
+	fn cull_winding(&self) -> Option<Winding> {
+		(!self.disable_culling).then_some(self.winding)
+	}
+	
+	/// Whether to draw the low detail face set of a room, by its size relative to its distance from the camera.
+	fn room_lod(&self, room: &RenderRoom) -> bool {
+		self.lod_threshold > 0.0 && room.radius < self.lod_threshold * self.pos.distance(room.center)
+	}
+	
+	/// Rooms rendered with the current room selection and flip group states.
+	fn visible_room_indices(&self) -> Vec<usize> {
+		match self.render_room_index {
+			Some(render_room_index) => vec![render_room_index],
+			None => self
+				.flip_groups
+				.iter()
+				.flat_map(|f| f.rooms.iter().map(|r| r.get(f.show_flipped)))
+				.chain(self.static_room_indices.iter().copied())
+				.collect(),
+		}
+	}
+	
+	/// Rooms of the flip states not selected, drawn ghosted with `show_both_flip_states`.
+	fn ghost_room_indices(&self) -> Vec<usize> {
+		if !self.show_both_flip_states || self.render_room_index.is_some() {
+			return vec![];
+		}
+		self.flip_groups.iter().flat_map(|f| f.rooms.iter().map(|r| r.get(!f.show_flipped))).collect()
+	}
+	
+}
+
+fn yaw_pitch(v: Vec3) -> (f32, f32) {
+	((-v.x).atan2(-v.z), v.y.atan2(v.xz().length()))
+}
+
+fn direction(yaw: f32, pitch: f32) -> Vec3 {
+	let (yaw_sin, yaw_cos) = yaw.sin_cos();
+	let (pitch_sin, pitch_cos) = pitch.sin_cos();
+	Vec3::new(-pitch_cos * yaw_sin, pitch_sin, -pitch_cos * yaw_cos)
+}
+
+fn make_interact_texture(device: &Device, PhysicalSize { width, height }: PhysicalSize<u32>) -> Texture {
+	make::texture(
+		device,
+		Extent3d {
+			width,
+			height,
+			depth_or_array_layers: 1,
+		},
+		TextureDimension::D2,
+		INTERACT_TEXTURE_FORMAT,
+		TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+	)
+}
+
+fn make_atlases_view_gen<T: ReinterpretAsBytes>(
+	device: &Device, queue: &Queue, atlases: &[T], format: TextureFormat, size: u32,
+) -> TextureView {
+	make::texture_view_with_data(
+		device,
+		queue,
+		Extent3d {
+			width: size,
+			height: size,
+			depth_or_array_layers: atlases.len() as u32,
+		},
+		TextureDimension::D2,
+		format,
+		TextureUsages::COPY_DST | TextureUsages::TEXTURE_BINDING,
+		atlases.as_bytes(),
+	)
+}
+
+fn make_atlases_view<T>(device: &Device, queue: &Queue, atlases: &[T], format: TextureFormat) -> TextureView
+where T: ReinterpretAsBytes {
+	make_atlases_view_gen(device, queue, atlases, format, tr1::ATLAS_SIDE_LEN as u32)
+}
+
+fn make_palette_view<T>(device: &Device, queue: &Queue, palette: &T) -> TextureView
+where T: ReinterpretAsBytes {
+	make::texture_view_with_data(
+		device,
+		queue,
+		Extent3d {
+			width: size_of::<T>() as u32,
+			height: 1,
+			depth_or_array_layers: 1,
+		},
+		TextureDimension::D1,
+		TextureFormat::R8Uint,
+		TextureUsages::COPY_DST | TextureUsages::TEXTURE_BINDING,
+		palette.as_bytes(),
+	)
+}
+
+fn read_level<L: Level>(reader: &mut LevelReader) -> Result<Box<L>> {
+	unsafe {
+		let mut level = Box::new(MaybeUninit::uninit());
+		L::read(reader, level.as_mut_ptr()).map_err(TrError::truncated_on_eof)?;
+		Ok(level.assume_init())
+	}
+}
+
+/// Uniforms a view of a level binds, written by the viewer as the window and options change.
+struct ViewBuffers {
+	perspective_transform: Buffer,
+	viewport: Buffer,
+	scroll_offset: Buffer,
+	time: Buffer,
+}
+
+impl ViewBuffers {
+	fn new(device: &Device, window_size: PhysicalSize<u32>) -> Self {
+		let perspective_transform = make_perspective_transform(window_size, DEFAULT_PROJECTION);
+		Self {
+			perspective_transform: make::writable_uniform(device, perspective_transform.as_bytes()),
+			viewport: make::writable_uniform(device, &[0; size_of::<Viewport>()]),
+			scroll_offset: make::writable_uniform(device, &[0; size_of::<Vec2>()]),
+			time: make::writable_uniform(device, 0f32.as_bytes()),
+		}
+	}
+}
+
+/// Entries of a level bind group other than its palette and atlases.
+fn common_entries<'a>(
+	statics_buffer: &'a Buffer, camera_transform_buffer: &'a Buffer, view_buffers: &'a ViewBuffers,
+) -> [BindGroupEntry<'a>; 6] {
+	[
+		make::entry(STATICS_ENTRY, statics_buffer.as_entire_binding()),
+		make::entry(CAMERA_ENTRY, camera_transform_buffer.as_entire_binding()),
+		make::entry(PERSPECTIVE_ENTRY, view_buffers.perspective_transform.as_entire_binding()),
+		make::entry(VIEWPORT_ENTRY, view_buffers.viewport.as_entire_binding()),
+		make::entry(SCROLL_OFFSET_ENTRY, view_buffers.scroll_offset.as_entire_binding()),
+		make::entry(TIME_ENTRY, view_buffers.time.as_entire_binding()),
+	]
+}
+
+impl TextureViews {
+	fn new<L: Level>(device: &Device, queue: &Queue, level: &L) -> Self {
+		let palette_views = level.atlases_palette().zip(level.palette_24bit()).map(|(atlases, palette)| {
+			let palette_view = make_palette_view(device, queue, palette);
+			(palette_view, make_atlases_view(device, queue, atlases, TextureFormat::R8Uint))
+		});
+		let (palette_24bit, atlases_palette) = palette_views.unzip();
+		TextureViews {
+			palette_24bit,
+			palette_32bit: level.palette_32bit().map(|palette| make_palette_view(device, queue, palette)),
+			atlases_palette,
+			atlases_16bit: level.atlases_16bit().map(|atlases| {
+				make_atlases_view(device, queue, atlases, TextureFormat::R16Uint)
+			}),
+			atlases_32bit: level.atlases_32bit().map(|atlases| {
+				make_atlases_view(device, queue, atlases, TextureFormat::R32Uint)
+			}),
+			dummy_palette: make_palette_view(device, queue, &0u8),
+			dummy_atlases: make_atlases_view_gen(device, queue, &[0u8; 2], TextureFormat::R8Uint, 1),
+		}
+	}
+}
+
+/// Upload a level's buffers and bind its textures. The depth target matches `interact_texture`.
+fn upload_level<L: Level>(
+	device: &Device,
+	shared: &TrToolShared,
+	level: &L,
+	level_buffers: LevelBuffers,
+	view_buffers: &ViewBuffers,
+	texture_views: &TextureViews,
+	interact_texture: &Texture,
+) -> LoadedLevel {
+	let LevelBuffers {
+		output: Output {
+			geom_output: geom_buffer::Output {
+				data_buffers,
+				transforms_offset,
+				transform_lights_offset,
+				face_array_offsets_offset,
+				object_textures_offset,
+				sprite_textures_offset,
+			},
+			face_buffer,
+			sprite_buffer,
+			portal_buffer,
+			..
+		},
+		render_rooms,
+		static_room_indices,
+		flip_groups,
+		..
+	} = level_buffers;
+	let statics = Statics {
+		transforms_offset,
+		transform_lights_offset,
+		face_array_offsets_offset,
+		object_textures_offset,
+		object_texture_size: size_of::<L::ObjectTexture>() as u32 / 2,
+		sprite_textures_offset,
+		num_atlases: level.num_atlases() as u32,
+		render_flags: RENDER_FLAG_SHINE | RENDER_FLAG_WATER_WAVES,
+		highlight_object_texture: NO_HIGHLIGHT,
+	};
+	let (yaw, pitch) = yaw_pitch(CAMERA_VEC);
+	let pos = render_rooms
+		.first()
+		.map(|&RenderRoom { center, radius, .. }| center - direction(yaw, pitch) * radius)
+		.unwrap_or_default();
+	let camera_transform = make_camera_transform(pos, yaw, pitch, 0.0);
+	//buffers
+	//copyable for buffer dumps
+	let geom_data_buffers = data_buffers
+		.iter()
+		.map(|data_buffer| make::buffer(device, &**data_buffer, BufferUsages::STORAGE | BufferUsages::COPY_SRC))
+		.collect::<Vec<_>>();
+	let geom_page_bgs = geom_data_buffers.iter().map(|data_buffer| {
+		let entries = [make::entry(GEOM_DATA_ENTRY, data_buffer.as_entire_binding())];
+		make::bind_group(device, &shared.geom_page_layout, &entries)
+	}).collect();
+	let statics_buffer = make::buffer(
+		device,
+		statics.as_bytes(),
+		BufferUsages::UNIFORM | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
+	);
+	let camera_transform_buffer = make::writable_uniform(device, camera_transform.as_bytes());
+	//bind groups
+	let common_entries = common_entries(&statics_buffer, &camera_transform_buffer, view_buffers);
+	let make_bind_group = |entries: [BindGroupEntry; 2]| {
+		let entries = [&common_entries[..], &entries].concat();
+		make::bind_group(device, &shared.bind_group_layout, &entries)
+	};
+	let [palette_24bit_bg, texture_16bit_bg, texture_32bit_bg] = {
+		[TextureMode::Palette, TextureMode::Bit16, TextureMode::Bit32]
+	}.map(|texture_mode| texture_views.texture_entries(texture_mode).map(make_bind_group));
+	let solid_32bit_bg = texture_views.solid_entries(SolidMode::Bit32).map(make_bind_group);
+	let solid_mode = [SolidMode::Bit32, SolidMode::Bit24]
+		.into_iter()
+		.find(|&solid_mode| texture_views.solid_entries(solid_mode).is_some());
+	//unwrap: all formats have at least one texture
+	let texture_mode = [TextureMode::Bit32, TextureMode::Bit16, TextureMode::Palette]
+		.into_iter()
+		.find(|&texture_mode| texture_views.texture_entries(texture_mode).is_some())
+		.unwrap();
+	let shared = Arc::new(LoadedLevelShared { palette_24bit_bg, texture_16bit_bg, texture_32bit_bg });
+	let interact_view = interact_texture.create_view(&TextureViewDescriptor::default());
+	let face_instance_buffer = make::buffer(
+		device,
+		face_buffer.as_bytes(),
+		BufferUsages::VERTEX | BufferUsages::COPY_SRC,
+	);
+	let sprite_instance_buffer = make::buffer(
+		device,
+		sprite_buffer.as_bytes(),
+		BufferUsages::VERTEX | BufferUsages::COPY_DST | BufferUsages::COPY_SRC,
+	);
+	let portal_instance_buffer = make::buffer(device, portal_buffer.as_bytes(), BufferUsages::VERTEX);
+	let background = Background { top: [0; 3], bottom: [0x40, 0x48, 0x58], gradient: false };
+	let sky_instance_buffer = make::buffer(
+		device,
+		background.sky_instance().as_bytes(),
+		BufferUsages::VERTEX | BufferUsages::COPY_DST,
+	);
+	let Extent3d { width, height, .. } = interact_texture.size();
+	LoadedLevel {
+		depth_view: make::depth_view(device, PhysicalSize::new(width, height)),
+		geom_page_bgs,
+		geom_data_buffers,
+		interact_view,
+		face_instance_buffer,
+		face_instances: face_buffer,
+		sprite_instance_buffer,
+		portal_instance_buffer,
+		statics_buffer,
+		camera_transform_buffer,
+		sky_instance_buffer,
+		solid_32bit_bg,
+		shared,
+		solid_mode,
+		texture_mode,
+		pos,
+		yaw,
+		pitch,
+		roll: 0.0,
+		render_rooms,
+		static_room_indices,
+		flip_groups,
+		show_both_flip_states: false,
+		render_room_index: None,
+		show_room_mesh: true,
+		show_static_meshes: true,
+		show_invisible_static_meshes: false,
+		show_entity_meshes: true,
+		show_room_sprites: true,
+		show_entity_sprites: true,
+		show_portals: false,
+		bias_reverse_faces: true,
+		background,
+		hidden_entities: HiddenEntities::Hide,
+		blend_filter: BlendFilter::All,
+		winding: Winding::Cw,
+		disable_culling: false,
+		face_coloring: FaceColoring::Textured,
+		lod_threshold: 0.0,
+	}
+}
+
+/// Load a level for offscreen rendering, with depth and interact targets of `window_size`.
+fn parse_level<L: Level>(
+	device: &Device,
+	queue: &Queue,
+	shared: &TrToolShared,
+	window_size: PhysicalSize<u32>,
+	reader: &mut LevelReader,
+) -> Result<LoadedLevel> {
+	let level = read_level::<L>(reader)?;
+	let level_buffers = level_buffers::build(level.as_ref())?;
+	let view_buffers = ViewBuffers::new(device, window_size);
+	let texture_views = TextureViews::new(device, queue, level.as_ref());
+	let interact_texture = make_interact_texture(device, window_size);
+	Ok(upload_level(
+		device,
+		shared,
+		level.as_ref(),
+		level_buffers,
+		&view_buffers,
+		&texture_views,
+		&interact_texture,
+	))
+}
+
+fn load_level(
+	device: &Device,
+	queue: &Queue,
+	win_size: PhysicalSize<u32>,
+	shared: &TrToolShared,
+	path: &Path,
+	version: Version,
+	large_file_mode: ReadMode,
+) -> Result<LoadedLevel> {
+	let mut reader = version::open_level(path, large_file_mode)?;
+	match version {
+		Version::Tr1 => parse_level::<tr1::Level>(device, queue, shared, win_size, &mut reader),
+		Version::Tr2 => parse_level::<tr2::Level>(device, queue, shared, win_size, &mut reader),
+		Version::Tr3 => parse_level::<tr3::Level>(device, queue, shared, win_size, &mut reader),
+		Version::Tr4 => parse_level::<tr4::Level>(device, queue, shared, win_size, &mut reader),
+		Version::Tr5 => parse_level::<tr5::Level>(device, queue, shared, win_size, &mut reader),
+	}
+}
+
+fn detect_version(path: &Path, learned_versions: &[LearnedVersion]) -> Result<(u32, Option<Version>)> {
+	//only the header is read
+	let mut reader = version::open_level(path, ReadMode::Buffered)?;
+	version::get_version(&mut reader, version::level_extension(path), learned_versions)
+}
+
+/// Faces that write depth, or blended faces drawn over them.
+#[derive(Clone, Copy)]
+enum FacePass {
+	Opaque,
+	Blended,
+}
+
+/// Draw the faces in geometry page `page`, which must be bound.
+#[allow(clippy::too_many_arguments)]
+fn draw_faces<'a>(
+	rpass: &mut CountingPass<'a>,
+	loaded_level: &'a LoadedLevel,
+	rooms: &[&'a RenderRoom],
+	ghost_rooms: &[&'a RenderRoom],
+	solid: Option<(&'a RenderPipeline, &'a BindGroup)>,
+	texture_pls: &'a TexturePipelines,
+	texture_bg: &'a BindGroup,
+	debug_pls: Option<(&'a Windings<RenderPipeline>, &'a Windings<RenderPipeline>)>,//obverse, reverse
+	face_pass: FacePass,
+	page: u8,
+) {
+	let faces = |faces: Range<u32>| page_faces(&loaded_level.face_instances, faces, page);
+	let show_hidden = loaded_level.hidden_entities == HiddenEntities::Show;
+	let blend_filter = loaded_level.blend_filter;
+	let (opaque, blended) = match face_pass {
+		FacePass::Opaque => (blend_filter.opaque(), false),
+		FacePass::Blended => (false, true),
+	};
+	let solid = solid.filter(|_| opaque);
+	if let Some((solid_pl, solid_bg)) = solid {
+		rpass.set_bind_group(0, solid_bg, &[]);
+		rpass.set_pipeline(solid_pl, DrawKind::Solid);
+		if loaded_level.show_static_meshes {
+			for &room in rooms {
+				for mesh in room.static_meshes(loaded_level.show_invisible_static_meshes) {
+					rpass.draw(0..NUM_QUAD_VERTICES, faces(mesh.solid_quads.clone()));
+					rpass.draw(0..NUM_TRI_VERTICES, faces(mesh.solid_tris.clone()));
+				}
+			}
+		}
+		if loaded_level.show_entity_meshes {
+			for &room in rooms {
+				//solid faces of ghosted entities are skipped
+				for mesh in room.entity_meshes(show_hidden) {
+					rpass.draw(0..NUM_QUAD_VERTICES, faces(mesh.solid_quads.clone()));
+					rpass.draw(0..NUM_TRI_VERTICES, faces(mesh.solid_tris.clone()));
+				}
+			}
+		}
+	}
+	rpass.set_bind_group(0, texture_bg, &[]);
+	let winding = loaded_level.cull_winding();
+	let bias_reverse = loaded_level.bias_reverse_faces;
+	let face_pl = |face_blend: FaceBlend, reverse: bool| {
+		let pls = match (debug_pls, face_blend, reverse && bias_reverse) {
+			//debug coloring draws blended faces opaque too
+			(Some((debug_pl, _)), _, false) => debug_pl,
+			(Some((_, debug_reverse_pl)), _, true) => debug_reverse_pl,
+			(None, FaceBlend::Opaque, false) => &texture_pls.opaque,
+			(None, FaceBlend::Additive, false) => &texture_pls.additive,
+			(None, FaceBlend::Alpha, false) => &texture_pls.alpha,
+			(None, FaceBlend::Opaque, true) => &texture_pls.opaque_reverse,
+			(None, FaceBlend::Additive, true) => &texture_pls.additive_reverse,
+			(None, FaceBlend::Alpha, true) => &texture_pls.alpha_reverse,
+		};
+		pls.get(winding)
+	};
+	let draw_blended = blended && blend_filter.blended();
+	//alpha blended faces before additive, additive faces are unaffected by what is drawn after them
+	let face_passes = [
+		(FaceBlend::Opaque, DrawKind::Opaque, opaque),
+		(FaceBlend::Alpha, DrawKind::Alpha, draw_blended),
+		(FaceBlend::Additive, DrawKind::Additive, draw_blended),
+	];
+	for (face_blend, draw_kind, _) in face_passes.into_iter().filter(|&(_, _, draw)| draw) {
+		rpass.set_pipeline(face_pl(face_blend, false), draw_kind);
+		for &room in rooms {
+			if loaded_level.show_room_mesh {
+				let lod = loaded_level.room_lod(room);
+				for RoomMesh { quads, tris } in &room.geom {
+					rpass.draw(0..NUM_QUAD_VERTICES, faces(quads.obverse(face_blend, lod)));
+					rpass.draw(0..NUM_TRI_VERTICES, faces(tris.obverse(face_blend, lod)));
+				}
+			}
+			if loaded_level.show_static_meshes {
+				for mesh in room.static_meshes(loaded_level.show_invisible_static_meshes) {
+					rpass.draw(0..NUM_QUAD_VERTICES, faces(mesh.textured_quads.faces(face_blend)));
+					rpass.draw(0..NUM_TRI_VERTICES, faces(mesh.textured_tris.faces(face_blend)));
+				}
+			}
+			if loaded_level.show_entity_meshes {
+				for mesh in room.entity_meshes(show_hidden) {
+					rpass.draw(0..NUM_QUAD_VERTICES, faces(mesh.textured_quads.faces(face_blend)));
+					rpass.draw(0..NUM_TRI_VERTICES, faces(mesh.textured_tris.faces(face_blend)));
+				}
+			}
+		}
+		if loaded_level.show_room_mesh {
+			rpass.set_pipeline(face_pl(face_blend, true), draw_kind);
+			for &room in rooms {
+				let lod = loaded_level.room_lod(room);
+				for RoomMesh { quads, tris } in &room.geom {
+					rpass.draw_indexed(0..NUM_QUAD_VERTICES, 0, faces(quads.reverse(face_blend, lod)));
+					rpass.draw_indexed(0..NUM_TRI_VERTICES, 0, faces(tris.reverse(face_blend, lod)));
+				}
+			}
+		}
+	}
+	let ghost = loaded_level.hidden_entities == HiddenEntities::Ghost;
+	if blended && ghost && loaded_level.show_entity_meshes {
+		rpass.set_pipeline(texture_pls.ghost.get(loaded_level.cull_winding()), DrawKind::Ghost);
+		for &room in rooms {
+			for mesh in room.hidden_entity_meshes.iter().flatten() {
+				for (show, quads, tris) in [
+					(blend_filter.opaque(), mesh.textured_quads.opaque(), mesh.textured_tris.opaque()),
+					(blend_filter.blended(), mesh.textured_quads.additive(), mesh.textured_tris.additive()),
+					(blend_filter.blended(), mesh.textured_quads.alpha(), mesh.textured_tris.alpha()),
+				] {
+					if show {
+						rpass.draw(0..NUM_QUAD_VERTICES, faces(quads));
+						rpass.draw(0..NUM_TRI_VERTICES, faces(tris));
+					}
+				}
+			}
+		}
+	}
+	if blended && !ghost_rooms.is_empty() {
+		//solid faces of ghosted flip states are skipped too
+		rpass.set_pipeline(texture_pls.ghost.get(winding), DrawKind::Ghost);
+		let face_blends = [
+			(FaceBlend::Opaque, blend_filter.opaque()),
+			(FaceBlend::Alpha, blend_filter.blended()),
+			(FaceBlend::Additive, blend_filter.blended()),
+		];
+		for (face_blend, _) in face_blends.into_iter().filter(|&(_, show)| show) {
+			for &room in ghost_rooms {
+				if loaded_level.show_room_mesh {
+					let lod = loaded_level.room_lod(room);
+					for RoomMesh { quads, tris } in &room.geom {
+						rpass.draw(0..NUM_QUAD_VERTICES, faces(quads.obverse(face_blend, lod)));
+						rpass.draw(0..NUM_TRI_VERTICES, faces(tris.obverse(face_blend, lod)));
+						rpass.draw_indexed(0..NUM_QUAD_VERTICES, 0, faces(quads.reverse(face_blend, lod)));
+						rpass.draw_indexed(0..NUM_TRI_VERTICES, 0, faces(tris.reverse(face_blend, lod)));
+					}
+				}
+				if loaded_level.show_static_meshes {
+					for mesh in room.static_meshes(loaded_level.show_invisible_static_meshes) {
+						rpass.draw(0..NUM_QUAD_VERTICES, faces(mesh.textured_quads.faces(face_blend)));
+						rpass.draw(0..NUM_TRI_VERTICES, faces(mesh.textured_tris.faces(face_blend)));
+					}
+				}
+				if loaded_level.show_entity_meshes {
+					for mesh in room.entity_meshes(show_hidden) {
+						rpass.draw(0..NUM_QUAD_VERTICES, faces(mesh.textured_quads.faces(face_blend)));
+						rpass.draw(0..NUM_TRI_VERTICES, faces(mesh.textured_tris.faces(face_blend)));
+					}
+				}
+			}
+		}
+	}
+}
+
+fn render_level(
+	encoder: &mut CommandEncoder, color_view: &TextureView, shared: &TrToolShared, loaded_level: &LoadedLevel,
+) -> DrawStats {
+	let rpass = encoder.begin_render_pass(&RenderPassDescriptor {
+		label: None,
+		color_attachments: &[
+			Some(RenderPassColorAttachment {
+				ops: Operations {
+					load: LoadOp::Clear(loaded_level.background.clear_color()),
+					store: StoreOp::Store,
+				},
+				resolve_target: None,
+				view: color_view,
+			}),
+			Some(RenderPassColorAttachment {
+				ops: Operations {
+					load: LoadOp::Clear(Color { r: f64::MAX, g: 0.0, b: 0.0, a: 0.0 }),
+					store: StoreOp::Store,
+				},
+				resolve_target: None,
+				view: &loaded_level.interact_view,
+			}),
+		],
+		depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+			depth_ops: Some(Operations {
+				load: LoadOp::Clear(0.0),//reversed depth
+				store: StoreOp::Store,
+			}),
+			stencil_ops: None,
+			view: &loaded_level.depth_view,
+		}),
+		timestamp_writes: None,
+		occlusion_query_set: None,
+	});
+	let mut rpass = CountingPass::new(rpass);
+	let [rooms, ghost_rooms] = [loaded_level.visible_room_indices(), loaded_level.ghost_room_indices()].map(|indices| {
+		indices.into_iter().map(|room_index| &loaded_level.render_rooms[room_index]).collect::<Vec<_>>()
+	});
+	//solid faces have no UVs, so UV check leaves them as they are
+	let debug = !matches!(loaded_level.face_coloring, FaceColoring::Textured | FaceColoring::UvCheck);
+	let solid = loaded_level.solid_mode.as_ref().map(|solid_mode| {
+		let (solid_pl, solid_bg) = match (solid_mode, debug) {
+			(SolidMode::Bit24, false) => (&shared.solid_24bit_pl, &loaded_level.shared.palette_24bit_bg),
+			(SolidMode::Bit24, true) => (&shared.solid_24bit_debug_pl, &loaded_level.shared.palette_24bit_bg),
+			(SolidMode::Bit32, false) => (&shared.solid_32bit_pl, &loaded_level.solid_32bit_bg),
+			(SolidMode::Bit32, true) => (&shared.solid_32bit_debug_pl, &loaded_level.solid_32bit_bg),
+		};
+		(solid_pl.get(loaded_level.cull_winding()), solid_bg.as_ref().unwrap())
+	});
+	let (texture_pls, texture_bg) = match loaded_level.texture_mode {
+		TextureMode::Palette => (&shared.palette_pls, &loaded_level.shared.palette_24bit_bg),
+		TextureMode::Bit16 => (&shared.bit16_pls, &loaded_level.shared.texture_16bit_bg),
+		TextureMode::Bit32 => (&shared.bit32_pls, &loaded_level.shared.texture_32bit_bg),
+	};
+	let texture_bg = texture_bg.as_ref().unwrap();
+	let debug_pls = match loaded_level.face_coloring {
+		FaceColoring::Textured => None,
+		FaceColoring::UvCheck => Some((&shared.texture_uv_check_pl, &shared.texture_uv_check_reverse_pl)),
+		_ => Some((&shared.texture_debug_pl, &shared.texture_debug_reverse_pl)),
+	};
+	let show_hidden = loaded_level.hidden_entities == HiddenEntities::Show;
+	
+	rpass.set_index_buffer(shared.reverse_indices_buffer.slice(..), IndexFormat::Uint16);
+	rpass.set_vertex_buffer(0, shared.face_vertex_index_buffer.slice(..));
+	if loaded_level.background.gradient {
+		rpass.set_vertex_buffer(1, loaded_level.sky_instance_buffer.slice(..));
+		rpass.set_pipeline(&shared.sky_pl, DrawKind::Sky);
+		rpass.draw(0..NUM_QUAD_VERTICES, 0..1);
+	}
+	rpass.set_vertex_buffer(1, loaded_level.face_instance_buffer.slice(..));
+	let ghost = loaded_level.hidden_entities == HiddenEntities::Ghost;
+	if ghost || !ghost_rooms.is_empty() {
+		rpass.set_blend_constant(Color {
+			r: GHOST_OPACITY,
+			g: GHOST_OPACITY,
+			b: GHOST_OPACITY,
+			a: GHOST_OPACITY,
+		});
+	}
+	//each page draws the faces whose face arrays it holds
+	for (page, geom_page_bg) in loaded_level.geom_page_bgs.iter().enumerate() {
+		rpass.set_bind_group(1, geom_page_bg, &[]);
+		draw_faces(
+			&mut rpass,
+			loaded_level,
+			&rooms,
+			&ghost_rooms,
+			solid,
+			texture_pls,
+			texture_bg,
+			debug_pls,
+			FacePass::Opaque,
+			page as u8,
+		);
+	}
+	//sprites are alpha tested and write depth, so they go before blended faces that must blend over them
+	//every page holds the sprite textures, so the last bound page serves sprites
+	rpass.set_vertex_buffer(1, loaded_level.sprite_instance_buffer.slice(..));
+	rpass.set_pipeline(&texture_pls.sprite, DrawKind::Sprite);
+	if loaded_level.show_room_sprites {
+		for &room in &rooms {
+			rpass.draw(0..NUM_QUAD_VERTICES, room.room_sprites.clone());
+		}
+	}
+	if loaded_level.show_entity_sprites {
+		for &room in &rooms {
+			rpass.draw(0..NUM_QUAD_VERTICES, room.entity_sprites(show_hidden));
+		}
+	}
+	rpass.set_vertex_buffer(1, loaded_level.face_instance_buffer.slice(..));
+	for (page, geom_page_bg) in loaded_level.geom_page_bgs.iter().enumerate() {
+		rpass.set_bind_group(1, geom_page_bg, &[]);
+		draw_faces(
+			&mut rpass,
+			loaded_level,
+			&rooms,
+			&ghost_rooms,
+			solid,
+			texture_pls,
+			texture_bg,
+			debug_pls,
+			FacePass::Blended,
+			page as u8,
+		);
+	}
+	if loaded_level.show_entity_sprites && ghost {
+		rpass.set_vertex_buffer(1, loaded_level.sprite_instance_buffer.slice(..));
+		rpass.set_pipeline(&texture_pls.sprite_ghost, DrawKind::Ghost);
+		for &room in &rooms {
+			rpass.draw(0..NUM_QUAD_VERTICES, room.hidden_entity_sprites.clone());
+		}
+	}
+	if !ghost_rooms.is_empty() {
+		rpass.set_vertex_buffer(1, loaded_level.sprite_instance_buffer.slice(..));
+		rpass.set_pipeline(&texture_pls.sprite_ghost, DrawKind::Ghost);
+		for &room in &ghost_rooms {
+			if loaded_level.show_room_sprites {
+				rpass.draw(0..NUM_QUAD_VERTICES, room.room_sprites.clone());
+			}
+			if loaded_level.show_entity_sprites {
+				rpass.draw(0..NUM_QUAD_VERTICES, room.entity_sprites(show_hidden));
+			}
+		}
+	}
+	//translucent, so last
+	if loaded_level.show_portals {
+		rpass.set_vertex_buffer(1, loaded_level.portal_instance_buffer.slice(..));
+		rpass.set_pipeline(&shared.portal_pl, DrawKind::Portal);
+		for &room in &rooms {
+			rpass.draw(0..NUM_QUAD_VERTICES, room.portal_instances.clone());
+		}
+	}
+	rpass.finish()
+}
+
+const FACE_INSTANCE_FORMAT: VertexFormat = VertexFormat::Uint32x3;
+/// Position and sprite texture index, object data index.
+const SPRITE_INSTANCE_FORMATS: [VertexFormat; 2] = [VertexFormat::Sint32x4, VertexFormat::Uint32];
+/// Four world space vertices, color, object data index.
+/// Top and bottom color.
+const SKY_INSTANCE_FORMATS: [VertexFormat; 2] = [VertexFormat::Uint32, VertexFormat::Uint32];
+
+const PORTAL_INSTANCE_FORMATS: [VertexFormat; 6] = [
+	VertexFormat::Sint32x3,
+	VertexFormat::Sint32x3,
+	VertexFormat::Sint32x3,
+	VertexFormat::Sint32x3,
+	VertexFormat::Uint32,
+	VertexFormat::Uint32,
+];
+
+const ADDITIVE_BLEND: BlendState = BlendState {
+	alpha: BlendComponent {
+		src_factor: BlendFactor::One,
+		dst_factor: BlendFactor::One,
+		operation: BlendOperation::Add,
+	},
+	color: BlendComponent {
+		src_factor: BlendFactor::One,
+		dst_factor: BlendFactor::One,
+		operation: BlendOperation::Add,
+	},
+};
+
+const GHOST_BLEND: BlendState = BlendState {
+	alpha: BlendComponent {
+		src_factor: BlendFactor::Constant,
+		dst_factor: BlendFactor::OneMinusConstant,
+		operation: BlendOperation::Add,
+	},
+	color: BlendComponent {
+		src_factor: BlendFactor::Constant,
+		dst_factor: BlendFactor::OneMinusConstant,
+		operation: BlendOperation::Add,
+	},
+};
+
+/// Blend constant for ghosted hidden entities and flip states.
+const GHOST_OPACITY: f64 = 0.25;
+
+const NO_DEPTH_BIAS: DepthBiasState = DepthBiasState { constant: 0, slope_scale: 0.0, clamp: 0.0 };
+
+/// Pushes the reverse side of double-sided room faces behind coplanar faces, such as those of the room across a
+/// portal, so they don't z-fight. Smaller than `OVERLAY_DEPTH_BIAS` in the opposite direction.
+const REVERSE_DEPTH_BIAS: DepthBiasState = DepthBiasState { constant: -4, slope_scale: -0.5, clamp: 0.0 };
+
+/// Pulls overlays in front of coplanar level faces. Depth is reversed, so nearer is greater. For `Depth32Float`,
+/// `constant` is in units of the float precision at the primitive's depth, and `slope_scale` covers oblique faces.
+const OVERLAY_DEPTH_BIAS: DepthBiasState = DepthBiasState { constant: 16, slope_scale: 1.0, clamp: 0.0 };
+
+const INTERACT_TARGET: ColorTargetState = ColorTargetState {
+	format: INTERACT_TEXTURE_FORMAT,
+	blend: None,
+	write_mask: ColorWrites::ALL,
+};
+
+fn make_pipeline(
+	device: &Device,
+	bind_group_layouts: &[&BindGroupLayout],
+	module: &ShaderModule,
+	vs_entry: &str,
+	fs_entry: &str,
+	instance: &[VertexFormat],
+	cull_mode: Option<wgpu::Face>,
+	front_face: FrontFace,
+	blend: Option<BlendState>,
+	interact: Option<ColorTargetState>,
+	depth: Option<DepthBiasState>,
+) -> RenderPipeline {
+	let vertex_step = (VertexStepMode::Vertex, &[VertexFormat::Uint32][..]);
+	let vertex_steps = match instance {
+		[] => &[vertex_step][..],
+		instance => &[vertex_step, (VertexStepMode::Instance, instance)],
+	};
+	let color_target = Some(ColorTargetState {
+		format: TextureFormat::Bgra8Unorm,
+		blend,
+		write_mask: ColorWrites::ALL,
+	});
+	let targets = if interact.is_some() {
+		&[color_target, interact][..]
+	} else {
+		&[color_target]
+	};
+	device.create_render_pipeline(
+		&RenderPipelineDescriptor {
+			label: None,
+			layout: Some(&device.create_pipeline_layout(
+				&PipelineLayoutDescriptor {
+					label: None,
+					bind_group_layouts,
+					push_constant_ranges: &[],
+				},
+			)),
+			vertex: VertexState {
+				module,
+				entry_point: vs_entry,
+				buffers: &make::vertex_buffer_layouts(
+					&mut vec![],
+					vertex_steps,
+				),
+			},
+			primitive: PrimitiveState {
+				topology: PrimitiveTopology::TriangleStrip,
+				cull_mode,
+				front_face,
+				strip_index_format: None,
+				..PrimitiveState::default()//other fields require features
+			},
+			depth_stencil: depth.map(|bias| make::depth_stencil_state(blend.is_none(), bias)),
+			multisample: MultisampleState::default(),
+			fragment: Some(FragmentState {
+				entry_point: fs_entry,
+				module,
+				targets,
+			}),
+			multiview: None,
+		},
+	)
+}
+
+fn make_mesh_shader(device: &Device) -> ShaderModule {
+	let shader_source = face_instance::wgsl_constants() + include_str!("shader/mesh.wgsl");
+	make::shader(device, &shader_source)
+}
+
+fn make_tr_tool_shared(device: &Device) -> TrToolShared {
+	let shader = make_mesh_shader(device);
+	let entries = [
+		(STATICS_ENTRY, make::uniform_layout_entry(size_of::<Statics>()), ShaderStages::VERTEX_FRAGMENT),
+		(CAMERA_ENTRY, make::uniform_layout_entry(size_of::<Mat4>()), ShaderStages::VERTEX),
+		(PERSPECTIVE_ENTRY, make::uniform_layout_entry(size_of::<Mat4>()), ShaderStages::VERTEX),
+		(PALETTE_ENTRY, make::texture_layout_entry(TextureViewDimension::D1), ShaderStages::FRAGMENT),
+		(ATLASES_ENTRY, make::texture_layout_entry(TextureViewDimension::D2Array), ShaderStages::VERTEX_FRAGMENT),
+		(VIEWPORT_ENTRY, make::uniform_layout_entry(size_of::<Viewport>()), ShaderStages::VERTEX),
+		(SCROLL_OFFSET_ENTRY, make::uniform_layout_entry(size_of::<Vec2>()), ShaderStages::VERTEX),
+		(TIME_ENTRY, make::uniform_layout_entry(size_of::<f32>()), ShaderStages::VERTEX),
+	];
+	let bind_group_layout = make::bind_group_layout(device, &entries);
+	let geom_page_entries = [
+		(GEOM_DATA_ENTRY, make::storage_layout_entry(GEOM_BUFFER_SIZE), ShaderStages::VERTEX),
+	];
+	let geom_page_layout = make::bind_group_layout(device, &geom_page_entries);
+	let geom_layouts = &[&bind_group_layout, &geom_page_layout][..];
+	//pipelines
+	let [
+		solid_24bit_pl,
+		solid_32bit_pl,
+		solid_24bit_debug_pl,
+		solid_32bit_debug_pl,
+		texture_debug_pl,
+		texture_debug_reverse_pl,
+		texture_uv_check_pl,
+		texture_uv_check_reverse_pl,
+	] = [
+		("solid_24bit_vs_main", "solid_24bit_fs_main", NO_DEPTH_BIAS),
+		("solid_32bit_vs_main", "solid_32bit_fs_main", NO_DEPTH_BIAS),
+		("solid_24bit_vs_main", "solid_debug_fs_main", NO_DEPTH_BIAS),
+		("solid_32bit_vs_main", "solid_debug_fs_main", NO_DEPTH_BIAS),
+		("texture_vs_main", "texture_debug_fs_main", NO_DEPTH_BIAS),
+		("texture_vs_main", "texture_debug_fs_main", REVERSE_DEPTH_BIAS),
+		("texture_vs_main", "texture_uv_check_fs_main", NO_DEPTH_BIAS),
+		("texture_vs_main", "texture_uv_check_fs_main", REVERSE_DEPTH_BIAS),
+	].map(|(vs_entry, fs_entry, bias)| {
+		Windings::new(|front_face, cull_mode| {
+			make_pipeline(
+				device,
+				geom_layouts,
+				&shader,
+				vs_entry,
+				fs_entry,
+				&[FACE_INSTANCE_FORMAT],
+				cull_mode,
+				front_face,
+				None,
+				Some(INTERACT_TARGET),
+				Some(bias),
+			)
+		})
+	});
+	let texture_modes = ["texture_palette_fs_main", "texture_16bit_fs_main", "texture_32bit_fs_main"];
+	let face_blends = [
+		(None, NO_DEPTH_BIAS),
+		(Some(ADDITIVE_BLEND), NO_DEPTH_BIAS),
+		(Some(BlendState::ALPHA_BLENDING), NO_DEPTH_BIAS),
+		(None, REVERSE_DEPTH_BIAS),
+		(Some(ADDITIVE_BLEND), REVERSE_DEPTH_BIAS),
+		(Some(BlendState::ALPHA_BLENDING), REVERSE_DEPTH_BIAS),
+		(Some(GHOST_BLEND), NO_DEPTH_BIAS),
+	];
+	let sprite_blends = [None, Some(GHOST_BLEND)];
+	let [palette_pls, bit16_pls, bit32_pls] = texture_modes.map(|tex_fs_entry| {
+		let face_pls = face_blends.map(|(blend, bias)| {
+			Windings::new(|front_face, cull_mode| {
+				make_pipeline(
+					device,
+					geom_layouts,
+					&shader,
+					"texture_vs_main",
+					tex_fs_entry,
+					&[FACE_INSTANCE_FORMAT],
+					cull_mode,
+					front_face,
+					blend,
+					Some(INTERACT_TARGET),
+					Some(bias),
+				)
+			})
+		});
+		let [opaque, additive, alpha, opaque_reverse, additive_reverse, alpha_reverse, ghost] = face_pls;
+		//sprite quads are built facing the camera, unaffected by level winding
+		let [sprite, sprite_ghost] = sprite_blends.map(|blend| {
+			make_pipeline(
+				device,
+				geom_layouts,
+				&shader,
+				"sprite_vs_main",
+				tex_fs_entry,
+				&SPRITE_INSTANCE_FORMATS,
+				Some(wgpu::Face::Back),
+				FrontFace::Cw,
+				blend,
+				Some(INTERACT_TARGET),
+				Some(NO_DEPTH_BIAS),
+			)
+		});
+		TexturePipelines {
+			opaque,
+			additive,
+			alpha,
+			opaque_reverse,
+			additive_reverse,
+			alpha_reverse,
+			sprite,
+			ghost,
+			sprite_ghost,
+		}
+	});
+	let portal_shader = make::shader(device, include_str!("shader/portal.wgsl"));
+	let portal_pl = make_pipeline(
+		device,
+		&[&bind_group_layout],
+		&portal_shader,
+		"portal_vs_main",
+		"portal_fs_main",
+		&PORTAL_INSTANCE_FORMATS,
+		None,
+		FrontFace::Cw,
+		Some(BlendState::ALPHA_BLENDING),
+		Some(INTERACT_TARGET),
+		Some(OVERLAY_DEPTH_BIAS),
+	);
+	let sky_shader = make::shader(device, include_str!("shader/sky.wgsl"));
+	//no depth write, so geometry always draws over it
+	let sky_pl = make_pipeline(
+		device,
+		&[],
+		&sky_shader,
+		"sky_vs_main",
+		"sky_fs_main",
+		&SKY_INSTANCE_FORMATS,
+		None,
+		FrontFace::Cw,
+		Some(BlendState::REPLACE),
+		Some(INTERACT_TARGET),
+		Some(NO_DEPTH_BIAS),
+	);
+	let face_vertex_index_buffer = make::buffer(device, FACE_VERTEX_INDICES.as_bytes(), BufferUsages::VERTEX);
+	let reverse_indices_buffer = make::buffer(device, REVERSE_INDICES.as_bytes(), BufferUsages::INDEX);
+	TrToolShared {
+		bind_group_layout,
+		geom_page_layout,
+		solid_24bit_pl,
+		solid_32bit_pl,
+		solid_24bit_debug_pl,
+		solid_32bit_debug_pl,
+		texture_debug_pl,
+		texture_debug_reverse_pl,
+		texture_uv_check_pl,
+		texture_uv_check_reverse_pl,
+		palette_pls,
+		bit16_pls,
+		bit32_pls,
+		portal_pl,
+		sky_pl,
+		face_vertex_index_buffer,
+		reverse_indices_buffer,
+	}
+}
+
@@ -1,5 +1,4 @@
-use crate::vec_tail::VecTail;
-use winit::dpi::PhysicalSize;
+use crate::{gpu::PhysicalSize, vec_tail::VecTail};
 use std::num::NonZeroU64;
 use wgpu::{
 	util::{BufferInitDescriptor, DeviceExt, TextureDataOrder}, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, Buffer, BufferBindingType, BufferUsages, CompareFunction, DepthBiasState, DepthStencilState, Device, Extent3d, Queue, ShaderModule, ShaderModuleDescriptor, ShaderSource, ShaderStages, StencilState, Texture, TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType, TextureUsages, TextureView, TextureViewDescriptor, TextureViewDimension, VertexAttribute, VertexBufferLayout, VertexFormat, VertexStepMode
@@ -77,15 +76,21 @@ pub fn texture(
 	device.create_texture(&texture_desc(size, dimension, format, usage))
 }
 
+/// 2D textures get array views, which a one layer texture otherwise wouldn't.
 pub fn texture_view_with_data(
 	device: &Device, queue: &Queue, size: Extent3d, dimension: TextureDimension, format: TextureFormat,
 	usage: TextureUsages, data: &[u8],
 ) -> TextureView {
+	let view_dimension = match dimension {
+		TextureDimension::D1 => TextureViewDimension::D1,
+		TextureDimension::D2 => TextureViewDimension::D2Array,
+		TextureDimension::D3 => TextureViewDimension::D3,
+	};
 	device
 		.create_texture_with_data(
 			queue, &texture_desc(size, dimension, format, usage), TextureDataOrder::default(), data,
 		)
-		.create_view(&TextureViewDescriptor::default())
+		.create_view(&TextureViewDescriptor { dimension: Some(view_dimension), ..TextureViewDescriptor::default() })
 }
 
 pub fn depth_view(device: &Device, PhysicalSize { width, height }: PhysicalSize<u32>) -> TextureView {
@@ -122,10 +127,10 @@ pub fn vertex_buffer_layouts<'a>(
 	buffers
 }
 
-pub fn depth_stencil_state(depth_write_enabled: bool) -> DepthStencilState {
+pub fn depth_stencil_state(depth_write_enabled: bool, bias: DepthBiasState) -> DepthStencilState {
 	DepthStencilState {
-		bias: DepthBiasState::default(),
-		depth_compare: CompareFunction::Less,
+		bias,
+		depth_compare: CompareFunction::Greater,//reversed depth
 		depth_write_enabled,
 		format: TextureFormat::Depth32Float,
 		stencil: StencilState::default(),
@@ -0,0 +1,118 @@
+use tr_model::{tr1, tr2, tr4};
+
+/// Palette index 0 is transparent.
+pub fn palette_images_to_rgba(
+	palette: &[tr1::Color24Bit; tr1::PALETTE_LEN], atlases: &[[u8; tr1::ATLAS_PIXELS]],
+) -> Vec<u8> {
+	atlases
+		.iter()
+		.flatten()
+		.flat_map(|&color_index| {
+			let tr1::Color24Bit { r, g, b } = palette[color_index as usize];
+			let [r, g, b] = [r, g, b].map(|c| c << 2);
+			[r, g, b, (color_index != 0) as u8 * 255]
+		})
+		.collect::<Vec<_>>()
+}
+
+pub fn bit16_images_to_rgba(atlases: &[[tr2::Color16BitArgb; tr1::ATLAS_PIXELS]]) -> Vec<u8> {
+	atlases
+		.iter()
+		.flatten()
+		.flat_map(|color| {
+			let [r, g, b] = [color.r(), color.g(), color.b()].map(|c| c << 3);
+			[r, g, b, color.a() as u8 * 255]
+		})
+		.collect::<Vec<_>>()
+}
+
+pub fn bit32_images_to_rgba(atlases: &[[tr4::Color32BitBgra; tr1::ATLAS_PIXELS]]) -> Vec<u8> {
+	atlases
+		.iter()
+		.flatten()
+		.flat_map(|&tr4::Color32BitBgra { b, g, r, a }| [r, g, b, a])
+		.collect::<Vec<_>>()
+}
+
+/// One opaque pixel per entry.
+pub fn palette_24bit_to_rgba(palette: &[tr1::Color24Bit; tr1::PALETTE_LEN]) -> Vec<u8> {
+	palette
+		.iter()
+		.flat_map(|&tr1::Color24Bit { r, g, b }| [r << 2, g << 2, b << 2, 255])
+		.collect::<Vec<_>>()
+}
+
+/// One opaque pixel per entry.
+pub fn palette_32bit_to_rgba(palette: &[tr2::Color32BitRgb; tr1::PALETTE_LEN]) -> Vec<u8> {
+	palette
+		.iter()
+		.flat_map(|&tr2::Color32BitRgb { r, g, b }| [r, g, b, 255])
+		.collect::<Vec<_>>()
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{array, mem};
+	use super::*;
+	
+	/// Pixel `index` of the output.
+	fn pixel(rgba: &[u8], index: usize) -> [u8; 4] {
+		rgba[index * 4..][..4].try_into().unwrap()
+	}
+	
+	fn palette_24bit() -> [tr1::Color24Bit; tr1::PALETTE_LEN] {
+		let mut palette = [tr1::Color24Bit { r: 0, g: 0, b: 0 }; tr1::PALETTE_LEN];
+		palette[0] = tr1::Color24Bit { r: 1, g: 2, b: 3 };
+		palette[1] = tr1::Color24Bit { r: 63, g: 32, b: 0 };
+		palette
+	}
+	
+	#[test]
+	fn palette_images() {
+		let mut atlases = vec![[0; tr1::ATLAS_PIXELS]; 2];
+		atlases[0][1] = 1;
+		atlases[1][0] = 1;
+		let rgba = palette_images_to_rgba(&palette_24bit(), &atlases);
+		assert_eq!(rgba.len(), 2 * tr1::ATLAS_PIXELS * 4);
+		//6-bit channels scaled to 8 bits, index 0 keeps its color but is transparent
+		assert_eq!(pixel(&rgba, 0), [4, 8, 12, 0]);
+		assert_eq!(pixel(&rgba, 1), [252, 128, 0, 255]);
+		assert_eq!(pixel(&rgba, tr1::ATLAS_PIXELS), [252, 128, 0, 255]);
+	}
+	
+	#[test]
+	fn bit16_images() {
+		//read from level bytes, it has no constructor. Safety: repr(C) over a u16.
+		let argb = |bits: u16| unsafe { mem::transmute::<u16, tr2::Color16BitArgb>(bits) };
+		let mut atlas = array::from_fn(|_| argb(0));
+		atlas[0] = argb(0x8000 | 31 << 10 | 16 << 5 | 1);
+		atlas[1] = argb(31 << 10 | 16 << 5 | 1);
+		let rgba = bit16_images_to_rgba(&[atlas]);
+		assert_eq!(rgba.len(), tr1::ATLAS_PIXELS * 4);
+		//5-bit channels scaled to 8 bits, the top bit is alpha
+		assert_eq!(pixel(&rgba, 0), [248, 128, 8, 255]);
+		assert_eq!(pixel(&rgba, 1), [248, 128, 8, 0]);
+	}
+	
+	#[test]
+	fn bit32_images() {
+		let mut atlas = array::from_fn(|_| tr4::Color32BitBgra { b: 0, g: 0, r: 0, a: 0 });
+		atlas[0] = tr4::Color32BitBgra { b: 1, g: 2, r: 3, a: 4 };
+		let rgba = bit32_images_to_rgba(&[atlas]);
+		assert_eq!(rgba.len(), tr1::ATLAS_PIXELS * 4);
+		assert_eq!(pixel(&rgba, 0), [3, 2, 1, 4]);
+	}
+	
+	#[test]
+	fn palettes() {
+		let rgba = palette_24bit_to_rgba(&palette_24bit());
+		assert_eq!(rgba.len(), tr1::PALETTE_LEN * 4);
+		assert_eq!(pixel(&rgba, 0), [4, 8, 12, 255]);
+		assert_eq!(pixel(&rgba, 1), [252, 128, 0, 255]);
+		let mut palette = [(); tr1::PALETTE_LEN].map(|_| tr2::Color32BitRgb { r: 0, g: 0, b: 0 });
+		palette[1] = tr2::Color32BitRgb { r: 1, g: 2, b: 3 };
+		let rgba = palette_32bit_to_rgba(&palette);
+		assert_eq!(rgba.len(), tr1::PALETTE_LEN * 4);
+		assert_eq!(pixel(&rgba, 1), [1, 2, 3, 255]);
+	}
+}
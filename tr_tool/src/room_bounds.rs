@@ -0,0 +1,28 @@
+use glam::{IVec3, Vec3};
+use shared::min_max::{MinMax, VecMinMaxFromIterator};
+use crate::{tr_traits::{Room, RoomVertex}, SECTOR_SIZE};
+
+/// World space box of the room as declared: x and z from the sector grid, y from `y_extent`.
+pub fn declared<R: Room>(room: &R) -> MinMax<Vec3> {
+	let [top, bottom] = room.y_extent();
+	let tr_model::tr1::NumSectors { x, z } = *room.num_sectors();
+	let pos = room.pos();
+	let min = IVec3::new(pos.x, top.min(bottom), pos.z).as_vec3();
+	let size = Vec3::new(x as f32 * SECTOR_SIZE, (bottom - top).abs() as f32, z as f32 * SECTOR_SIZE);
+	MinMax { min, max: min + size }
+}
+
+/// World space box of the room's vertices, `None` if it has none.
+pub fn vertices<R: Room>(room: &R) -> Option<MinMax<Vec3>> {
+	let pos = room.pos().as_vec3();
+	let MinMax { min, max } = room.vertices().iter().map(|v| v.pos()).min_max()?;
+	Some(MinMax { min: min + pos, max: max + pos })
+}
+
+/// Some side of the declared box is more than a sector from the vertex box. The sector grid includes the outer
+/// walls, so a well formed room differs by one sector on each horizontal side.
+pub fn mismatched(declared: &MinMax<Vec3>, vertices: &MinMax<Vec3>) -> bool {
+	let min_diff = (declared.min - vertices.min).abs();
+	let max_diff = (declared.max - vertices.max).abs();
+	min_diff.max(max_diff).max_element() > SECTOR_SIZE
+}
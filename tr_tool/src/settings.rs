@@ -0,0 +1,36 @@
+use std::{collections::BTreeMap, fs, io::{Error, ErrorKind}};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::{console, version::{LearnedVersion, ReadMode}};
+
+/// Next to the `dir` file of the file dialog.
+pub const PATH: &str = "settings.json";
+
+/// Kept between runs in a JSON file in the working directory.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Settings {
+	#[serde(default)]
+	pub learned_versions: Vec<LearnedVersion>,
+	/// Render options changed on a level, by level key. Kept as plain JSON so one bad entry is skipped on use
+	/// rather than failing the whole file.
+	#[serde(default)]
+	pub level_overrides: BTreeMap<String, Value>,
+	/// How large level files are read for parsing.
+	#[serde(default)]
+	pub large_file_mode: ReadMode,
+}
+
+impl Settings {
+	/// Default if there is no settings file yet. An unreadable file is reported and replaced by default.
+	pub fn load() -> Self {
+		let result = fs::read_to_string(PATH).and_then(|json| serde_json::from_str(&json).map_err(Error::other));
+		match result {
+			Ok(settings) => settings,
+			Err(e) if e.kind() == ErrorKind::NotFound => Self::default(),
+			Err(e) => {
+				console::log_warn!("failed to read settings: {}", e);
+				Self::default()
+			},
+		}
+	}
+}
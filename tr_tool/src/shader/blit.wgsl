@@ -0,0 +1,22 @@
+//copies an offscreen render over the whole viewport, which egui sets to the paint callback rect
+
+@group(0) @binding(0) var image: texture_2d<f32>;
+
+struct BlitVTF {
+	@builtin(position) position: vec4f,
+	@location(0) uv: vec2f,
+}
+
+@vertex
+fn blit_vs_main(@location(0) face_vertex_index: u32) -> BlitVTF {
+	let uv = vec2f(f32(((face_vertex_index + 1) / 2) % 2), f32(face_vertex_index / 2));
+	let position = vec4f(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+	return BlitVTF(position, uv);
+}
+
+@fragment
+fn blit_fs_main(vtf: BlitVTF) -> @location(0) vec4f {
+	let size = textureDimensions(image, 0);
+	let pixel = min(vec2u(vtf.uv * vec2f(size)), size - 1);
+	return textureLoad(image, pixel, 0);
+}
@@ -1,17 +1,59 @@
 struct DataOffsets {
 	transforms_offset: u32,//16-byte units
+	transform_lights_offset: u32,//4-byte units
 	face_array_offsets_offset: u32,//4-byte units
 	object_textures_offset: u32,//2-byte units
 	object_texture_size: u32,//2-byte units
 	sprite_textures_offset: u32,//2-byte units
 	num_atlases: u32,
-}
-
-//2MB
-@group(0) @binding(0) var<storage> data: array<vec4u, 131072>;
+	render_flags: u32,
+	highlight_object_texture: u32,
+}
+
+//FACE_* constants are prepended from face_instance.rs
+const RENDER_FLAG_TINT_DEGENERATE: u32 = 1;
+const RENDER_FLAG_SHINE: u32 = 2;
+const RENDER_FACE_COLORING_SHIFT: u32 = 2;
+const RENDER_FACE_COLORING_MASK: u32 = 3;
+const FACE_COLORING_ATLAS: u32 = 2;
+const FACE_COLORING_BLEND_MODE: u32 = 3;
+const RENDER_SHADING_SHIFT: u32 = 4;
+const RENDER_SHADING_MASK: u32 = 3;
+const SHADING_OFF: u32 = 0;
+const SHADING_SMOOTH: u32 = 2;
+//brightness of faces edge-on to the headlight
+const HEADLIGHT_AMBIENT: f32 = 0.35;
+const RENDER_FLAG_WATER_WAVES: u32 = 64;
+//TR2-4 room vertices, the only ones with attributes, 2-byte units
+const ROOM_VERTEX_ATTRS_SIZE: u32 = 6;
+const ROOM_VERTEX_ATTRS_OFFSET: u32 = 4;
+//ROOM_VERTEX_WATER_WAVE in tr_traits.rs
+const ROOM_VERTEX_WATER_WAVE: u32 = 0x2000;
+//REVERSED_FACE_ARRAY in geom_buffer.rs
+const REVERSED_FACE_ARRAY: u32 = 0x8000;
+const WATER_WAVE_AMPLITUDE: f32 = 16.0;
+const WATER_WAVE_SPEED: f32 = 2.0;//radians per second
+const WATER_WAVE_PHASE_SCALE: f32 = 0.006;//radians per world unit
+//mesh vertices written with normals, 2-byte units
+const MESH_VERTEX_SIZE: u32 = 8;
+const DEGENERATE_TINT: vec4f = vec4f(1.0, 0.0, 1.0, 1.0);
+//TextureVTF tint bits
+const TINT_DEGENERATE: u32 = 1;
+const TINT_HIGHLIGHT: u32 = 2;
+const HIGHLIGHT_COLOR: vec3f = vec3f(0.0, 1.0, 1.0);
+const HIGHLIGHT_MIX: f32 = 0.6;
+const BLEND_MODE_OPAQUE: u32 = 0;
+//texels with alpha below this are cut out of alpha-tested faces
+const ALPHA_TEST_THRESHOLD: f32 = 0.5;
+//atlas pixels per checker square
+const UV_CHECK_SIZE: f32 = 8.0;
+
+//one geometry page, up to 4MB
+@group(1) @binding(0) var<storage> data: array<vec4u>;
 @group(0) @binding(1) var<uniform> data_offsets: DataOffsets;
 @group(0) @binding(2) var<uniform> camera_transform: mat4x4f;
 @group(0) @binding(3) var<uniform> perspective_transform: mat4x4f;
+@group(0) @binding(8) var<uniform> time: f32;//seconds, for water waves
 
 fn get_data_u32(offset: u32) -> u32 {
 	return data[offset / 4][offset % 4];
@@ -23,8 +65,13 @@ fn get_data_u16(offset: u32) -> u32 {
 
 struct PositionTexture {
 	position: vec4f,
+	view_position: vec3f,
 	texture_index: u32,
 	object_id: u32,
+	flags: u32,
+	view_normal: vec3f,//zero if the vertex has no normal
+	face_vertex_index: u32,//after winding reversal, for looking up the matching uv
+	light: vec3f,//of the transform, multiplied into the face color
 }
 
 fn get_position_texture(face: vec3u, face_vertex_index: u32) -> PositionTexture {
@@ -32,6 +79,7 @@ fn get_position_texture(face: vec3u, face_vertex_index: u32) -> PositionTexture
 	let face_array_index = face.x & 0xFFFF;
 	let face_index = face.x >> 16;
 	let transform_index = face.y & 0xFFFF;
+	let flags = face.y >> 16;
 	let object_id = face.z;
 	//transform
 	let transform_offset = data_offsets.transforms_offset + transform_index * 4;
@@ -41,16 +89,24 @@ fn get_position_texture(face: vec3u, face_vertex_index: u32) -> PositionTexture
 		bitcast<vec4f>(data[transform_offset + 2]),
 		bitcast<vec4f>(data[transform_offset + 3]),
 	);
+	let light = unpack4x8unorm(get_data_u32(data_offsets.transform_lights_offset + transform_index)).rgb;
 	//position
 	let face_array_offset = get_data_u32(data_offsets.face_array_offsets_offset + face_array_index);//4-byte units
 	let vertex_array_offset = get_data_u32(face_array_offset);//4-byte units
 	let vertex_size = get_data_u32(vertex_array_offset);//2-byte units
 	let face_info_packed = get_data_u32(face_array_offset + 1);
-	let face_size = face_info_packed & 0xFFFF;//2-byte units
-	let face_texture_index_offset = face_info_packed >> 16;//2-byte units
+	let face_size = face_info_packed & 0xFFFF & ~REVERSED_FACE_ARRAY;//2-byte units
+	let face_texture_index_offset = face_info_packed >> 16;//2-byte units, also the number of vertex indices
 	let face_offset = (face_array_offset + 2) * 2 + (face_index * face_size);//2-byte units
-	let vertex_index = get_data_u16(face_offset + face_vertex_index);
+	var wound_vertex_index = face_vertex_index;
+	if (face_info_packed & REVERSED_FACE_ARRAY) != 0 {
+		//keeps the first vertex, so a quad's two triangles still share its diagonal
+		wound_vertex_index = (face_texture_index_offset - face_vertex_index) % face_texture_index_offset;
+	}
+	let vertex_index = get_data_u16(face_offset + wound_vertex_index);
 	var vertex_relative: vec3f;
+	var normal_relative = vec3f(0.0);
+	var water_wave = false;
 	if vertex_size == 14 {
 		//TR5
 		let vertex_offset = vertex_array_offset + 1 + (vertex_index * 7);//4-byte units
@@ -59,6 +115,11 @@ fn get_position_texture(face: vec3u, face_vertex_index: u32) -> PositionTexture
 			bitcast<f32>(get_data_u32(vertex_offset + 1)),
 			bitcast<f32>(get_data_u32(vertex_offset + 2)),
 		);
+		normal_relative = vec3f(
+			bitcast<f32>(get_data_u32(vertex_offset + 3)),
+			bitcast<f32>(get_data_u32(vertex_offset + 4)),
+			bitcast<f32>(get_data_u32(vertex_offset + 5)),
+		);
 	} else {
 		//TR1234
 		let vertex_offset = (vertex_array_offset + 1) * 2 + (vertex_index * vertex_size);//2-byte units
@@ -69,12 +130,39 @@ fn get_position_texture(face: vec3u, face_vertex_index: u32) -> PositionTexture
 		);
 		let vertex_signed = vec3i(vertex_unsigned << vec3u(16)) >> vec3u(16);//interpret lower 16 as i16
 		vertex_relative = vec3f(vertex_signed);
+		if vertex_size == MESH_VERTEX_SIZE {
+			let normal_unsigned = vec3u(
+				get_data_u16(vertex_offset + 3),
+				get_data_u16(vertex_offset + 4),
+				get_data_u16(vertex_offset + 5),
+			);
+			normal_relative = vec3f(vec3i(normal_unsigned << vec3u(16)) >> vec3u(16));//scale dropped by normalize
+		} else if vertex_size == ROOM_VERTEX_ATTRS_SIZE {
+			let attrs = get_data_u16(vertex_offset + ROOM_VERTEX_ATTRS_OFFSET);
+			water_wave = (attrs & ROOM_VERTEX_WATER_WAVE) != 0;
+		}
 	}
-	let vertex_absolute = local_transform * vec4f(vertex_relative, 1.0);
-	let position = perspective_transform * camera_transform * vertex_absolute;
+	var vertex_absolute = local_transform * vec4f(vertex_relative, 1.0);
+	if water_wave && (data_offsets.render_flags & RENDER_FLAG_WATER_WAVES) != 0 {
+		//phase from the absolute position so vertices shared between rooms move together
+		let phase = (vertex_absolute.x + vertex_absolute.z) * WATER_WAVE_PHASE_SCALE;
+		vertex_absolute.y += WATER_WAVE_AMPLITUDE * sin(time * WATER_WAVE_SPEED + phase);
+	}
+	let view_position = camera_transform * vertex_absolute;
+	let position = perspective_transform * view_position;
+	let view_normal = (camera_transform * local_transform * vec4f(normal_relative, 0.0)).xyz;
 	//texture
 	let texture_index = get_data_u16(face_offset + face_texture_index_offset);
-	return PositionTexture(position, texture_index, object_id);
+	return PositionTexture(
+		position,
+		view_position.xyz,
+		texture_index,
+		object_id,
+		flags,
+		view_normal,
+		wound_vertex_index,
+		light,
+	);
 }
 
 struct TextureVTF {
@@ -82,6 +170,13 @@ struct TextureVTF {
 	@location(0) atlas_index: u32,
 	@location(1) uv: vec2f,
 	@location(2) object_id: u32,
+	@location(3) tint: u32,
+	@location(4) view_position: vec3f,
+	@location(5) shine: f32,
+	@location(6) alpha_test: u32,
+	@location(7) debug_key: u32,//colored by texture_debug_fs_main
+	@location(8) view_normal: vec3f,
+	@location(9) light: vec3f,
 }
 
 @vertex
@@ -94,7 +189,9 @@ fn texture_vs_main(
 	let object_texture_index = position_texture.texture_index & 0x3FFF;
 	let object_id = position_texture.object_id;
 	let object_texture_offset = data_offsets.object_textures_offset + object_texture_index * data_offsets.object_texture_size;
-	// let blend_mode = get_data_u16(object_texture_offset);
+	let blend_mode = get_data_u16(object_texture_offset);
+	//additive textures are cut out too, only opaque ones draw their transparent texels
+	let alpha_test = u32(blend_mode != BLEND_MODE_OPAQUE);
 	let atlas_index = get_data_u16(object_texture_offset + 1) & 0x7FFF;
 	var uvs_offset: u32;
 	if data_offsets.object_texture_size == 10 {
@@ -104,19 +201,55 @@ fn texture_vs_main(
 		//TR45
 		uvs_offset = 3u;
 	}
-	let uv_offset = (object_texture_offset + uvs_offset) + face_vertex_index * 2;
+	let uv_offset = (object_texture_offset + uvs_offset) + position_texture.face_vertex_index * 2;
 	let uv_subpixel = vec2u(
 		get_data_u16(uv_offset),
 		get_data_u16(uv_offset + 1),
 	);
 	let uv = vec2f((uv_subpixel + 128) / 256);//round to nearest whole pixel
-	return TextureVTF(position, atlas_index, uv, object_id);
+	let degenerate = position_texture.flags & FACE_FLAG_DEGENERATE_TEXTURE;
+	var tint = select(0u, TINT_DEGENERATE, (degenerate & data_offsets.render_flags & RENDER_FLAG_TINT_DEGENERATE) != 0);
+	if object_texture_index == data_offsets.highlight_object_texture {
+		tint |= TINT_HIGHLIGHT;
+	}
+	var shine = 0.0;
+	if (data_offsets.render_flags & RENDER_FLAG_SHINE) != 0 {
+		shine = f32((position_texture.flags >> FACE_SHINE_SHIFT) & FACE_SHINE_MASK) / f32(FACE_SHINE_MASK);
+	}
+	var debug_key: u32;
+	switch (data_offsets.render_flags >> RENDER_FACE_COLORING_SHIFT) & RENDER_FACE_COLORING_MASK {
+		case FACE_COLORING_ATLAS: {
+			debug_key = atlas_index;
+		}
+		case FACE_COLORING_BLEND_MODE: {
+			debug_key = blend_mode;
+		}
+		default: {//by texture id
+			debug_key = object_texture_index;
+		}
+	}
+	return TextureVTF(
+		position,
+		atlas_index,
+		uv,
+		object_id,
+		tint,
+		position_texture.view_position,
+		shine,
+		alpha_test,
+		debug_key,
+		position_texture.view_normal,
+		position_texture.light,
+	);
 }
 
 struct SolidVTF {
 	@builtin(position) position: vec4f,
 	@location(0) color_index: u32,
 	@location(1) object_id: u32,
+	@location(2) view_position: vec3f,
+	@location(3) view_normal: vec3f,
+	@location(4) light: vec3f,
 }
 
 fn solid_vs(
@@ -128,7 +261,14 @@ fn solid_vs(
 	let position = position_texture.position;
 	let color_index = (position_texture.texture_index >> (mode * 8)) & 0xFF;
 	let object_id = position_texture.object_id;
-	return SolidVTF(position, color_index, object_id);
+	return SolidVTF(
+		position,
+		color_index,
+		object_id,
+		position_texture.view_position,
+		position_texture.view_normal,
+		position_texture.light,
+	);
 }
 
 @vertex
@@ -147,14 +287,16 @@ fn solid_32bit_vs_main(
 	return solid_vs(face_vertex_index, face, 1u);
 }
 
+const SPRITE_DEPTH_BIAS: f32 = 32.0;//world units
+
 @vertex
 fn sprite_vs_main(
 	@location(0) face_vertex_index: u32,//vertex
 	@location(1) sprite: vec4i,//instance
+	@location(2) object_id: u32,//instance
 ) -> TextureVTF {
 	let uv_index = vec2u(((face_vertex_index + 1) / 2) % 2, face_vertex_index / 2);
-	let sprite_texture_index = u32(sprite.w) & 0xFFFF;
-	let object_id = u32(sprite.w) >> 16;
+	let sprite_texture_index = u32(sprite.w);
 	let sprite_texture_offset = data_offsets.sprite_textures_offset + sprite_texture_index * 8;//8: size of SpriteTexture in u16s
 	let atlas_index = get_data_u16(sprite_texture_offset);
 	let sprite_pos_packed = get_data_u16(sprite_texture_offset + 1);
@@ -171,13 +313,27 @@ fn sprite_vs_main(
 	let world_offset_int = vec2i(world_offset_unsigned << vec2u(16)) >> vec2u(16);//interpret lower 16 as i16
 	let world_offset = vec2f(world_offset_int);
 	let vertex = vec4f(vec3f(sprite.xyz), 1.0);
-	var position_camera = camera_transform * vertex;
+	let anchor_camera = camera_transform * vertex;
+	//sprite is camera-facing and sized in world units
+	var position_camera = anchor_camera;
 	position_camera.x += world_offset.x;
-	position_camera.y -= world_offset.y - 0;//constant: move sprites up to prevent ground clipping
-	let position = perspective_transform * position_camera;
+	position_camera.y -= world_offset.y;
+	var position = perspective_transform * position_camera;
+	/*
+	pickups sit exactly on the floor, so take depth from a point slightly toward the camera to prevent the
+	floor from z-fighting with the bottom edge, without changing the sprite's screen size
+	*/
+	//clamped to the near plane so close sprites aren't clipped, z where clip z equals w in either projection
+	let near = perspective_transform[3].z / (perspective_transform[2].z + 1.0);
+	var depth_camera = anchor_camera;
+	depth_camera.z = min(depth_camera.z + SPRITE_DEPTH_BIAS, -near);
+	let depth_position = perspective_transform * depth_camera;
+	position.z = depth_position.z / depth_position.w * position.w;
 	let uv_int = sprite_pos + sprite_size * uv_index;
 	let uv = vec2f(uv_int);
-	return TextureVTF(position, atlas_index, uv, object_id);
+	return TextureVTF(
+		position, atlas_index, uv, object_id, 0u, position_camera.xyz, 0.0, 1u, 0u, vec3f(0.0), vec3f(1.0),
+	);
 }
 
 struct Out {
@@ -207,75 +363,153 @@ fn get_palette_color(color_index: u32, color_size: u32, divisor: f32) -> vec4f {
 	);
 }
 
+//light from the camera, so faces darken as they turn away from the view
+fn headlight(view_position: vec3f, view_normal: vec3f) -> f32 {
+	//derivatives before any branch, they need uniform control flow
+	let face_normal = cross(dpdx(view_position), dpdy(view_position));
+	let shading = (data_offsets.render_flags >> RENDER_SHADING_SHIFT) & RENDER_SHADING_MASK;
+	if shading == SHADING_OFF {
+		return 1.0;
+	}
+	var normal = normalize(face_normal);
+	if shading == SHADING_SMOOTH && dot(view_normal, view_normal) > 0.0 {
+		normal = normalize(view_normal);
+	}
+	let facing = abs(dot(normal, normalize(-view_position)));
+	return mix(HEADLIGHT_AMBIENT, 1.0, facing);
+}
+
+fn solid_out(vtf: SolidVTF, color: vec4f) -> Out {
+	let lit = vec4f(color.rgb * vtf.light * headlight(vtf.view_position, vtf.view_normal), color.a);
+	return Out(lit, vtf.object_id);
+}
+
 @fragment
 fn solid_24bit_fs_main(vtf: SolidVTF) -> Out {
 	let color = get_palette_color(vtf.color_index, 3u, 63.0);
-	return Out(color, vtf.object_id);
+	return solid_out(vtf, color);
 }
 
 @fragment
 fn solid_32bit_fs_main(vtf: SolidVTF) -> Out {
 	let color = get_palette_color(vtf.color_index, 4u, 255.0);
-	return Out(color, vtf.object_id);
+	return solid_out(vtf, color);
+}
+
+//pseudo-random opaque color, so neighboring keys contrast
+fn debug_color(key: u32) -> vec4f {
+	var hash = key * 0x9E3779B9u;
+	hash ^= hash >> 16;
+	hash *= 0x85EBCA6Bu;
+	hash ^= hash >> 13;
+	let channels = (vec3u(hash) >> vec3u(0, 8, 16)) & vec3u(0xFF);
+	return vec4f(vec3f(channels) / 255.0, 1.0);
+}
+
+@fragment
+fn solid_debug_fs_main(vtf: SolidVTF) -> Out {
+	return Out(debug_color(vtf.color_index), vtf.object_id);
 }
 
 fn get_pixel(atlas_index: u32, uv: vec2f) -> u32 {
 	return textureLoad(atlases, vec2i(uv), atlas_index, 0).x;
 }
 
+//transparent texels have alpha 0, kept or discarded by the caller
 fn get_palette_color_24bit(color_index: u32) -> vec4f {
-	if color_index == 0 {
-		discard;
-	} else {
-		return get_palette_color(color_index, 3u, 63.0);
-	}
+	let color = get_palette_color(color_index, 3u, 63.0);
+	return vec4f(color.rgb, select(1.0, 0.0, color_index == 0));
 }
 
 fn get_color_16bit(color: u32) -> vec4f {
-	if (color & 0x8000) == 0 {
-		discard;
-	} else {
-		return to_f32_color(
-			(color >> 10) & 0x1F,
-			(color >> 5) & 0x1F,
-			color & 0x1F,
-			31.0,
-		);
-	}
+	let color_rgb = to_f32_color(
+		(color >> 10) & 0x1F,
+		(color >> 5) & 0x1F,
+		color & 0x1F,
+		31.0,
+	);
+	return vec4f(color_rgb.rgb, select(0.0, 1.0, (color & 0x8000) != 0));
 }
 
 fn get_color_32bit(color: u32) -> vec4f {
-	if (color & 0xFF000000) == 0 {
+	let color_rgb = to_f32_color(
+		(color >> 16) & 0xFF,
+		(color >> 8) & 0xFF,
+		color & 0xFF,
+		255.0,
+	);
+	return vec4f(color_rgb.rgb, f32(color >> 24) / 255.0);
+}
+
+fn cut_out(color: vec4f) -> vec4f {
+	if color.a < ALPHA_TEST_THRESHOLD {
 		discard;
-	} else {
-		return to_f32_color(
-			(color >> 16) & 0xFF,
-			(color >> 8) & 0xFF,
-			color & 0xFF,
-			255.0,
-		);
 	}
+	return vec4f(color.rgb, 1.0);
+}
+
+//no vertex normals, so use the flat face normal from screen-space derivatives
+fn shine_term(vtf: TextureVTF) -> f32 {
+	let normal = normalize(cross(dpdx(vtf.view_position), dpdy(vtf.view_position)));
+	let view_dir = normalize(-vtf.view_position);
+	let fresnel = pow(1.0 - abs(dot(normal, view_dir)), 3.0);
+	let reflected = reflect(-view_dir, normal);
+	let specular = pow(abs(reflected.y), 16.0);//fake overhead light
+	return vtf.shine * (fresnel + specular);
+}
+
+fn texture_out(vtf: TextureVTF, texel: vec4f) -> Out {
+	var color = vec4f(texel.rgb, 1.0);
+	if vtf.alpha_test != 0 {
+		color = cut_out(texel);
+	}
+	color = vec4f(color.rgb * vtf.light * headlight(vtf.view_position, vtf.view_normal), color.a);
+	let shine = shine_term(vtf);
+	let shiny = vec4f(min(color.rgb + shine, vec3f(1.0)), color.a);
+	return Out(highlight(vtf, select(shiny, DEGENERATE_TINT, (vtf.tint & TINT_DEGENERATE) != 0)), vtf.object_id);
+}
+
+//faces using the object texture picked in the Textures window
+fn highlight(vtf: TextureVTF, color: vec4f) -> vec4f {
+	if (vtf.tint & TINT_HIGHLIGHT) == 0 {
+		return color;
+	}
+	return vec4f(mix(color.rgb, HIGHLIGHT_COLOR, HIGHLIGHT_MIX), color.a);
 }
 
 @fragment
 fn texture_palette_fs_main(vtf: TextureVTF) -> Out {
 	let color_index = get_pixel(vtf.atlas_index, vtf.uv);
 	let color = get_palette_color_24bit(color_index);
-	return Out(color, vtf.object_id);
+	return texture_out(vtf, color);
 }
 
 @fragment
 fn texture_16bit_fs_main(vtf: TextureVTF) -> Out {
 	let color_16bit = get_pixel(vtf.atlas_index, vtf.uv);
 	let color = get_color_16bit(color_16bit);
-	return Out(color, vtf.object_id);
+	return texture_out(vtf, color);
 }
 
 @fragment
 fn texture_32bit_fs_main(vtf: TextureVTF) -> Out {
 	let color_32bit = get_pixel(vtf.atlas_index, vtf.uv);
 	let color = get_color_32bit(color_32bit);
-	return Out(color, vtf.object_id);
+	return texture_out(vtf, color);
+}
+
+@fragment
+fn texture_debug_fs_main(vtf: TextureVTF) -> Out {
+	return Out(highlight(vtf, debug_color(vtf.debug_key)), vtf.object_id);
+}
+
+//checkerboard over atlas UVs, tinted by position in the atlas so flipped and rotated mapping shows
+@fragment
+fn texture_uv_check_fs_main(vtf: TextureVTF) -> Out {
+	let square = vec2i(floor(vtf.uv / UV_CHECK_SIZE));
+	let shade = select(0.4, 1.0, ((square.x + square.y) & 1) == 0);
+	let tint = mix(vec3f(0.5), vec3f(fract(vtf.uv / 256.0), 1.0), 0.5);
+	return Out(highlight(vtf, vec4f(tint * shade, 1.0)), vtf.object_id);
 }
 
 //==== flat texture ====
@@ -288,6 +522,8 @@ struct Rect {
 struct Viewport {
 	clip: Rect,
 	view: Rect,
+	pixels_per_point: f32,
+	num_images: u32,
 }
 
 @group(0) @binding(6) var<uniform> viewport: Viewport;
@@ -301,23 +537,26 @@ struct FlatVTF {
 @vertex
 fn flat_vs_main(@location(0) vertex: u32) -> FlatVTF {
 	let uv = vec2u(((vertex + 1) / 2) % 2, vertex / 2);
-	let pixel_uv = uv * 256 * vec2u(1, data_offsets.num_atlases);
+	//images of the bound texture stacked vertically, misc images may differ in count from atlases
+	let pixel_uv = uv * textureDimensions(atlases, 0) * vec2u(1, viewport.num_images);
 	/*
 	manual offsetting due to scrolling is necessary once the top of the viewport hits the top of the window
 	since egui/wgpu clamps the top of the viewport to the top of the window
+	the image is laid out in points, viewport rects are in physical pixels
 	*/
-	var offset = vec2i(0);
+	var offset = vec2f(0.0);
 	if viewport.view.pos.y == 0 {
-		offset.y = (viewport.clip.pos.y + 3) - i32(round(scroll_offset.y));
+		offset.y = f32(viewport.clip.pos.y) + (3.0 - scroll_offset.y) * viewport.pixels_per_point;
 	}
-	let ss = vec2f((vec2i(pixel_uv) + offset) * 2) / vec2f(viewport.view.size);
+	let ss = (vec2f(pixel_uv) * viewport.pixels_per_point + offset) * 2.0 / vec2f(viewport.view.size);
 	return FlatVTF(vec4f(ss.x - 1, 1 - ss.y, 0, 1), vec2f(pixel_uv));
 }
 
 fn get_pixel2(pixel: vec2f) -> u32 {
 	let pixel_int = vec2i(pixel);
-	let atlas_pixel = vec2i(pixel_int.x, pixel_int.y % 256);
-	let atlas_index = pixel_int.y / 256;
+	let atlas_height = i32(textureDimensions(atlases, 0).y);
+	let atlas_pixel = vec2i(pixel_int.x, pixel_int.y % atlas_height);
+	let atlas_index = pixel_int.y / atlas_height;
 	return textureLoad(atlases, atlas_pixel, atlas_index, 0).x;
 }
 
@@ -325,19 +564,19 @@ fn get_pixel2(pixel: vec2f) -> u32 {
 fn flat_palette_fs_main(vtf: FlatVTF) -> @location(0) vec4f {
 	let color_index = get_pixel2(vtf.pixel);
 	let color = get_palette_color_24bit(color_index);
-	return color;
+	return cut_out(color);
 }
 
 @fragment
 fn flat_16bit_fs_main(vtf: FlatVTF) -> @location(0) vec4f {
 	let color_16bit = get_pixel2(vtf.pixel);
 	let color = get_color_16bit(color_16bit);
-	return color;
+	return cut_out(color);
 }
 
 @fragment
 fn flat_32bit_fs_main(vtf: FlatVTF) -> @location(0) vec4f {
 	let color_32bit = get_pixel2(vtf.pixel);
 	let color = get_color_32bit(color_32bit);
-	return color;
+	return cut_out(color);
 }
@@ -0,0 +1,34 @@
+@group(0) @binding(2) var<uniform> camera_transform: mat4x4f;
+@group(0) @binding(3) var<uniform> perspective_transform: mat4x4f;
+
+struct PortalVTF {
+	@builtin(position) position: vec4f,
+	@location(0) color: vec4f,
+	@location(1) object_id: u32,
+}
+
+@vertex
+fn portal_vs_main(
+	@location(0) face_vertex_index: u32,//vertex
+	@location(1) v0: vec3i,//instance
+	@location(2) v1: vec3i,
+	@location(3) v2: vec3i,
+	@location(4) v3: vec3i,
+	@location(5) color: u32,
+	@location(6) object_id: u32,
+) -> PortalVTF {
+	var vertices = array(v0, v1, v2, v3);
+	let vertex_absolute = vec4f(vec3f(vertices[face_vertex_index]), 1.0);
+	let position = perspective_transform * camera_transform * vertex_absolute;
+	return PortalVTF(position, unpack4x8unorm(color), object_id);
+}
+
+struct Out {
+	@location(0) color: vec4f,
+	@location(1) object_id: u32,
+}
+
+@fragment
+fn portal_fs_main(vtf: PortalVTF) -> Out {
+	return Out(vtf.color, vtf.object_id);
+}
@@ -0,0 +1,30 @@
+//behind everything, without writing depth
+const SKY_DEPTH: f32 = 0.0000001;
+const NO_OBJECT: u32 = 0xFFFFFFFF;
+
+struct SkyVTF {
+	@builtin(position) position: vec4f,
+	@location(0) color: vec4f,
+}
+
+@vertex
+fn sky_vs_main(
+	@location(0) face_vertex_index: u32,//vertex
+	@location(1) top: u32,//instance
+	@location(2) bottom: u32,
+) -> SkyVTF {
+	let corner = vec2f(f32(((face_vertex_index + 1) / 2) % 2), f32(face_vertex_index / 2));
+	let position = vec4f(corner.x * 2.0 - 1.0, 1.0 - corner.y * 2.0, SKY_DEPTH, 1.0);
+	let color = mix(unpack4x8unorm(top), unpack4x8unorm(bottom), corner.y);
+	return SkyVTF(position, color);
+}
+
+struct Out {
+	@location(0) color: vec4f,
+	@location(1) object_id: u32,
+}
+
+@fragment
+fn sky_fs_main(vtf: SkyVTF) -> Out {
+	return Out(vtf.color, NO_OBJECT);
+}
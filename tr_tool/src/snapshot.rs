@@ -0,0 +1,349 @@
+use std::{
+	fmt::{self, Display, Formatter},
+	io::{Error, ErrorKind, Result},
+	path::Path,
+	str::FromStr,
+};
+use glam::Vec3;
+use image::RgbaImage;
+use wgpu::{
+	Buffer, BufferDescriptor, BufferUsages, CommandEncoderDescriptor, Device, Extent3d, ImageCopyBuffer,
+	ImageDataLayout, Instance, Maintain, MapMode, PowerPreference, Queue, RequestAdapterOptions, SubmissionIndex,
+	TextureDimension, TextureFormat, TextureUsages, TextureViewDescriptor, COPY_BYTES_PER_ROW_ALIGNMENT,
+};
+use crate::{
+	buffer_dump, detect_version, gpu::{self, PhysicalSize}, load_level, make, make_tr_tool_shared, render_level,
+	version::{LearnedVersion, ReadMode}, LoadedLevel, TrToolShared,
+};
+use gpu::Wait;
+
+const PIXEL_SIZE: u32 = 4;
+
+/// Prefix of the shareable camera string.
+const CAMERA_PREFIX: &str = "camera";
+
+/// Camera placement for a snapshot. Angles in radians.
+#[derive(Clone, Copy)]
+pub struct CameraSpec {
+	pub pos: Vec3,
+	pub yaw: f32,
+	pub pitch: f32,
+}
+
+/// `camera x y z yaw pitch`, angles in degrees.
+impl Display for CameraSpec {
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+		let Vec3 { x, y, z } = self.pos;
+		let [yaw, pitch] = [self.yaw, self.pitch].map(f32::to_degrees);
+		write!(f, "{} {:.0} {:.0} {:.0} {:.2} {:.2}", CAMERA_PREFIX, x, y, z, yaw, pitch)
+	}
+}
+
+/// Accepts the `Display` form, with or without the prefix and with spaces or commas between values.
+impl FromStr for CameraSpec {
+	type Err = Error;
+	
+	fn from_str(s: &str) -> Result<Self> {
+		let s = s.trim();
+		let s = s.strip_prefix(CAMERA_PREFIX).unwrap_or(s);
+		let values = s
+			.split(|c: char| c == ',' || c.is_whitespace())
+			.filter(|value| !value.is_empty())
+			.map(|value| value.parse::<f32>().map_err(Error::other))
+			.collect::<Result<Vec<_>>>()?;
+		let &[x, y, z, yaw, pitch] = &values[..] else {
+			return Err(Error::other("expected camera x y z yaw pitch"));
+		};
+		Ok(CameraSpec { pos: Vec3::new(x, y, z), yaw: yaw.to_radians(), pitch: pitch.to_radians() })
+	}
+}
+
+/// Load a level on a device without a window, with depth and interact targets of `size`.
+fn load_headless(
+	path: &Path, size: PhysicalSize<u32>, learned_versions: &[LearnedVersion],
+) -> Result<(Device, Queue, TrToolShared, LoadedLevel)> {
+	let version = match detect_version(path, learned_versions)? {
+		(_, Some(version)) => version,
+		(magic, None) => {
+			let msg = format!("unrecognized level file (magic 0x{:08X})", magic);
+			return Err(Error::new(ErrorKind::InvalidData, msg));
+		},
+	};
+	let instance = Instance::default();
+	let adapter = instance
+		.request_adapter(
+			&RequestAdapterOptions {
+				power_preference: PowerPreference::HighPerformance,
+				force_fallback_adapter: false,
+				compatible_surface: None,
+			},
+		)
+		.wait()
+		.ok_or_else(|| Error::other("no graphics adapter"))?;
+	let (device, queue) = gpu::request_device(&adapter).map_err(Error::other)?;
+	let shared = make_tr_tool_shared(&device);
+	let loaded_level = load_level(&device, &queue, size, &shared, path, version, ReadMode::Buffered)?;
+	Ok((device, queue, shared, loaded_level))
+}
+
+/// Render one frame of a level to an image without a window.
+/// If `camera` is `None`, the camera is placed as in the viewer: looking at the first room.
+#[cfg(feature = "headless")]
+pub fn render_snapshot(path: &Path, width: u32, height: u32, camera: Option<CameraSpec>) -> Result<RgbaImage> {
+	render_snapshot_with(path, width, height, camera, &[])
+}
+
+/// `render_snapshot`, trying `learned_versions` after the built-in version detection as in the viewer's settings.
+#[cfg(feature = "headless")]
+pub fn render_snapshot_with(
+	path: &Path, width: u32, height: u32, camera: Option<CameraSpec>, learned_versions: &[LearnedVersion],
+) -> Result<RgbaImage> {
+	let size = PhysicalSize::new(width.max(1), height.max(1));
+	let (device, queue, shared, mut loaded_level) = load_headless(path, size, learned_versions)?;
+	if let Some(CameraSpec { pos, yaw, pitch }) = camera {
+		loaded_level.pos = pos;
+		loaded_level.yaw = yaw;
+		loaded_level.pitch = pitch;
+		loaded_level.update_camera_transform(&queue);
+	}
+	Ok(render_image(&device, &queue, &shared, &loaded_level, size))
+}
+
+/// Load a level without a window and write its GPU buffers to `out_dir`, for command line use.
+pub fn dump_level_buffers(path: &Path, out_dir: &Path, learned_versions: &[LearnedVersion]) -> Result<()> {
+	let (device, queue, _, loaded_level) = load_headless(path, PhysicalSize::new(1, 1), learned_versions)?;
+	buffer_dump::dump_buffers(&device, &queue, &loaded_level, out_dir)
+}
+
+/// Offscreen render of a level being copied back from the GPU.
+pub struct PendingImage {
+	buffer: Buffer,
+	size: PhysicalSize<u32>,
+	submission_index: SubmissionIndex,
+}
+
+impl PendingImage {
+	/// Block until the copy is done and take the image.
+	pub fn wait(self, device: &Device) -> RgbaImage {
+		device.poll(Maintain::WaitForSubmissionIndex(self.submission_index.clone()));
+		self.take()
+	}
+	
+	/// Must be mapped.
+	pub fn take(self) -> RgbaImage {
+		let PhysicalSize { width, height } = self.size;
+		let row_size = width * PIXEL_SIZE;
+		let padded_row_size = row_size.next_multiple_of(COPY_BYTES_PER_ROW_ALIGNMENT);
+		let bytes = &*self.buffer.slice(..).get_mapped_range();
+		let mut pixels = Vec::with_capacity((row_size * height) as usize);
+		for row in bytes.chunks_exact(padded_row_size as usize) {
+			for bgra in row[..row_size as usize].chunks_exact(PIXEL_SIZE as usize) {
+				pixels.extend_from_slice(&[bgra[2], bgra[1], bgra[0], 255]);
+			}
+		}
+		//unwrap: pixels has exactly width * height * 4 bytes
+		RgbaImage::from_raw(width, height, pixels).unwrap()
+	}
+}
+
+/// Render the current view of a level offscreen and start reading it back without waiting. `size` must match
+/// the level's depth and interact targets. `on_mapped` is called once the image can be taken, while the device is
+/// polled.
+pub fn start_render_image(
+	device: &Device,
+	queue: &Queue,
+	shared: &TrToolShared,
+	loaded_level: &LoadedLevel,
+	size: PhysicalSize<u32>,
+	on_mapped: impl FnOnce() + Send + 'static,
+) -> PendingImage {
+	let extent = Extent3d { width: size.width, height: size.height, depth_or_array_layers: 1 };
+	let texture = make::texture(
+		device,
+		extent,
+		TextureDimension::D2,
+		TextureFormat::Bgra8Unorm,
+		TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+	);
+	let view = texture.create_view(&TextureViewDescriptor::default());
+	let padded_row_size = (size.width * PIXEL_SIZE).next_multiple_of(COPY_BYTES_PER_ROW_ALIGNMENT);
+	let buffer = device.create_buffer(&BufferDescriptor {
+		label: None,
+		size: (padded_row_size * size.height) as u64,
+		usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+		mapped_at_creation: false,
+	});
+	let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor::default());
+	render_level(&mut encoder, &view, shared, loaded_level);
+	encoder.copy_texture_to_buffer(
+		texture.as_image_copy(),
+		ImageCopyBuffer {
+			buffer: &buffer,
+			layout: ImageDataLayout { offset: 0, bytes_per_row: Some(padded_row_size), rows_per_image: None },
+		},
+		extent,
+	);
+	let submission_index = queue.submit([encoder.finish()]);
+	buffer.slice(..).map_async(MapMode::Read, move |r| {
+		r.expect("map snapshot texture");
+		on_mapped();
+	});
+	PendingImage { buffer, size, submission_index }
+}
+
+/// Render the current view of a level offscreen and read it back. `size` must match the level's depth and
+/// interact targets.
+pub fn render_image(
+	device: &Device, queue: &Queue, shared: &TrToolShared, loaded_level: &LoadedLevel, size: PhysicalSize<u32>,
+) -> RgbaImage {
+	start_render_image(device, queue, shared, loaded_level, size, || {}).wait(device)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{collections::BTreeMap, fs};
+	use glam::I16Vec3;
+	use image::Rgba;
+	use tr_model::{tr1, tr2};
+	use wgpu::BindingResource;
+	use crate::{
+		common_entries, make_atlases_view_gen, make_interact_texture, make_palette_view, test_level::{self, TempDir},
+		SolidMode, ViewBuffers, ATLASES_ENTRY, PALETTE_ENTRY,
+	};
+	use super::*;
+	
+	const SIZE: PhysicalSize<u32> = PhysicalSize { width: 1, height: 1 };
+	
+	/// The tiny level, looking at the entity's solid tri from behind it, so it covers the one pixel.
+	fn load_tiny(name: &str) -> (Device, Queue, TrToolShared, LoadedLevel) {
+		let dir = TempDir::new(&format!("snapshot_{}", name));
+		let level_path = dir.join("tiny.phd");
+		fs::write(&level_path, test_level::tiny().bytes()).unwrap();
+		let (device, queue, shared, mut loaded_level) = load_headless(&level_path, SIZE, &[]).unwrap();
+		//the tri leans from its base at z 2624 to its apex at (1536, -128, 2560)
+		loaded_level.pos = Vec3::new(1536.0, -43.0, 3500.0);
+		loaded_level.yaw = 0.0;
+		loaded_level.pitch = 0.0;
+		loaded_level.update_camera_transform(&queue);
+		loaded_level.disable_culling = true;
+		(device, queue, shared, loaded_level)
+	}
+	
+	fn render_pixel(device: &Device, queue: &Queue, shared: &TrToolShared, loaded_level: &LoadedLevel) -> Rgba<u8> {
+		*render_image(device, queue, shared, loaded_level, SIZE).get_pixel(0, 0)
+	}
+	
+	fn assert_color(pixel: Rgba<u8>, expected: [u8; 3]) {
+		let close = pixel.0[..3].iter().zip(expected).all(|(&c, e)| c.abs_diff(e) <= 1);
+		assert!(close, "{:?} != {:?}", pixel, expected);
+	}
+	
+	#[test]
+	#[ignore = "needs a graphics adapter"]
+	fn solid_24bit_face_color() {
+		let (device, queue, shared, loaded_level) = load_tiny("24bit");
+		assert!(matches!(loaded_level.solid_mode, Some(SolidMode::Bit24)));
+		//color index 5 of the tiny palette is (5, 1, 58) in 6-bit channels
+		let expected = [5, 1, 58].map(|c: u32| ((c * 255) as f32 / 63.0).round() as u8);
+		assert_color(render_pixel(&device, &queue, &shared, &loaded_level), expected);
+	}
+	
+	#[test]
+	#[ignore = "needs a graphics adapter"]
+	fn solid_32bit_face_color() {
+		let (device, queue, shared, mut loaded_level) = load_tiny("32bit");
+		//TR1 has no 32-bit palette so one is bound in its place. The 32-bit index is the high byte of the color
+		//index, which is 0 in TR1.
+		let mut palette = [(); tr1::PALETTE_LEN].map(|_| tr2::Color32BitRgb { r: 0, g: 0, b: 0 });
+		palette[0] = tr2::Color32BitRgb { r: 200, g: 100, b: 50 };
+		let palette_view = make_palette_view(&device, &queue, &palette);
+		let dummy_atlases = make_atlases_view_gen(&device, &queue, &[0u8; 2], TextureFormat::R8Uint, 1);
+		let view_buffers = ViewBuffers::new(&device, SIZE);
+		let statics_buffer = &loaded_level.statics_buffer;
+		let common_entries = common_entries(statics_buffer, &loaded_level.camera_transform_buffer, &view_buffers);
+		let entries = [
+			make::entry(PALETTE_ENTRY, BindingResource::TextureView(&palette_view)),
+			make::entry(ATLASES_ENTRY, BindingResource::TextureView(&dummy_atlases)),
+		];
+		let entries = [&common_entries[..], &entries].concat();
+		let bind_group = make::bind_group(&device, &shared.bind_group_layout, &entries);
+		loaded_level.solid_32bit_bg = Some(bind_group);
+		loaded_level.solid_mode = Some(SolidMode::Bit32);
+		assert_color(render_pixel(&device, &queue, &shared, &loaded_level), [200, 100, 50]);
+	}
+	
+	/// Render the level and count the pixels of each object id in the interact target. The clear value is counted
+	/// too, drivers differ in how they convert it.
+	fn interact_counts(
+		device: &Device, queue: &Queue, shared: &TrToolShared, loaded_level: &mut LoadedLevel, size: PhysicalSize<u32>,
+	) -> BTreeMap<u32, usize> {
+		let interact_texture = make_interact_texture(device, size);
+		loaded_level.interact_view = interact_texture.create_view(&TextureViewDescriptor::default());
+		render_image(device, queue, shared, loaded_level, size);
+		let row_size = size.width * PIXEL_SIZE;
+		let padded_row_size = row_size.next_multiple_of(COPY_BYTES_PER_ROW_ALIGNMENT);
+		let buffer = device.create_buffer(&BufferDescriptor {
+			label: None,
+			size: (padded_row_size * size.height) as u64,
+			usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+			mapped_at_creation: false,
+		});
+		let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor::default());
+		encoder.copy_texture_to_buffer(
+			interact_texture.as_image_copy(),
+			ImageCopyBuffer {
+				buffer: &buffer,
+				layout: ImageDataLayout { offset: 0, bytes_per_row: Some(padded_row_size), rows_per_image: None },
+			},
+			interact_texture.size(),
+		);
+		let submission_index = queue.submit([encoder.finish()]);
+		buffer.slice(..).map_async(MapMode::Read, |r| r.expect("map interact texture"));
+		device.poll(Maintain::WaitForSubmissionIndex(submission_index));
+		let bytes = &*buffer.slice(..).get_mapped_range();
+		let mut counts = BTreeMap::new();
+		for row in bytes.chunks_exact(padded_row_size as usize) {
+			for pixel in row[..row_size as usize].chunks_exact(PIXEL_SIZE as usize) {
+				*counts.entry(u32::from_le_bytes(pixel.try_into().unwrap())).or_default() += 1;
+			}
+		}
+		counts
+	}
+	
+	#[test]
+	#[ignore = "needs a graphics adapter"]
+	fn inside_out_mesh_silhouette() {
+		const SIZE: PhysicalSize<u32> = PhysicalSize { width: 32, height: 32 };
+		let tetrahedron = [
+			I16Vec3::new(0, -256, 0), I16Vec3::new(-256, 0, -256), I16Vec3::new(256, 0, -256), I16Vec3::new(0, 0, 256),
+		];
+		let clockwise = [[0, 2, 1], [0, 3, 2], [0, 1, 3], [1, 2, 3]];
+		let counter_clockwise = [[0, 1, 2], [0, 2, 3], [0, 3, 1], [1, 3, 2]];
+		for (name, tris) in [("clockwise", clockwise), ("inside_out", counter_clockwise)] {
+			let tris = tris.map(|vertex_indices| tr1::SolidTri { vertex_indices, color_index: 5 });
+			let mut test_level = test_level::tiny();
+			test_level.mesh_data = test_level::mesh_data(I16Vec3::ZERO, 256, &tetrahedron, &[], &[], &[], &tris);
+			//the entity and one room quad off to the side, so the entity's faces are object ids 1 to 4
+			let room = &mut test_level.rooms[0];
+			room.tris.clear();
+			room.room_static_meshes.clear();
+			let dir = TempDir::new(&format!("snapshot_silhouette_{}", name));
+			let level_path = dir.join("tetrahedron.phd");
+			fs::write(&level_path, test_level.bytes()).unwrap();
+			let (device, queue, shared, mut loaded_level) = load_headless(&level_path, SIZE, &[]).unwrap();
+			//above the entity, looking down at its three sloped faces
+			loaded_level.pos = Vec3::new(1536.0, -900.0, 2600.0);
+			loaded_level.yaw = 0.0;
+			loaded_level.pitch = 1.5;
+			loaded_level.update_camera_transform(&queue);
+			//with culling, the faces drawn must be the near ones drawn without it, covering the same pixels. Culling
+			//the wrong winding draws the base through the same silhouette.
+			let culled = interact_counts(&device, &queue, &shared, &mut loaded_level, SIZE);
+			loaded_level.disable_culling = true;
+			let unculled = interact_counts(&device, &queue, &shared, &mut loaded_level, SIZE);
+			let sloped_faces = [1, 2, 3].iter().all(|object_id| culled.contains_key(object_id));
+			assert!(sloped_faces && !culled.contains_key(&4), "{}: {:?}", name, culled);
+			assert_eq!(culled, unculled, "{}", name);
+		}
+	}
+}
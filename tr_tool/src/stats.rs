@@ -0,0 +1,86 @@
+use std::ops::{Deref, DerefMut, Range};
+use wgpu::{RenderPass, RenderPipeline};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum DrawKind {
+	Sky,
+	Solid,
+	Opaque,
+	Additive,
+	Alpha,
+	Ghost,
+	Sprite,
+	Portal,
+}
+
+impl DrawKind {
+	pub const ALL: [DrawKind; 8] = [
+		DrawKind::Sky,
+		DrawKind::Solid,
+		DrawKind::Opaque,
+		DrawKind::Additive,
+		DrawKind::Alpha,
+		DrawKind::Ghost,
+		DrawKind::Sprite,
+		DrawKind::Portal,
+	];
+}
+
+/// Draw calls and instances issued in one main pass.
+#[derive(Clone, Copy, Default)]
+pub struct DrawStats {
+	pub draw_calls: u32,
+	/// By `DrawKind`.
+	pub instances: [u32; DrawKind::ALL.len()],
+}
+
+/// Render pass that counts draws under the kind of the last set pipeline.
+pub struct CountingPass<'a> {
+	rpass: RenderPass<'a>,
+	kind: DrawKind,
+	stats: DrawStats,
+}
+
+impl<'a> CountingPass<'a> {
+	pub fn new(rpass: RenderPass<'a>) -> Self {
+		Self { rpass, kind: DrawKind::Opaque, stats: DrawStats::default() }
+	}
+	
+	pub fn set_pipeline(&mut self, pipeline: &'a RenderPipeline, kind: DrawKind) {
+		self.kind = kind;
+		self.rpass.set_pipeline(pipeline);
+	}
+	
+	fn count(&mut self, instances: &Range<u32>) {
+		self.stats.draw_calls += 1;
+		self.stats.instances[self.kind as usize] += instances.len() as u32;
+	}
+	
+	pub fn draw(&mut self, vertices: Range<u32>, instances: Range<u32>) {
+		self.count(&instances);
+		self.rpass.draw(vertices, instances);
+	}
+	
+	pub fn draw_indexed(&mut self, indices: Range<u32>, base_vertex: i32, instances: Range<u32>) {
+		self.count(&instances);
+		self.rpass.draw_indexed(indices, base_vertex, instances);
+	}
+	
+	/// End the pass.
+	pub fn finish(self) -> DrawStats {
+		self.stats
+	}
+}
+
+impl<'a> Deref for CountingPass<'a> {
+	type Target = RenderPass<'a>;
+	fn deref(&self) -> &Self::Target {
+		&self.rpass
+	}
+}
+
+impl<'a> DerefMut for CountingPass<'a> {
+	fn deref_mut(&mut self) -> &mut Self::Target {
+		&mut self.rpass
+	}
+}
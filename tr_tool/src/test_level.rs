@@ -0,0 +1,330 @@
+//! Small TR1 levels assembled byte by byte for tests, then parsed by the same reader as level files.
+use std::{
+	env, fs, io::Cursor, mem::size_of_val, path::{Path, PathBuf}, process, slice::from_raw_parts,
+};
+use glam::{I16Vec2, I16Vec3, IVec3, U16Vec2};
+use glam_traits::ext::U8Vec2;
+use shared::min_max::MinMax;
+use tr_model::tr1;
+use crate::{read_level, version::LevelReader};
+
+/// `Sector.floor` and `Sector.ceiling` of a wall, in 256 unit clicks.
+pub const WALL: i8 = -127;
+pub const NO_ROOM: u8 = 255;
+
+/// Directory in the system temp directory, removed with its contents when dropped, even by a failed assert.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+	/// Named after `name` and the process, so tests running in parallel don't share one.
+	pub fn new(name: &str) -> Self {
+		let dir = env::temp_dir().join(format!("tr_tool_{}_{}", name, process::id()));
+		fs::create_dir_all(&dir).unwrap();
+		Self(dir)
+	}
+	
+	pub fn join<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+		self.0.join(path)
+	}
+}
+
+impl Drop for TempDir {
+	fn drop(&mut self) {
+		_ = fs::remove_dir_all(&self.0);
+	}
+}
+
+pub struct TestRoom {
+	pub x: i32,
+	pub z: i32,
+	pub y_bottom: i32,
+	pub y_top: i32,
+	pub vertices: Vec<tr1::RoomVertex>,
+	pub quads: Vec<tr1::TexturedQuad>,
+	pub tris: Vec<tr1::TexturedTri>,
+	pub sprites: Vec<tr1::Sprite>,
+	pub portals: Vec<tr1::Portal>,
+	/// Sectors along z then x, `sectors.len()` is `num_sectors.0 * num_sectors.1`.
+	pub num_sectors: (u16, u16),
+	pub sectors: Vec<tr1::Sector>,
+	pub room_static_meshes: Vec<tr1::RoomStaticMesh>,
+	pub flip_room_index: u16,
+	pub water: bool,
+}
+
+impl TestRoom {
+	/// Room of `num_sectors` with a floor at `floor` clicks, surrounded by walls.
+	pub fn walled(x: i32, z: i32, num_sectors: (u16, u16), floor: i8) -> Self {
+		let (size_z, size_x) = num_sectors;
+		let sectors = (0..size_x).flat_map(|sector_x| {
+			(0..size_z).map(move |sector_z| {
+				let edge = sector_x == 0 || sector_z == 0 || sector_x == size_x - 1 || sector_z == size_z - 1;
+				sector(if edge { WALL } else { floor }, if edge { WALL } else { floor - 8 })
+			})
+		}).collect();
+		Self {
+			x,
+			z,
+			y_bottom: floor as i32 * 256,
+			y_top: (floor as i32 - 8) * 256,
+			vertices: vec![],
+			quads: vec![],
+			tris: vec![],
+			sprites: vec![],
+			portals: vec![],
+			num_sectors,
+			sectors,
+			room_static_meshes: vec![],
+			flip_room_index: u16::MAX,
+			water: false,
+		}
+	}
+}
+
+pub fn sector(floor: i8, ceiling: i8) -> tr1::Sector {
+	tr1::Sector {
+		floor_data_index: 0,
+		box_index: u16::MAX,
+		room_below_index: NO_ROOM,
+		floor,
+		room_above_index: NO_ROOM,
+		ceiling,
+	}
+}
+
+pub struct TestStaticMesh {
+	pub id: u32,
+	pub mesh_offset_index: u16,
+	pub flags: u16,
+}
+
+/// Parts of a level with content in tests. The rest are written empty.
+pub struct TestLevel {
+	/// Atlas pixels are all this palette index.
+	pub atlases: Vec<u8>,
+	pub rooms: Vec<TestRoom>,
+	pub mesh_data: Vec<u16>,
+	pub mesh_offsets: Vec<u32>,
+	pub frame_data: Vec<u16>,
+	pub models: Vec<tr1::Model>,
+	pub static_meshes: Vec<TestStaticMesh>,
+	pub object_textures: Vec<tr1::ObjectTexture>,
+	pub sprite_textures: Vec<tr1::SpriteTexture>,
+	pub boxes: Vec<tr1::TrBox>,
+	pub entities: Vec<tr1::Entity>,
+	pub palette: Vec<tr1::Color24Bit>,
+	pub demo_data: Vec<u8>,
+}
+
+/// Bytes of `items` as stored in level files. Only for the `repr(C)` level types, which have no padding.
+fn raw<T>(items: &[T]) -> &[u8] {
+	unsafe { from_raw_parts(items.as_ptr().cast(), size_of_val(items)) }
+}
+
+struct Writer(Vec<u8>);
+
+impl Writer {
+	fn raw<T>(&mut self, items: &[T]) {
+		self.0.extend_from_slice(raw(items));
+	}
+	
+	fn u16(&mut self, val: u16) {
+		self.raw(&[val]);
+	}
+	
+	fn u32(&mut self, val: u32) {
+		self.raw(&[val]);
+	}
+	
+	fn list_u16<T>(&mut self, items: &[T]) {
+		self.u16(items.len() as u16);
+		self.raw(items);
+	}
+	
+	fn list_u32<T>(&mut self, items: &[T]) {
+		self.u32(items.len() as u32);
+		self.raw(items);
+	}
+	
+	fn room(&mut self, room: &TestRoom) {
+		self.raw(&[room.x, room.z, room.y_bottom, room.y_top]);
+		let geom_data_size = [raw(&room.vertices), raw(&room.quads), raw(&room.tris), raw(&room.sprites)]
+			.iter()
+			.map(|bytes| 1 + bytes.len() / 2)
+			.sum::<usize>();
+		self.u32(geom_data_size as u32);
+		self.list_u16(&room.vertices);
+		self.list_u16(&room.quads);
+		self.list_u16(&room.tris);
+		self.list_u16(&room.sprites);
+		self.list_u16(&room.portals);
+		self.raw(&[room.num_sectors.0, room.num_sectors.1]);
+		self.raw(&room.sectors);
+		self.u16(0);//ambient light
+		self.u16(0);//lights
+		self.list_u16(&room.room_static_meshes);
+		self.u16(room.flip_room_index);
+		self.u16(room.water as u16);
+	}
+}
+
+impl TestLevel {
+	pub fn bytes(&self) -> Vec<u8> {
+		let mut w = Writer(vec![]);
+		w.u32(0x20);
+		w.u32(self.atlases.len() as u32);
+		for &color_index in &self.atlases {
+			w.raw(&vec![color_index; tr1::ATLAS_PIXELS]);
+		}
+		w.u32(0);//unused
+		w.u16(self.rooms.len() as u16);
+		for room in &self.rooms {
+			w.room(room);
+		}
+		w.u32(0);//floor data
+		w.list_u32(&self.mesh_data);
+		w.list_u32(&self.mesh_offsets);
+		for _ in 0..5 {
+			w.u32(0);//animations, state changes, anim dispatches, anim commands, mesh node data
+		}
+		w.list_u32(&self.frame_data);
+		w.list_u32(&self.models);
+		w.u32(self.static_meshes.len() as u32);
+		for static_mesh in &self.static_meshes {
+			w.u32(static_mesh.id);
+			w.u16(static_mesh.mesh_offset_index);
+			w.raw(&[0i16; 12]);//visibility and collision boxes
+			w.u16(static_mesh.flags);
+		}
+		w.list_u32(&self.object_textures);
+		w.list_u32(&self.sprite_textures);
+		for _ in 0..3 {
+			w.u32(0);//sprite sequences, cameras, sound sources
+		}
+		w.list_u32(&self.boxes);
+		w.u32(0);//overlap data
+		w.raw(&vec![[0u16; 6]; self.boxes.len()]);//zone data
+		w.u32(0);//animated textures
+		w.list_u32(&self.entities);
+		w.raw(&[[0u8; tr1::PALETTE_LEN]; tr1::LIGHT_MAP_LEN]);
+		let mut palette = self.palette.clone();
+		palette.resize(tr1::PALETTE_LEN, tr1::Color24Bit { r: 0, g: 0, b: 0 });
+		w.raw(&palette);
+		w.u16(0);//cinematic frames
+		w.list_u16(&self.demo_data);
+		w.raw(&[u16::MAX; tr1::SOUND_MAP_LEN]);
+		for _ in 0..3 {
+			w.u32(0);//sound details, sample data, sample indices
+		}
+		w.0
+	}
+	
+	pub fn level(&self) -> Box<tr1::Level> {
+		read_level(&mut LevelReader::Memory(Cursor::new(self.bytes()))).unwrap()
+	}
+}
+
+/// TR1 mesh data with lights rather than normals.
+pub fn mesh_data(
+	center: I16Vec3, radius: i32, vertices: &[I16Vec3], textured_quads: &[tr1::TexturedQuad],
+	textured_tris: &[tr1::TexturedTri], solid_quads: &[tr1::SolidQuad], solid_tris: &[tr1::SolidTri],
+) -> Vec<u16> {
+	let mut w = Writer(vec![]);
+	w.raw(&[center]);
+	w.raw(&[radius]);
+	w.list_u16(vertices);
+	w.u16((vertices.len() as i16).wrapping_neg() as u16);
+	w.raw(&vec![0x1000u16; vertices.len()]);
+	w.list_u16(textured_quads);
+	w.list_u16(textured_tris);
+	w.list_u16(solid_quads);
+	w.list_u16(solid_tris);
+	w.0.chunks(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect()
+}
+
+pub fn vertex(x: i16, y: i16, z: i16) -> tr1::RoomVertex {
+	tr1::RoomVertex { pos: I16Vec3::new(x, y, z), light: 0x1000 }
+}
+
+/// Frame data of a one mesh model with no rotation.
+pub fn one_mesh_frame() -> Vec<u16> {
+	vec![0; 12]
+}
+
+/// Room with a two sector floor: 5 vertices, 1 quad and 1 tri.
+fn floor_room() -> TestRoom {
+	let mut room = TestRoom::walled(0, 0, (4, 3), 0);
+	room.vertices = vec![
+		vertex(1024, 0, 1024), vertex(2048, 0, 1024), vertex(2048, 0, 2048), vertex(1024, 0, 2048),
+		vertex(1024, 0, 3072),
+	];
+	room.quads = vec![tr1::TexturedQuad { vertex_indices: [0, 1, 2, 3], object_texture_index: 0 }];
+	room.tris = vec![tr1::TexturedTri { vertex_indices: [3, 2, 4], object_texture_index: 0 }];
+	room.room_static_meshes = vec![tr1::RoomStaticMesh {
+		pos: IVec3::new(1536, 0, 1536),
+		angle: 0x4000,
+		light: 0x0FFF,
+		static_mesh_id: 10,
+	}];
+	room
+}
+
+/// One room, one static mesh, one entity with a single mesh model and a little of everything the dumps cover.
+pub fn tiny() -> TestLevel {
+	let mesh_vertices = [
+		I16Vec3::new(-64, 0, -64), I16Vec3::new(64, 0, -64), I16Vec3::new(64, 0, 64), I16Vec3::new(-64, 0, 64),
+		I16Vec3::new(0, -128, 0),
+	];
+	let mesh_data = mesh_data(
+		I16Vec3::new(0, -64, 0),
+		128,
+		&mesh_vertices,
+		&[tr1::TexturedQuad { vertex_indices: [0, 1, 2, 3], object_texture_index: 0 }],
+		&[],
+		&[],
+		&[tr1::SolidTri { vertex_indices: [0, 1, 4], color_index: 5 }],
+	);
+	TestLevel {
+		atlases: vec![1],
+		rooms: vec![floor_room()],
+		mesh_data,
+		mesh_offsets: vec![0],
+		frame_data: one_mesh_frame(),
+		models: vec![tr1::Model {
+			id: 0,
+			num_meshes: 1,
+			mesh_offset_index: 0,
+			mesh_node_offset: 0,
+			frame_byte_offset: 0,
+			anim_index: 0,
+		}],
+		static_meshes: vec![TestStaticMesh { id: 10, mesh_offset_index: 0, flags: 2 }],
+		object_textures: vec![tr1::ObjectTexture {
+			blend_mode: tr1::blend_mode::OPAQUE,
+			atlas_index: 0,
+			uvs: [[0, 0], [0x3F00, 0], [0x3F00, 0x3F00], [0, 0x3F00]].map(U16Vec2::from_array),
+		}],
+		sprite_textures: vec![tr1::SpriteTexture {
+			atlas_index: 0,
+			pos: U8Vec2::new(64, 0),
+			size: U16Vec2::new(0x1F00, 0x1F00),
+			world_bounds: [I16Vec2::new(-128, -256), I16Vec2::new(128, 0)],
+		}],
+		boxes: vec![tr1::TrBox {
+			z: MinMax { min: 1024, max: 3072 },
+			x: MinMax { min: 1024, max: 2048 },
+			y: 0,
+			overlap: 0,
+		}],
+		entities: vec![tr1::Entity {
+			model_id: 0,
+			room_index: 0,
+			pos: IVec3::new(1536, 0, 2560),
+			angle: 0x8000,
+			brightness: u16::MAX,
+			flags: 0x3E00,
+		}],
+		palette: (0..=u8::MAX).map(|i| tr1::Color24Bit { r: i % 64, g: i / 4, b: 63 - i % 64 }).collect(),
+		demo_data: vec![],
+	}
+}
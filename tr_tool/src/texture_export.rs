@@ -0,0 +1,121 @@
+use std::{ffi::OsString, fs, io::{Error, Result}, path::Path};
+use tr_model::tr1;
+use crate::{dump, rgba, tr_traits::LevelDyn};
+
+pub const SIDE: usize = tr1::ATLAS_SIDE_LEN;
+
+pub fn save_png(path: &Path, pixels: &[u8], width: u32, height: u32) -> Result<()> {
+	image::save_buffer(path, pixels, width, height, image::ColorType::Rgba8).map_err(Error::other)
+}
+
+/// Image sets written by `--export-textures`, named as in `--format` and the file names.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum AtlasFormat {
+	Palette,
+	Bit16,
+	Bit32,
+	/// TR4-5 misc images.
+	Misc,
+}
+
+impl AtlasFormat {
+	const ALL: [AtlasFormat; 4] = [AtlasFormat::Palette, AtlasFormat::Bit16, AtlasFormat::Bit32, AtlasFormat::Misc];
+	
+	fn label(&self) -> &'static str {
+		match self {
+			AtlasFormat::Palette => "palette",
+			AtlasFormat::Bit16 => "16bit",
+			AtlasFormat::Bit32 => "32bit",
+			AtlasFormat::Misc => "misc",
+		}
+	}
+	
+	/// The level's images in this format stacked vertically, `None` if it has none.
+	fn rgba(&self, level: &dyn LevelDyn) -> Option<Vec<u8>> {
+		match self {
+			AtlasFormat::Palette => {
+				Some(rgba::palette_images_to_rgba(level.palette_24bit()?, level.atlases_palette()?))
+			},
+			AtlasFormat::Bit16 => level.atlases_16bit().map(rgba::bit16_images_to_rgba),
+			AtlasFormat::Bit32 => level.atlases_32bit().map(rgba::bit32_images_to_rgba),
+			AtlasFormat::Misc => level.misc_images().map(rgba::bit32_images_to_rgba),
+		}
+	}
+	
+	fn file_name(&self, index: usize) -> String {
+		match self {
+			AtlasFormat::Misc => format!("misc_{}.png", index),
+			_ => format!("atlas_{}_{}.png", self.label(), index),
+		}
+	}
+}
+
+/// Write each image of the level in each of `formats` it has. With `Palette`, also write its palettes as 256×1
+/// images.
+pub fn export_atlases(level_path: &Path, out_dir: &Path, formats: &[AtlasFormat]) -> Result<()> {
+	let level = dump::read_level_store(level_path)?;
+	let level = level.as_dyn();
+	fs::create_dir_all(out_dir)?;
+	for format in formats {
+		let Some(images) = format.rgba(level) else {
+			continue;
+		};
+		for (index, image) in images.chunks_exact(SIDE * SIDE * 4).enumerate() {
+			save_png(&out_dir.join(format.file_name(index)), image, SIDE as u32, SIDE as u32)?;
+		}
+	}
+	if formats.contains(&AtlasFormat::Palette) {
+		let palettes = [
+			("palette.png", level.palette_24bit().map(rgba::palette_24bit_to_rgba)),
+			("palette_32bit.png", level.palette_32bit().map(rgba::palette_32bit_to_rgba)),
+		];
+		for (file_name, palette) in palettes {
+			if let Some(palette) = palette {
+				save_png(&out_dir.join(file_name), &palette, tr1::PALETTE_LEN as u32, 1)?;
+			}
+		}
+	}
+	Ok(())
+}
+
+/// `--export-textures [--format <formats>] <level>... <out_dir>` with `formats` comma separated, all by
+/// default. Several levels are written to subfolders named after them. Failures are printed to stderr with the
+/// file name, false if any.
+pub fn export_atlases_cli(args: &[OsString]) -> bool {
+	let (formats, args) = match args {
+		[flag, names, rest @ ..] if flag == "--format" => {
+			let names = names.to_string_lossy();
+			let formats = names
+				.split(',')
+				.map(|name| AtlasFormat::ALL.into_iter().find(|format| format.label() == name).ok_or(name))
+				.collect::<std::result::Result<Vec<_>, _>>();
+			match formats {
+				Ok(formats) => (formats, rest),
+				Err(name) => {
+					let names = AtlasFormat::ALL.map(|format| format.label()).join(", ");
+					eprintln!("unknown format {}, expected {}", name, names);
+					return false;
+				},
+			}
+		},
+		_ => (AtlasFormat::ALL.to_vec(), args),
+	};
+	let Some((out_dir, level_paths)) = args.split_last().filter(|(_, level_paths)| !level_paths.is_empty()) else {
+		eprintln!("usage: --export-textures [--format <formats>] <level>... <out_dir>");
+		return false;
+	};
+	let out_dir = Path::new(out_dir);
+	let mut ok = true;
+	for level_path in level_paths {
+		let level_path = Path::new(level_path);
+		let level_out_dir = match (level_paths.len(), level_path.file_stem()) {
+			(2.., Some(stem)) => out_dir.join(stem),
+			_ => out_dir.to_path_buf(),
+		};
+		if let Err(e) = export_atlases(level_path, &level_out_dir, &formats) {
+			eprintln!("{}: {}", level_path.display(), e);
+			ok = false;
+		}
+	}
+	ok
+}
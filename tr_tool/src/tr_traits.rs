@@ -1,7 +1,8 @@
 use std::f32::consts::TAU;
-use glam::{I16Vec3, IVec3, Mat4, U16Vec2, U16Vec3, Vec3};
+use glam::{EulerRot, I16Vec3, IVec3, Quat, U16Vec2, U16Vec3, Vec3};
+use shared::min_max::MinMax;
 use tr_model::{tr1, tr2, tr3, tr4, tr5, Readable};
-use crate::{as_bytes::ReinterpretAsBytes, object_data::PolyType};
+use crate::{as_bytes::{AsBytes, ReinterpretAsBytes}, object_data::PolyType, version::Version};
 
 pub enum LevelStore {
 	Tr1(Box<tr1::Level>),
@@ -11,15 +12,43 @@ pub enum LevelStore {
 	Tr5(Box<tr5::Level>),
 }
 
+/// Evaluate `$body` once per version with `$level` bound to the `&impl Level` held by the `&LevelStore`.
+macro_rules! with_level {
+	($store:expr, $level:ident => $body:expr) => {
+		match $store {
+			$crate::tr_traits::LevelStore::Tr1($level) => {
+				let $level = &**$level;
+				$body
+			},
+			$crate::tr_traits::LevelStore::Tr2($level) => {
+				let $level = &**$level;
+				$body
+			},
+			$crate::tr_traits::LevelStore::Tr3($level) => {
+				let $level = &**$level;
+				$body
+			},
+			$crate::tr_traits::LevelStore::Tr4($level) => {
+				let $level = &**$level;
+				$body
+			},
+			$crate::tr_traits::LevelStore::Tr5($level) => {
+				let $level = &**$level;
+				$body
+			},
+		}
+	};
+}
+
+pub(crate) use with_level;
+
 impl LevelStore {
 	pub fn as_dyn(&self) -> &dyn LevelDyn {
-		match self {
-			LevelStore::Tr1(level) => level.as_ref(),
-			LevelStore::Tr2(level) => level.as_ref(),
-			LevelStore::Tr3(level) => level.as_ref(),
-			LevelStore::Tr4(level) => level.as_ref(),
-			LevelStore::Tr5(level) => level.as_ref(),
-		}
+		with_level!(self, level => level)
+	}
+	
+	pub fn version(&self) -> Version {
+		self.as_dyn().version()
 	}
 }
 
@@ -33,16 +62,62 @@ pub trait Model {
 	fn id(&self) -> u32;
 	fn mesh_offset_index(&self) -> u16;
 	fn num_meshes(&self) -> u16;
+	/// Index of the model's first animation.
+	fn anim_index(&self) -> u16;
+}
+
+pub trait Animation {
+	fn frame_byte_offset(&self) -> u32;
+	/// Game frames per stored frame.
+	fn frame_duration(&self) -> u8;
+	/// Length of each stored frame in u16s.
+	fn frame_size(&self) -> u8;
+	/// First and last game frame numbers.
+	fn frame_start(&self) -> u16;
+	fn frame_end(&self) -> u16;
+}
+
+/// Effect bits of TR2-4 room vertex attributes, also read by mesh.wgsl.
+pub const ROOM_VERTEX_WATER_WAVE: u16 = 0x2000;
+pub const ROOM_VERTEX_GLOW: u16 = 0x4000;
+
+/// Effects the game applies to a room vertex.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct RoomVertexAttrs {
+	/// Bobs vertically, on water and quicksand surfaces.
+	pub water_wave: bool,
+	/// Light flickers, as seen through water surfaces and in sunset rooms.
+	pub glow: bool,
+}
+
+impl RoomVertexAttrs {
+	pub fn from_bits(attrs: u16) -> Self {
+		Self {
+			water_wave: attrs & ROOM_VERTEX_WATER_WAVE != 0,
+			glow: attrs & ROOM_VERTEX_GLOW != 0,
+		}
+	}
 }
 
 pub trait RoomVertex: ReinterpretAsBytes {
 	fn pos(&self) -> Vec3;
+	/// None in TR1 and TR5.
+	fn attrs(&self) -> RoomVertexAttrs { RoomVertexAttrs::default() }
 }
 
 pub trait Face: ReinterpretAsBytes {
 	const POLY_TYPE: PolyType;
 }
 
+/// Vertex indices are the first field of every face type.
+pub fn vertex_indices<F: Face>(face: &F) -> impl Iterator<Item = u16> + '_ {
+	let len = match F::POLY_TYPE {
+		PolyType::Quad => 4,
+		PolyType::Tri => 3,
+	};
+	face.as_bytes().chunks_exact(2).take(len).map(|b| u16::from_le_bytes([b[0], b[1]]))
+}
+
 pub trait TexturedFace: Face {
 	fn object_texture_index(&self) -> u16;
 }
@@ -53,6 +128,11 @@ pub trait RoomFace: TexturedFace {
 
 pub trait MeshTexturedFace: TexturedFace {
 	fn additive(&self) -> bool;
+	fn shine_strength(&self) -> u8;
+	/// Has a specular highlight, TR4-5 only.
+	fn shiny(&self) -> bool {
+		self.shine_strength() > 0
+	}
 }
 
 pub trait SolidFace: Face {
@@ -64,6 +144,24 @@ pub trait RoomStaticMesh {
 	fn static_mesh_id(&self) -> u16;
 	fn pos(&self) -> IVec3;
 	fn angle(&self) -> u16;
+	/// Light as stored in the level. TR3+ color is packed back from its 5-bit components.
+	fn light_raw(&self) -> u16;
+	/// Tint of the mesh, RGB from 0 to 1. TR1-2 store only a brightness, given as gray.
+	fn light(&self) -> Vec3;
+}
+
+pub trait Light {
+	/// World space, not relative to the room.
+	fn pos(&self) -> Vec3;
+	/// RGB from 0 to 1. TR1-2 lights have only a brightness, given as gray.
+	fn color(&self) -> Vec3;
+}
+
+pub trait StaticMesh {
+	fn id(&self) -> u32;
+	fn mesh_offset_index(&self) -> u16;
+	fn non_collidable(&self) -> bool;
+	fn visible(&self) -> bool;
 }
 
 pub trait Room {
@@ -71,13 +169,33 @@ pub trait Room {
 	type RoomQuad: RoomFace;
 	type RoomTri: RoomFace;
 	type RoomStaticMesh: RoomStaticMesh;
+	type Light: Light;
+	/// Horizontal offset of room vertices and portals, whose y is already absolute in every version.
 	fn pos(&self) -> IVec3;
+	/// Declared `[top, bottom]` y of the room. Y points down, so top is less than bottom in a well formed room.
+	fn y_extent(&self) -> [i32; 2];
 	fn vertices(&self) -> &[Self::RoomVertex];
 	fn geom(&self) -> impl IntoIterator<Item = RoomGeom<Self::RoomVertex, Self::RoomQuad, Self::RoomTri>>;
 	fn sprites(&self) -> &[tr1::Sprite];
 	fn room_static_meshes(&self) -> &[Self::RoomStaticMesh];
+	fn lights(&self) -> &[Self::Light];
 	fn flip_room_index(&self) -> u16;
 	fn flip_group(&self) -> u8;
+	fn portals(&self) -> &[tr1::Portal];
+	fn num_sectors(&self) -> &tr1::NumSectors;
+	/// Indexed by `x * num_sectors.z + z`.
+	fn sectors(&self) -> &[tr1::Sector];
+	fn water(&self) -> bool;
+	/// Contains filler or layers that don't match the vertex data. Such rooms load with the bad parts empty.
+	fn malformed(&self) -> bool { false }
+}
+
+pub trait SoundDetails {
+	/// Index into the level's sample indices of the first sample.
+	fn sample_index(&self) -> u16;
+	fn details(&self) -> u16;
+	/// Samples the game picks between at random. Same bits in all versions.
+	fn num_samples(&self) -> u16 { (self.details() >> 2) & 0xF }
 }
 
 pub trait Entity {
@@ -85,6 +203,23 @@ pub trait Entity {
 	fn model_id(&self) -> u16;
 	fn pos(&self) -> IVec3;
 	fn angle(&self) -> u16;
+	fn flags(&self) -> u16;
+	fn ocb(&self) -> Option<u16>;
+	/// Not drawn until triggered. Same bit in all versions.
+	fn initially_invisible(&self) -> bool { self.flags() & 0x100 != 0 }
+	/// Trigger activation bits, entity is active when all 5 are set. Same bits in all versions.
+	fn activation_mask(&self) -> u8 { ((self.flags() >> 9) & 0x1F) as u8 }
+	/// TR4-5 body is removed after death, `None` for earlier versions.
+	fn clear_body(&self) -> Option<bool> { None }
+}
+
+pub trait TrBox {
+	/// World coords.
+	fn z(&self) -> MinMax<u32>;
+	/// World coords.
+	fn x(&self) -> MinMax<u32>;
+	fn y(&self) -> i16;
+	fn overlap(&self) -> u16;
 }
 
 #[allow(dead_code)]//todo: remove
@@ -93,6 +228,8 @@ pub trait ObjectTexture: ReinterpretAsBytes {
 	fn blend_mode(&self) -> u16;
 	fn atlas_index(&self) -> u16;
 	fn uvs(&self) -> [U16Vec2; 4];
+	/// TR4-5 flag for textures meant for tris, `None` for earlier versions.
+	fn tri(&self) -> Option<bool>;
 }
 
 pub trait Mesh<'a> {
@@ -109,7 +246,8 @@ pub trait Mesh<'a> {
 
 pub trait Frame {
 	fn offset(&self) -> I16Vec3;
-	fn iter_rotations(&self) -> impl Iterator<Item = Mat4>;
+	/// Rotation of each mesh. May end early in frames cut off by the end of the frame data.
+	fn iter_rotations(&self) -> impl Iterator<Item = Quat>;
 }
 
 pub trait LevelDyn {
@@ -124,6 +262,17 @@ pub trait LevelDyn {
 	fn atlases_16bit(&self) -> Option<&[[tr2::Color16BitArgb; tr1::ATLAS_PIXELS]]>;
 	fn atlases_32bit(&self) -> Option<&[[tr4::Color32BitBgra; tr1::ATLAS_PIXELS]]>;
 	fn misc_images(&self) -> Option<&[[tr4::Color32BitBgra; tr1::ATLAS_PIXELS]]>;
+	fn cameras(&self) -> &[tr1::Camera];
+	/// TR4-5 flyby camera nodes, empty for earlier versions.
+	fn flyby_cameras(&self) -> &[tr4::FlybyCamera];
+	/// TR1-3 cutscene camera track, empty for later versions.
+	fn cinematic_frames(&self) -> &[tr1::CinematicFrame];
+	/// Recorded demo, decoded for TR1 by `demo::decode`.
+	fn demo_data(&self) -> &[u8];
+	fn sound_sources(&self) -> &[tr1::SoundSource];
+	fn sound_map(&self) -> &[u16];
+	fn floor_data(&self) -> &[u16];
+	fn version(&self) -> Version;
 	fn store(self: Box<Self>) -> LevelStore;
 }
 
@@ -132,15 +281,28 @@ pub trait Level: LevelDyn + Readable {
 	type Room: Room;
 	type Entity: Entity;
 	type ObjectTexture: ObjectTexture;
+	type TrBox: TrBox;
 	type Mesh<'a>: Mesh<'a> where Self: 'a;
 	type Frame<'a>: Frame where Self: 'a;
+	type Animation: Animation;
+	type SoundDetails: SoundDetails;
 	fn models(&self) -> &[Self::Model];
 	fn rooms(&self) -> &[Self::Room];
 	fn entities(&self) -> &[Self::Entity];
 	fn object_textures(&self) -> &[Self::ObjectTexture];
+	fn boxes(&self) -> &[Self::TrBox];
 	fn get_mesh_nodes(&self, model: &Self::Model) -> &[tr1::MeshNode];
 	fn get_mesh(&self, mesh_offset: u32) -> Self::Mesh<'_>;
-	fn get_frame(&self, model: &Self::Model) -> Self::Frame<'_>;
+	/// The model's first frame, `None` if out of bounds.
+	fn get_frame(&self, model: &Self::Model) -> Option<Self::Frame<'_>>;
+	/// Frame at any offset into the frame data, `None` if out of bounds.
+	fn get_frame_at(&self, frame_byte_offset: u32, num_meshes: u16) -> Option<Self::Frame<'_>>;
+	fn animations(&self) -> &[Self::Animation];
+	fn get_anim_commands(&self, animation: &Self::Animation) -> Vec<tr1::AnimCommand>;
+	fn sound_details(&self) -> &[Self::SoundDetails];
+	/// TR1 offsets into the level's sample data, TR2-3 indices into MAIN.SFX, TR4-5 indices into the level's
+	/// samples.
+	fn sample_indices(&self) -> &[u32];
 }
 
 //impl helpers
@@ -149,9 +311,9 @@ fn to_radians(angle: u16, divisor: f32) -> f32 {
 	angle as f32 / divisor * TAU
 }
 
-fn to_mat(angles: U16Vec3) -> Mat4 {
+fn to_quat(angles: U16Vec3) -> Quat {
 	let [x, y, z] = angles.to_array().map(|a| to_radians(a, 1024.0));
-	Mat4::from_rotation_y(y) * Mat4::from_rotation_x(x) * Mat4::from_rotation_z(z)
+	Quat::from_euler(EulerRot::YXZ, y, x, z)
 }
 
 //impls
@@ -162,6 +324,20 @@ impl Model for tr1::Model {
 	fn id(&self) -> u32 { self.id }
 	fn mesh_offset_index(&self) -> u16 { self.mesh_offset_index }
 	fn num_meshes(&self) -> u16 { self.num_meshes }
+	fn anim_index(&self) -> u16 { self.anim_index }
+}
+
+impl Animation for tr1::Animation {
+	fn frame_byte_offset(&self) -> u32 { self.frame_byte_offset }
+	fn frame_duration(&self) -> u8 { self.frame_duration }
+	fn frame_size(&self) -> u8 { self.frame_size }
+	fn frame_start(&self) -> u16 { self.frame_start }
+	fn frame_end(&self) -> u16 { self.frame_end }
+}
+
+impl SoundDetails for tr1::SoundDetails {
+	fn sample_index(&self) -> u16 { self.sample_index }
+	fn details(&self) -> u16 { self.details }
 }
 
 impl RoomVertex for tr1::RoomVertex {
@@ -191,6 +367,32 @@ impl RoomStaticMesh for tr1::RoomStaticMesh {
 	fn static_mesh_id(&self) -> u16 { self.static_mesh_id }
 	fn pos(&self) -> IVec3 { self.pos }
 	fn angle(&self) -> u16 { self.angle }
+	fn light_raw(&self) -> u16 { self.light }
+	fn light(&self) -> Vec3 { static_mesh_light(self.light) }
+}
+
+impl StaticMesh for tr1::StaticMesh {
+	fn id(&self) -> u32 { self.id }
+	fn mesh_offset_index(&self) -> u16 { self.mesh_offset_index }
+	fn non_collidable(&self) -> bool { self.flags.non_collidable() }
+	fn visible(&self) -> bool { self.flags.visible() }
+}
+
+/// Brightness of TR1-2 lights at full.
+const MAX_LIGHT_BRIGHTNESS: f32 = 0x1FFF as f32;
+
+/// TR1-2 static mesh light is darkness up to `MAX_LIGHT_BRIGHTNESS`. Larger values (0xFFFF) leave it unlit.
+fn static_mesh_light(light: u16) -> Vec3 {
+	let light = light as f32;
+	if light > MAX_LIGHT_BRIGHTNESS {
+		return Vec3::ONE;
+	}
+	Vec3::splat(1.0 - light / MAX_LIGHT_BRIGHTNESS)
+}
+
+impl Light for tr1::Light {
+	fn pos(&self) -> Vec3 { { self.pos }.as_vec3() }
+	fn color(&self) -> Vec3 { Vec3::splat((self.brightness as f32 / MAX_LIGHT_BRIGHTNESS).min(1.0)) }
 }
 
 impl Room for tr1::Room {
@@ -198,15 +400,22 @@ impl Room for tr1::Room {
 	type RoomQuad = tr1::TexturedQuad;
 	type RoomTri = tr1::TexturedTri;
 	type RoomStaticMesh = tr1::RoomStaticMesh;
+	type Light = tr1::Light;
 	fn pos(&self) -> IVec3 { IVec3::new(self.x, 0, self.z) }
+	fn y_extent(&self) -> [i32; 2] { [self.y_top, self.y_bottom] }
 	fn vertices(&self) -> &[Self::RoomVertex] { &self.vertices }
 	fn geom(&self) -> impl IntoIterator<Item = RoomGeom<Self::RoomVertex, Self::RoomQuad, Self::RoomTri>> {
 		[RoomGeom { vertices: &self.vertices, quads: &self.quads, tris: &self.tris }]
 	}
 	fn sprites(&self) -> &[tr1::Sprite] { &self.sprites }
 	fn room_static_meshes(&self) -> &[Self::RoomStaticMesh] { &self.room_static_meshes }
+	fn lights(&self) -> &[Self::Light] { &self.lights }
 	fn flip_room_index(&self) -> u16 { self.flip_room_index }
 	fn flip_group(&self) -> u8 { 0 }
+	fn portals(&self) -> &[tr1::Portal] { &self.portals }
+	fn num_sectors(&self) -> &tr1::NumSectors { &self.num_sectors }
+	fn sectors(&self) -> &[tr1::Sector] { &self.sectors }
+	fn water(&self) -> bool { self.flags.water() }
 }
 
 impl Entity for tr1::Entity {
@@ -214,6 +423,15 @@ impl Entity for tr1::Entity {
 	fn model_id(&self) -> u16 { self.model_id }
 	fn pos(&self) -> IVec3 { self.pos }
 	fn angle(&self) -> u16 { self.angle }
+	fn flags(&self) -> u16 { self.flags }
+	fn ocb(&self) -> Option<u16> { None }
+}
+
+impl TrBox for tr1::TrBox {
+	fn z(&self) -> MinMax<u32> { self.z }
+	fn x(&self) -> MinMax<u32> { self.x }
+	fn y(&self) -> i16 { self.y }
+	fn overlap(&self) -> u16 { self.overlap }
 }
 
 impl ObjectTexture for tr1::ObjectTexture {
@@ -221,6 +439,7 @@ impl ObjectTexture for tr1::ObjectTexture {
 	fn blend_mode(&self) -> u16 { self.blend_mode }
 	fn atlas_index(&self) -> u16 { self.atlas_index }
 	fn uvs(&self) -> [U16Vec2; 4] { self.uvs }
+	fn tri(&self) -> Option<bool> { None }
 }
 
 impl Face for tr1::SolidQuad { const POLY_TYPE: PolyType = PolyType::Quad; }
@@ -238,10 +457,12 @@ impl SolidFace for tr1::SolidTri {
 
 impl MeshTexturedFace for tr1::TexturedQuad {
 	fn additive(&self) -> bool { false }
+	fn shine_strength(&self) -> u8 { 0 }
 }
 
 impl MeshTexturedFace for tr1::TexturedTri {
 	fn additive(&self) -> bool { false }
+	fn shine_strength(&self) -> u8 { 0 }
 }
 
 impl<'a> Mesh<'a> for tr1::Mesh<'a> {
@@ -258,8 +479,8 @@ impl<'a> Mesh<'a> for tr1::Mesh<'a> {
 
 impl Frame for &tr1::Frame {
 	fn offset(&self) -> I16Vec3 { self.offset }
-	fn iter_rotations(&self) -> impl Iterator<Item = Mat4> {
-		self.rotations.iter().map(|rot| to_mat(rot.get_angles()))
+	fn iter_rotations(&self) -> impl Iterator<Item = Quat> {
+		self.rotations.iter().map(|rot| to_quat(rot.get_angles()))
 	}
 }
 
@@ -275,6 +496,14 @@ impl LevelDyn for tr1::Level {
 	fn atlases_16bit(&self) -> Option<&[[tr2::Color16BitArgb; tr1::ATLAS_PIXELS]]> { None }
 	fn atlases_32bit(&self) -> Option<&[[tr4::Color32BitBgra; tr1::ATLAS_PIXELS]]> { None }
 	fn misc_images(&self) -> Option<&[[tr4::Color32BitBgra; tr1::ATLAS_PIXELS]]> { None }
+	fn cameras(&self) -> &[tr1::Camera] { &self.cameras }
+	fn flyby_cameras(&self) -> &[tr4::FlybyCamera] { &[] }
+	fn cinematic_frames(&self) -> &[tr1::CinematicFrame] { &self.cinematic_frames }
+	fn demo_data(&self) -> &[u8] { &self.demo_data }
+	fn sound_sources(&self) -> &[tr1::SoundSource] { &self.sound_sources }
+	fn sound_map(&self) -> &[u16] { &self.sound_map[..] }
+	fn floor_data(&self) -> &[u16] { &self.floor_data }
+	fn version(&self) -> Version { Version::Tr1 }
 	fn store(self: Box<Self>) -> LevelStore { LevelStore::Tr1(self) }
 }
 
@@ -283,27 +512,50 @@ impl Level for tr1::Level {
 	type Room = tr1::Room;
 	type Entity = tr1::Entity;
 	type ObjectTexture = tr1::ObjectTexture;
+	type TrBox = tr1::TrBox;
 	type Mesh<'a> = tr1::Mesh<'a>;
 	type Frame<'a> = &'a tr1::Frame;
+	type Animation = tr1::Animation;
+	type SoundDetails = tr1::SoundDetails;
 	fn models(&self) -> &[Self::Model] { &self.models }
 	fn rooms(&self) -> &[Self::Room] { &self.rooms }
 	fn entities(&self) -> &[Self::Entity] { &self.entities }
 	fn object_textures(&self) -> &[Self::ObjectTexture] { &self.object_textures }
+	fn boxes(&self) -> &[Self::TrBox] { &self.boxes }
 	fn get_mesh_nodes(&self, model: &Self::Model) -> &[tr1::MeshNode] { self.get_mesh_nodes(model) }
 	fn get_mesh(&self, mesh_offset: u32) -> Self::Mesh<'_> { self.get_mesh(mesh_offset) }
-	fn get_frame(&self, model: &Self::Model) -> Self::Frame<'_> { self.get_frame(model) }
+	fn get_frame(&self, model: &Self::Model) -> Option<Self::Frame<'_>> {
+		self.get_frame_at(model.frame_byte_offset, model.num_meshes)
+	}
+	fn get_frame_at(&self, frame_byte_offset: u32, num_meshes: u16) -> Option<Self::Frame<'_>> {
+		self.get_frame_at(frame_byte_offset, num_meshes)
+	}
+	fn animations(&self) -> &[Self::Animation] { &self.animations }
+	fn get_anim_commands(&self, animation: &Self::Animation) -> Vec<tr1::AnimCommand> {
+		self.get_anim_commands(animation)
+	}
+	fn sound_details(&self) -> &[Self::SoundDetails] { &self.sound_details }
+	fn sample_indices(&self) -> &[u32] { &self.sample_indices }
 }
 
 //tr2
 
 impl RoomVertex for tr2::RoomVertex {
 	fn pos(&self) -> Vec3 { self.pos.as_vec3() }
+	fn attrs(&self) -> RoomVertexAttrs { RoomVertexAttrs::from_bits(self.attrs) }
 }
 
 impl RoomStaticMesh for tr2::RoomStaticMesh {
 	fn static_mesh_id(&self) -> u16 { self.static_mesh_id }
 	fn pos(&self) -> IVec3 { self.pos }
 	fn angle(&self) -> u16 { self.angle }
+	fn light_raw(&self) -> u16 { self.light }
+	fn light(&self) -> Vec3 { static_mesh_light(self.light) }
+}
+
+impl Light for tr2::Light {
+	fn pos(&self) -> Vec3 { self.pos.as_vec3() }
+	fn color(&self) -> Vec3 { Vec3::splat((self.brightness as f32 / MAX_LIGHT_BRIGHTNESS).min(1.0)) }
 }
 
 impl Room for tr2::Room {
@@ -311,15 +563,22 @@ impl Room for tr2::Room {
 	type RoomQuad = tr1::TexturedQuad;
 	type RoomTri = tr1::TexturedTri;
 	type RoomStaticMesh = tr2::RoomStaticMesh;
+	type Light = tr2::Light;
 	fn pos(&self) -> IVec3 { IVec3::new(self.x, 0, self.z) }
+	fn y_extent(&self) -> [i32; 2] { [self.y_top, self.y_bottom] }
 	fn vertices(&self) -> &[Self::RoomVertex] { &self.vertices }
 	fn geom(&self) -> impl IntoIterator<Item = RoomGeom<Self::RoomVertex, Self::RoomQuad, Self::RoomTri>> {
 		[RoomGeom { vertices: &self.vertices, quads: &self.quads, tris: &self.tris }]
 	}
 	fn sprites(&self) -> &[tr1::Sprite] { &self.sprites }
 	fn room_static_meshes(&self) -> &[Self::RoomStaticMesh] { &self.room_static_meshes }
+	fn lights(&self) -> &[Self::Light] { &self.lights }
 	fn flip_room_index(&self) -> u16 { self.flip_room_index }
 	fn flip_group(&self) -> u8 { 0 }
+	fn portals(&self) -> &[tr1::Portal] { &self.portals }
+	fn num_sectors(&self) -> &tr1::NumSectors { &self.num_sectors }
+	fn sectors(&self) -> &[tr1::Sector] { &self.sectors }
+	fn water(&self) -> bool { self.flags.water() }
 }
 
 impl Entity for tr2::Entity {
@@ -327,6 +586,19 @@ impl Entity for tr2::Entity {
 	fn model_id(&self) -> u16 { self.model_id }
 	fn pos(&self) -> IVec3 { self.pos }
 	fn angle(&self) -> u16 { self.angle }
+	fn flags(&self) -> u16 { self.flags }
+	fn ocb(&self) -> Option<u16> { None }
+}
+
+fn sectors_to_world(MinMax { min, max }: MinMax<u8>) -> MinMax<u32> {
+	MinMax { min: min as u32 * 1024, max: max as u32 * 1024 }
+}
+
+impl TrBox for tr2::TrBox {
+	fn z(&self) -> MinMax<u32> { sectors_to_world(self.z) }
+	fn x(&self) -> MinMax<u32> { sectors_to_world(self.x) }
+	fn y(&self) -> i16 { self.y }
+	fn overlap(&self) -> u16 { self.overlap }
 }
 
 impl Face for tr2::SolidQuad { const POLY_TYPE: PolyType = PolyType::Quad; }
@@ -356,16 +628,16 @@ impl<'a> Mesh<'a> for tr2::Mesh<'a> {
 
 impl<'a> Frame for tr2::Frame<'a> {
 	fn offset(&self) -> I16Vec3 { self.frame_data.offset }
-	fn iter_rotations(&self) -> impl Iterator<Item = Mat4> {
+	fn iter_rotations(&self) -> impl Iterator<Item = Quat> {
 		self.iter_rotations().map(|rot| {
 			match rot {
-				tr2::FrameRotation::AllAxes(angles) => to_mat(angles),
+				tr2::FrameRotation::AllAxes(angles) => to_quat(angles),
 				tr2::FrameRotation::SingleAxis(axis, angle) => {
 					let angle = to_radians(angle, 1024.0);
 					match axis {
-						tr2::Axis::X => Mat4::from_rotation_x(angle),
-						tr2::Axis::Y => Mat4::from_rotation_y(angle),
-						tr2::Axis::Z => Mat4::from_rotation_z(angle),
+						tr2::Axis::X => Quat::from_rotation_x(angle),
+						tr2::Axis::Y => Quat::from_rotation_y(angle),
+						tr2::Axis::Z => Quat::from_rotation_z(angle),
 					}
 				},
 			}
@@ -387,6 +659,14 @@ impl LevelDyn for tr2::Level {
 	}
 	fn atlases_32bit(&self) -> Option<&[[tr4::Color32BitBgra; tr1::ATLAS_PIXELS]]> { None }
 	fn misc_images(&self) -> Option<&[[tr4::Color32BitBgra; tr1::ATLAS_PIXELS]]> { None }
+	fn cameras(&self) -> &[tr1::Camera] { &self.cameras }
+	fn flyby_cameras(&self) -> &[tr4::FlybyCamera] { &[] }
+	fn cinematic_frames(&self) -> &[tr1::CinematicFrame] { &self.cinematic_frames }
+	fn demo_data(&self) -> &[u8] { &self.demo_data }
+	fn sound_sources(&self) -> &[tr1::SoundSource] { &self.sound_sources }
+	fn sound_map(&self) -> &[u16] { &self.sound_map[..] }
+	fn floor_data(&self) -> &[u16] { &self.floor_data }
+	fn version(&self) -> Version { Version::Tr2 }
 	fn store(self: Box<Self>) -> LevelStore { LevelStore::Tr2(self) }
 }
 
@@ -395,21 +675,42 @@ impl Level for tr2::Level {
 	type Room = tr2::Room;
 	type Entity = tr2::Entity;
 	type ObjectTexture = tr1::ObjectTexture;
+	type TrBox = tr2::TrBox;
 	type Mesh<'a> = tr2::Mesh<'a>;
 	type Frame<'a> = tr2::Frame<'a>;
+	type Animation = tr1::Animation;
+	type SoundDetails = tr1::SoundDetails;
 	fn models(&self) -> &[Self::Model] { &self.models }
 	fn rooms(&self) -> &[Self::Room] { &self.rooms }
 	fn entities(&self) -> &[Self::Entity] { &self.entities }
 	fn object_textures(&self) -> &[Self::ObjectTexture] { &self.object_textures }
+	fn boxes(&self) -> &[Self::TrBox] { &self.boxes }
 	fn get_mesh_nodes(&self, model: &Self::Model) -> &[tr1::MeshNode] { self.get_mesh_nodes(model) }
 	fn get_mesh(&self, mesh_offset: u32) -> Self::Mesh<'_> { self.get_mesh(mesh_offset) }
-	fn get_frame(&self, model: &Self::Model) -> Self::Frame<'_> { self.get_frame(model) }
+	fn get_frame(&self, model: &Self::Model) -> Option<Self::Frame<'_>> {
+		self.get_frame_at(model.frame_byte_offset, model.num_meshes)
+	}
+	fn get_frame_at(&self, frame_byte_offset: u32, num_meshes: u16) -> Option<Self::Frame<'_>> {
+		self.get_frame_at(frame_byte_offset, num_meshes)
+	}
+	fn animations(&self) -> &[Self::Animation] { &self.animations }
+	fn get_anim_commands(&self, animation: &Self::Animation) -> Vec<tr1::AnimCommand> {
+		self.get_anim_commands(animation)
+	}
+	fn sound_details(&self) -> &[Self::SoundDetails] { &self.sound_details }
+	fn sample_indices(&self) -> &[u32] { &self.sample_indices }
 }
 
 //tr3
 
+impl SoundDetails for tr3::SoundDetails {
+	fn sample_index(&self) -> u16 { self.sample_index }
+	fn details(&self) -> u16 { self.details }
+}
+
 impl RoomVertex for tr3::RoomVertex {
 	fn pos(&self) -> Vec3 { self.pos.as_vec3() }
+	fn attrs(&self) -> RoomVertexAttrs { RoomVertexAttrs::from_bits(self.attrs) }
 }
 
 impl Face for tr3::DsQuad { const POLY_TYPE: PolyType = PolyType::Quad; }
@@ -435,6 +736,21 @@ impl RoomStaticMesh for tr3::RoomStaticMesh {
 	fn static_mesh_id(&self) -> u16 { self.static_mesh_id }
 	fn pos(&self) -> IVec3 { self.pos }
 	fn angle(&self) -> u16 { self.angle }
+	fn light_raw(&self) -> u16 {
+		(self.color.r() as u16) << 10 | (self.color.g() as u16) << 5 | self.color.b() as u16
+	}
+	fn light(&self) -> Vec3 {
+		Vec3::new(self.color.r() as f32, self.color.g() as f32, self.color.b() as f32) / 31.0
+	}
+}
+
+fn color_24bit(tr1::Color24Bit { r, g, b }: tr1::Color24Bit) -> Vec3 {
+	Vec3::new(r as f32, g as f32, b as f32) / 255.0
+}
+
+impl Light for tr3::Light {
+	fn pos(&self) -> Vec3 { self.pos.as_vec3() }
+	fn color(&self) -> Vec3 { color_24bit(self.color) }
 }
 
 impl Room for tr3::Room {
@@ -442,15 +758,22 @@ impl Room for tr3::Room {
 	type RoomQuad = tr3::DsQuad;
 	type RoomTri = tr3::DsTri;
 	type RoomStaticMesh = tr3::RoomStaticMesh;
+	type Light = tr3::Light;
 	fn pos(&self) -> IVec3 { IVec3::new(self.x, 0, self.z) }
+	fn y_extent(&self) -> [i32; 2] { [self.y_top, self.y_bottom] }
 	fn vertices(&self) -> &[Self::RoomVertex] { &self.vertices }
 	fn geom(&self) -> impl IntoIterator<Item = RoomGeom<Self::RoomVertex, Self::RoomQuad, Self::RoomTri>> {
 		[RoomGeom { vertices: &self.vertices, quads: &self.quads, tris: &self.tris }]
 	}
 	fn sprites(&self) -> &[tr1::Sprite] { &self.sprites }
 	fn room_static_meshes(&self) -> &[Self::RoomStaticMesh] { &self.room_static_meshes }
+	fn lights(&self) -> &[Self::Light] { &self.lights }
 	fn flip_room_index(&self) -> u16 { self.flip_room_index }
 	fn flip_group(&self) -> u8 { 0 }
+	fn portals(&self) -> &[tr1::Portal] { &self.portals }
+	fn num_sectors(&self) -> &tr1::NumSectors { &self.num_sectors }
+	fn sectors(&self) -> &[tr1::Sector] { &self.sectors }
+	fn water(&self) -> bool { self.flags.water() }
 }
 
 impl LevelDyn for tr3::Level {
@@ -467,6 +790,14 @@ impl LevelDyn for tr3::Level {
 	}
 	fn atlases_32bit(&self) -> Option<&[[tr4::Color32BitBgra; tr1::ATLAS_PIXELS]]> { None }
 	fn misc_images(&self) -> Option<&[[tr4::Color32BitBgra; tr1::ATLAS_PIXELS]]> { None }
+	fn cameras(&self) -> &[tr1::Camera] { &self.cameras }
+	fn flyby_cameras(&self) -> &[tr4::FlybyCamera] { &[] }
+	fn cinematic_frames(&self) -> &[tr1::CinematicFrame] { &self.cinematic_frames }
+	fn demo_data(&self) -> &[u8] { &self.demo_data }
+	fn sound_sources(&self) -> &[tr1::SoundSource] { &self.sound_sources }
+	fn sound_map(&self) -> &[u16] { &self.sound_map[..] }
+	fn floor_data(&self) -> &[u16] { &self.floor_data }
+	fn version(&self) -> Version { Version::Tr3 }
 	fn store(self: Box<Self>) -> LevelStore { LevelStore::Tr3(self) }
 }
 
@@ -475,33 +806,60 @@ impl Level for tr3::Level {
 	type Room = tr3::Room;
 	type Entity = tr2::Entity;
 	type ObjectTexture = tr1::ObjectTexture;
+	type TrBox = tr2::TrBox;
 	type Mesh<'a> = tr2::Mesh<'a>;
 	type Frame<'a> = tr2::Frame<'a>;
+	type Animation = tr1::Animation;
+	type SoundDetails = tr3::SoundDetails;
 	fn models(&self) -> &[Self::Model] { &self.models }
 	fn rooms(&self) -> &[Self::Room] { &self.rooms }
 	fn entities(&self) -> &[Self::Entity] { &self.entities }
 	fn object_textures(&self) -> &[Self::ObjectTexture] { &self.object_textures }
+	fn boxes(&self) -> &[Self::TrBox] { &self.boxes }
 	fn get_mesh_nodes(&self, model: &Self::Model) -> &[tr1::MeshNode] { self.get_mesh_nodes(model) }
 	fn get_mesh(&self, mesh_offset: u32) -> Self::Mesh<'_> { self.get_mesh(mesh_offset) }
-	fn get_frame(&self, model: &Self::Model) -> Self::Frame<'_> { self.get_frame(model) }
+	fn get_frame(&self, model: &Self::Model) -> Option<Self::Frame<'_>> {
+		self.get_frame_at(model.frame_byte_offset, model.num_meshes)
+	}
+	fn get_frame_at(&self, frame_byte_offset: u32, num_meshes: u16) -> Option<Self::Frame<'_>> {
+		self.get_frame_at(frame_byte_offset, num_meshes)
+	}
+	fn animations(&self) -> &[Self::Animation] { &self.animations }
+	fn get_anim_commands(&self, animation: &Self::Animation) -> Vec<tr1::AnimCommand> {
+		self.get_anim_commands(animation)
+	}
+	fn sound_details(&self) -> &[Self::SoundDetails] { &self.sound_details }
+	fn sample_indices(&self) -> &[u32] { &self.sample_indices }
 }
 
 //tr4
 
+impl Light for tr4::Light {
+	fn pos(&self) -> Vec3 { { self.pos }.as_vec3() }
+	fn color(&self) -> Vec3 { color_24bit(self.color) }
+}
+
 impl Room for tr4::Room {
 	type RoomVertex = tr3::RoomVertex;
 	type RoomQuad = tr3::DsQuad;
 	type RoomTri = tr3::DsTri;
 	type RoomStaticMesh = tr3::RoomStaticMesh;
+	type Light = tr4::Light;
 	fn pos(&self) -> IVec3 { IVec3::new(self.x, 0, self.z) }
+	fn y_extent(&self) -> [i32; 2] { [self.y_top, self.y_bottom] }
 	fn vertices(&self) -> &[Self::RoomVertex] { &self.vertices }
 	fn geom(&self) -> impl IntoIterator<Item = RoomGeom<Self::RoomVertex, Self::RoomQuad, Self::RoomTri>> {
 		[RoomGeom { vertices: &self.vertices, quads: &self.quads, tris: &self.tris }]
 	}
 	fn sprites(&self) -> &[tr1::Sprite] { &self.sprites }
 	fn room_static_meshes(&self) -> &[Self::RoomStaticMesh] { &self.room_static_meshes }
+	fn lights(&self) -> &[Self::Light] { &self.lights }
 	fn flip_room_index(&self) -> u16 { self.flip_room_index }
 	fn flip_group(&self) -> u8 { self.flip_group }
+	fn portals(&self) -> &[tr1::Portal] { &self.portals }
+	fn num_sectors(&self) -> &tr1::NumSectors { &self.num_sectors }
+	fn sectors(&self) -> &[tr1::Sector] { &self.sectors }
+	fn water(&self) -> bool { self.flags.water() }
 }
 
 impl Entity for tr4::Entity {
@@ -509,6 +867,9 @@ impl Entity for tr4::Entity {
 	fn model_id(&self) -> u16 { self.model_id }
 	fn pos(&self) -> IVec3 { self.pos }
 	fn angle(&self) -> u16 { self.angle }
+	fn flags(&self) -> u16 { self.flags }
+	fn ocb(&self) -> Option<u16> { Some(self.ocb) }
+	fn clear_body(&self) -> Option<bool> { Some(self.flags & 0x80 != 0) }
 }
 
 impl ObjectTexture for tr4::ObjectTexture {
@@ -516,6 +877,7 @@ impl ObjectTexture for tr4::ObjectTexture {
 	fn blend_mode(&self) -> u16 { self.blend_mode }
 	fn atlas_index(&self) -> u16 { self.atlas_index_face_type.atlas_index() }
 	fn uvs(&self) -> [U16Vec2; 4] { self.uvs }
+	fn tri(&self) -> Option<bool> { Some(self.atlas_index_face_type.tri()) }
 }
 
 impl Face for tr4::EffectsQuad { const POLY_TYPE: PolyType = PolyType::Quad; }
@@ -531,10 +893,12 @@ impl TexturedFace for tr4::EffectsTri {
 
 impl MeshTexturedFace for tr4::EffectsQuad {
 	fn additive(&self) -> bool { self.flags.additive() }
+	fn shine_strength(&self) -> u8 { self.flags.shine_strength() }
 }
 
 impl MeshTexturedFace for tr4::EffectsTri {
 	fn additive(&self) -> bool { self.flags.additive() }
+	fn shine_strength(&self) -> u8 { self.flags.shine_strength() }
 }
 
 impl<'a> Mesh<'a> for tr4::Mesh<'a> {
@@ -549,18 +913,26 @@ impl<'a> Mesh<'a> for tr4::Mesh<'a> {
 	fn solid_tris(&self) -> &'a [Self::SolidTri] { &[] }
 }
 
+impl Animation for tr4::Animation {
+	fn frame_byte_offset(&self) -> u32 { self.frame_byte_offset }
+	fn frame_duration(&self) -> u8 { self.frame_duration }
+	fn frame_size(&self) -> u8 { self.frame_size }
+	fn frame_start(&self) -> u16 { self.frame_start }
+	fn frame_end(&self) -> u16 { self.frame_end }
+}
+
 impl<'a> Frame for tr4::Frame<'a> {
 	fn offset(&self) -> I16Vec3 { self.frame_data.offset }
-	fn iter_rotations(&self) -> impl Iterator<Item = Mat4> {
+	fn iter_rotations(&self) -> impl Iterator<Item = Quat> {
 		self.iter_rotations().map(|rot| {
 			match rot {
-				tr4::FrameRotation::AllAxes(angles) => to_mat(angles),
+				tr4::FrameRotation::AllAxes(angles) => to_quat(angles),
 				tr4::FrameRotation::SingleAxis(axis, angle) => {
 					let angle = to_radians(angle, 4096.0);
 					match axis {
-						tr2::Axis::X => Mat4::from_rotation_x(angle),
-						tr2::Axis::Y => Mat4::from_rotation_y(angle),
-						tr2::Axis::Z => Mat4::from_rotation_z(angle),
+						tr2::Axis::X => Quat::from_rotation_x(angle),
+						tr2::Axis::Y => Quat::from_rotation_y(angle),
+						tr2::Axis::Z => Quat::from_rotation_z(angle),
 					}
 				},
 			}
@@ -586,6 +958,19 @@ impl LevelDyn for tr4::Level {
 	fn misc_images(&self) -> Option<&[[tr4::Color32BitBgra; tr1::ATLAS_PIXELS]]> {
 		Some(&self.misc_images[..])
 	}
+	fn cameras(&self) -> &[tr1::Camera] { &self.level_data.cameras }
+	fn flyby_cameras(&self) -> &[tr4::FlybyCamera] { &self.level_data.flyby_cameras }
+	fn cinematic_frames(&self) -> &[tr1::CinematicFrame] { &[] }
+	fn demo_data(&self) -> &[u8] { &self.level_data.demo_data }
+	fn sound_sources(&self) -> &[tr1::SoundSource] { &self.level_data.sound_sources }
+	fn sound_map(&self) -> &[u16] {
+		match &self.level_data.sound_map {
+			tr4::SoundMap::Original(sound_map) => &sound_map[..],
+			tr4::SoundMap::Extended(sound_map) => &sound_map[..],
+		}
+	}
+	fn floor_data(&self) -> &[u16] { &self.level_data.floor_data }
+	fn version(&self) -> Version { Version::Tr4 }
 	fn store(self: Box<Self>) -> LevelStore { LevelStore::Tr4(self) }
 }
 
@@ -594,15 +979,30 @@ impl Level for tr4::Level {
 	type Room = tr4::Room;
 	type Entity = tr4::Entity;
 	type ObjectTexture = tr4::ObjectTexture;
+	type TrBox = tr2::TrBox;
 	type Mesh<'a> = tr4::Mesh<'a>;
 	type Frame<'a> = tr4::Frame<'a>;
+	type Animation = tr4::Animation;
+	type SoundDetails = tr3::SoundDetails;
 	fn models(&self) -> &[Self::Model] { &self.level_data.models }
 	fn rooms(&self) -> &[Self::Room] { &self.level_data.rooms }
 	fn entities(&self) -> &[Self::Entity] { &self.level_data.entities }
 	fn object_textures(&self) -> &[Self::ObjectTexture] { &self.level_data.object_textures }
+	fn boxes(&self) -> &[Self::TrBox] { &self.level_data.boxes }
 	fn get_mesh_nodes(&self, model: &Self::Model) -> &[tr1::MeshNode] { self.get_mesh_nodes(model) }
 	fn get_mesh(&self, mesh_offset: u32) -> Self::Mesh<'_> { self.get_mesh(mesh_offset) }
-	fn get_frame(&self, model: &Self::Model) -> Self::Frame<'_> { self.get_frame(model) }
+	fn get_frame(&self, model: &Self::Model) -> Option<Self::Frame<'_>> {
+		self.get_frame_at(model.frame_byte_offset, model.num_meshes)
+	}
+	fn get_frame_at(&self, frame_byte_offset: u32, num_meshes: u16) -> Option<Self::Frame<'_>> {
+		self.get_frame_at(frame_byte_offset, num_meshes)
+	}
+	fn animations(&self) -> &[Self::Animation] { &self.level_data.animations }
+	fn get_anim_commands(&self, animation: &Self::Animation) -> Vec<tr1::AnimCommand> {
+		self.get_anim_commands(animation)
+	}
+	fn sound_details(&self) -> &[Self::SoundDetails] { &self.level_data.sound_details }
+	fn sample_indices(&self) -> &[u32] { &self.level_data.sample_indices }
 }
 
 //tr5
@@ -611,6 +1011,7 @@ impl Model for tr5::Model {
 	fn id(&self) -> u32 { self.id }
 	fn mesh_offset_index(&self) -> u16 { self.mesh_offset_index }
 	fn num_meshes(&self) -> u16 { self.num_meshes }
+	fn anim_index(&self) -> u16 { self.anim_index }
 }
 
 impl RoomVertex for tr5::RoomVertex {
@@ -641,29 +1042,44 @@ impl RoomFace for tr5::EffectsTri {
 	fn double_sided(&self) -> bool { self.texture.double_sided() }
 }
 
+impl Light for tr5::Light {
+	fn pos(&self) -> Vec3 { self.pos }
+	fn color(&self) -> Vec3 { self.color.clamp(Vec3::ZERO, Vec3::ONE) }
+}
+
 impl Room for tr5::Room {
 	type RoomVertex = tr5::RoomVertex;
 	type RoomQuad = tr5::EffectsQuad;
 	type RoomTri = tr5::EffectsTri;
 	type RoomStaticMesh = tr3::RoomStaticMesh;
-	fn pos(&self) -> IVec3 { self.pos1 }
+	type Light = tr5::Light;
+	//vertex y is absolute as in TR1-4, so pos1.y is not applied
+	fn pos(&self) -> IVec3 { IVec3::new(self.pos1.x, 0, self.pos1.z) }
+	fn y_extent(&self) -> [i32; 2] { [self.y_top1, self.y_bottom1] }
 	fn vertices(&self) -> &[Self::RoomVertex] { &self.vertices }
 	fn geom(&self) -> impl IntoIterator<Item = RoomGeom<Self::RoomVertex, Self::RoomQuad, Self::RoomTri>> {
-		let mut vertex_offset = 0;
-		self.layers.iter().enumerate().map(move |(index, layer)| {
-			let offset = vertex_offset;
-			vertex_offset += layer.num_vertices;
-			RoomGeom {
-				vertices: &self.vertices[offset as usize..][..layer.num_vertices as usize],
-				quads: &self.layer_faces[index].quads,
-				tris: &self.layer_faces[index].tris,
-			}
+		let mut vertices = &self.vertices[..];
+		self.layers.iter().zip(&self.layer_faces).map(move |(layer, layer_faces)| {
+			//clamp layers claiming more vertices than remain
+			let num_vertices = (layer.num_vertices as usize).min(vertices.len());
+			let (layer_vertices, rest) = vertices.split_at(num_vertices);
+			vertices = rest;
+			RoomGeom { vertices: layer_vertices, quads: &layer_faces.quads, tris: &layer_faces.tris }
 		})
 	}
 	fn sprites(&self) -> &[tr1::Sprite] { &[] }
 	fn room_static_meshes(&self) -> &[Self::RoomStaticMesh] { &self.room_static_meshes }
+	fn lights(&self) -> &[Self::Light] { &self.lights }
 	fn flip_room_index(&self) -> u16 { self.flip_room_index }
 	fn flip_group(&self) -> u8 { self.flip_group }
+	fn portals(&self) -> &[tr1::Portal] { &self.portals }
+	fn num_sectors(&self) -> &tr1::NumSectors { &self.num_sectors }
+	fn sectors(&self) -> &[tr1::Sector] { &self.sectors }
+	fn water(&self) -> bool { self.flags.water() }
+	fn malformed(&self) -> bool {
+		let layer_vertices = self.layers.iter().map(|layer| layer.num_vertices as usize).sum::<usize>();
+		self.has_filler() || layer_vertices > self.vertices.len()
+	}
 }
 
 impl ObjectTexture for tr5::ObjectTexture {
@@ -671,6 +1087,7 @@ impl ObjectTexture for tr5::ObjectTexture {
 	fn blend_mode(&self) -> u16 { self.blend_mode }
 	fn atlas_index(&self) -> u16 { self.atlas_index_face_type.atlas_index() }
 	fn uvs(&self) -> [U16Vec2; 4] { self.uvs }
+	fn tri(&self) -> Option<bool> { Some(self.atlas_index_face_type.tri()) }
 }
 
 impl LevelDyn for tr5::Level {
@@ -691,6 +1108,14 @@ impl LevelDyn for tr5::Level {
 	fn misc_images(&self) -> Option<&[[tr4::Color32BitBgra; tr1::ATLAS_PIXELS]]> {
 		Some(&self.misc_images[..])
 	}
+	fn cameras(&self) -> &[tr1::Camera] { &self.cameras }
+	fn flyby_cameras(&self) -> &[tr4::FlybyCamera] { &self.flyby_cameras }
+	fn cinematic_frames(&self) -> &[tr1::CinematicFrame] { &[] }
+	fn demo_data(&self) -> &[u8] { &self.demo_data }
+	fn sound_sources(&self) -> &[tr1::SoundSource] { &self.sound_sources }
+	fn sound_map(&self) -> &[u16] { &self.sound_map[..] }
+	fn floor_data(&self) -> &[u16] { &self.floor_data }
+	fn version(&self) -> Version { Version::Tr5 }
 	fn store(self: Box<Self>) -> LevelStore { LevelStore::Tr5(self) }
 }
 
@@ -699,13 +1124,88 @@ impl Level for tr5::Level {
 	type Room = tr5::Room;
 	type Entity = tr4::Entity;
 	type ObjectTexture = tr5::ObjectTexture;
+	type TrBox = tr2::TrBox;
 	type Mesh<'a> = tr4::Mesh<'a>;
 	type Frame<'a> = tr4::Frame<'a>;
+	type Animation = tr4::Animation;
+	type SoundDetails = tr3::SoundDetails;
 	fn models(&self) -> &[Self::Model] { &self.models }
 	fn rooms(&self) -> &[Self::Room] { &self.rooms }
 	fn entities(&self) -> &[Self::Entity] { &self.entities }
 	fn object_textures(&self) -> &[Self::ObjectTexture] { &self.object_textures }
+	fn boxes(&self) -> &[Self::TrBox] { &self.boxes }
 	fn get_mesh_nodes(&self, model: &Self::Model) -> &[tr1::MeshNode] { self.get_mesh_nodes(model) }
 	fn get_mesh(&self, mesh_offset: u32) -> Self::Mesh<'_> { self.get_mesh(mesh_offset) }
-	fn get_frame(&self, model: &Self::Model) -> Self::Frame<'_> { self.get_frame(model) }
+	fn get_frame(&self, model: &Self::Model) -> Option<Self::Frame<'_>> {
+		self.get_frame_at(model.frame_byte_offset, model.num_meshes)
+	}
+	fn get_frame_at(&self, frame_byte_offset: u32, num_meshes: u16) -> Option<Self::Frame<'_>> {
+		self.get_frame_at(frame_byte_offset, num_meshes)
+	}
+	fn animations(&self) -> &[Self::Animation] { &self.animations }
+	fn get_anim_commands(&self, animation: &Self::Animation) -> Vec<tr1::AnimCommand> {
+		self.get_anim_commands(animation)
+	}
+	fn sound_details(&self) -> &[Self::SoundDetails] { &self.sound_details }
+	fn sample_indices(&self) -> &[u32] { &self.sample_indices }
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{io::Cursor, mem::MaybeUninit};
+	use super::*;
+	
+	/// Empty TR5 room: zeroed header, all data offsets at an empty portal list.
+	fn empty_tr5_room() -> tr5::Room {
+		let mut bytes = vec![0; 218];
+		bytes[..4].copy_from_slice(b"XELA");
+		bytes[4..8].copy_from_slice(&210u32.to_le_bytes());
+		let mut room = MaybeUninit::<tr5::Room>::uninit();
+		unsafe {
+			tr5::Room::read(&mut Cursor::new(bytes), room.as_mut_ptr()).unwrap();
+			room.assume_init()
+		}
+	}
+	
+	fn layer(num_vertices: u16) -> tr5::Layer {
+		tr5::Layer {
+			num_vertices,
+			unused1: [0; 2],
+			num_quads: 0,
+			num_tris: 0,
+			unused2: [0; 3],
+			bound_box: MinMax { min: Vec3::ZERO, max: Vec3::ZERO },
+			unused3: [0; 4],
+		}
+	}
+	
+	fn vertex(x: f32) -> tr5::RoomVertex {
+		tr5::RoomVertex { pos: Vec3::new(x, 0.0, 0.0), normal: Vec3::Y, color: 0 }
+	}
+	
+	fn empty_faces() -> tr5::LayerFaces {
+		tr5::LayerFaces { quads: Box::new([]), tris: Box::new([]) }
+	}
+	
+	#[test]
+	fn tr5_placeholder_room_is_malformed() {
+		let mut room = empty_tr5_room();
+		assert!(!room.malformed());
+		room.num_layers = tr5::RoomCount(tr5::FILLER);
+		assert!(room.malformed());
+		assert_eq!(room.geom().into_iter().count(), 0);
+	}
+	
+	#[test]
+	fn tr5_geom_clamps_layers() {
+		let mut room = empty_tr5_room();
+		room.layers = Box::new([layer(2), layer(3), layer(1)]);
+		room.layer_faces = Box::new([empty_faces(), empty_faces()]);
+		room.vertices = Box::new([vertex(0.0), vertex(1.0), vertex(2.0)]);
+		assert!(room.malformed());
+		let layer_xs = room.geom().into_iter()
+			.map(|geom| geom.vertices.iter().map(|v| v.pos.x).collect::<Vec<_>>())
+			.collect::<Vec<_>>();
+		assert_eq!(layer_xs, [vec![0.0, 1.0], vec![2.0]]);
+	}
 }
@@ -0,0 +1,449 @@
+use std::{
+	fs::File, io::{BufRead, BufReader, Cursor, Read, Result, Seek, SeekFrom}, path::Path,
+};
+use flate2::read::GzDecoder;
+use memmap2::Mmap;
+use serde::{Deserialize, Serialize};
+use tr_model::{tr1, TrError};
+
+const TR1_MAGIC: u32 = 0x00000020;
+const TR2_MAGIC: u32 = 0x0000002D;
+const TR3_MAGICS: [u32; 3] = [0xFF080038, 0xFF180038, 0xFF180034];
+const TR4_MAGIC: u32 = 0x00345254;//"TR4\0", also used by TR5
+const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+/// Saturn level files are tagged chunks, the first being the room file.
+const SATURN_TAG: &[u8; 8] = b"ROOMFILE";
+/// Smallest file read with the large file `ReadMode`. Smaller files are always buffered.
+const LARGE_FILE_MIN_SIZE: u64 = 1 << 20;
+
+/// Offset of the first zlib chunk in TR4 and TR5 files: version and three atlas counts.
+pub const TR4_CHUNKS_OFFSET: u64 = 10;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Version {
+	Tr1,
+	Tr2,
+	Tr3,
+	Tr4,
+	Tr5,
+}
+
+impl Version {
+	pub const ALL: [Version; 5] = [Version::Tr1, Version::Tr2, Version::Tr3, Version::Tr4, Version::Tr5];
+	
+	pub fn label(&self) -> &'static str {
+		match self {
+			Version::Tr1 => "TR1",
+			Version::Tr2 => "TR2",
+			Version::Tr3 => "TR3",
+			Version::Tr4 => "TR4",
+			Version::Tr5 => "TR5",
+		}
+	}
+	
+	/// Sectors carry a footstep sound material, TR3 onward.
+	pub fn has_footstep_materials(&self) -> bool {
+		matches!(self, Version::Tr3 | Version::Tr4 | Version::Tr5)
+	}
+	
+	/// Sound samples are in the game's MAIN.SFX rather than the level, TR2 and TR3.
+	pub fn uses_main_sfx(&self) -> bool {
+		matches!(self, Version::Tr2 | Version::Tr3)
+	}
+}
+
+/// Console releases of TR1. Their levels differ from PC in header, palette and 4 bit texture layout,
+/// which `tr1::Level` doesn't read.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ConsoleVariant {
+	Psx,
+	Saturn,
+}
+
+impl ConsoleVariant {
+	pub fn label(&self) -> &'static str {
+		match self {
+			ConsoleVariant::Psx => "PlayStation",
+			ConsoleVariant::Saturn => "Saturn",
+		}
+	}
+	
+	/// `extension` is lowercase.
+	fn from_extension(extension: &str) -> Option<Self> {
+		match extension {
+			"psx" => Some(ConsoleVariant::Psx),
+			"sat" => Some(ConsoleVariant::Saturn),
+			_ => None,
+		}
+	}
+	
+	/// By the Saturn chunk tag, else by extension. PSX levels have no known signature.
+	fn detect<R: Read + Seek>(reader: &mut R, extension: &str) -> Result<Option<Self>> {
+		reader.rewind()?;
+		let mut tag = [0; SATURN_TAG.len()];
+		let tagged = reader.read_exact(&mut tag).is_ok() && &tag == SATURN_TAG;
+		Ok(if tagged { Some(ConsoleVariant::Saturn) } else { Self::from_extension(extension) })
+	}
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32> {
+	let mut bytes = [0; 4];
+	reader.read_exact(&mut bytes)?;
+	Ok(u32::from_le_bytes(bytes))
+}
+
+/// Uncompressed size of the misc images chunk, which follows the 32 bit and 16 bit atlas chunks.
+fn read_misc_images_size<R: Read + Seek>(reader: &mut R) -> Result<u32> {
+	reader.seek(SeekFrom::Start(TR4_CHUNKS_OFFSET))?;
+	for _ in 0..2 {
+		let _uncompressed_size = read_u32(reader)?;
+		let compressed_size = read_u32(reader)?;
+		reader.seek(SeekFrom::Current(compressed_size as i64))?;
+	}
+	read_u32(reader)
+}
+
+/// Version by magic alone, for extensions that don't tell.
+fn version_from_magic<R: Read + Seek>(reader: &mut R, magic: u32) -> Option<Version> {
+	match magic {
+		TR1_MAGIC => Some(Version::Tr1),
+		TR2_MAGIC => Some(Version::Tr2),
+		magic if TR3_MAGICS.contains(&magic) => Some(Version::Tr3),
+		TR4_MAGIC => tr4_or_tr5(reader),
+		_ => None,
+	}
+}
+
+/// TR4 and TR5 share a magic. TR4 has two misc images, TR5 has three.
+fn tr4_or_tr5<R: Read + Seek>(reader: &mut R) -> Option<Version> {
+	const IMAGE_SIZE: u32 = (tr1::ATLAS_PIXELS * 4) as u32;
+	match read_misc_images_size(reader).ok()? {
+		size if size == IMAGE_SIZE * 2 => Some(Version::Tr4),
+		size if size == IMAGE_SIZE * 3 => Some(Version::Tr5),
+		_ => None,
+	}
+}
+
+/// Version picked by hand for a file `get_version` didn't recognize, applied to files with the same magic and
+/// extension.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LearnedVersion {
+	pub magic: u32,
+	/// Lowercase.
+	pub extension: String,
+	pub version: Version,
+}
+
+/// Reads the magic and determines the version from it and the file extension, falling back to
+/// `learned_versions` for combinations not recognized. Returns the magic and `None` for the version if neither
+/// knows the combination. Leaves the reader at the start.
+/// Console extensions don't tell the version, so a PC level renamed to one is recognized by magic alone.
+/// Unrecognized console TR1 levels are an error rather than unrecognized, so they aren't loaded as a PC version.
+pub fn get_version<R: Read + Seek>(
+	reader: &mut R, extension: &str, learned_versions: &[LearnedVersion],
+) -> Result<(u32, Option<Version>)> {
+	let magic = read_u32(reader)?;
+	let extension = extension.to_ascii_lowercase();
+	let version = match (magic, extension.as_str()) {
+		(TR1_MAGIC, "phd") => Some(Version::Tr1),
+		(TR2_MAGIC, "tr2") => Some(Version::Tr2),
+		(magic, "tr2") if TR3_MAGICS.contains(&magic) => Some(Version::Tr3),
+		(TR4_MAGIC, "tr4") => Some(Version::Tr4),
+		(TR4_MAGIC, "trc") => tr4_or_tr5(reader),
+		(magic, "psx" | "sat") => version_from_magic(reader, magic),
+		_ => None,
+	};
+	let version = version.or_else(|| {
+		learned_versions
+			.iter()
+			.find(|learned| learned.magic == magic && learned.extension == extension)
+			.map(|learned| learned.version)
+	});
+	if version.is_none() {
+		if let Some(variant) = ConsoleVariant::detect(reader, &extension)? {
+			let msg = format!(
+				"{} TR1 levels are not supported yet (console header and 4 bit textures)",
+				variant.label(),
+			);
+			return Err(TrError::Unsupported(msg).into());
+		}
+	}
+	reader.rewind()?;
+	Ok((magic, version))
+}
+
+/// How a level file is read for parsing.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum ReadMode {
+	#[default]
+	//`Whole` read the file into memory first, which measured slower than buffered reads
+	#[serde(alias = "Whole")]
+	Buffered,
+	/// Memory-mapped. The file must not be changed while parsing, or the process may crash with SIGBUS.
+	Mapped,
+}
+
+/// Level file as read by its `ReadMode`. Gzipped files are decompressed into memory so they can be seeked.
+pub enum LevelReader {
+	File(BufReader<File>),
+	Memory(Cursor<Vec<u8>>),
+	Mapped(Cursor<Mmap>),
+}
+
+impl Read for LevelReader {
+	fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+		match self {
+			LevelReader::File(reader) => reader.read(buf),
+			LevelReader::Memory(reader) => reader.read(buf),
+			LevelReader::Mapped(reader) => reader.read(buf),
+		}
+	}
+}
+
+impl Seek for LevelReader {
+	fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+		match self {
+			LevelReader::File(reader) => reader.seek(pos),
+			LevelReader::Memory(reader) => reader.seek(pos),
+			LevelReader::Mapped(reader) => reader.seek(pos),
+		}
+	}
+}
+
+/// Files of at least `LARGE_FILE_MIN_SIZE` are read with `large_file_mode`, smaller ones buffered.
+pub fn open_level(path: &Path, large_file_mode: ReadMode) -> Result<LevelReader> {
+	let file = File::open(path)?;
+	let read_mode = match file.metadata()?.len() {
+		size if size >= LARGE_FILE_MIN_SIZE => large_file_mode,
+		_ => ReadMode::Buffered,
+	};
+	open_file(file, read_mode)
+}
+
+fn open_file(file: File, read_mode: ReadMode) -> Result<LevelReader> {
+	let mut reader = BufReader::new(file);
+	if reader.fill_buf()?.starts_with(&GZIP_MAGIC) {
+		let mut bytes = vec![];
+		GzDecoder::new(reader).read_to_end(&mut bytes)?;
+		return Ok(LevelReader::Memory(Cursor::new(bytes)));
+	}
+	match read_mode {
+		ReadMode::Buffered => Ok(LevelReader::File(reader)),
+		ReadMode::Mapped => {
+			//SAFETY: the map is only read while the background load parses it. If the file is truncated or
+			//rewritten meanwhile, reading past its new end raises SIGBUS and takes down the whole tool rather than
+			//failing the load. That's the documented risk of the opt-in `ReadMode::Mapped`.
+			let mmap = unsafe { Mmap::map(reader.get_ref())? };
+			Ok(LevelReader::Mapped(Cursor::new(mmap)))
+		},
+	}
+}
+
+fn file_extension(path: &Path) -> &str {
+	path.extension().and_then(|e| e.to_str()).unwrap_or_default()
+}
+
+/// Extension to detect the version with. For gzipped files, that of the inner file (`tr4` of `level.tr4.gz`).
+pub fn level_extension(path: &Path) -> &str {
+	match file_extension(path) {
+		extension if extension.eq_ignore_ascii_case("gz") => {
+			path.file_stem().map(|stem| file_extension(Path::new(stem))).unwrap_or_default()
+		},
+		extension => extension,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{fs, io::Write, time::Instant};
+	use flate2::{write::GzEncoder, Compression};
+	use crate::{read_level, test_level::{self, TempDir}};
+	use super::*;
+	
+	const IMAGE_SIZE: u32 = (tr1::ATLAS_PIXELS * 4) as u32;
+	
+	fn detect(bytes: Vec<u8>, extension: &str) -> Option<Version> {
+		let mut reader = Cursor::new(bytes);
+		let (_, version) = get_version(&mut reader, extension, &[]).unwrap();
+		assert_eq!(reader.stream_position().unwrap(), 0);
+		version
+	}
+	
+	fn magic_only(magic: u32) -> Vec<u8> {
+		magic.to_le_bytes().to_vec()
+	}
+	
+	/// Magic, atlas counts, two atlas chunks of `chunk_len` bytes, then the misc images uncompressed size.
+	fn tr4_header(misc_images_size: u32, chunk_len: u32) -> Vec<u8> {
+		let mut bytes = TR4_MAGIC.to_le_bytes().to_vec();
+		bytes.extend_from_slice(&[0; 6]);
+		for _ in 0..2 {
+			bytes.extend_from_slice(&0u32.to_le_bytes());
+			bytes.extend_from_slice(&chunk_len.to_le_bytes());
+			bytes.resize(bytes.len() + chunk_len as usize, 0xAA);
+		}
+		bytes.extend_from_slice(&misc_images_size.to_le_bytes());
+		bytes
+	}
+	
+	#[test]
+	fn matching_magic_and_extension() {
+		assert_eq!(detect(magic_only(TR1_MAGIC), "phd"), Some(Version::Tr1));
+		assert_eq!(detect(magic_only(TR2_MAGIC), "tr2"), Some(Version::Tr2));
+		for magic in TR3_MAGICS {
+			assert_eq!(detect(magic_only(magic), "tr2"), Some(Version::Tr3));
+		}
+		assert_eq!(detect(magic_only(TR4_MAGIC), "tr4"), Some(Version::Tr4));
+	}
+	
+	#[test]
+	fn extension_is_case_insensitive() {
+		assert_eq!(detect(magic_only(TR1_MAGIC), "PHD"), Some(Version::Tr1));
+		assert_eq!(detect(magic_only(TR3_MAGICS[1]), "Tr2"), Some(Version::Tr3));
+	}
+	
+	#[test]
+	fn trc_by_misc_image_count() {
+		assert_eq!(detect(tr4_header(IMAGE_SIZE * 3, 5), "trc"), Some(Version::Tr5));
+		assert_eq!(detect(tr4_header(IMAGE_SIZE * 2, 5), "trc"), Some(Version::Tr4));
+		assert_eq!(detect(tr4_header(IMAGE_SIZE, 5), "trc"), None);
+		assert_eq!(detect(tr4_header(IMAGE_SIZE * 3, 0), "trc"), Some(Version::Tr5));
+	}
+	
+	#[test]
+	fn trc_truncated_header() {
+		let mut bytes = tr4_header(IMAGE_SIZE * 3, 5);
+		bytes.truncate(bytes.len() - 2);
+		assert_eq!(detect(bytes, "trc"), None);
+	}
+	
+	#[test]
+	fn mismatched_magic_and_extension() {
+		assert_eq!(detect(magic_only(TR2_MAGIC), "phd"), None);
+		assert_eq!(detect(magic_only(TR3_MAGICS[0]), "phd"), None);
+		assert_eq!(detect(magic_only(TR1_MAGIC), "tr2"), None);
+		assert_eq!(detect(magic_only(TR4_MAGIC), "tr2"), None);
+		assert_eq!(detect(magic_only(TR1_MAGIC), "tr4"), None);
+		assert_eq!(detect(magic_only(TR3_MAGICS[2]), "tr4"), None);
+		assert_eq!(detect(magic_only(TR2_MAGIC), "trc"), None);
+		assert_eq!(detect(tr4_header(IMAGE_SIZE * 3, 5), "phd"), None);
+		assert_eq!(detect(magic_only(TR4_MAGIC), "dat"), None);
+	}
+	
+	#[test]
+	fn learned_version_fallback() {
+		let learned = [LearnedVersion { magic: TR4_MAGIC, extension: "dat".to_string(), version: Version::Tr4 }];
+		let mut reader = Cursor::new(magic_only(TR4_MAGIC));
+		assert_eq!(get_version(&mut reader, "DAT", &learned).unwrap(), (TR4_MAGIC, Some(Version::Tr4)));
+		let mut reader = Cursor::new(magic_only(TR1_MAGIC));
+		assert_eq!(get_version(&mut reader, "dat", &learned).unwrap(), (TR1_MAGIC, None));
+	}
+	
+	#[test]
+	fn precedence_built_in_then_learned_then_prompt() {
+		let learned = [(TR1_MAGIC, "phd", Version::Tr2), (TR4_MAGIC, "dat", Version::Tr4)]
+			.map(|(magic, extension, version)| LearnedVersion { magic, extension: extension.to_string(), version });
+		let detect = |magic, extension| get_version(&mut Cursor::new(magic_only(magic)), extension, &learned).unwrap().1;
+		//built-in detection wins over a learned choice for the same signature
+		assert_eq!(detect(TR1_MAGIC, "phd"), Some(Version::Tr1));
+		//learned choice for a signature detection doesn't know
+		assert_eq!(detect(TR4_MAGIC, "Dat"), Some(Version::Tr4));
+		//neither, so the caller prompts
+		assert_eq!(detect(TR2_MAGIC, "dat"), None);
+	}
+	
+	fn unsupported(bytes: Vec<u8>, extension: &str) -> bool {
+		let error = get_version(&mut Cursor::new(bytes), extension, &[]).unwrap_err();
+		matches!(TrError::of(&error), Some(TrError::Unsupported(_)))
+	}
+	
+	#[test]
+	fn renamed_pc_level_with_console_extension() {
+		assert_eq!(detect(magic_only(TR1_MAGIC), "psx"), Some(Version::Tr1));
+		assert_eq!(detect(magic_only(TR2_MAGIC), "SAT"), Some(Version::Tr2));
+		assert_eq!(detect(magic_only(TR3_MAGICS[0]), "psx"), Some(Version::Tr3));
+		assert_eq!(detect(tr4_header(IMAGE_SIZE * 3, 5), "sat"), Some(Version::Tr5));
+	}
+	
+	#[test]
+	fn console_level_is_unsupported_after_lookups_fail() {
+		assert!(unsupported(magic_only(0x12345678), "psx"));
+		assert!(unsupported(b"ROOMFILE\0\0\0\0".to_vec(), "sat"));
+		assert!(unsupported(b"ROOMFILE\0\0\0\0".to_vec(), "dat"));
+		let learned = [LearnedVersion { magic: 0x12345678, extension: "psx".to_string(), version: Version::Tr1 }];
+		let mut reader = Cursor::new(magic_only(0x12345678));
+		assert_eq!(get_version(&mut reader, "psx", &learned).unwrap(), (0x12345678, Some(Version::Tr1)));
+	}
+	
+	#[test]
+	fn short_file_is_an_error() {
+		assert!(get_version(&mut Cursor::new(vec![0x20, 0]), "phd", &[]).is_err());
+	}
+	
+	#[test]
+	fn gzipped_level() {
+		let tiny = test_level::tiny();
+		let mut encoder = GzEncoder::new(vec![], Compression::default());
+		encoder.write_all(&tiny.bytes()).unwrap();
+		let dir = TempDir::new("gzip");
+		let level_path = dir.join("tiny.PHD.gz");
+		fs::write(&level_path, encoder.finish().unwrap()).unwrap();
+		let mut reader = open_level(&level_path, ReadMode::Buffered).unwrap();
+		assert!(matches!(reader, LevelReader::Memory(_)));
+		let extension = level_extension(&level_path);
+		assert_eq!(extension, "PHD");
+		let (_, version) = get_version(&mut reader, extension, &[]).unwrap();
+		assert_eq!(version, Some(Version::Tr1));
+		let level = read_level::<tr1::Level>(&mut reader).unwrap();
+		assert_eq!(level.rooms.len(), tiny.rooms.len());
+		assert_eq!(level.rooms[0].x, tiny.rooms[0].x);
+	}
+	
+	#[test]
+	fn mapped_level() {
+		let tiny = test_level::tiny();
+		let dir = TempDir::new("mapped");
+		let level_path = dir.join("tiny.PHD");
+		fs::write(&level_path, tiny.bytes()).unwrap();
+		let mut reader = open_file(File::open(&level_path).unwrap(), ReadMode::Mapped).unwrap();
+		assert!(matches!(reader, LevelReader::Mapped(_)));
+		let (_, version) = get_version(&mut reader, "PHD", &[]).unwrap();
+		assert_eq!(version, Some(Version::Tr1));
+		let level = read_level::<tr1::Level>(&mut reader).unwrap();
+		assert_eq!(level.rooms.len(), tiny.rooms.len());
+		assert_eq!(level.rooms[0].x, tiny.rooms[0].x);
+	}
+	
+	#[test]
+	fn dropped_whole_mode_reads_as_buffered() {
+		let read_mode = serde_json::from_str::<ReadMode>("\"Whole\"").unwrap();
+		assert_eq!(read_mode, ReadMode::Buffered);
+		assert_eq!(ReadMode::default(), ReadMode::Buffered);
+	}
+	
+	/// Parse times of a 64 MB level in each mode, not run by default:
+	/// `cargo test --release -p tr_tool read_mode_timings -- --ignored --nocapture`
+	#[test]
+	#[ignore]
+	fn read_mode_timings() {
+		let mut test_level = test_level::tiny();
+		test_level.rooms = (0..4000)
+			.map(|_| {
+				let mut room = test_level::TestRoom::walled(0, 0, (32, 32), 0);
+				room.vertices = vec![test_level::vertex(0, 0, 0); 1000];
+				room
+			})
+			.collect();
+		let dir = TempDir::new("timings");
+		let level_path = dir.join("large.PHD");
+		fs::write(&level_path, test_level.bytes()).unwrap();
+		for read_mode in [ReadMode::Buffered, ReadMode::Mapped] {
+			let start = Instant::now();
+			for _ in 0..10 {
+				let mut reader = open_level(&level_path, read_mode).unwrap();
+				read_level::<tr1::Level>(&mut reader).unwrap();
+			}
+			println!("{:?}: {:?}", read_mode, start.elapsed() / 10);
+		}
+	}
+}